@@ -0,0 +1,66 @@
+//! Measures how long a greedy client pipelining requests keeps the other tasks of a worker
+//! waiting, with and without [Embed::yield_every](actix_embed::Embed::yield_every).
+//!
+//! Run with `cargo bench --bench fairness`.
+
+use std::cell::Cell;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use actix_embed::Embed;
+use actix_web::rt::task;
+use actix_web::test::{self, TestRequest};
+use actix_web::App;
+use rust_embed::RustEmbed;
+
+#[derive(RustEmbed)]
+#[folder = "testdata/"]
+struct Assets;
+
+const REQUESTS: u32 = 20_000;
+
+fn main() {
+    actix_rt::System::new().block_on(async {
+        for every in [0, 64, 16] {
+            let srv =
+                test::init_service(App::new().service(Embed::new("/", &Assets).yield_every(every)))
+                    .await;
+
+            // another connection of the worker, which only needs to run now and then
+            let done = Rc::new(Cell::new(false));
+            let probe = actix_web::rt::spawn({
+                let done = done.clone();
+                async move {
+                    let mut runs = 0u32;
+                    let mut longest = Duration::ZERO;
+                    let mut last = Instant::now();
+                    while !done.get() {
+                        task::yield_now().await;
+                        longest = longest.max(last.elapsed());
+                        last = Instant::now();
+                        runs += 1;
+                    }
+                    (runs, longest)
+                }
+            });
+            task::yield_now().await;
+
+            let start = Instant::now();
+            for _ in 0..REQUESTS {
+                let req = TestRequest::get().uri("/index.html").to_request();
+                test::call_service(&srv, req).await;
+            }
+            let elapsed = start.elapsed();
+            done.set(true);
+            let (runs, longest) = probe.await.unwrap();
+            println!(
+                "yield_every({:>2}): {:>10.2?} per request, other task ran {:>5} times, \
+                 waited at most {:.2?}",
+                every,
+                elapsed / REQUESTS,
+                runs,
+                longest
+            );
+        }
+    });
+}
@@ -0,0 +1,63 @@
+//! Measures the time to answer requests which need no file data: `304 Not Modified` for
+//! conditional requests and `405 Method Not Allowed`, next to full `200 OK` responses.
+//!
+//! Run with `cargo bench --bench header_only`.
+
+use std::time::{Duration, Instant};
+
+use actix_embed::Embed;
+use actix_web::http::{header, Method, StatusCode};
+use actix_web::test::{self, TestRequest};
+use actix_web::App;
+use rust_embed::RustEmbed;
+
+#[derive(RustEmbed)]
+#[folder = "testdata/"]
+struct Assets;
+
+const ITERATIONS: u32 = 20_000;
+
+fn main() {
+    actix_rt::System::new().block_on(async {
+        let srv = test::init_service(
+            App::new().service(Embed::new("/", &Assets).hide_missing_paths(true)),
+        )
+        .await;
+        let req = TestRequest::get().uri("/index.html").to_request();
+        let resp = test::call_service(&srv, req).await;
+        let etag = resp.headers().get(header::ETAG).unwrap().clone();
+
+        let cases = [
+            ("200 OK", Method::GET, false, StatusCode::OK),
+            (
+                "304 Not Modified",
+                Method::GET,
+                true,
+                StatusCode::NOT_MODIFIED,
+            ),
+            (
+                "405 Method Not Allowed",
+                Method::POST,
+                false,
+                StatusCode::METHOD_NOT_ALLOWED,
+            ),
+        ];
+        for (name, method, conditional, status) in cases {
+            let mut total = Duration::ZERO;
+            for _ in 0..ITERATIONS {
+                let mut req = TestRequest::default()
+                    .method(method.clone())
+                    .uri("/index.html");
+                if conditional {
+                    req = req.insert_header((header::IF_NONE_MATCH, etag.clone()));
+                }
+                let req = req.to_request();
+                let start = Instant::now();
+                let resp = test::call_service(&srv, req).await;
+                total += start.elapsed();
+                assert_eq!(resp.status(), status);
+            }
+            println!("{:<24} {:>10.2?} per request", name, total / ITERATIONS);
+        }
+    });
+}
@@ -0,0 +1,199 @@
+use std::cell::Cell;
+use std::fmt::{Debug, Formatter};
+use std::sync::atomic::{AtomicBool, AtomicU8, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::glob::Glob;
+
+/// The state of a toggle, overriding the configured option once set.
+const UNSET: u8 = 0;
+const OFF: u8 = 1;
+const ON: u8 = 2;
+
+/// Toggles features of [Embed](crate::Embed) services at runtime, from application code,
+/// e.g. for feature-flagged rollouts.
+///
+/// `EmbedAdmin` is a handle, clones control the same services. Every [Embed](crate::Embed)
+/// has one, see [Embed::admin](crate::Embed::admin). Services created for several workers
+/// share one with [Embed::with_admin](crate::Embed::with_admin).
+///
+/// Toggles are not exposed over HTTP, routes driving them are up to the application.
+///
+/// # Examples
+/// ```
+/// use actix_embed::{Embed, EmbedAdmin};
+/// use actix_web::{App, HttpServer};
+/// use rust_embed::RustEmbed;
+///
+/// #[derive(RustEmbed)]
+/// #[folder = "testdata/"]
+/// struct Assets;
+///
+/// let admin = EmbedAdmin::new();
+/// let server_admin = admin.clone();
+/// let server = HttpServer::new(move || {
+///     App::new().service(
+///         Embed::new("/", &Assets)
+///             .suggestions(3)
+///             .with_admin(&server_admin),
+///     )
+/// });
+///
+/// // later, e.g. when a feature flag changes
+/// admin.set_suggestions(false);
+/// admin.bust_derived_caches();
+/// ```
+#[derive(Clone, Default)]
+pub struct EmbedAdmin {
+    inner: Arc<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    /// The number of purges, read by services on each request without locking `purges`.
+    generation: AtomicUsize,
+    /// The globs of the purges in order, `None` purging everything.
+    purges: Mutex<Vec<Option<Glob>>>,
+    /// The configuration epoch, see [EmbedAdmin::bump_epoch].
+    epoch: AtomicUsize,
+    suggestions: AtomicBool,
+    resolution_trace: AtomicU8,
+}
+
+impl Debug for EmbedAdmin {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EmbedAdmin")
+            .field("generation", &self.generation())
+            .field("epoch", &self.epoch())
+            .field("suggestions_disabled", &self.suggestions_disabled())
+            .field("resolution_trace", &self.resolution_trace(false))
+            .finish()
+    }
+}
+
+impl EmbedAdmin {
+    /// Create new [EmbedAdmin], which overrides no option.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Drop the responses, minified variants and prepared files cached by the services, so
+    /// they are derived again on the next requests, e.g. after changing what
+    /// [on_serve](crate::Embed::on_serve) hooks or header rules depend on.
+    ///
+    /// Variants compressed when services are created are kept, as they only depend on the
+    /// embedded files. See [purge](Self::purge) to drop the artifacts of some files only.
+    pub fn bust_derived_caches(&self) {
+        self.push_purge(None);
+    }
+
+    /// Drop the derived artifacts of the files whose paths match `glob`, like
+    /// [bust_derived_caches](Self::bust_derived_caches) does for all files.
+    ///
+    /// Paths are the keys of the embedded files, e.g. `assets/index.css`, matched as by
+    /// [Embed::precompress](crate::Embed::precompress). Services drop the artifacts before
+    /// serving their next request.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::EmbedAdmin;
+    ///
+    /// let admin = EmbedAdmin::new();
+    /// admin.purge("**/*.css");
+    /// ```
+    pub fn purge<G: AsRef<str>>(&self, glob: G) {
+        self.push_purge(Some(Glob::new(glob)));
+    }
+
+    fn push_purge(&self, glob: Option<Glob>) {
+        let mut purges = self
+            .inner
+            .purges
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        purges.push(glob);
+        self.inner.generation.store(purges.len(), Ordering::Release);
+    }
+
+    /// Start a new configuration epoch, so the responses and prepared files cached in earlier
+    /// epochs are derived again when they are next used, e.g. after changing what header
+    /// rules or hooks depend on.
+    ///
+    /// Unlike [bust_derived_caches](Self::bust_derived_caches), nothing is dropped up front:
+    /// each artifact is replaced lazily, so a change does not make every worker derive all
+    /// files again at once. Changing a toggle of the admin starts a new epoch too. Compressed
+    /// and minified variants only depend on the embedded files and are kept.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::EmbedAdmin;
+    ///
+    /// let admin = EmbedAdmin::new();
+    /// admin.bump_epoch();
+    /// assert_eq!(admin.epoch(), 1);
+    /// ```
+    pub fn bump_epoch(&self) {
+        self.inner.epoch.fetch_add(1, Ordering::AcqRel);
+    }
+
+    /// Returns the configuration epoch, `0` until it is [bumped](Self::bump_epoch), e.g. to
+    /// confirm in diagnostics that services picked up a change, see
+    /// [ServeContext::epoch](crate::ServeContext::epoch).
+    pub fn epoch(&self) -> usize {
+        self.inner.epoch.load(Ordering::Acquire)
+    }
+
+    /// Switch [suggestions](crate::Embed::suggestions) off or back on, taking effect for the
+    /// next requests. Suggestions are never made unless they are configured.
+    pub fn set_suggestions(&self, enabled: bool) {
+        self.inner.suggestions.store(!enabled, Ordering::Relaxed);
+        self.bump_epoch();
+    }
+
+    /// Switch the [resolution trace](crate::Embed::resolution_trace) on or off, overriding
+    /// the configured option for the next requests. The trace is never sent by release
+    /// builds.
+    pub fn set_resolution_trace(&self, enabled: bool) {
+        let state = if enabled { ON } else { OFF };
+        self.inner.resolution_trace.store(state, Ordering::Relaxed);
+        self.bump_epoch();
+    }
+
+    /// Returns the number of purges.
+    pub(crate) fn generation(&self) -> usize {
+        self.inner.generation.load(Ordering::Acquire)
+    }
+
+    pub(crate) fn suggestions_disabled(&self) -> bool {
+        self.inner.suggestions.load(Ordering::Relaxed)
+    }
+
+    /// Returns whether the resolution trace is enabled, `configured` unless overridden.
+    pub(crate) fn resolution_trace(&self, configured: bool) -> bool {
+        match self.inner.resolution_trace.load(Ordering::Relaxed) {
+            UNSET => configured,
+            state => state == ON,
+        }
+    }
+}
+
+/// The number of purges a service has applied to its derived caches.
+#[derive(Debug, Default)]
+pub(crate) struct Generation(Cell<usize>);
+
+impl Generation {
+    /// Returns the globs of the purges of `admin` not applied yet, marking them applied.
+    pub(crate) fn purges(&self, admin: &EmbedAdmin) -> Vec<Option<Glob>> {
+        let applied = self.0.get();
+        if admin.generation() == applied {
+            return Vec::new();
+        }
+        let purges = admin
+            .inner
+            .purges
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        self.0.set(purges.len());
+        purges[applied..].to_vec()
+    }
+}
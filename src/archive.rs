@@ -0,0 +1,241 @@
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use actix_web::web::Bytes;
+use futures_core::Stream;
+
+use crate::file_index::Visibility;
+use crate::shared::WarmState;
+
+/// The size of the blocks of tar archives.
+const BLOCK: usize = 512;
+
+/// The largest size of files fitting the octal size field of ustar headers, 8 GiB - 1.
+const MAX_OCTAL_SIZE: u64 = (1 << 33) - 1;
+
+/// The maximum size of the chunks of archive bodies, the files are split into chunks so
+/// large files are not held twice while they are sent.
+const CHUNK: usize = 64 << 10;
+
+/// Streams the files under a directory as a tar archive, see
+/// [Embed::archive](crate::Embed::archive).
+///
+/// Each poll yields at most one chunk, so the body is written as fast as the client reads
+/// it, and only the chunk being sent is buffered.
+pub(crate) struct TarStream<E> {
+    warm: Arc<WarmState>,
+    /// The paths of the files not yet sent.
+    paths: VecDeque<String>,
+    /// The length of the directory prefix removed from the paths.
+    skip: usize,
+    /// The remaining data of the file being sent.
+    current: Option<(Bytes, usize)>,
+    finished: bool,
+    _e: PhantomData<E>,
+}
+
+impl<E> TarStream<E>
+where
+    E: 'static + rust_embed::RustEmbed,
+{
+    /// Archives the files under `dir` shown by `visibility`, `""` being the root.
+    pub(crate) fn new(warm: Arc<WarmState>, dir: &str, visibility: &Visibility<'_>) -> Self {
+        let paths = warm
+            .index::<E>()
+            .visible_under(dir, visibility)
+            .cloned()
+            .collect();
+        let skip = match dir.trim_matches('/') {
+            "" => 0,
+            dir => dir.len() + 1,
+        };
+        TarStream {
+            warm,
+            paths,
+            skip,
+            current: None,
+            finished: false,
+            _e: PhantomData,
+        }
+    }
+
+    /// Returns the header and the data of the next file which can be archived.
+    fn next_file(&mut self) -> Option<(Bytes, Bytes)> {
+        while let Some(path) = self.paths.pop_front() {
+            let file = match self.warm.keys.get::<E>(&path) {
+                Some((_, file)) => file,
+                None => continue,
+            };
+            let header = header(&path[self.skip..], file.data.len() as u64);
+            let data = match file.data {
+                Cow::Borrowed(data) => Bytes::from_static(data),
+                Cow::Owned(data) => Bytes::from(data),
+            };
+            return Some((Bytes::from(header), data));
+        }
+        None
+    }
+
+    /// Returns the next chunk of the archive, `None` once it is complete.
+    fn next_chunk(&mut self) -> Option<Bytes> {
+        if let Some((data, offset)) = &mut self.current {
+            let end = (*offset + CHUNK).min(data.len());
+            let chunk = data.slice(*offset..end);
+            *offset = end;
+            if end < data.len() {
+                return Some(chunk);
+            }
+            // pad the file to a whole block
+            let padding = padding(data.len());
+            self.current = None;
+            if padding == 0 {
+                return Some(chunk);
+            }
+            let mut last = chunk.to_vec();
+            last.resize(last.len() + padding, 0);
+            return Some(Bytes::from(last));
+        }
+        if self.finished {
+            return None;
+        }
+        match self.next_file() {
+            Some((header, data)) => {
+                self.current = (!data.is_empty()).then_some((data, 0));
+                Some(header)
+            }
+            None => {
+                self.finished = true;
+                // the end of the archive, two empty blocks
+                Some(Bytes::from_static(&[0; 2 * BLOCK]))
+            }
+        }
+    }
+
+    /// Returns the whole archive, for clients which need its length up front, see
+    /// [Embed::http10_compat](crate::Embed::http10_compat).
+    pub(crate) fn into_bytes(mut self) -> Bytes {
+        let mut archive = Vec::new();
+        while let Some(chunk) = self.next_chunk() {
+            archive.extend_from_slice(&chunk);
+        }
+        Bytes::from(archive)
+    }
+}
+
+impl<E> Unpin for TarStream<E> {}
+
+impl<E> Stream for TarStream<E>
+where
+    E: 'static + rust_embed::RustEmbed,
+{
+    type Item = Result<Bytes, actix_web::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Poll::Ready(self.get_mut().next_chunk().map(Ok))
+    }
+}
+
+/// Returns the number of bytes padding a file of `len` bytes to a whole block.
+fn padding(len: usize) -> usize {
+    (BLOCK - len % BLOCK) % BLOCK
+}
+
+/// Returns the headers of a regular file named `name` with `size` bytes: a ustar header,
+/// preceded by a pax extended header if the name or the size do not fit it.
+///
+/// Modification times, owners and permissions are fixed, so archives of the same files are
+/// byte-identical.
+fn header(name: &str, size: u64) -> Vec<u8> {
+    let mut records = String::new();
+    let (prefix, name) = match split_name(name) {
+        Some(split) => split,
+        None => {
+            records.push_str(&pax_record("path", name));
+            ("", truncate(name, 100))
+        }
+    };
+    if size > MAX_OCTAL_SIZE {
+        records.push_str(&pax_record("size", &size.to_string()));
+    }
+
+    let mut headers = Vec::new();
+    if !records.is_empty() {
+        let len = records.len();
+        headers.extend_from_slice(&ustar("././@PaxHeader", "", len as u64, b'x'));
+        headers.extend_from_slice(records.as_bytes());
+        headers.resize(headers.len() + padding(len), 0);
+    }
+    headers.extend_from_slice(&ustar(name, prefix, size, b'0'));
+    headers
+}
+
+/// Splits `name` into the prefix and name fields of ustar headers, `None` if it does not
+/// fit them.
+fn split_name(name: &str) -> Option<(&str, &str)> {
+    if name.len() <= 100 {
+        return Some(("", name));
+    }
+    // split at a slash, with up to 155 bytes before it and 100 after it
+    let split = name
+        .match_indices('/')
+        .map(|(i, _)| i)
+        .find(|&i| i <= 155 && name.len() - i - 1 <= 100)?;
+    Some((&name[..split], &name[split + 1..]))
+}
+
+/// Returns the last bytes of `name`, at most `max`, at a character boundary.
+fn truncate(name: &str, max: usize) -> &str {
+    let mut start = name.len().saturating_sub(max);
+    while !name.is_char_boundary(start) {
+        start += 1;
+    }
+    &name[start..]
+}
+
+/// Returns the pax extended header record setting `key` to `value`, prefixed with its own
+/// length in bytes.
+fn pax_record(key: &str, value: &str) -> String {
+    let rest = key.len() + value.len() + 3;
+    let mut len = rest + 1;
+    // the length includes its own digits
+    while len != rest + len.to_string().len() {
+        len = rest + len.to_string().len();
+    }
+    format!("{} {}={}\n", len, key, value)
+}
+
+/// Returns the ustar header of an entry of type `kind` named `prefix/name` with `size`
+/// bytes, sizes too large for the octal field are encoded in base-256 as by GNU tar.
+fn ustar(name: &str, prefix: &str, size: u64, kind: u8) -> [u8; BLOCK] {
+    let mut header = [0; BLOCK];
+    let mut field = |offset: usize, value: &[u8]| {
+        header[offset..offset + value.len()].copy_from_slice(value);
+    };
+    field(0, name.as_bytes());
+    field(100, b"0000644\0");
+    field(108, b"0000000\0");
+    field(116, b"0000000\0");
+    if size <= MAX_OCTAL_SIZE {
+        field(124, format!("{:011o}\0", size).as_bytes());
+    } else {
+        let mut base256 = [0; 12];
+        base256[0] = 0x80;
+        base256[4..].copy_from_slice(&size.to_be_bytes());
+        field(124, &base256);
+    }
+    field(136, b"00000000000\0");
+    field(156, &[kind]);
+    field(257, b"ustar\0");
+    field(263, b"00");
+    field(345, prefix.as_bytes());
+
+    // the checksum is computed with the checksum field filled with spaces
+    header[148..156].copy_from_slice(b"        ");
+    let checksum = header.iter().map(|&b| u32::from(b)).sum::<u32>();
+    header[148..156].copy_from_slice(format!("{:06o}\0 ", checksum).as_bytes());
+    header
+}
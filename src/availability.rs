@@ -0,0 +1,114 @@
+use std::ops::{Bound, RangeBounds};
+use std::time::SystemTime;
+
+use crate::describe::Value;
+use crate::glob::Glob;
+use crate::http_util;
+
+/// Clocks tell the time availability windows are evaluated at, see
+/// [Embed::clock](crate::Embed::clock).
+///
+/// Closures returning a [SystemTime] are clocks, e.g. to serve files as at a fixed time in
+/// tests.
+pub trait Clock: 'static {
+    /// Returns the current time.
+    fn now(&self) -> SystemTime;
+}
+
+impl<T> Clock for T
+where
+    T: Fn() -> SystemTime + 'static,
+{
+    fn now(&self) -> SystemTime {
+        (self)()
+    }
+}
+
+/// Whether a file is served at a point in time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Window {
+    /// Before the start of a window, the file is served as if it was missing.
+    Before,
+    Open,
+    /// After the end of a window, the file is answered with `410 Gone`.
+    After,
+}
+
+/// The files matching `glob` served from `from` until `until`, see
+/// [Embed::available](crate::Embed::available).
+#[derive(Debug, Clone)]
+pub(crate) struct Availability {
+    pub(crate) glob: Glob,
+    from: Bound<SystemTime>,
+    until: Bound<SystemTime>,
+}
+
+impl Availability {
+    pub(crate) fn new<R: RangeBounds<SystemTime>>(glob: Glob, window: R) -> Self {
+        Availability {
+            glob,
+            from: window.start_bound().cloned(),
+            until: window.end_bound().cloned(),
+        }
+    }
+
+    /// Returns `true` if no point in time is within the window.
+    pub(crate) fn is_empty(&self) -> bool {
+        match (self.from, self.until) {
+            (Bound::Included(from), Bound::Included(until)) => from > until,
+            (Bound::Included(from) | Bound::Excluded(from), Bound::Excluded(until))
+            | (Bound::Excluded(from), Bound::Included(until)) => from >= until,
+            _ => false,
+        }
+    }
+
+    pub(crate) fn window(&self, now: SystemTime) -> Window {
+        let started = match self.from {
+            Bound::Included(from) => now >= from,
+            Bound::Excluded(from) => now > from,
+            Bound::Unbounded => true,
+        };
+        let ended = match self.until {
+            Bound::Included(until) => now > until,
+            Bound::Excluded(until) => now >= until,
+            Bound::Unbounded => false,
+        };
+        match (started, ended) {
+            (_, true) => Window::After,
+            (false, false) => Window::Before,
+            (true, false) => Window::Open,
+        }
+    }
+
+    pub(crate) fn describe(&self) -> Value {
+        let bound = |bound: Bound<SystemTime>| match bound {
+            Bound::Included(time) | Bound::Excluded(time) => {
+                Value::from(Some(http_util::http_date(time)))
+            }
+            Bound::Unbounded => Value::from(None::<String>),
+        };
+        Value::object([
+            ("glob", self.glob.as_str().into()),
+            ("from", bound(self.from)),
+            ("until", bound(self.until)),
+        ])
+    }
+}
+
+/// Returns whether the file at `path` is served at `now`: it is gone once a window of the
+/// rules matching it ended, and missing until all of them started.
+pub(crate) fn window(rules: &[Availability], path: &str, now: SystemTime) -> Window {
+    let windows = rules
+        .iter()
+        .filter(|rule| rule.glob.matches(path))
+        .map(|rule| rule.window(now));
+    let mut result = Window::Open;
+    for window in windows {
+        match window {
+            Window::After => return Window::After,
+            Window::Before => result = Window::Before,
+            Window::Open => {}
+        }
+    }
+    result
+}
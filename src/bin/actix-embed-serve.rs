@@ -0,0 +1,86 @@
+//! Serves a folder embedded at build time, to smoke-test asset bundles outside of an
+//! application.
+//!
+//! The folder is read from the `ACTIX_EMBED_SERVE_DIR` environment variable when building,
+//! relative to the crate root, and defaults to the test data of this crate:
+//!
+//! ```sh
+//! ACTIX_EMBED_SERVE_DIR=/path/to/dist cargo run --features bin --bin actix-embed-serve
+//! ```
+//!
+//! The address to listen on is read from `ACTIX_EMBED_SERVE_ADDR` when starting, and defaults
+//! to `127.0.0.1:8080`. Each request is logged to stderr.
+
+use actix_embed::{Embed, ServeMetrics, SharedState, VersionInfo};
+use actix_web::{App, HttpServer};
+use rust_embed::RustEmbed;
+
+#[derive(RustEmbed)]
+#[folder = "${ACTIX_EMBED_SERVE_DIR:-testdata/}"]
+struct Assets;
+
+#[actix_web::main]
+async fn main() -> std::io::Result<()> {
+    let addr =
+        std::env::var("ACTIX_EMBED_SERVE_ADDR").unwrap_or_else(|_| "127.0.0.1:8080".to_owned());
+    let state = SharedState::new();
+    let metrics = ServeMetrics::new();
+
+    let embed = {
+        let state = state.clone();
+        let metrics = metrics.clone();
+        move || {
+            let embed = Embed::new("/", &Assets)
+                .index_file("index.html")
+                .fonts_preset()
+                .suggestions(3)
+                .responsive_images(true)
+                .variant_cache(16)
+                .version_endpoint("/__version", VersionInfo::new())
+                .metrics(metrics.clone())
+                .shared_state(&state)
+                .on_serve(|record| {
+                    eprintln!(
+                        "{} {} {} {:?}",
+                        record.method,
+                        record.path.as_deref().unwrap_or("-"),
+                        record.status.as_u16(),
+                        record.duration
+                    )
+                });
+            #[cfg(feature = "minify")]
+            let embed = embed.minify(true).serve_originals(true);
+            #[cfg(feature = "compress-brotli")]
+            let embed = embed.precompress(
+                actix_embed::Encoding::Brotli,
+                11,
+                ["**/*.html", "**/*.css", "**/*.js", "**/*.svg"],
+            );
+            #[cfg(feature = "compress-gzip")]
+            let embed = embed.precompress(
+                actix_embed::Encoding::Gzip,
+                9,
+                ["**/*.html", "**/*.css", "**/*.js", "**/*.svg"],
+            );
+            embed
+        }
+    };
+
+    eprintln!(
+        "serving {} files on http://{}",
+        Assets::iter().count(),
+        addr
+    );
+    eprintln!("configuration: {}", embed().describe());
+    HttpServer::new(move || App::new().service(embed()))
+        .bind(addr)?
+        .run()
+        .await?;
+
+    let snapshot = metrics.snapshot();
+    eprintln!(
+        "served {} files, {} not modified, {} fallbacks, {} bytes",
+        snapshot.ok, snapshot.not_modified, snapshot.fallback, snapshot.bytes_served
+    );
+    Ok(())
+}
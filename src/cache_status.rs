@@ -0,0 +1,61 @@
+use actix_web::http::header::{HeaderMap, HeaderName, HeaderValue};
+
+/// The `Cache-Status` header of RFC 9211.
+const CACHE_STATUS: HeaderName = HeaderName::from_static("cache-status");
+
+/// The `CDN-Cache-Control` header of RFC 9213, see
+/// [Embed::cdn_cache_control](crate::Embed::cdn_cache_control).
+pub(crate) const CDN_CACHE_CONTROL: HeaderName = HeaderName::from_static("cdn-cache-control");
+
+/// The identifier of the variant cache in `Cache-Status`, see
+/// [Embed::variant_cache](crate::Embed::variant_cache).
+const VARIANT_CACHE: &str = "actix-embed";
+
+/// The identifier of the cache of prepared files in `Cache-Status`, see
+/// [Embed::derived_cache](crate::Embed::derived_cache).
+const DERIVED_CACHE: &str = "actix-embed-derived";
+
+/// How the caches of a service took part in a response, see
+/// [Embed::cache_status](crate::Embed::cache_status).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CacheStatus {
+    /// The response was sent from the variant cache.
+    VariantHit,
+    /// The response was generated for the request.
+    Generated {
+        /// Whether the file was already prepared.
+        derived_hit: bool,
+        /// Whether the response was stored in the variant cache, `None` without one.
+        stored: Option<bool>,
+    },
+}
+
+impl CacheStatus {
+    /// Appends the members of the caches to the `Cache-Status` of `headers`, the cache closest
+    /// to the embedded files first.
+    pub(crate) fn apply(self, headers: &mut HeaderMap) {
+        let value = match self {
+            CacheStatus::VariantHit => format!("{}; hit", VARIANT_CACHE),
+            CacheStatus::Generated {
+                derived_hit,
+                stored,
+            } => {
+                let mut value = match derived_hit {
+                    true => format!("{}; hit", DERIVED_CACHE),
+                    false => format!("{}; fwd=miss; stored", DERIVED_CACHE),
+                };
+                match stored {
+                    Some(true) => {
+                        value.push_str(&format!(", {}; fwd=uri-miss; stored", VARIANT_CACHE))
+                    }
+                    Some(false) => value.push_str(&format!(", {}; fwd=uri-miss", VARIANT_CACHE)),
+                    None => {}
+                }
+                value
+            }
+        };
+        if let Ok(value) = HeaderValue::from_str(&value) {
+            headers.append(CACHE_STATUS, value);
+        }
+    }
+}
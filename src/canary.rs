@@ -0,0 +1,129 @@
+use std::collections::hash_map::RandomState;
+use std::fmt::{Debug, Formatter};
+use std::hash::BuildHasher;
+use std::rc::Rc;
+
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{self, HeaderValue};
+use actix_web::{HttpRequest, HttpResponse};
+use futures_core::future::LocalBoxFuture;
+
+use crate::fallback_handler::FallbackHandler;
+use crate::http_util;
+use crate::language;
+use crate::options::EmbedOptions;
+
+/// The name of the cookie keeping sessions in their cohort.
+const COOKIE: &str = "embed-canary";
+
+/// The service serving the files of the canary bundle.
+pub(crate) type CanaryService = Rc<
+    dyn Fn(ServiceRequest) -> LocalBoxFuture<'static, Result<ServiceResponse, actix_web::Error>>,
+>;
+
+/// Creates the [CanaryService] of a bundle from the options of the stable service.
+pub(crate) type CanaryFactory =
+    fn(EmbedOptions, ErasedFallback) -> LocalBoxFuture<'static, Result<CanaryService, ()>>;
+
+/// An alternative bundle served to a share of the sessions, see
+/// [Embed::canary](crate::Embed::canary).
+#[derive(Clone)]
+pub(crate) struct Canary {
+    pub(crate) percent: u8,
+    /// The name of the type of the embedded files of the bundle.
+    pub(crate) name: &'static str,
+    pub(crate) factory: CanaryFactory,
+}
+
+impl Debug for Canary {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Canary")
+            .field("percent", &self.percent)
+            .field("name", &self.name)
+            .finish()
+    }
+}
+
+/// The fallback handler of the stable service, shared with the canary service whose type
+/// cannot name it.
+#[derive(Clone)]
+pub(crate) struct ErasedFallback(Rc<dyn DynFallback>);
+
+/// The object-safe part of [FallbackHandler].
+trait DynFallback {
+    fn execute(&self, req: &HttpRequest) -> HttpResponse;
+    fn execute_async(&self, req: HttpRequest) -> LocalBoxFuture<'static, HttpResponse>;
+}
+
+impl<F: FallbackHandler> DynFallback for F {
+    fn execute(&self, req: &HttpRequest) -> HttpResponse {
+        FallbackHandler::execute(self, req)
+    }
+
+    fn execute_async(&self, req: HttpRequest) -> LocalBoxFuture<'static, HttpResponse> {
+        FallbackHandler::execute_async(self, req)
+    }
+}
+
+impl ErasedFallback {
+    pub(crate) fn new<F: FallbackHandler>(handler: F) -> Self {
+        ErasedFallback(Rc::new(handler))
+    }
+}
+
+impl FallbackHandler for ErasedFallback {
+    fn execute(&self, req: &HttpRequest) -> HttpResponse {
+        self.0.execute(req)
+    }
+
+    fn execute_async(&self, req: HttpRequest) -> LocalBoxFuture<'static, HttpResponse> {
+        self.0.execute_async(req)
+    }
+}
+
+/// The cohort of the session of a request.
+#[derive(Debug)]
+pub(crate) struct Cohort {
+    pub(crate) canary: bool,
+    /// Whether the session was just assigned to the cohort, so it is remembered in a cookie.
+    assigned: bool,
+    /// The path of the cookie.
+    path: String,
+}
+
+impl Cohort {
+    /// Returns the cohort remembered by the cookie of `req`, or assigns one, with `percent`
+    /// percent of the sessions served by the canary bundle.
+    pub(crate) fn of(req: &HttpRequest, percent: u8, path: String) -> Self {
+        let remembered = match language::cookie(req, COOKIE) {
+            Some("canary") => Some(true),
+            Some("stable") => Some(false),
+            _ => None,
+        };
+        Cohort {
+            canary: remembered.unwrap_or_else(|| roll() < percent),
+            assigned: remembered.is_none(),
+            path,
+        }
+    }
+
+    /// Marks `resp` as depending on the cohort, and remembers a newly assigned cohort.
+    pub(crate) fn apply(&self, resp: &mut ServiceResponse) {
+        let headers = resp.headers_mut();
+        headers.append(header::VARY, HeaderValue::from_static("Cookie"));
+        if self.assigned {
+            let name = if self.canary { "canary" } else { "stable" };
+            let cookie = format!(
+                "{}={}; Path={}; Max-Age=2592000; SameSite=Lax",
+                COOKIE, name, self.path
+            );
+            headers.append(header::SET_COOKIE, http_util::header_value(&cookie));
+        }
+    }
+}
+
+/// Returns a number from 0 to 99, uniformly distributed enough to split sessions.
+fn roll() -> u8 {
+    // every `RandomState` is seeded differently
+    (RandomState::new().hash_one(()) % 100) as u8
+}
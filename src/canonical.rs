@@ -0,0 +1,59 @@
+use std::fmt::{Display, Formatter};
+
+use actix_web::HttpRequest;
+
+use crate::forwarded::ForwardedPolicy;
+
+/// URL schemes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scheme {
+    /// `http`
+    Http,
+    /// `https`
+    Https,
+}
+
+impl Scheme {
+    /// Returns the scheme as it appears in URLs.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Scheme::Http => "http",
+            Scheme::Https => "https",
+        }
+    }
+}
+
+impl Display for Scheme {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The host and scheme all requests are redirected to.
+#[derive(Debug, Clone)]
+pub(crate) struct CanonicalHost {
+    pub(crate) host: String,
+    pub(crate) scheme: Scheme,
+}
+
+impl CanonicalHost {
+    /// Returns the URL the request should be redirected to, `None` if the request already
+    /// uses the canonical host and scheme.
+    pub(crate) fn redirect_url(
+        &self,
+        req: &HttpRequest,
+        policy: &ForwardedPolicy,
+    ) -> Option<String> {
+        let origin = policy.origin(req);
+        if origin.host.eq_ignore_ascii_case(&self.host) && origin.scheme == self.scheme.as_str() {
+            return None;
+        }
+
+        let path = req
+            .uri()
+            .path_and_query()
+            .map(|path| path.as_str())
+            .unwrap_or("/");
+        Some(format!("{}://{}{}", self.scheme, self.host, path))
+    }
+}
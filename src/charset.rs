@@ -0,0 +1,60 @@
+use actix_web::http::header;
+use actix_web::HttpRequest;
+
+/// Media types outside `text/*` whose content is text, served as UTF-8.
+const TEXT_TYPES: &[&str] = &[
+    "application/javascript",
+    "application/json",
+    "application/manifest+json",
+    "application/xml",
+    "image/svg+xml",
+];
+
+/// Returns `true` if files with `content_type` are text.
+pub(crate) fn is_text(content_type: &str) -> bool {
+    let mime = content_type.split(';').next().unwrap_or("").trim();
+    mime.starts_with("text/") || TEXT_TYPES.contains(&mime)
+}
+
+/// Returns `content_type` with the UTF-8 charset if it is text and has no charset yet.
+pub(crate) fn with_utf8(content_type: String) -> String {
+    if is_text(&content_type) && !content_type.contains("charset=") {
+        format!("{}; charset=utf-8", content_type)
+    } else {
+        content_type
+    }
+}
+
+/// Returns `false` if `req` has an `Accept-Charset` header excluding UTF-8.
+///
+/// UTF-8 is acceptable if it is listed with a non-zero quality, or if it is not listed and
+/// `*` is, see RFC 9110 section 12.5.2.
+pub(crate) fn accepts_utf8(req: &HttpRequest) -> bool {
+    let mut values = req
+        .headers()
+        .get_all(header::ACCEPT_CHARSET)
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .peekable();
+    if values.peek().is_none() {
+        return true;
+    }
+    let mut wildcard = None;
+    for item in values {
+        let mut parts = item.split(';');
+        let charset = parts.next().unwrap_or("").trim();
+        let quality = parts
+            .filter_map(|param| param.trim().strip_prefix("q="))
+            .find_map(|q| q.trim().parse::<f32>().ok())
+            .unwrap_or(1.0);
+        if charset.eq_ignore_ascii_case("utf-8") || charset.eq_ignore_ascii_case("utf8") {
+            return quality > 0.0;
+        }
+        if charset == "*" {
+            wildcard = Some(quality > 0.0);
+        }
+    }
+    wildcard.unwrap_or(false)
+}
@@ -0,0 +1,46 @@
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use actix_web::web::Bytes;
+use futures_core::Stream;
+
+/// The default size of the chunks file bodies are streamed in, see
+/// [Embed::stream_chunk_size](crate::Embed::stream_chunk_size).
+///
+/// A TLS record carries at most 16 KiB, so chunks of whole records are not split into a
+/// partially filled record at each chunk boundary.
+pub(crate) const DEFAULT_CHUNK: usize = 4 * (16 << 10);
+
+/// A body yielded in chunks of at most `size` bytes, each a slice of the body, so it is not
+/// copied into a single write buffer at once.
+pub(crate) struct Chunked {
+    remaining: Bytes,
+    size: usize,
+}
+
+impl Chunked {
+    pub(crate) fn new(body: Bytes, size: usize) -> Self {
+        Chunked {
+            remaining: body,
+            size: size.max(1),
+        }
+    }
+}
+
+impl Stream for Chunked {
+    type Item = Result<Bytes, actix_web::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if this.remaining.is_empty() {
+            return Poll::Ready(None);
+        }
+        let len = this.size.min(this.remaining.len());
+        Poll::Ready(Some(Ok(this.remaining.split_to(len))))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let chunks = self.remaining.len().div_ceil(self.size);
+        (chunks, Some(chunks))
+    }
+}
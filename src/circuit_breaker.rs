@@ -0,0 +1,192 @@
+use std::cell::Cell;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use actix_web::{HttpRequest, HttpResponse};
+use futures_core::future::LocalBoxFuture;
+
+use crate::fallback_handler::FallbackHandler;
+
+/// A fallback handler passing misses to an upstream handler, which fails fast to a local
+/// handler while the upstream handler keeps failing.
+///
+/// The upstream handler is awaited with [execute_async](FallbackHandler::execute_async), e.g.
+/// forwarding misses to a development server, for at most [timeout](Self::timeout). Server
+/// error responses and timeouts are failures, answered by the local handler instead. After
+/// [failure_threshold](Self::failure_threshold) consecutive failures the circuit opens:
+/// misses are passed to the local handler only, without calling the upstream handler, so
+/// they fail fast instead of waiting for a server which is down. Once
+/// [open_for](Self::open_for) has elapsed, the next miss probes the upstream handler again,
+/// closing the circuit if it succeeds and opening it again if it fails. Other misses are
+/// passed to the local handler while the probe is pending.
+///
+/// Called synchronously with [execute](FallbackHandler::execute), e.g. by handlers wrapping
+/// it, the breaker calls the upstream handler synchronously, without timing it out.
+///
+/// Clones share the state of the circuit.
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+///
+/// use actix_embed::{CircuitBreaker, DefaultFallbackHandler, Embed, FallbackHandler};
+/// use actix_web::{HttpRequest, HttpResponse};
+/// use futures_core::future::LocalBoxFuture;
+/// use rust_embed::RustEmbed;
+///
+/// #[derive(RustEmbed)]
+/// #[folder = "testdata/"]
+/// struct Assets;
+///
+/// #[derive(Clone)]
+/// struct DevServer;
+///
+/// impl FallbackHandler for DevServer {
+///     fn execute(&self, _: &HttpRequest) -> HttpResponse {
+///         HttpResponse::BadGateway().finish()
+///     }
+///
+///     fn execute_async(&self, req: HttpRequest) -> LocalBoxFuture<'static, HttpResponse> {
+///         Box::pin(async move {
+///             // forward `req` to the development server
+///             HttpResponse::BadGateway().finish()
+///         })
+///     }
+/// }
+///
+/// let embed = Embed::new("/", &Assets).fallback_handler(
+///     CircuitBreaker::new(DevServer, DefaultFallbackHandler::new())
+///         .failure_threshold(3)
+///         .open_for(Duration::from_secs(10))
+///         .timeout(Duration::from_secs(2)),
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct CircuitBreaker<U, L> {
+    upstream: U,
+    local: L,
+    threshold: u32,
+    open_for: Duration,
+    timeout: Duration,
+    state: Rc<Cell<State>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum State {
+    /// The upstream handler is called, after the given number of consecutive failures.
+    Closed(u32),
+    /// The local handler is called until the instant.
+    Open(Instant),
+}
+
+impl<U, L> CircuitBreaker<U, L>
+where
+    U: FallbackHandler,
+    L: FallbackHandler,
+{
+    /// Create a new [CircuitBreaker] passing misses to `upstream`, or to `local` while the
+    /// circuit is open.
+    pub fn new(upstream: U, local: L) -> Self {
+        CircuitBreaker {
+            upstream,
+            local,
+            threshold: 5,
+            open_for: Duration::from_secs(30),
+            timeout: Duration::from_secs(10),
+            state: Rc::new(Cell::new(State::Closed(0))),
+        }
+    }
+
+    /// Set the number of consecutive failures opening the circuit.
+    ///
+    /// Defaults to `5`, `0` is treated as `1`.
+    pub fn failure_threshold(mut self, failures: u32) -> Self {
+        self.threshold = failures.max(1);
+        self
+    }
+
+    /// Set how long the circuit stays open before the upstream is probed again.
+    ///
+    /// Defaults to 30 seconds.
+    pub fn open_for(mut self, duration: Duration) -> Self {
+        self.open_for = duration;
+        self
+    }
+
+    /// Set how long the upstream handler is awaited before the miss counts as a failure.
+    ///
+    /// Defaults to 10 seconds.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Returns `true` if misses are currently passed to the local handler only.
+    pub fn is_open(&self) -> bool {
+        matches!(self.state.get(), State::Open(until) if Instant::now() < until)
+    }
+}
+
+impl<U, L> FallbackHandler for CircuitBreaker<U, L>
+where
+    U: FallbackHandler,
+    L: FallbackHandler,
+{
+    fn execute(&self, req: &HttpRequest) -> HttpResponse {
+        let failures = match self.state.get() {
+            State::Open(until) if Instant::now() < until => return self.local.execute(req),
+            // the probe of a half-open circuit opens it again if it fails
+            State::Open(_) => self.threshold - 1,
+            State::Closed(failures) => failures,
+        };
+        let resp = self.upstream.execute(req);
+        if !resp.status().is_server_error() {
+            self.state.set(State::Closed(0));
+            return resp;
+        }
+        self.fail(failures);
+        self.local.execute(req)
+    }
+
+    fn execute_async(&self, req: HttpRequest) -> LocalBoxFuture<'static, HttpResponse> {
+        let breaker = self.clone();
+        Box::pin(async move {
+            match breaker.state.get() {
+                State::Open(until) if Instant::now() < until => return breaker.local.execute(&req),
+                // other misses go to the local handler while the probe is pending
+                State::Open(_) => breaker.open(),
+                State::Closed(_) => {}
+            }
+            let upstream = breaker.upstream.execute_async(req.clone());
+            let resp = actix_web::rt::time::timeout(breaker.timeout, upstream).await;
+            match resp {
+                Ok(resp) if !resp.status().is_server_error() => {
+                    breaker.state.set(State::Closed(0));
+                    return resp;
+                }
+                _ => {}
+            }
+            // the state is read again, since other misses may have failed meanwhile
+            let failures = match breaker.state.get() {
+                State::Open(_) => breaker.threshold - 1,
+                State::Closed(failures) => failures,
+            };
+            breaker.fail(failures);
+            breaker.local.execute(&req)
+        })
+    }
+}
+
+impl<U, L> CircuitBreaker<U, L> {
+    /// Records a failure after `failures` consecutive failures.
+    fn fail(&self, failures: u32) {
+        match failures + 1 {
+            failures if failures < self.threshold => self.state.set(State::Closed(failures)),
+            _ => self.open(),
+        }
+    }
+
+    fn open(&self) {
+        self.state.set(State::Open(Instant::now() + self.open_for));
+    }
+}
@@ -0,0 +1,133 @@
+//! Generation of Rust constants holding the content-addressed URLs of embedded files, for use
+//! in build scripts.
+//!
+//! Server-rendered templates reference the [content-addressed](crate::Embed::content_addressed)
+//! URLs of assets through the generated constants, so a renamed or removed asset fails the
+//! build instead of producing a broken link. The URLs are computed the same way as
+//! [Embed::content_addresses](crate::Embed::content_addresses) does at runtime, so both stay
+//! in sync as long as the folder and the mount path match those of the [Embed](crate::Embed).
+//!
+//! # Examples
+//! In `build.rs`, with `actix-embed` as a build dependency:
+//! ```
+//! use actix_embed::codegen::UrlConstants;
+//!
+//! # std::env::set_var("OUT_DIR", std::env::temp_dir());
+//! let out_dir = std::env::var("OUT_DIR").unwrap();
+//! UrlConstants::new("testdata/", "/static")
+//!     .file("assets/index.css")
+//!     .file("index.html")
+//!     .write(std::path::Path::new(&out_dir).join("assets.rs"))
+//!     .unwrap();
+//! ```
+//!
+//! In the application:
+//! ```ignore
+//! mod assets {
+//!     include!(concat!(env!("OUT_DIR"), "/assets.rs"));
+//! }
+//!
+//! let stylesheet = assets::ASSETS_INDEX_CSS_URL;
+//! ```
+
+use std::collections::HashSet;
+use std::fmt::Write as _;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+
+use crate::mount_registry;
+
+/// Generates a Rust module with a `pub const <NAME>_URL: &str` per selected file, holding its
+/// content-addressed URL, e.g. `ASSETS_INDEX_CSS_URL` for `assets/index.css`.
+///
+/// Constant names are the paths in upper case, with characters not allowed in identifiers
+/// replaced by `_`.
+#[derive(Debug, Clone)]
+pub struct UrlConstants {
+    folder: PathBuf,
+    mount_path: String,
+    files: Vec<String>,
+}
+
+impl UrlConstants {
+    /// Create new [UrlConstants] for the files in `folder`, the folder given to
+    /// `#[folder = "..."]` of the embedded files, served at `mount_path`.
+    ///
+    /// Relative folders are resolved against the working directory, which is the root of the
+    /// crate for build scripts.
+    pub fn new<F: Into<PathBuf>, M: AsRef<str>>(folder: F, mount_path: M) -> Self {
+        UrlConstants {
+            folder: folder.into(),
+            mount_path: mount_registry::normalize(mount_path.as_ref()),
+            files: Vec::new(),
+        }
+    }
+
+    /// Add a constant for the file at `path` in the folder.
+    pub fn file<P: AsRef<str>>(mut self, path: P) -> Self {
+        self.files
+            .push(path.as_ref().trim_start_matches('/').to_owned());
+        self
+    }
+
+    /// Returns the source of the generated module.
+    ///
+    /// Fails if a file cannot be read, or if two paths map to the same constant name.
+    pub fn generate(&self) -> io::Result<String> {
+        let mut source = String::from("// Generated by actix-embed, do not edit.\n");
+        let mut names = HashSet::new();
+        for path in &self.files {
+            let name = constant_name(path);
+            if !names.insert(name.clone()) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("`{}` maps to the constant `{}` of another file", path, name),
+                ));
+            }
+            let data = std::fs::read(self.folder.join(path)).map_err(|err| {
+                io::Error::new(err.kind(), format!("cannot read `{}`: {}", path, err))
+            })?;
+            let hash = hex::encode(Sha256::digest(&data));
+            // the same URL as content_address::url
+            let url = format!("{}/_by-hash/{}/{}", self.mount_path, hash, path);
+            let _ = write!(
+                source,
+                "\n/// The content-addressed URL of `{}`.\npub const {}: &str = {:?};\n",
+                path, name, url
+            );
+        }
+        Ok(source)
+    }
+
+    /// Write the generated module to `out`, and tell Cargo to rerun the build script when
+    /// one of the files changes.
+    pub fn write<P: AsRef<Path>>(&self, out: P) -> io::Result<()> {
+        let source = self.generate()?;
+        std::fs::write(out, source)?;
+        for path in &self.files {
+            println!(
+                "cargo:rerun-if-changed={}",
+                self.folder.join(path).display()
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Returns the name of the constant for the file at `path`.
+fn constant_name(path: &str) -> String {
+    let mut name = path
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' => c.to_ascii_uppercase(),
+            _ => '_',
+        })
+        .collect::<String>();
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        name.insert(0, '_');
+    }
+    name.push_str("_URL");
+    name
+}
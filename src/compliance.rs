@@ -0,0 +1,398 @@
+//! Conformance checks for conditional requests and deterministic responses.
+//!
+//! The conditional request checks encode the decision table of RFC 9110 section 13.2.2 and
+//! run it against a configured [Embed], so applications can verify their configuration still
+//! handles conditional requests per spec.
+//!
+//! The determinism checks compare responses of independently created services, so
+//! applications can verify replicas and restarted workers serve byte-identical responses
+//! with the same entity tags, which caches rely on for revalidation.
+//!
+//! # Examples
+//! ```
+//! use actix_embed::{compliance, Embed};
+//! use rust_embed::RustEmbed;
+//!
+//! #[derive(RustEmbed)]
+//! #[folder = "testdata/"]
+//! struct Assets;
+//!
+//! # actix_web::rt::System::new().block_on(async {
+//! let report =
+//!     compliance::check_conditional_requests(Embed::new("/static", &Assets), "/static/index.html")
+//!         .await;
+//! report.assert_compliant();
+//! # });
+//! ```
+
+use std::cell::RefCell;
+use std::fmt::{Display, Formatter};
+use std::rc::Rc;
+
+use actix_web::dev::ServiceResponse;
+use actix_web::http::header::{self, HeaderValue};
+use actix_web::http::StatusCode;
+use actix_web::test::{self, TestRequest};
+use actix_web::App;
+use sha2::{Digest, Sha256};
+
+use crate::decision::Decision;
+use crate::{Embed, FallbackHandler};
+
+/// A single failed check.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ComplianceFailure {
+    /// The name of the check.
+    pub case: &'static str,
+    /// What the check expected.
+    pub expected: String,
+    /// What the service actually did.
+    pub actual: String,
+}
+
+impl Display for ComplianceFailure {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: expected {}, got {}",
+            self.case, self.expected, self.actual
+        )
+    }
+}
+
+/// The outcome of running the checks.
+#[derive(Debug, Clone, Default)]
+pub struct ComplianceReport {
+    /// The names of the checks that passed.
+    pub passed: Vec<&'static str>,
+    /// The checks that failed.
+    pub failures: Vec<ComplianceFailure>,
+}
+
+impl ComplianceReport {
+    /// Returns `true` if no check failed.
+    pub fn is_compliant(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    /// Panics with the list of failures if any check failed.
+    pub fn assert_compliant(&self) {
+        if !self.is_compliant() {
+            let failures = self
+                .failures
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join("\n  ");
+            panic!("compliance checks failed:\n  {}", failures);
+        }
+    }
+
+    fn check(&mut self, case: &'static str, expected: impl Display, actual: impl Display) {
+        let (expected, actual) = (expected.to_string(), actual.to_string());
+        if expected == actual {
+            self.passed.push(case);
+        } else {
+            self.failures.push(ComplianceFailure {
+                case,
+                expected,
+                actual,
+            });
+        }
+    }
+}
+
+/// The kind of validator a case is built from.
+#[derive(Clone, Copy)]
+enum Validator {
+    /// The current entity tag, e.g. `"abc"`.
+    Current,
+    /// The current entity tag marked weak, e.g. `W/"abc"`.
+    Weak,
+    /// A list containing the current entity tag.
+    InList,
+    /// The wildcard `*`.
+    Any,
+    /// An entity tag which never matches.
+    Other,
+}
+
+impl Validator {
+    fn format(self, etag: &str) -> String {
+        match self {
+            Validator::Current => etag.to_owned(),
+            Validator::Weak => format!("W/{}", etag),
+            Validator::InList => format!("\"actix-embed-other\", {}", etag),
+            Validator::Any => "*".to_owned(),
+            Validator::Other => "\"actix-embed-other\"".to_owned(),
+        }
+    }
+}
+
+/// `(case, if-match, if-none-match, expected status)`
+const DECISION_TABLE: &[(&str, Option<Validator>, Option<Validator>, StatusCode)] = &[
+    (
+        "if-none-match: current",
+        None,
+        Some(Validator::Current),
+        StatusCode::NOT_MODIFIED,
+    ),
+    (
+        "if-none-match: weak comparison",
+        None,
+        Some(Validator::Weak),
+        StatusCode::NOT_MODIFIED,
+    ),
+    (
+        "if-none-match: list",
+        None,
+        Some(Validator::InList),
+        StatusCode::NOT_MODIFIED,
+    ),
+    (
+        "if-none-match: any",
+        None,
+        Some(Validator::Any),
+        StatusCode::NOT_MODIFIED,
+    ),
+    (
+        "if-none-match: other",
+        None,
+        Some(Validator::Other),
+        StatusCode::OK,
+    ),
+    (
+        "if-match: current",
+        Some(Validator::Current),
+        None,
+        StatusCode::OK,
+    ),
+    (
+        "if-match: list",
+        Some(Validator::InList),
+        None,
+        StatusCode::OK,
+    ),
+    ("if-match: any", Some(Validator::Any), None, StatusCode::OK),
+    (
+        "if-match: strong comparison",
+        Some(Validator::Weak),
+        None,
+        StatusCode::PRECONDITION_FAILED,
+    ),
+    (
+        "if-match: other",
+        Some(Validator::Other),
+        None,
+        StatusCode::PRECONDITION_FAILED,
+    ),
+    (
+        "if-match is evaluated before if-none-match",
+        Some(Validator::Other),
+        Some(Validator::Current),
+        StatusCode::PRECONDITION_FAILED,
+    ),
+    (
+        "if-none-match is evaluated after a passing if-match",
+        Some(Validator::Current),
+        Some(Validator::Current),
+        StatusCode::NOT_MODIFIED,
+    ),
+];
+
+/// Runs the conditional request checks against `embed` for the file served at `path`.
+///
+/// `path` is the full request path, including the mount path, of an existing file.
+///
+/// This function must be called within an actix runtime, e.g. in an `#[actix_web::test]`.
+pub async fn check_conditional_requests<E, F>(embed: Embed<E, F>, path: &str) -> ComplianceReport
+where
+    E: 'static + rust_embed::RustEmbed,
+    F: FallbackHandler,
+{
+    let srv = test::init_service(App::new().service(embed)).await;
+    let mut report = ComplianceReport::default();
+
+    let resp = test::call_service(&srv, TestRequest::get().uri(path).to_request()).await;
+    report.check("unconditional request", StatusCode::OK, resp.status());
+    let etag = match resp.headers().get(header::ETAG).map(HeaderValue::to_str) {
+        Some(Ok(etag)) => etag.to_owned(),
+        _ => {
+            report.check("representation has an entity tag", "ETag", "none");
+            return report;
+        }
+    };
+
+    for &(case, if_match, if_none_match, expected) in DECISION_TABLE {
+        let mut req = TestRequest::get().uri(path);
+        if let Some(validator) = if_match {
+            req = req.insert_header((header::IF_MATCH, validator.format(&etag)));
+        }
+        if let Some(validator) = if_none_match {
+            req = req.insert_header((header::IF_NONE_MATCH, validator.format(&etag)));
+        }
+        let resp = test::call_service(&srv, req.to_request()).await;
+        report.check(case, expected, resp.status());
+
+        if resp.status() == StatusCode::NOT_MODIFIED {
+            let actual = resp
+                .headers()
+                .get(header::ETAG)
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or("none");
+            report.check("not modified responses carry the entity tag", &etag, actual);
+        }
+    }
+
+    report
+}
+
+/// The `Accept-Encoding` values each path is requested with by
+/// [check_deterministic_responses].
+const ACCEPT_ENCODINGS: &[&str] = &["identity", "gzip", "br", "br, gzip"];
+
+/// Checks that two services created by `build` serve byte-identical responses for `paths`.
+///
+/// Each path is requested with several `Accept-Encoding` values, and the status, `ETag`,
+/// `Content-Encoding` and body of the responses are compared. Derived artifacts, like
+/// compressed or minified variants, are created independently by each service, as they are by
+/// each worker and replica of an application.
+///
+/// `paths` are full request paths, including the mount path.
+///
+/// This function must be called within an actix runtime, e.g. in an `#[actix_web::test]`.
+///
+/// # Examples
+/// ```
+/// use actix_embed::{compliance, Embed};
+/// use rust_embed::RustEmbed;
+///
+/// #[derive(RustEmbed)]
+/// #[folder = "testdata/"]
+/// struct Assets;
+///
+/// # actix_web::rt::System::new().block_on(async {
+/// let report = compliance::check_deterministic_responses(
+///     || Embed::new("/static", &Assets),
+///     &["/static/index.html"],
+/// )
+/// .await;
+/// report.assert_compliant();
+/// # });
+/// ```
+pub async fn check_deterministic_responses<B, E, F>(build: B, paths: &[&str]) -> ComplianceReport
+where
+    B: Fn() -> Embed<E, F>,
+    E: 'static + rust_embed::RustEmbed,
+    F: FallbackHandler,
+{
+    let first = test::init_service(App::new().service(build())).await;
+    let second = test::init_service(App::new().service(build())).await;
+    let mut report = ComplianceReport::default();
+
+    for path in paths {
+        for accept_encoding in ACCEPT_ENCODINGS {
+            let request = || {
+                TestRequest::get()
+                    .uri(path)
+                    .insert_header((header::ACCEPT_ENCODING, *accept_encoding))
+                    .to_request()
+            };
+            let describe = |resp: &ServiceResponse| {
+                let header = |name| {
+                    resp.headers()
+                        .get(name)
+                        .and_then(|v| v.to_str().ok())
+                        .unwrap_or("none")
+                        .to_owned()
+                };
+                format!(
+                    "{} ({}): {}, etag {}, encoding {}",
+                    path,
+                    accept_encoding,
+                    resp.status(),
+                    header(header::ETAG),
+                    header(header::CONTENT_ENCODING)
+                )
+            };
+
+            let resp = test::call_service(&first, request()).await;
+            let expected = describe(&resp);
+            let expected_body = test::read_body(resp).await;
+            let resp = test::call_service(&second, request()).await;
+            let actual = describe(&resp);
+            let actual_body = test::read_body(resp).await;
+
+            report.check("responses have the same headers", expected, actual);
+            report.check(
+                "responses have the same body",
+                format!("{} ({}): {}", path, accept_encoding, digest(&expected_body)),
+                format!("{} ({}): {}", path, accept_encoding, digest(&actual_body)),
+            );
+        }
+    }
+
+    report
+}
+
+/// Replays `decisions` recorded with [Embed::record_decisions](crate::Embed::record_decisions)
+/// against `embed`, checking it makes the same decision for each request.
+///
+/// The decisions of `embed` are recorded for the comparison, replacing its own
+/// [record_decisions](crate::Embed::record_decisions) sink. Requests the service does not
+/// handle, e.g. outside its mount path, fail the check.
+///
+/// # Examples
+/// ```
+/// use actix_embed::decision::Decision;
+/// use actix_embed::{compliance, Embed};
+/// use rust_embed::RustEmbed;
+///
+/// #[derive(RustEmbed)]
+/// #[folder = "testdata/"]
+/// struct Assets;
+///
+/// let recorded = r#"{"method":"GET","uri":"/static/","headers":[],
+///     "outcome":{"status":200,"file":"index.html","fallback":false,"encoding":null,"location":null}}"#;
+/// let decisions = vec![serde_json::from_str::<Decision>(recorded).unwrap()];
+///
+/// # actix_web::rt::System::new().block_on(async {
+/// let embed = Embed::new("/static", &Assets).index_file("index.html");
+/// compliance::replay_decisions(embed, &decisions).await.assert_compliant();
+/// # });
+/// ```
+pub async fn replay_decisions<E, F>(embed: Embed<E, F>, decisions: &[Decision]) -> ComplianceReport
+where
+    E: 'static + rust_embed::RustEmbed,
+    F: FallbackHandler,
+{
+    let replayed = Rc::new(RefCell::new(None));
+    let sink = replayed.clone();
+    let embed = embed.record_decisions(move |decision| *sink.borrow_mut() = Some(decision));
+    let srv = test::init_service(App::new().service(embed)).await;
+    let mut report = ComplianceReport::default();
+
+    for decision in decisions {
+        test::call_service(&srv, decision.request().to_request()).await;
+        let actual = match replayed.borrow_mut().take() {
+            Some(replayed) => Decision {
+                outcome: replayed.outcome,
+                ..decision.clone()
+            }
+            .to_string(),
+            None => format!("{} {} not handled", decision.method, decision.uri),
+        };
+        report.check("replayed decisions are identical", decision, actual);
+    }
+
+    report
+}
+
+/// Summarizes a body for reports, as its length and sha256 hash.
+fn digest(body: &[u8]) -> String {
+    format!(
+        "{} bytes, sha256 {}",
+        body.len(),
+        hex::encode(Sha256::digest(body))
+    )
+}
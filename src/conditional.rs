@@ -0,0 +1,111 @@
+use std::time::SystemTime;
+
+use actix_web::http::header::{
+    EntityTag, IfMatch, IfModifiedSince, IfNoneMatch, IfRange, IfUnmodifiedSince,
+};
+use actix_web::http::Method;
+use actix_web::{HttpMessage, HttpRequest};
+
+use crate::context::ServeContext;
+
+/// The result of evaluating the preconditions of a request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Precondition {
+    /// The request should be served as usual.
+    Passed,
+    /// The selected representation has not been modified, respond with `304 Not Modified`.
+    NotModified,
+    /// A precondition failed, respond with `412 Precondition Failed`.
+    Failed,
+}
+
+/// The validators of the representation of an embedded file a conditional request is
+/// evaluated against, see [Embed::revalidation_hook](crate::Embed::revalidation_hook).
+#[derive(Debug, Clone, Copy)]
+pub struct Validators<'a> {
+    /// The key of the embedded file.
+    pub path: &'a str,
+    /// The entity tag of the representation, sent in `ETag`.
+    pub etag: &'a EntityTag,
+    /// The modification time of the file, if embedded, sent in `Last-Modified`.
+    pub last_modified: Option<SystemTime>,
+    /// What is known about the request so far, e.g. the negotiated encoding.
+    pub context: &'a ServeContext,
+}
+
+/// The decision made by a [revalidation hook](crate::Embed::revalidation_hook).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RevalidationDecision {
+    /// The preconditions of the request are evaluated as usual.
+    Evaluate,
+    /// The full representation is sent, whatever the preconditions of the request.
+    Full,
+    /// The request is answered with `304 Not Modified`.
+    NotModified,
+}
+
+pub(crate) type RevalidationHook = dyn Fn(&HttpRequest, &Validators<'_>) -> RevalidationDecision;
+
+/// Evaluates `If-Match`, `If-Unmodified-Since`, `If-None-Match` and `If-Modified-Since` in
+/// the order defined by RFC 9110 section 13.2.2.
+///
+/// The dates are only evaluated if the modification time is known and the corresponding
+/// entity tag header is absent.
+pub(crate) fn evaluate(
+    req: &HttpRequest,
+    etag: &EntityTag,
+    last_modified: Option<SystemTime>,
+) -> Precondition {
+    // If-Match uses the strong comparison function.
+    match req.get_header::<IfMatch>() {
+        Some(IfMatch::Items(ref items)) if !items.iter().any(|item| item.strong_eq(etag)) => {
+            return Precondition::Failed;
+        }
+        Some(_) => {}
+        None => {
+            let since = req.get_header::<IfUnmodifiedSince>();
+            if let (Some(IfUnmodifiedSince(since)), Some(modified)) = (since, last_modified) {
+                if modified > SystemTime::from(since) {
+                    return Precondition::Failed;
+                }
+            }
+        }
+    }
+
+    // If-None-Match uses the weak comparison function.
+    match req.get_header::<IfNoneMatch>() {
+        Some(IfNoneMatch::Any) => Precondition::NotModified,
+        Some(IfNoneMatch::Items(ref items)) if items.iter().any(|item| item.weak_eq(etag)) => {
+            Precondition::NotModified
+        }
+        Some(_) => Precondition::Passed,
+        None => {
+            let since = req.get_header::<IfModifiedSince>();
+            let head_or_get = matches!(*req.method(), Method::GET | Method::HEAD);
+            match (since, last_modified) {
+                (Some(IfModifiedSince(since)), Some(modified))
+                    if head_or_get && modified <= SystemTime::from(since) =>
+                {
+                    Precondition::NotModified
+                }
+                _ => Precondition::Passed,
+            }
+        }
+    }
+}
+
+/// Returns `true` if the `Range` header of `req` applies to the representation with `etag`,
+/// modified at `last_modified`, i.e. `If-Range` is absent or matches it.
+///
+/// Entity tags are compared with the strong comparison function, dates must match exactly.
+pub(crate) fn range_applies(
+    req: &HttpRequest,
+    etag: &EntityTag,
+    last_modified: Option<SystemTime>,
+) -> bool {
+    match req.get_header::<IfRange>() {
+        None => true,
+        Some(IfRange::EntityTag(tag)) => tag.strong_eq(etag),
+        Some(IfRange::Date(date)) => last_modified == Some(SystemTime::from(date)),
+    }
+}
@@ -0,0 +1,68 @@
+use std::collections::HashMap;
+
+use rust_embed::EmbeddedFile;
+
+use crate::file_index::{FileIndex, KeyMap};
+
+/// The directory content-addressed URLs are served under, relative to the mount path.
+pub(crate) const BY_HASH: &str = "_by-hash/";
+
+/// The content-addressed URLs of the files served by [Embed](crate::Embed), keyed by the path
+/// of the files relative to the mount path.
+///
+/// See [Embed::content_addresses](crate::Embed::content_addresses).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ContentAddresses {
+    urls: HashMap<String, String>,
+}
+
+impl ContentAddresses {
+    pub(crate) fn build<E>(mount_path: &str, keys: &KeyMap) -> Self
+    where
+        E: 'static + rust_embed::RustEmbed,
+    {
+        let index = FileIndex::build::<E>(keys);
+        let urls = index
+            .paths()
+            .iter()
+            .filter_map(|path| {
+                let (_, file) = keys.get::<E>(path)?;
+                let url = format!("{}/{}", mount_path, url(path, &file));
+                Some((path.clone(), url))
+            })
+            .collect();
+        ContentAddresses { urls }
+    }
+
+    /// Returns the content-addressed URL of the file at `path`, e.g.
+    /// `/static/_by-hash/<sha256>/index.css` for `index.css`.
+    ///
+    /// URLs start with the mount path, paths of enclosing scopes are not included.
+    pub fn get<P: AsRef<str>>(&self, path: P) -> Option<&str> {
+        self.urls
+            .get(path.as_ref().trim_start_matches('/'))
+            .map(String::as_str)
+    }
+
+    /// Returns the paths of the files and their content-addressed URLs, in arbitrary order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.urls
+            .iter()
+            .map(|(path, url)| (path.as_str(), url.as_str()))
+    }
+}
+
+/// Returns the content-addressed URL of `file` served at `path`, relative to the mount path.
+pub(crate) fn url(path: &str, file: &EmbeddedFile) -> String {
+    format!("{}{}/{}", BY_HASH, hash(file), path)
+}
+
+/// Splits a content-addressed request path into the hash and the path of the file.
+pub(crate) fn split(path: &str) -> Option<(&str, &str)> {
+    path.strip_prefix(BY_HASH)?.split_once('/')
+}
+
+/// Returns the hash content-addressed URLs of `file` contain, the hex-encoded SHA-256.
+pub(crate) fn hash(file: &EmbeddedFile) -> String {
+    hex::encode(file.metadata.sha256_hash())
+}
@@ -0,0 +1,153 @@
+use actix_web::http::Method;
+
+use crate::encoding::{Compression, Encoding};
+
+/// What [Embed](crate::Embed) knows about a request while serving it: the request, the
+/// embedded file it resolved to, the selected variant and the decisions of its options.
+///
+/// The context is built up as the request goes through lookup, negotiation and the headers of
+/// the response, and hooks see it as far as it got, read-only:
+///
+/// - [revalidation hooks](crate::Embed::revalidation_hook) in
+///   [Validators::context](crate::Validators::context),
+/// - the [fallback handler](crate::Embed::fallback_handler) and wrapping middlewares in the
+///   extensions of the request,
+/// - [on_serve](crate::Embed::on_serve) in [ServeRecord::context](crate::ServeRecord::context).
+///
+/// # Examples
+/// ```
+/// use actix_embed::{Embed, ServeContext};
+/// use actix_web::{HttpMessage, HttpRequest, HttpResponse};
+/// use rust_embed::RustEmbed;
+///
+/// #[derive(RustEmbed)]
+/// #[folder = "testdata/"]
+/// struct Assets;
+///
+/// let embed = Embed::new("/", &Assets).fallback_handler(|req: &HttpRequest| {
+///     let extensions = req.extensions();
+///     match extensions.get::<ServeContext>() {
+///         Some(context) => HttpResponse::NotFound().body(format!("{} not found", context.path())),
+///         None => HttpResponse::NotFound().finish(),
+///     }
+/// });
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServeContext {
+    method: Method,
+    path: String,
+    file: Option<String>,
+    language: Option<String>,
+    encoding: Option<Encoding>,
+    compression: Option<Compression>,
+    decisions: Vec<PolicyDecision>,
+    epoch: usize,
+}
+
+/// A decision made by an option of [Embed](crate::Embed) while serving a request, see
+/// [ServeContext::decisions].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PolicyDecision {
+    /// The name of the option, as in [Embed::describe](crate::Embed::describe).
+    pub option: &'static str,
+    /// What the option decided, e.g. `rejected`.
+    pub outcome: &'static str,
+}
+
+impl ServeContext {
+    pub(crate) fn new(method: Method, path: &str, epoch: usize) -> Self {
+        ServeContext {
+            method,
+            path: path.to_owned(),
+            file: None,
+            language: None,
+            encoding: None,
+            compression: None,
+            decisions: Vec::new(),
+            epoch,
+        }
+    }
+
+    /// Returns the method of the request.
+    pub fn method(&self) -> &Method {
+        &self.method
+    }
+
+    /// Returns the path files are looked up at, relative to the mount path, e.g. `index.html`,
+    /// or the path of the selected file once the index file or an image variant is selected.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Returns the key of the embedded file the request resolved to, `None` until a file is
+    /// found.
+    pub fn file(&self) -> Option<&str> {
+        self.file.as_deref()
+    }
+
+    /// Returns the language of the selected [localized index](crate::Embed::localized_index).
+    pub fn language(&self) -> Option<&str> {
+        self.language.as_deref()
+    }
+
+    /// Returns the content encoding of the selected variant, `None` for the identity encoding
+    /// or until it is negotiated.
+    pub fn encoding(&self) -> Option<Encoding> {
+        self.encoding
+    }
+
+    /// Returns why the file is served with or without compression, `None` until it is
+    /// negotiated.
+    pub fn compression(&self) -> Option<Compression> {
+        self.compression
+    }
+
+    /// Returns the decisions made so far, in order.
+    pub fn decisions(&self) -> &[PolicyDecision] {
+        &self.decisions
+    }
+
+    /// Returns the outcome of the last decision of `option`, if it made one.
+    pub fn decision(&self, option: &str) -> Option<&'static str> {
+        self.decisions
+            .iter()
+            .rev()
+            .find(|decision| decision.option == option)
+            .map(|decision| decision.outcome)
+    }
+
+    /// Returns the [configuration epoch](crate::EmbedAdmin::epoch) the request is served in.
+    /// Responses and prepared files cached in earlier epochs are derived again.
+    pub fn epoch(&self) -> usize {
+        self.epoch
+    }
+
+    pub(crate) fn set_path(&mut self, path: &str) {
+        if self.path != path {
+            self.path = path.to_owned();
+        }
+    }
+
+    pub(crate) fn resolve(&mut self, file: &str) {
+        self.file = Some(file.to_owned());
+    }
+
+    pub(crate) fn select_language(&mut self, language: &str) {
+        self.language = Some(language.to_owned());
+    }
+
+    /// Records the negotiated encoding, before its variant is loaded.
+    pub(crate) fn negotiate(&mut self, encoding: Option<Encoding>) {
+        self.encoding = encoding;
+    }
+
+    pub(crate) fn select_encoding(&mut self, encoding: Option<Encoding>, compression: Compression) {
+        self.encoding = encoding;
+        self.compression = Some(compression);
+    }
+
+    /// Records that `option` decided `outcome`.
+    pub(crate) fn decide(&mut self, option: &'static str, outcome: &'static str) {
+        self.decisions.push(PolicyDecision { option, outcome });
+    }
+}
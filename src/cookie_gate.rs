@@ -0,0 +1,157 @@
+#[cfg(feature = "secure-cookies")]
+use actix_web::cookie::{Cookie, CookieJar, Key};
+use actix_web::http::header::{self, HeaderMap, HeaderValue};
+use actix_web::{HttpRequest, HttpResponse};
+
+use crate::cache_status::CDN_CACHE_CONTROL;
+use crate::{headers, http_util, language};
+
+/// Cookie verifiers decide whether the value of the gating cookie grants access to a mount.
+///
+/// Closures receive the raw value of the cookie. With the `secure-cookies` feature,
+/// [SignedCookie] and [PrivateCookie] verify cookies set with the signed and private jars of
+/// `actix-web`. See [Embed::require_signed_cookie](crate::Embed::require_signed_cookie).
+pub trait CookieVerifier: 'static {
+    /// Returns `true` if the cookie `name` with `value` grants access.
+    fn verify(&self, name: &str, value: &str) -> bool;
+}
+
+impl<T> CookieVerifier for T
+where
+    T: Fn(&str) -> bool + 'static,
+{
+    fn verify(&self, _name: &str, value: &str) -> bool {
+        (self)(value)
+    }
+}
+
+/// Accepts cookies signed with a key, as set by the signed jar of `actix-web`, whatever
+/// their value.
+///
+/// Requires the `secure-cookies` feature.
+#[cfg(feature = "secure-cookies")]
+#[derive(Clone)]
+pub struct SignedCookie(pub Key);
+
+#[cfg(feature = "secure-cookies")]
+impl std::fmt::Debug for SignedCookie {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("SignedCookie")
+    }
+}
+
+#[cfg(feature = "secure-cookies")]
+impl CookieVerifier for SignedCookie {
+    fn verify(&self, name: &str, value: &str) -> bool {
+        let mut jar = CookieJar::new();
+        jar.add_original(Cookie::new(name.to_owned(), value.to_owned()));
+        jar.signed(&self.0).get(name).is_some()
+    }
+}
+
+/// Accepts cookies encrypted with a key, as set by the private jar of `actix-web`, whatever
+/// their value.
+///
+/// Requires the `secure-cookies` feature.
+#[cfg(feature = "secure-cookies")]
+#[derive(Clone)]
+pub struct PrivateCookie(pub Key);
+
+#[cfg(feature = "secure-cookies")]
+impl std::fmt::Debug for PrivateCookie {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("PrivateCookie")
+    }
+}
+
+#[cfg(feature = "secure-cookies")]
+impl CookieVerifier for PrivateCookie {
+    fn verify(&self, name: &str, value: &str) -> bool {
+        let mut jar = CookieJar::new();
+        jar.add_original(Cookie::new(name.to_owned(), value.to_owned()));
+        jar.private(&self.0).get(name).is_some()
+    }
+}
+
+/// The placeholder of login URLs replaced with the URL of the rejected request.
+const RETURN_TO: &str = "{return_to}";
+
+/// Gates a whole mount behind a cookie, see
+/// [Embed::require_signed_cookie](crate::Embed::require_signed_cookie).
+pub(crate) struct CookieGate {
+    pub(crate) name: String,
+    pub(crate) verifier: Box<dyn CookieVerifier>,
+}
+
+impl CookieGate {
+    /// Returns the value of the gating cookie of `req`, if any.
+    pub(crate) fn value<'a>(&self, req: &'a HttpRequest) -> Option<&'a str> {
+        language::cookie(req, &self.name)
+    }
+
+    /// Returns the response to a request without a valid cookie, a `303 See Other` redirect
+    /// to `login_url` or `401 Unauthorized`.
+    pub(crate) fn reject(&self, req: &HttpRequest, login_url: Option<&str>) -> HttpResponse {
+        let mut builder = match login_url {
+            Some(url) => {
+                let location = url.replace(RETURN_TO, &encode_component(&req.uri().to_string()));
+                let mut builder = HttpResponse::SeeOther();
+                builder.insert_header((header::LOCATION, http_util::header_value(&location)));
+                builder
+            }
+            None => HttpResponse::Unauthorized(),
+        };
+        builder
+            .insert_header((header::CACHE_CONTROL, headers::NO_STORE))
+            .finish()
+    }
+
+    /// Keeps gated responses out of shared caches, which would serve them without checking
+    /// the cookie.
+    pub(crate) fn apply(&self, headers: &mut HeaderMap) {
+        headers.append(header::VARY, HeaderValue::from_static("Cookie"));
+        // CDNs follow `CDN-Cache-Control` instead of the `private` directive
+        headers.remove(CDN_CACHE_CONTROL);
+        let directives = headers
+            .get(header::CACHE_CONTROL)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| {
+                value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|directive| !directive.is_empty())
+                    .map(str::to_owned)
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        if directives
+            .iter()
+            .any(|directive| directive == "private" || directive == "no-store")
+        {
+            return;
+        }
+        let mut value = vec!["private".to_owned()];
+        value.extend(
+            directives
+                .into_iter()
+                .filter(|directive| directive != "public"),
+        );
+        headers.insert(
+            header::CACHE_CONTROL,
+            http_util::header_value(&value.join(", ")),
+        );
+    }
+}
+
+/// Percent-encodes `value` as a query component, keeping only unreserved characters.
+fn encode_component(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
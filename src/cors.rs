@@ -0,0 +1,104 @@
+use actix_web::http::header::{self, HeaderMap, HeaderName, HeaderValue};
+use actix_web::http::Method;
+use actix_web::{HttpRequest, HttpResponse};
+
+/// Sent by Chrome in preflights of requests from public pages to private networks.
+const REQUEST_PRIVATE_NETWORK: HeaderName =
+    HeaderName::from_static("access-control-request-private-network");
+
+/// Allows the request of a private network preflight.
+const ALLOW_PRIVATE_NETWORK: HeaderName =
+    HeaderName::from_static("access-control-allow-private-network");
+
+/// The cross-origin resource sharing settings, see [Embed::cors](crate::Embed::cors).
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Cors {
+    /// The allowed origins, `*` allowing any origin, no origin disabling CORS.
+    pub(crate) origins: Vec<String>,
+    pub(crate) credentials: bool,
+    pub(crate) private_network: bool,
+}
+
+impl Cors {
+    /// Returns `true` if any origin is allowed.
+    pub(crate) fn is_enabled(&self) -> bool {
+        !self.origins.is_empty()
+    }
+
+    /// Returns `true` if `req` is a preflight request.
+    pub(crate) fn is_preflight(req: &HttpRequest) -> bool {
+        req.method() == Method::OPTIONS
+            && req
+                .headers()
+                .contains_key(header::ACCESS_CONTROL_REQUEST_METHOD)
+    }
+
+    /// Returns the response to the preflight request `req`, the headers allowing the origin
+    /// are added by [apply](Self::apply).
+    pub(crate) fn preflight(&self, req: &HttpRequest) -> HttpResponse {
+        let mut resp = HttpResponse::NoContent();
+        if self.allowed_origin(req).is_none() {
+            return resp.finish();
+        }
+        resp.insert_header((header::ACCESS_CONTROL_ALLOW_METHODS, "GET, HEAD"));
+        if let Some(headers) = req.headers().get(header::ACCESS_CONTROL_REQUEST_HEADERS) {
+            resp.insert_header((header::ACCESS_CONTROL_ALLOW_HEADERS, headers.clone()));
+        }
+        let private_network = req
+            .headers()
+            .get(REQUEST_PRIVATE_NETWORK)
+            .is_some_and(|value| value.as_bytes().eq_ignore_ascii_case(b"true"));
+        if private_network && self.private_network {
+            resp.insert_header((ALLOW_PRIVATE_NETWORK, "true"));
+        }
+        resp.finish()
+    }
+
+    /// Returns `true` if credentials are allowed together with any origin, which would let
+    /// every site read responses with the cookies of the user.
+    pub(crate) fn credentials_with_any_origin(&self) -> bool {
+        self.credentials && self.origins.iter().any(|allowed| allowed == "*")
+    }
+
+    /// Adds the headers allowing the origin of `req` to read the response, if it is allowed.
+    ///
+    /// Credentials are only allowed for origins listed explicitly, origins only allowed by
+    /// `*` get `*` without credentials. Responses vary by `Origin` whatever it is, so caches
+    /// never serve the headers of one origin to another, or to same-origin requests.
+    pub(crate) fn apply(&self, req: &HttpRequest, headers: &mut HeaderMap) {
+        headers.append(header::VARY, HeaderValue::from_static("Origin"));
+        let origin = match self.allowed_origin(req) {
+            Some(origin) => origin,
+            None => return,
+        };
+        let listed = self
+            .origins
+            .iter()
+            .any(|allowed| allowed.as_bytes().eq_ignore_ascii_case(origin.as_bytes()));
+        if !listed {
+            headers.insert(
+                header::ACCESS_CONTROL_ALLOW_ORIGIN,
+                HeaderValue::from_static("*"),
+            );
+            return;
+        }
+        headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin.clone());
+        if self.credentials {
+            headers.insert(
+                header::ACCESS_CONTROL_ALLOW_CREDENTIALS,
+                HeaderValue::from_static("true"),
+            );
+        }
+    }
+
+    /// Returns the `Origin` of `req` if it is allowed.
+    fn allowed_origin<'a>(&self, req: &'a HttpRequest) -> Option<&'a HeaderValue> {
+        let origin = req.headers().get(header::ORIGIN)?;
+        self.origins
+            .iter()
+            .any(|allowed| {
+                allowed == "*" || allowed.as_bytes().eq_ignore_ascii_case(origin.as_bytes())
+            })
+            .then_some(origin)
+    }
+}
@@ -0,0 +1,151 @@
+//! Records of the decisions made by [Embed](crate::Embed), for replay tests.
+//!
+//! Decisions recorded with [Embed::record_decisions](crate::Embed::record_decisions), e.g. from
+//! a staging deployment or an integration test, are replayed with
+//! [compliance::replay_decisions](crate::compliance::replay_decisions) to check a
+//! configuration still serves the same files, statuses and redirects, e.g. after upgrading
+//! this crate.
+
+use std::fmt::{Display, Formatter};
+
+use actix_web::dev::ServiceResponse;
+use actix_web::http::header;
+use actix_web::test::TestRequest;
+use serde::{Deserialize, Serialize};
+
+use crate::events::Served;
+
+/// The request headers recorded with decisions, those the options of [Embed](crate::Embed)
+/// decide on, except `Cookie`, which may carry credentials.
+pub const RECORDED_HEADERS: &[&str] = &[
+    "host",
+    "accept",
+    "accept-charset",
+    "accept-encoding",
+    "accept-language",
+    "range",
+    "if-match",
+    "if-none-match",
+    "if-modified-since",
+    "if-unmodified-since",
+    "if-range",
+    "origin",
+    "access-control-request-method",
+    "access-control-request-headers",
+    "sec-ch-dpr",
+    "sec-ch-width",
+    "sec-ch-viewport-width",
+    "x-forwarded-for",
+    "x-forwarded-host",
+    "x-forwarded-proto",
+    "forwarded",
+];
+
+/// A request and the decision [Embed](crate::Embed) made for it, see
+/// [Embed::record_decisions](crate::Embed::record_decisions).
+///
+/// Decisions are serializable, e.g. as JSON lines committed next to the tests replaying them
+/// with [compliance::replay_decisions](crate::compliance::replay_decisions).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Decision {
+    /// The method of the request.
+    pub method: String,
+    /// The path and query of the request, including the mount path.
+    pub uri: String,
+    /// The [recorded headers](RECORDED_HEADERS) of the request, in the order they were sent.
+    pub headers: Vec<(String, String)>,
+    /// What the service decided.
+    pub outcome: DecisionOutcome,
+}
+
+/// What [Embed](crate::Embed) decided for a request, without the parts of the response
+/// derived from the content of files, like entity tags and bodies.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DecisionOutcome {
+    /// The status code of the response.
+    pub status: u16,
+    /// The key of the embedded file the response was generated with, if any.
+    pub file: Option<String>,
+    /// Whether the response was generated by the fallback handler.
+    pub fallback: bool,
+    /// The value of the `Content-Encoding` header of the response, if any other than
+    /// `identity`.
+    pub encoding: Option<String>,
+    /// The value of the `Location` header of the response, if any.
+    pub location: Option<String>,
+}
+
+impl Display for DecisionOutcome {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.status)?;
+        if let Some(file) = &self.file {
+            write!(f, " file {:?}", file)?;
+        }
+        if self.fallback {
+            f.write_str(" fallback")?;
+        }
+        if let Some(encoding) = &self.encoding {
+            write!(f, " encoded {}", encoding)?;
+        }
+        if let Some(location) = &self.location {
+            write!(f, " to {:?}", location)?;
+        }
+        Ok(())
+    }
+}
+
+impl Decision {
+    pub(crate) fn new(resp: &ServiceResponse, served: &Served) -> Self {
+        let req = resp.request();
+        let headers = req
+            .headers()
+            .iter()
+            .filter(|(name, _)| RECORDED_HEADERS.contains(&name.as_str()))
+            .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_owned())))
+            .collect();
+        let header = |name| {
+            resp.headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_owned)
+        };
+        Decision {
+            method: req.method().to_string(),
+            uri: req
+                .uri()
+                .path_and_query()
+                .map_or_else(|| req.path().to_owned(), ToString::to_string),
+            headers,
+            outcome: DecisionOutcome {
+                status: resp.status().as_u16(),
+                file: match served {
+                    Served::File(key) => Some(key.clone()),
+                    Served::Fallback | Served::Rejected => None,
+                },
+                fallback: matches!(served, Served::Fallback),
+                encoding: header(header::CONTENT_ENCODING).filter(|value| value != "identity"),
+                location: header(header::LOCATION),
+            },
+        }
+    }
+
+    /// Returns a request like the recorded one.
+    pub(crate) fn request(&self) -> TestRequest {
+        let method = self.method.parse().unwrap_or_default();
+        let mut req = TestRequest::default().method(method).uri(&self.uri);
+        for (name, value) in &self.headers {
+            req = req.append_header((name.as_str(), value.as_str()));
+        }
+        req
+    }
+}
+
+impl Display for Decision {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.method, self.uri)?;
+        for (name, value) in &self.headers {
+            write!(f, " [{}: {}]", name, value)?;
+        }
+        write!(f, " -> {}", self.outcome)
+    }
+}
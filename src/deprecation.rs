@@ -0,0 +1,66 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use actix_web::http::header::{HeaderName, HeaderValue};
+
+use crate::http_util;
+
+/// When deprecated files are going to be removed, see
+/// [Embed::deprecated](crate::Embed::deprecated).
+///
+/// # Examples
+/// ```
+/// use std::time::{Duration, SystemTime};
+/// use actix_embed::Sunset;
+///
+/// let in_30_days = SystemTime::now() + Duration::from_secs(30 * 24 * 60 * 60);
+/// let sunset = Sunset::date(in_30_days).deprecated_since(SystemTime::now());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sunset {
+    date: Option<SystemTime>,
+    since: Option<SystemTime>,
+}
+
+impl Sunset {
+    /// The files are removed at `date`, announced with a `Sunset` header.
+    pub fn date(date: SystemTime) -> Self {
+        Sunset {
+            date: Some(date),
+            since: None,
+        }
+    }
+
+    /// The files are deprecated, but no removal date is announced.
+    pub fn unscheduled() -> Self {
+        Sunset {
+            date: None,
+            since: None,
+        }
+    }
+
+    /// Set when the files were deprecated, sent in the `Deprecation` header.
+    ///
+    /// Defaults to the time [Embed::deprecated](crate::Embed::deprecated) is called.
+    pub fn deprecated_since(mut self, since: SystemTime) -> Self {
+        self.since = Some(since);
+        self
+    }
+
+    /// Returns the `Deprecation` and `Sunset` headers, deprecated since `now` unless set.
+    pub(crate) fn headers(&self, now: SystemTime) -> Vec<(HeaderName, HeaderValue)> {
+        // a structured field date, e.g. `@1688169599`
+        let since = self.since.unwrap_or(now);
+        let seconds = since.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        let mut headers = vec![(
+            HeaderName::from_static("deprecation"),
+            http_util::header_value(&format!("@{}", seconds)),
+        )];
+        if let Some(date) = self.date {
+            headers.push((
+                HeaderName::from_static("sunset"),
+                http_util::header_value(&http_util::http_date(date)),
+            ));
+        }
+        headers
+    }
+}
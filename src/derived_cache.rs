@@ -0,0 +1,83 @@
+use std::any::TypeId;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt::{Debug, Formatter};
+use std::rc::Rc;
+
+use crate::hasher::ContentHashes;
+#[cfg(feature = "minify")]
+use crate::minify::MinifyCache;
+use crate::prepare::PreparedFiles;
+
+/// Caches of the values derived from embedded files while they are served, like content
+/// hashes, prepared headers and minified variants, shared by the services of a worker.
+///
+/// Each service keeps its own caches, so the same embedded files mounted under multiple
+/// paths, e.g. `/static` and `/cdn`, are hashed and minified once per mount. Create one
+/// `DerivedCache` in the application factory and pass it to
+/// [Embed::derived_cache](crate::Embed::derived_cache) of each mount to derive them once.
+/// Indexes and compressed variants are shared with a [SharedState] instead.
+///
+/// `DerivedCache` is a handle, clones share the same caches. Share it only between services
+/// with the same options, except for the mount path, since the values are derived with the
+/// options of the service deriving them first. Services serving different embedded files
+/// keep separate caches.
+///
+/// [SharedState]: crate::SharedState
+///
+/// # Examples
+/// ```
+/// use actix_embed::{DerivedCache, Embed};
+/// use actix_web::App;
+/// use rust_embed::RustEmbed;
+///
+/// #[derive(RustEmbed)]
+/// #[folder = "testdata/"]
+/// struct Assets;
+///
+/// let cache = DerivedCache::new();
+/// let app = App::new()
+///     .service(Embed::new("/static", &Assets).derived_cache(&cache))
+///     .service(Embed::new("/cdn", &Assets).derived_cache(&cache));
+/// ```
+#[derive(Clone, Default)]
+pub struct DerivedCache {
+    caches: Rc<RefCell<HashMap<TypeId, Rc<Caches>>>>,
+}
+
+impl Debug for DerivedCache {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DerivedCache")
+            .field("embeds", &self.caches.borrow().len())
+            .finish()
+    }
+}
+
+impl DerivedCache {
+    /// Create new empty [DerivedCache].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the caches of the files embedded by `E`, creating them with `init` if no other
+    /// service did yet.
+    pub(crate) fn get_or_init<E, I>(&self, init: I) -> Rc<Caches>
+    where
+        E: 'static,
+        I: FnOnce() -> Caches,
+    {
+        self.caches
+            .borrow_mut()
+            .entry(TypeId::of::<E>())
+            .or_insert_with(|| Rc::new(init()))
+            .clone()
+    }
+}
+
+/// The values derived from the files of a service.
+pub(crate) struct Caches {
+    #[cfg(feature = "minify")]
+    pub(crate) minify: Option<MinifyCache>,
+    pub(crate) hashes: ContentHashes,
+    pub(crate) prepared: PreparedFiles,
+}
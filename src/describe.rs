@@ -0,0 +1,161 @@
+use std::fmt::{Display, Formatter};
+
+use serde::{Serialize, Serializer};
+
+/// A value in a [Description], shaped like JSON.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    /// `null`, for unset options.
+    Null,
+    /// `true` or `false`.
+    Bool(bool),
+    /// A non-negative number.
+    Number(u64),
+    /// A string.
+    String(String),
+    /// A list of values.
+    List(Vec<Value>),
+    /// Named values, in a stable order.
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    /// Creates an object from `(name, value)` pairs.
+    pub(crate) fn object<I, K, V>(entries: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<Value>,
+    {
+        Value::Object(
+            entries
+                .into_iter()
+                .map(|(name, value)| (name.into(), value.into()))
+                .collect(),
+        )
+    }
+
+    /// Creates a list from `values`.
+    pub(crate) fn list<I, V>(values: I) -> Self
+    where
+        I: IntoIterator<Item = V>,
+        V: Into<Value>,
+    {
+        Value::List(values.into_iter().map(Into::into).collect())
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::Bool(value)
+    }
+}
+
+impl From<u64> for Value {
+    fn from(value: u64) -> Self {
+        Value::Number(value)
+    }
+}
+
+impl From<u32> for Value {
+    fn from(value: u32) -> Self {
+        Value::Number(value.into())
+    }
+}
+
+impl From<usize> for Value {
+    fn from(value: usize) -> Self {
+        Value::Number(value as u64)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::String(value.to_owned())
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::String(value)
+    }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Value::Null, Into::into)
+    }
+}
+
+impl Serialize for Value {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Value::Null => serializer.serialize_unit(),
+            Value::Bool(value) => serializer.serialize_bool(*value),
+            Value::Number(value) => serializer.serialize_u64(*value),
+            Value::String(value) => serializer.serialize_str(value),
+            Value::List(values) => serializer.collect_seq(values),
+            Value::Object(entries) => serialize_object(serializer, entries),
+        }
+    }
+}
+
+impl Display for Value {
+    /// Formats the value as JSON.
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let json = serde_json::to_string(self).map_err(|_| std::fmt::Error)?;
+        f.write_str(&json)
+    }
+}
+
+/// Serializes `entries` as an object, in their order.
+fn serialize_object<S: Serializer>(
+    serializer: S,
+    entries: &[(String, Value)],
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_map(entries.iter().map(|(name, value)| (name, value)))
+}
+
+/// The effective configuration of an [Embed](crate::Embed), returned by
+/// [Embed::describe](crate::Embed::describe).
+///
+/// Every option is listed, unset ones as [Value::Null]. Hooks and other user code can't be
+/// described, so only whether they are set is listed. The [Display] implementation formats
+/// the description as JSON, to be logged at startup or attached to bug reports.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Description {
+    entries: Vec<(String, Value)>,
+}
+
+impl Description {
+    pub(crate) fn new(entries: Vec<(String, Value)>) -> Self {
+        Description { entries }
+    }
+
+    /// Returns the value of the option named `name`.
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.entries
+            .iter()
+            .find(|(entry, _)| entry == name)
+            .map(|(_, value)| value)
+    }
+
+    /// Returns the names and values of all options, in a stable order.
+    pub fn entries(&self) -> &[(String, Value)] {
+        &self.entries
+    }
+}
+
+impl Serialize for Description {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_object(serializer, &self.entries)
+    }
+}
+
+impl Display for Description {
+    /// Formats the description as a JSON object.
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let json = serde_json::to_string(self).map_err(|_| std::fmt::Error)?;
+        f.write_str(&json)
+    }
+}
@@ -0,0 +1,88 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use actix_web::web::{self, Bytes};
+use sha2::{Digest, Sha256};
+
+/// Stores derived artifacts in a directory, so they don't have to be kept in memory and can
+/// be reused after restarts.
+///
+/// Each artifact is stored with a sidecar file holding its sha256 hash, which is verified
+/// when the artifact is loaded.
+#[derive(Debug, Clone)]
+pub(crate) struct DiskCache {
+    dir: PathBuf,
+}
+
+/// An artifact stored in a [DiskCache].
+#[derive(Debug, Clone)]
+pub(crate) struct StoredArtifact {
+    path: PathBuf,
+    len: u64,
+}
+
+impl DiskCache {
+    pub(crate) fn new<P: Into<PathBuf>>(dir: P) -> Self {
+        DiskCache { dir: dir.into() }
+    }
+
+    pub(crate) fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Returns the artifact stored under `key` if it exists and passes the integrity check.
+    pub(crate) fn load(&self, key: &str) -> Option<StoredArtifact> {
+        let path = self.dir.join(key);
+        let data = fs::read(&path).ok()?;
+        let expected = fs::read_to_string(checksum_path(&path)).ok()?;
+        if hex::encode(Sha256::digest(&data)) != expected.trim() {
+            return None;
+        }
+        Some(StoredArtifact {
+            path,
+            len: data.len() as u64,
+        })
+    }
+
+    /// Stores `data` under `key`, replacing any existing artifact.
+    pub(crate) fn store(&self, key: &str, data: &[u8]) -> io::Result<StoredArtifact> {
+        fs::create_dir_all(&self.dir)?;
+        let path = self.dir.join(key);
+
+        // Write to temporary files first, so concurrent readers never see partial artifacts.
+        let tmp = self.dir.join(format!("{}.tmp-{}", key, std::process::id()));
+        fs::write(&tmp, data)?;
+        let tmp_checksum = checksum_path(&tmp);
+        fs::write(&tmp_checksum, hex::encode(Sha256::digest(data)))?;
+        fs::rename(&tmp, &path)?;
+        fs::rename(&tmp_checksum, checksum_path(&path))?;
+
+        Ok(StoredArtifact {
+            path,
+            len: data.len() as u64,
+        })
+    }
+}
+
+impl StoredArtifact {
+    /// Returns the size of the artifact in bytes.
+    pub(crate) fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Reads the artifact on the blocking thread pool.
+    ///
+    /// Returns `None` if the artifact can no longer be read or its size changed.
+    pub(crate) async fn read(&self) -> Option<Bytes> {
+        let path = self.path.clone();
+        let data = web::block(move || fs::read(path)).await.ok()?.ok()?;
+        (data.len() as u64 == self.len).then(|| Bytes::from(data))
+    }
+}
+
+fn checksum_path(path: &Path) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(".sha256");
+    PathBuf::from(path)
+}
@@ -0,0 +1,114 @@
+use std::fmt::{Display, Formatter};
+
+use actix_web::http::header::{self, AcceptEncoding};
+use actix_web::{HttpMessage, HttpRequest};
+
+/// Content encodings of embedded file variants.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Encoding {
+    /// The `br` encoding.
+    Brotli,
+    /// The `gzip` encoding.
+    Gzip,
+}
+
+impl Encoding {
+    /// Returns the content coding token used in `Accept-Encoding` and `Content-Encoding`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Encoding::Brotli => "br",
+            Encoding::Gzip => "gzip",
+        }
+    }
+
+    /// Returns the extension of files compressed with the encoding, e.g. `gz` for `app.js.gz`.
+    pub(crate) fn file_extension(self) -> &'static str {
+        match self {
+            Encoding::Brotli => "br",
+            Encoding::Gzip => "gz",
+        }
+    }
+
+    fn to_header(self) -> header::Encoding {
+        match self {
+            Encoding::Brotli => header::Encoding::brotli(),
+            Encoding::Gzip => header::Encoding::gzip(),
+        }
+    }
+}
+
+impl Display for Encoding {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Extensions of file types which are compressed already, and are never compressed again.
+const INCOMPRESSIBLE: &[&str] = &[
+    "png", "jpg", "jpeg", "gif", "webp", "avif", "mp4", "webm", "mov", "mp3", "ogg", "woff",
+    "woff2", "zip", "gz", "br", "zst", "7z", "rar", "pdf",
+];
+
+/// Returns `true` if the file at `path` is compressed already, by its extension.
+///
+/// `extra` are extensions in addition to the built-in list, in lowercase.
+pub(crate) fn is_incompressible(path: &str, extra: &[String]) -> bool {
+    crate::headers::extension(path).is_some_and(|ext| {
+        let ext = ext.to_ascii_lowercase();
+        INCOMPRESSIBLE.contains(&ext.as_str()) || extra.contains(&ext)
+    })
+}
+
+/// Why an embedded file was served with or without a compressed variant.
+///
+/// See [ServeRecord::compression](crate::ServeRecord::compression).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Compression {
+    /// A variant compressed with the encoding was served.
+    Compressed(Encoding),
+    /// The file type is compressed already, so it is never compressed again, see
+    /// [Embed::skip_compression](crate::Embed::skip_compression).
+    Incompressible,
+    /// The file has no compressed variant, e.g. because no rule matches it, compressing it
+    /// saves nothing, or the variant could not be loaded in time.
+    Unavailable,
+    /// The client accepts none of the encodings of the compressed variants.
+    NotAccepted,
+}
+
+/// How responses cooperate with compressing middleware wrapping [Embed](crate::Embed), such as
+/// `actix_web::middleware::Compress`.
+///
+/// Such middleware compresses responses without `Content-Encoding`, but keeps their entity tags
+/// and does not know about the compressed variants of embedded files. Without adapting
+/// responses, different bytes are served with the same strong entity tag.
+///
+/// See [Embed::outer_compression](crate::Embed::outer_compression).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OuterCompression {
+    /// No compressing middleware wraps the service, responses are not adapted.
+    #[default]
+    Absent,
+    /// Mark uncompressed responses with `Content-Encoding: identity`, so compressing
+    /// middleware leaves all responses as served. Only variants compressed by `Embed` are
+    /// sent compressed.
+    Bypass,
+    /// Let compressing middleware compress uncompressed responses, and serve them with weak
+    /// entity tags and `Vary: Accept-Encoding`, since the bytes sent depend on the middleware.
+    Allow,
+}
+
+/// Selects the encoding the client prefers among `available`, `None` means identity.
+pub(crate) fn negotiate(req: &HttpRequest, available: &[Encoding]) -> Option<Encoding> {
+    let accept = req.get_header::<AcceptEncoding>()?;
+    let supported = available
+        .iter()
+        .map(|encoding| encoding.to_header())
+        .chain(std::iter::once(header::Encoding::identity()))
+        .collect::<Vec<_>>();
+    let selected = accept.negotiate(supported.iter())?;
+    available
+        .iter()
+        .copied()
+        .find(|encoding| encoding.to_header() == selected)
+}
@@ -0,0 +1,183 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use actix_web::body::MessageBody;
+use actix_web::http::header::{self, EntityTag, HeaderMap, HeaderValue};
+use actix_web::http::StatusCode;
+use actix_web::web::Bytes;
+use actix_web::{HttpRequest, HttpResponse};
+use sha2::{Digest, Sha256};
+
+use crate::conditional::{self, Precondition};
+use crate::encoding::{self, Encoding};
+use crate::metrics::CacheGauge;
+use crate::{headers, http_util, precompress};
+
+/// The maximum number of cached bodies, further bodies are generated for each request, so
+/// requests with arbitrary queries cannot grow the cache without bound.
+const MAX_ENTRIES: usize = 256;
+
+/// The generated bodies of the version, import map and listing endpoints, and their
+/// compressed variants, keyed by request path and query, and the state of the files they
+/// were generated from.
+///
+/// See [Embed::compress_endpoints](crate::Embed::compress_endpoints).
+#[derive(Debug, Default)]
+pub(crate) struct EndpointCache {
+    encodings: Vec<(Encoding, u32)>,
+    entries: RefCell<HashMap<String, Rc<CachedBody>>>,
+    /// The bytes of the cached bodies and their variants.
+    pub(crate) gauge: CacheGauge,
+}
+
+#[derive(Debug)]
+struct CachedBody {
+    headers: HeaderMap,
+    body: Bytes,
+    /// The hash the entity tags of the body and its variants are derived from.
+    hash: String,
+    variants: Vec<(Encoding, Bytes)>,
+}
+
+impl EndpointCache {
+    pub(crate) fn new(encodings: Vec<(Encoding, u32)>) -> Self {
+        EndpointCache {
+            encodings,
+            entries: RefCell::default(),
+            gauge: CacheGauge::default(),
+        }
+    }
+
+    /// Returns the response to `req` with the cached body, generating it with `generate` on
+    /// the first request.
+    ///
+    /// `state` distinguishes bodies generated while different files were shown, e.g. the
+    /// state of the [availability windows](crate::Embed::available). Only `200 OK` responses
+    /// are cached, other responses are returned as generated.
+    pub(crate) fn respond<G>(&self, req: &HttpRequest, state: &str, generate: G) -> HttpResponse
+    where
+        G: FnOnce() -> HttpResponse,
+    {
+        let key = match req.query_string() {
+            "" => format!("{} {}", state, req.path()),
+            query => format!("{} {}?{}", state, req.path(), query),
+        };
+        let cached = self.entries.borrow().get(&key).cloned();
+        let cached = match cached {
+            Some(cached) => cached,
+            None => {
+                // preconditions are evaluated against the cached body instead
+                let resp = generate();
+                if resp.status() != StatusCode::OK {
+                    return resp;
+                }
+                let (resp, body) = resp.into_parts();
+                let body = match body.try_into_bytes() {
+                    Ok(body) => body,
+                    Err(body) => return resp.set_body(body),
+                };
+                let cached = Rc::new(self.compress(resp.headers().clone(), body));
+                let mut entries = self.entries.borrow_mut();
+                if entries.len() < MAX_ENTRIES {
+                    self.gauge.add(cached.len());
+                    entries.insert(key, cached.clone());
+                }
+                cached
+            }
+        };
+        cached.respond(req)
+    }
+
+    /// Drops the cached bodies.
+    pub(crate) fn clear(&self) {
+        for (_, cached) in self.entries.borrow_mut().drain() {
+            self.gauge.sub(cached.len());
+        }
+    }
+
+    fn compress(&self, mut headers: HeaderMap, body: Bytes) -> CachedBody {
+        // generated entity tags are replaced by ones distinguishing the variants
+        let hash = match headers.remove(header::ETAG).next() {
+            Some(etag) => etag
+                .to_str()
+                .ok()
+                .and_then(|etag| etag.parse::<EntityTag>().ok())
+                .map(|etag| etag.tag().to_owned()),
+            None => None,
+        }
+        .unwrap_or_else(|| hex::encode(Sha256::digest(&body)));
+        let mut variants = Vec::new();
+        for &(encoding, level) in &self.encodings {
+            if variants.iter().any(|(e, _)| *e == encoding) {
+                continue;
+            }
+            match precompress::compress(encoding, level, &body) {
+                Some(variant) if variant.len() < body.len() => {
+                    variants.push((encoding, Bytes::from(variant)));
+                }
+                _ => {}
+            }
+        }
+        CachedBody {
+            headers,
+            body,
+            hash,
+            variants,
+        }
+    }
+}
+
+impl CachedBody {
+    /// Returns the bytes of the body and its variants.
+    fn len(&self) -> u64 {
+        let variants = self
+            .variants
+            .iter()
+            .map(|(_, body)| body.len())
+            .sum::<usize>();
+        (self.body.len() + variants) as u64
+    }
+
+    fn respond(&self, req: &HttpRequest) -> HttpResponse {
+        let available = self.variants.iter().map(|(e, _)| *e).collect::<Vec<_>>();
+        let encoding = encoding::negotiate(req, &available);
+        let etag = http_util::entity_tag(&self.hash, encoding);
+        let precondition = conditional::evaluate(req, &etag, None);
+        let mut builder = match precondition {
+            Precondition::Passed => HttpResponse::Ok(),
+            Precondition::NotModified => HttpResponse::NotModified(),
+            Precondition::Failed => return HttpResponse::PreconditionFailed().finish(),
+        };
+        builder
+            .insert_header((header::CACHE_CONTROL, headers::NO_CACHE))
+            .insert_header(header::ETag(etag));
+        if !self.variants.is_empty() {
+            builder.insert_header((header::VARY, "Accept-Encoding"));
+        }
+        if precondition == Precondition::NotModified {
+            return builder.finish();
+        }
+        let body = match encoding {
+            Some(encoding) => {
+                builder.insert_header((
+                    header::CONTENT_ENCODING,
+                    HeaderValue::from_static(encoding.as_str()),
+                ));
+                self.variants
+                    .iter()
+                    .find(|(e, _)| *e == encoding)
+                    .map_or(&self.body, |(_, body)| body)
+            }
+            None => &self.body,
+        };
+        let mut resp = builder.body(body.clone());
+        let set = resp.headers().keys().cloned().collect::<Vec<_>>();
+        for (name, value) in &self.headers {
+            if !set.contains(name) {
+                resp.headers_mut().append(name.clone(), value.clone());
+            }
+        }
+        resp
+    }
+}
@@ -0,0 +1,62 @@
+use std::fmt::{Display, Formatter};
+
+/// How serious a [ConfigIssue] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// The option works, but likely not as intended, e.g. it refers to a file which is not
+    /// embedded.
+    Warning,
+    /// The option is invalid and ignored. An [Embed](crate::Embed) with errors panics when
+    /// it is registered.
+    Error,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        })
+    }
+}
+
+/// A configuration problem, reported by [Embed::try_build](crate::Embed::try_build) and
+/// [Embed::issues](crate::Embed::issues).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigIssue {
+    /// How serious the problem is.
+    pub severity: Severity,
+    /// The name of the option, as in [Embed::describe](crate::Embed::describe).
+    pub option: &'static str,
+    /// What is wrong with the option.
+    pub message: String,
+}
+
+impl ConfigIssue {
+    pub(crate) fn error<M: Display>(option: &'static str, message: M) -> Self {
+        ConfigIssue {
+            severity: Severity::Error,
+            option,
+            message: message.to_string(),
+        }
+    }
+
+    pub(crate) fn warning<M: Display>(option: &'static str, message: M) -> Self {
+        ConfigIssue {
+            severity: Severity::Warning,
+            option,
+            message: message.to_string(),
+        }
+    }
+}
+
+impl Display for ConfigIssue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.severity {
+            Severity::Warning => write!(f, "suspicious `{}`: {}", self.option, self.message),
+            Severity::Error => write!(f, "invalid `{}`: {}", self.option, self.message),
+        }
+    }
+}
+
+impl std::error::Error for ConfigIssue {}
@@ -0,0 +1,275 @@
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use actix_web::body::{BodySize, MessageBody};
+use actix_web::dev::ServiceResponse;
+use actix_web::http::header::EntityTag;
+use actix_web::http::{header, Method, StatusCode};
+use actix_web::web::Bytes;
+use actix_web::HttpMessage;
+
+use crate::context::ServeContext;
+use crate::encoding::Compression;
+use crate::hooks::HookFailure;
+use crate::metadata::FileMetadata;
+
+/// Describes a response generated by [Embed](crate::Embed), once its body was sent.
+///
+/// See [Embed::on_serve](crate::Embed::on_serve).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServeRecord {
+    /// The method of the request.
+    pub method: Method,
+    /// The path of the embedded file that was served, `None` if no file was served.
+    pub path: Option<String>,
+    /// The status code of the response.
+    pub status: StatusCode,
+    /// What kind of response was generated.
+    pub outcome: ServeOutcome,
+    /// The value of the `Content-Encoding` header of the response, if any other than
+    /// `identity`.
+    pub encoding: Option<String>,
+    /// The number of bytes in the response body.
+    ///
+    /// This is the number of bytes actually sent, i.e. zero for `HEAD` requests, the size of
+    /// the range for ranged requests, and the bytes sent before the client went away for
+    /// responses which were not sent completely.
+    pub bytes: u64,
+    /// The time taken to generate the response.
+    pub duration: Duration,
+    /// The hook guarding the request which failed to decide, if any.
+    pub hook_failure: Option<HookFailure>,
+    /// Why the file was served with or without compression, `None` if no file was served.
+    pub compression: Option<Compression>,
+    /// The time spent in each stage, if the serve took longer than the
+    /// [slow serve threshold](crate::Embed::slow_serve_threshold).
+    pub slow: Option<SlowServe>,
+    /// The metadata of the file that was served, if the
+    /// [metadata registry](crate::Embed::metadata_registry) is enabled.
+    pub metadata: Option<FileMetadata>,
+    /// Whether the file that was served is [deprecated](crate::Embed::deprecated).
+    pub deprecated: bool,
+    /// What was known about the request once it was served.
+    pub context: ServeContext,
+}
+
+/// A stage of serving a request, see [SlowServe].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ServeStage {
+    /// Checking the request and looking up the file, including hooks and the rate limiter.
+    Lookup,
+    /// Selecting the content encoding of the response.
+    Negotiation,
+    /// Loading the compressed variant, or deriving a variant, e.g. minifying the file.
+    Compression,
+    /// Building the response, e.g. its headers and conditional handling.
+    Write,
+}
+
+/// The time spent in each stage of a serve which took longer than the
+/// [slow serve threshold](crate::Embed::slow_serve_threshold).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlowServe {
+    /// The threshold the serve exceeded.
+    pub threshold: Duration,
+    /// The stages the serve went through and the time spent in each, in order.
+    ///
+    /// Requests not served with a file only go through [ServeStage::Lookup] and
+    /// [ServeStage::Write].
+    pub stages: Vec<(ServeStage, Duration)>,
+}
+
+impl SlowServe {
+    /// Returns the stage the most time was spent in.
+    pub fn dominant(&self) -> Option<ServeStage> {
+        self.stages
+            .iter()
+            .max_by_key(|(_, duration)| *duration)
+            .map(|(stage, _)| *stage)
+    }
+}
+
+/// The kind of a response generated by [Embed](crate::Embed).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ServeOutcome {
+    /// An embedded file was served with `200 OK`.
+    Ok,
+    /// A range of an embedded file was served with `206 Partial Content`.
+    PartialContent,
+    /// The cached copy of the client is still valid, `304 Not Modified`.
+    NotModified,
+    /// The response was generated by the fallback handler.
+    Fallback,
+    /// Any other response, e.g. redirects and rejected requests.
+    Other,
+}
+
+/// The embedded file a response was generated with.
+///
+/// [Embed](crate::Embed) inserts it into the extensions of the request and of the response
+/// when serving a file with `200 OK` or `304 Not Modified`, so wrapping middlewares can see
+/// what was served without parsing the headers.
+///
+/// # Examples
+/// ```
+/// use actix_embed::{Embed, ServedAsset};
+/// use actix_web::dev::Service;
+/// use actix_web::{App, HttpMessage};
+/// use rust_embed::RustEmbed;
+///
+/// #[derive(RustEmbed)]
+/// #[folder = "testdata/"]
+/// struct Assets;
+///
+/// let app = App::new()
+///     .wrap_fn(|req, srv| {
+///         let fut = srv.call(req);
+///         async move {
+///             let resp = fut.await?;
+///             if let Some(asset) = resp.request().extensions().get::<ServedAsset>() {
+///                 println!("served {} ({} bytes)", asset.path, asset.size);
+///             }
+///             Ok(resp)
+///         }
+///     })
+///     .service(Embed::new("/", &Assets));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServedAsset {
+    /// The path of the embedded file.
+    pub path: String,
+    /// The size of the body in bytes, i.e. of the compressed or derived variant if one was
+    /// selected.
+    pub size: u64,
+    /// The entity tag of the served variant.
+    pub etag: EntityTag,
+    /// The media type of the response.
+    pub mime: String,
+}
+
+/// Marks requests served with a deprecated file, see [ServeRecord::deprecated].
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Deprecated;
+
+/// How a request was handled by `Embed`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Served {
+    /// The embedded file at the path was used to generate the response.
+    File(String),
+    /// The fallback handler was called.
+    Fallback,
+    /// The request was answered without looking up a file, e.g. redirected or rejected.
+    Rejected,
+}
+
+impl ServeRecord {
+    pub(crate) fn new(
+        resp: &ServiceResponse,
+        served: Served,
+        context: ServeContext,
+        duration: Duration,
+        slow: Option<SlowServe>,
+    ) -> Self {
+        let method = resp.request().method().clone();
+        let encoding = resp
+            .headers()
+            .get(header::CONTENT_ENCODING)
+            .and_then(|v| v.to_str().ok())
+            .filter(|encoding| *encoding != "identity")
+            .map(str::to_owned);
+        let outcome = match (&served, resp.status()) {
+            (Served::Fallback, _) => ServeOutcome::Fallback,
+            (Served::File(_), StatusCode::OK) => ServeOutcome::Ok,
+            (Served::File(_), StatusCode::PARTIAL_CONTENT) => ServeOutcome::PartialContent,
+            (Served::File(_), StatusCode::NOT_MODIFIED) => ServeOutcome::NotModified,
+            _ => ServeOutcome::Other,
+        };
+        let path = match served {
+            Served::File(path) => Some(path),
+            Served::Fallback | Served::Rejected => None,
+        };
+
+        let hook_failure = resp.request().extensions().get::<HookFailure>().cloned();
+        let compression = resp.request().extensions().get::<Compression>().copied();
+        let metadata = resp.request().extensions().get::<FileMetadata>().cloned();
+        let deprecated = resp.request().extensions().contains::<Deprecated>();
+
+        ServeRecord {
+            method,
+            path,
+            status: resp.status(),
+            outcome,
+            encoding,
+            // counted while the body is sent, see [RecordedBody]
+            bytes: 0,
+            duration,
+            hook_failure,
+            compression,
+            slow,
+            metadata,
+            deprecated,
+            context,
+        }
+    }
+}
+
+/// Receives the [ServeRecord] of a response once its body was sent.
+pub(crate) type EmitRecord = Box<dyn FnOnce(ServeRecord)>;
+
+/// Emits the [ServeRecord] of a response once its body is complete, failed or dropped, e.g.
+/// because the client went away, with the number of bytes sent.
+pub(crate) struct RecordedBody<B> {
+    body: B,
+    /// The bytes yielded by the body so far.
+    sent: u64,
+    pending: Option<(ServeRecord, EmitRecord)>,
+}
+
+impl<B> RecordedBody<B> {
+    pub(crate) fn new(body: B, record: ServeRecord, emit: EmitRecord) -> Self {
+        RecordedBody {
+            body,
+            sent: 0,
+            pending: Some((record, emit)),
+        }
+    }
+
+    fn finish(&mut self) {
+        if let Some((mut record, emit)) = self.pending.take() {
+            // bodies of responses to `HEAD` requests are never sent
+            if record.method != Method::HEAD {
+                record.bytes = self.sent;
+            }
+            emit(record);
+        }
+    }
+}
+
+impl<B> Drop for RecordedBody<B> {
+    fn drop(&mut self) {
+        self.finish();
+    }
+}
+
+impl<B: MessageBody + Unpin> MessageBody for RecordedBody<B> {
+    type Error = B::Error;
+
+    fn size(&self) -> BodySize {
+        self.body.size()
+    }
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Self::Error>>> {
+        let this = self.get_mut();
+        let next = Pin::new(&mut this.body).poll_next(cx);
+        match &next {
+            Poll::Ready(Some(Ok(chunk))) => this.sent += chunk.len() as u64,
+            Poll::Ready(Some(Err(_)) | None) => this.finish(),
+            Poll::Pending => {}
+        }
+        next
+    }
+}
@@ -1,28 +1,204 @@
-use actix_web::{HttpRequest, HttpResponse};
+use std::borrow::Cow;
+
+use actix_web::http::StatusCode;
+use actix_web::web::Bytes;
+use actix_web::{HttpMessage, HttpRequest, HttpResponse, Responder};
+use futures_core::future::LocalBoxFuture;
+use mime_guess::MimeGuess;
+use rust_embed::EmbeddedFile;
+
+use crate::suggest::Suggestions;
 
 /// Fallback handlers will be called when no matched file could be found.
 pub trait FallbackHandler: 'static + Clone {
     #[allow(missing_docs)]
     fn execute(&self, req: &HttpRequest) -> HttpResponse;
+
+    /// Handles the request asynchronously, e.g. by forwarding it to another server.
+    ///
+    /// The service awaits this method, which calls [execute](Self::execute) by default.
+    /// Handlers overriding it are abandoned after the
+    /// [hook timeout](crate::Embed::hook_timeout), while `execute` is used by handlers
+    /// calling them synchronously.
+    fn execute_async(&self, req: HttpRequest) -> LocalBoxFuture<'static, HttpResponse> {
+        let resp = self.execute(&req);
+        Box::pin(async move { resp })
+    }
 }
 
-impl<T> FallbackHandler for T
+impl<T, R> FallbackHandler for T
 where
-    T: Fn(&HttpRequest) -> HttpResponse + Clone + 'static,
+    T: Fn(&HttpRequest) -> R + Clone + 'static,
+    R: Responder,
 {
     fn execute(&self, req: &HttpRequest) -> HttpResponse {
-        (self)(req)
+        (self)(req).respond_to(req).map_into_boxed_body()
     }
 }
 
 /// The default fallback handler.
 ///
-/// It returns 404 response regardless request information.
+/// By default it returns 404 response regardless request information. If [Suggestions] are
+/// available, the response is an HTML page listing them.
+///
+/// The status code, content type and body can be configured, without implementing
+/// [FallbackHandler].
+///
+/// # Examples
+/// ```
+/// use actix_embed::{DefaultFallbackHandler, Embed};
+/// use actix_web::http::StatusCode;
+/// use rust_embed::RustEmbed;
+///
+/// #[derive(RustEmbed)]
+/// #[folder = "testdata/"]
+/// struct Assets;
+///
+/// // respond with the embedded `index.html`, e.g. for single-page applications
+/// let embed = Embed::new("/", &Assets).fallback_handler(
+///     DefaultFallbackHandler::new()
+///         .status(StatusCode::OK)
+///         .file::<Assets, _>("index.html"),
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct DefaultFallbackHandler {
+    status: StatusCode,
+    content_type: Option<String>,
+    body: Body,
+}
+
 #[derive(Debug, Clone)]
-pub struct DefaultFallbackHandler;
+enum Body {
+    /// A plain text message, or the suggestions page if there are suggestions.
+    Default,
+    Static(Bytes),
+    File {
+        get: fn(&str) -> Option<EmbeddedFile>,
+        path: String,
+    },
+}
+
+impl Default for DefaultFallbackHandler {
+    fn default() -> Self {
+        DefaultFallbackHandler {
+            status: StatusCode::NOT_FOUND,
+            content_type: None,
+            body: Body::Default,
+        }
+    }
+}
+
+impl DefaultFallbackHandler {
+    /// Create new [DefaultFallbackHandler] returning 404 responses.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the status code of responses.
+    ///
+    /// Defaults to `404 Not Found`.
+    pub fn status(mut self, status: StatusCode) -> Self {
+        self.status = status;
+        self
+    }
+
+    /// Set the content type of responses.
+    ///
+    /// By default it is derived from the body.
+    pub fn content_type<T: AsRef<str>>(mut self, content_type: T) -> Self {
+        self.content_type = Some(content_type.as_ref().to_owned());
+        self
+    }
+
+    /// Respond with a static body. Responses have no content type unless it's set.
+    pub fn body<B: Into<Bytes>>(mut self, body: B) -> Self {
+        self.body = Body::Static(body.into());
+        self
+    }
+
+    /// Respond with the embedded file at `path` of `E`, whose content type is guessed from
+    /// its path unless it's set.
+    ///
+    /// If the file does not exist, responses have the default body.
+    pub fn file<E, P>(mut self, path: P) -> Self
+    where
+        E: rust_embed::RustEmbed,
+        P: AsRef<str>,
+    {
+        self.body = Body::File {
+            get: E::get,
+            path: path.as_ref().trim_start_matches('/').to_owned(),
+        };
+        self
+    }
+}
 
 impl FallbackHandler for DefaultFallbackHandler {
-    fn execute(&self, _: &HttpRequest) -> HttpResponse {
-        HttpResponse::NotFound().body("404 Not Found")
+    fn execute(&self, req: &HttpRequest) -> HttpResponse {
+        let mut builder = HttpResponse::build(self.status);
+        let (content_type, body) = match &self.body {
+            Body::Static(body) => (None, body.clone()),
+            Body::File { get, path } => match get(path) {
+                Some(file) => (
+                    Some(
+                        MimeGuess::from_path(path)
+                            .first_or_octet_stream()
+                            .to_string(),
+                    ),
+                    match file.data {
+                        Cow::Borrowed(data) => Bytes::from_static(data),
+                        Cow::Owned(data) => Bytes::from(data),
+                    },
+                ),
+                None => default_body(req),
+            },
+            Body::Default => default_body(req),
+        };
+        if let Some(content_type) = self.content_type.clone().or(content_type) {
+            builder.content_type(content_type);
+        }
+        builder.body(body)
+    }
+}
+
+/// Returns the content type and body of the default response.
+fn default_body(req: &HttpRequest) -> (Option<String>, Bytes) {
+    match req.extensions().get::<Suggestions>() {
+        Some(suggestions) if !suggestions.urls.is_empty() => (
+            Some("text/html; charset=utf-8".to_owned()),
+            Bytes::from(did_you_mean_page(&suggestions.urls)),
+        ),
+        _ => (None, Bytes::from_static(b"404 Not Found")),
+    }
+}
+
+fn did_you_mean_page(urls: &[String]) -> String {
+    let items = urls
+        .iter()
+        .map(|url| {
+            let url = escape_html(url);
+            format!("<li><a href=\"{}\">{}</a></li>", url, url)
+        })
+        .collect::<String>();
+    format!(
+        "<!DOCTYPE html><html><head><meta charset=\"utf-8\"><title>404 Not Found</title></head>\
+         <body><h1>404 Not Found</h1><p>Did you mean:</p><ul>{}</ul></body></html>",
+        items
+    )
+}
+
+pub(crate) fn escape_html(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
     }
+    escaped
 }
@@ -0,0 +1,481 @@
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use actix_web::http::StatusCode;
+use rust_embed::EmbeddedFile;
+
+use crate::availability::{self, Availability, Window};
+use crate::glob::Glob;
+use crate::mime_allowlist::MimeAllowlist;
+use crate::registry::{AssetCollision, CollisionPolicy, EmbedRegistry, Source};
+
+/// Maps request paths to the keys of embedded files, see
+/// [Embed::strip_embed_prefix](crate::Embed::strip_embed_prefix) and
+/// [Embed::folder](crate::Embed::folder).
+#[derive(Debug, Clone, Default)]
+pub(crate) struct KeyMap {
+    /// The prefix removed from keys, ending with `/`.
+    strip: Option<String>,
+    /// The folder of the served files in the rewritten keys, ending with `/`.
+    folder: Option<String>,
+    /// The files registered with [Embed::registry](crate::Embed::registry).
+    sources: Vec<Source>,
+    policy: CollisionPolicy,
+    /// Whether SVG images are sanitized, see [Embed::sanitize_svg](crate::Embed::sanitize_svg).
+    sanitize_svg: bool,
+    sanitized: Sanitized,
+    /// The media types of the served files, with the media types by extension, see
+    /// [Embed::allowed_mime_types](crate::Embed::allowed_mime_types).
+    allowlist: Option<Arc<(MimeAllowlist, HashMap<String, String>)>>,
+}
+
+/// Appended to the content hashes of sanitized files, which are computed from the embedded
+/// content, so entity tags and cached artifacts change with the served content.
+pub(crate) const SANITIZED: &str = "-sanitized";
+
+/// The sanitized content of SVG images, by key, served instead of the embedded content.
+#[derive(Clone, Default)]
+struct Sanitized(Option<Arc<HashMap<String, Vec<u8>>>>);
+
+impl Debug for Sanitized {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let files = self.0.as_ref().map_or(0, |files| files.len());
+        write!(f, "Sanitized({} files)", files)
+    }
+}
+
+impl KeyMap {
+    pub(crate) fn new(strip: Option<&str>, folder: Option<&str>) -> Self {
+        KeyMap {
+            strip: strip.map(|strip| format!("{}/", strip)),
+            folder: folder.map(|folder| format!("{}/", folder)),
+            sources: Vec::new(),
+            policy: CollisionPolicy::default(),
+            sanitize_svg: false,
+            sanitized: Sanitized::default(),
+            allowlist: None,
+        }
+    }
+
+    /// Only serve the files whose media types, resolved with `mime_types`, are in `allowlist`.
+    pub(crate) fn with_allowlist(
+        mut self,
+        allowlist: Option<&MimeAllowlist>,
+        mime_types: &HashMap<String, String>,
+    ) -> Self {
+        self.allowlist =
+            allowlist.map(|allowlist| Arc::new((allowlist.clone(), mime_types.clone())));
+        self
+    }
+
+    /// Returns `true` if the file at `path` may be served by its media type.
+    pub(crate) fn allows(&self, path: &str) -> bool {
+        self.allowlist
+            .as_ref()
+            .is_none_or(|allowlist| allowlist.0.allows(path, &allowlist.1))
+    }
+
+    /// Returns the status of the response to requests for `path`, if there is a file at `path`
+    /// whose media type is not allowed.
+    pub(crate) fn refused<E>(&self, path: &str) -> Option<StatusCode>
+    where
+        E: 'static + rust_embed::RustEmbed,
+    {
+        let allowlist = self.allowlist.as_ref()?;
+        match self.allows(path) {
+            true => None,
+            false => self.lookup::<E>(path).map(|_| allowlist.0.refused),
+        }
+    }
+
+    /// Sanitize SVG images once [with_sanitized](Self::with_sanitized) sets their content.
+    #[cfg(feature = "sanitize-svg")]
+    pub(crate) fn sanitize_svg(mut self, enabled: bool) -> Self {
+        self.sanitize_svg = enabled;
+        self
+    }
+
+    /// Returns `true` if SVG images are sanitized.
+    #[cfg(feature = "sanitize-svg")]
+    pub(crate) fn sanitizes_svg(&self) -> bool {
+        self.sanitize_svg
+    }
+
+    /// Serves `files`, by key, instead of the embedded content of SVG images.
+    #[cfg(feature = "sanitize-svg")]
+    pub(crate) fn with_sanitized(&mut self, files: HashMap<String, Vec<u8>>) {
+        self.sanitized = Sanitized(Some(Arc::new(files)));
+    }
+
+    /// Returns `true` if the file with `key` is served with sanitized content.
+    pub(crate) fn is_sanitized(&self, key: &str) -> bool {
+        self.sanitized
+            .0
+            .as_ref()
+            .is_some_and(|files| files.contains_key(key))
+    }
+
+    /// Returns `file`, with its sanitized content if the file with `key` is sanitized.
+    pub(crate) fn content(&self, key: &str, mut file: EmbeddedFile) -> EmbeddedFile {
+        if let Some(data) = self.sanitized.0.as_ref().and_then(|files| files.get(key)) {
+            file.data = Cow::Owned(data.clone());
+        }
+        file
+    }
+
+    /// Also serve the files registered in `registry`.
+    pub(crate) fn with_registry(mut self, registry: &EmbedRegistry) -> Self {
+        self.sources = registry.sources.clone();
+        self.policy = registry.policy;
+        self
+    }
+
+    /// Returns the registered sources.
+    pub(crate) fn sources(&self) -> &[Source] {
+        &self.sources
+    }
+
+    /// Returns the path at which the embedded file with `key` is served, `None` if it is not.
+    pub(crate) fn path<'a>(&self, key: &'a str) -> Option<&'a str> {
+        let key = match &self.strip {
+            Some(strip) => key.strip_prefix(strip.as_str()).unwrap_or(key),
+            None => key,
+        };
+        match &self.folder {
+            Some(folder) => key.strip_prefix(folder.as_str()),
+            None => Some(key),
+        }
+    }
+
+    /// Returns the key and content of the embedded file served at `path`, sanitized if it is
+    /// an SVG image being sanitized.
+    ///
+    /// Files with the stripped prefix take precedence over files without it. Which of the
+    /// embedded files and the files of registered sources takes precedence depends on the
+    /// [CollisionPolicy], except under the prefix of a [namespace](crate::Embed::namespace),
+    /// where only its files are served. Keys of the files of registered sources are the paths
+    /// they are served at. Files whose media types are not
+    /// [allowed](crate::Embed::allowed_mime_types) are never returned.
+    pub(crate) fn get<E>(&self, path: &str) -> Option<(String, EmbeddedFile)>
+    where
+        E: 'static + rust_embed::RustEmbed,
+    {
+        if !self.allows(path) {
+            return None;
+        }
+        let (key, file) = self.lookup::<E>(path)?;
+        let file = self.content(&key, file);
+        Some((key, file))
+    }
+
+    /// Returns the key and embedded content of the file at `path`, see [get](Self::get).
+    fn lookup<E>(&self, path: &str) -> Option<(String, EmbeddedFile)>
+    where
+        E: 'static + rust_embed::RustEmbed,
+    {
+        let sourced = |source: &Source| Some((path.to_owned(), source.get(path)?));
+        match self.namespace(path) {
+            Some(namespace) => sourced(namespace),
+            None => match self.policy {
+                CollisionPolicy::LastWins => self
+                    .sources
+                    .iter()
+                    .rev()
+                    .find_map(sourced)
+                    .or_else(|| self.embedded::<E>(path)),
+                CollisionPolicy::FirstWins | CollisionPolicy::Error => self
+                    .embedded::<E>(path)
+                    .or_else(|| self.sources.iter().find_map(sourced)),
+            },
+        }
+    }
+
+    /// Returns the namespace owning `path`, the one added last if their prefixes are nested.
+    fn namespace(&self, path: &str) -> Option<&Source> {
+        self.sources.iter().rev().find(|source| source.owns(path))
+    }
+
+    /// Returns `true` if `path` is served from the files of `source`, `None` for the embedded
+    /// files, rather than hidden by a namespace.
+    pub(crate) fn visible(&self, path: &str, source: Option<&Source>) -> bool {
+        match self.namespace(path) {
+            Some(namespace) => source.is_some_and(|source| std::ptr::eq(source, namespace)),
+            None => true,
+        }
+    }
+
+    /// Returns the paths at which the embedded files and the files of registered sources
+    /// collide, sorted.
+    ///
+    /// Files hidden by a [namespace](crate::Embed::namespace) are not collisions, as the
+    /// namespace claims its prefix explicitly.
+    pub(crate) fn collisions<E>(&self) -> Vec<AssetCollision>
+    where
+        E: 'static + rust_embed::RustEmbed,
+    {
+        if self.sources.is_empty() {
+            return Vec::new();
+        }
+        let mut providers = BTreeMap::<String, Vec<&'static str>>::new();
+        let mut embedded = E::iter()
+            .filter_map(|key| self.path(&key).map(str::to_owned))
+            .filter(|path| self.visible(path, None))
+            .collect::<Vec<_>>();
+        embedded.sort_unstable();
+        embedded.dedup();
+        for path in embedded {
+            providers
+                .entry(path)
+                .or_default()
+                .push(std::any::type_name::<E>());
+        }
+        for source in &self.sources {
+            for path in source
+                .paths()
+                .filter(|path| self.visible(path, Some(source)))
+            {
+                providers.entry(path).or_default().push(source.name);
+            }
+        }
+        providers
+            .into_iter()
+            .filter(|(_, names)| names.len() > 1)
+            .map(|(path, mut names)| {
+                if self.policy == CollisionPolicy::LastWins {
+                    names.reverse();
+                }
+                let served = names.remove(0);
+                AssetCollision {
+                    path,
+                    served,
+                    shadowed: names,
+                }
+            })
+            .collect()
+    }
+
+    fn embedded<E>(&self, path: &str) -> Option<(String, EmbeddedFile)>
+    where
+        E: 'static + rust_embed::RustEmbed,
+    {
+        let key = match &self.folder {
+            Some(folder) => format!("{}{}", folder, path),
+            None => path.to_owned(),
+        };
+        if let Some(strip) = &self.strip {
+            let stripped = format!("{}{}", strip, key);
+            if let Some(file) = E::get(&stripped) {
+                return Some((stripped, file));
+            }
+            // keys with the prefix are only served without it
+            if key.starts_with(strip.as_str()) {
+                return None;
+            }
+        }
+        let file = E::get(&key)?;
+        Some((key, file))
+    }
+}
+
+/// The paths of the served files in sorted order, relative to the served folder.
+///
+/// Files under a directory are adjacent in sorted order, so lookups, directory contents and
+/// index resolution take `O(log n)` binary searches, even for very large embeds.
+#[derive(Debug, Default)]
+pub(crate) struct FileIndex {
+    paths: Vec<String>,
+}
+
+/// The files of a [FileIndex] enumerated by the endpoints listing or bundling them, e.g.
+/// listings and archives: files which are not [excluded](crate::Embed::exclude) and within
+/// their [availability windows](crate::Embed::available), like the files served at their
+/// paths.
+///
+/// Files whose media types are not [allowed](crate::Embed::allowed_mime_types) are not
+/// indexed at all.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Visibility<'a> {
+    excluded: &'a [Glob],
+    availability: &'a [Availability],
+    now: SystemTime,
+}
+
+impl<'a> Visibility<'a> {
+    pub(crate) fn new(
+        excluded: &'a [Glob],
+        availability: &'a [Availability],
+        now: SystemTime,
+    ) -> Self {
+        Visibility {
+            excluded,
+            availability,
+            now,
+        }
+    }
+
+    /// Returns `true` if `path` is excluded.
+    pub(crate) fn excludes(&self, path: &str) -> bool {
+        self.excluded.iter().any(|glob| glob.matches(path))
+    }
+
+    /// Returns `true` if the file at `path` is shown.
+    pub(crate) fn shows(&self, path: &str) -> bool {
+        !self.excludes(path)
+            && availability::window(self.availability, path, self.now) == Window::Open
+    }
+
+    /// Returns the state of the availability windows, which changes whenever a window opens
+    /// or closes, e.g. to key responses enumerating files.
+    pub(crate) fn windows(&self) -> String {
+        self.availability
+            .iter()
+            .map(|rule| match rule.window(self.now) {
+                Window::Before => 'b',
+                Window::Open => 'o',
+                Window::After => 'a',
+            })
+            .collect()
+    }
+}
+
+/// An entry of a directory in a [FileIndex].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Entry<'a> {
+    /// A file, by its name.
+    File(&'a str),
+    /// A directory, by its name.
+    Dir(&'a str),
+}
+
+impl FileIndex {
+    /// Indexes the embedded files served with `keys`.
+    pub(crate) fn build<E>(keys: &KeyMap) -> Self
+    where
+        E: 'static + rust_embed::RustEmbed,
+    {
+        let embedded = E::iter()
+            .filter_map(|key| keys.path(&key).map(str::to_owned))
+            .filter(|path| keys.visible(path, None));
+        let sourced = keys.sources.iter().flat_map(|source| {
+            source
+                .paths()
+                .filter(move |path| keys.visible(path, Some(source)))
+        });
+        let mut paths = embedded
+            .chain(sourced)
+            .filter(|path| keys.allows(path))
+            .collect::<Vec<_>>();
+        paths.sort_unstable();
+        // a file with the stripped prefix and one without it, or a file of a registered source,
+        // may be served at the same path
+        paths.dedup();
+        FileIndex { paths }
+    }
+
+    /// Returns the paths of all files, sorted.
+    pub(crate) fn paths(&self) -> &[String] {
+        &self.paths
+    }
+
+    /// Returns `true` if there is a file at `path`.
+    pub(crate) fn contains(&self, path: &str) -> bool {
+        self.paths
+            .binary_search_by(|probe| probe.as_str().cmp(path))
+            .is_ok()
+    }
+
+    /// Returns the paths of the files under the directory `dir`, `""` being the root.
+    pub(crate) fn under(&self, dir: &str) -> &[String] {
+        let dir = dir.trim_matches('/');
+        if dir.is_empty() {
+            return &self.paths;
+        }
+        let prefix = format!("{}/", dir);
+        let start = self
+            .paths
+            .partition_point(|path| path.as_str() < prefix.as_str());
+        let len = self.paths[start..].partition_point(|path| path.starts_with(prefix.as_str()));
+        &self.paths[start..start + len]
+    }
+
+    /// Returns the paths of the files under the directory `dir` shown by `visibility`, sorted.
+    pub(crate) fn visible_under<'a>(
+        &'a self,
+        dir: &str,
+        visibility: &'a Visibility<'_>,
+    ) -> impl Iterator<Item = &'a String> + 'a {
+        self.under(dir)
+            .iter()
+            .filter(move |path| visibility.shows(path))
+    }
+
+    /// Returns `true` if `dir` is a directory containing files, `""` being the root.
+    pub(crate) fn is_dir(&self, dir: &str) -> bool {
+        !self.under(dir).is_empty()
+    }
+
+    /// Returns the files and directories directly in the directory `dir`, sorted by name.
+    pub(crate) fn entries(&self, dir: &str) -> Vec<Entry<'_>> {
+        entries(dir, self.under(dir))
+    }
+
+    /// Returns the entries of the directory `dir` like [entries](Self::entries), with only the
+    /// files shown by `visibility` and the directories containing any.
+    pub(crate) fn visible_entries(&self, dir: &str, visibility: &Visibility<'_>) -> Vec<Entry<'_>> {
+        let paths = self.under(dir).iter();
+        entries(dir, paths.filter(|path| visibility.shows(path)))
+    }
+
+    /// Returns the path of the file named `name` in the deepest directory containing `path`,
+    /// `None` if no such directory has one.
+    ///
+    /// `path` itself is treated as a directory if the index has files under it.
+    pub(crate) fn nearest(&self, path: &str, name: &str) -> Option<String> {
+        let path = path.trim_matches('/');
+        let mut dir = if self.is_dir(path) {
+            path
+        } else {
+            path.rsplit_once('/').map_or("", |(dir, _)| dir)
+        };
+        loop {
+            let candidate = match dir {
+                "" => name.to_owned(),
+                dir => format!("{}/{}", dir, name),
+            };
+            if self.contains(&candidate) {
+                return Some(candidate);
+            }
+            if dir.is_empty() {
+                return None;
+            }
+            dir = dir.rsplit_once('/').map_or("", |(dir, _)| dir);
+        }
+    }
+}
+
+/// Returns the files and directories directly in the directory `dir` of the sorted `paths`
+/// under it, sorted by name.
+fn entries<'a>(dir: &str, paths: impl IntoIterator<Item = &'a String>) -> Vec<Entry<'a>> {
+    let skip = match dir.trim_matches('/') {
+        "" => 0,
+        dir => dir.len() + 1,
+    };
+    let mut entries = Vec::new();
+    for path in paths {
+        let entry = match path[skip..].split_once('/') {
+            Some((name, _)) => Entry::Dir(name),
+            None => Entry::File(&path[skip..]),
+        };
+        // the files of a directory are adjacent, so each directory is only added once
+        if entries.last() != Some(&entry) {
+            entries.push(entry);
+        }
+    }
+    entries.sort_by_key(|entry| match entry {
+        Entry::File(name) | Entry::Dir(name) => *name,
+    });
+    entries
+}
@@ -0,0 +1,101 @@
+use std::net::IpAddr;
+
+use actix_web::http::header;
+use actix_web::HttpRequest;
+
+/// Determines whether `Forwarded` and `X-Forwarded-*` headers are trusted when generating
+/// absolute URLs, e.g. for [Embed::canonical_host](crate::Embed::canonical_host).
+///
+/// Defaults to [ForwardedPolicy::Ignore], since any client can set these headers.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum ForwardedPolicy {
+    /// Use the scheme of the connection and the `Host` header.
+    #[default]
+    Ignore,
+    /// Use the scheme and host reported by forwarded headers if present.
+    Trust,
+    /// Use the scheme and host reported by forwarded headers only if the peer is one of
+    /// the given proxies.
+    TrustedProxies(Vec<IpAddr>),
+}
+
+/// The scheme and host a request was sent to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Origin {
+    pub(crate) scheme: String,
+    pub(crate) host: String,
+}
+
+impl ForwardedPolicy {
+    fn trusts(&self, req: &HttpRequest) -> bool {
+        match self {
+            ForwardedPolicy::Ignore => false,
+            ForwardedPolicy::Trust => true,
+            ForwardedPolicy::TrustedProxies(proxies) => req
+                .peer_addr()
+                .is_some_and(|peer| proxies.contains(&peer.ip())),
+        }
+    }
+
+    /// Returns the origin of the request according to the policy.
+    pub(crate) fn origin(&self, req: &HttpRequest) -> Origin {
+        let (mut scheme, mut host) = (None, None);
+        if self.trusts(req) {
+            (scheme, host) = forwarded(req);
+            scheme = scheme.or_else(|| first_header_value(req, "x-forwarded-proto"));
+            host = host.or_else(|| first_header_value(req, "x-forwarded-host"));
+        }
+
+        let scheme = scheme.unwrap_or_else(|| {
+            let secure = req.app_config().secure() || req.uri().scheme_str() == Some("https");
+            (if secure { "https" } else { "http" }).to_owned()
+        });
+        let host = host
+            .or_else(|| first_header_value(req, header::HOST.as_str()))
+            .or_else(|| req.uri().authority().map(|a| a.as_str().to_owned()))
+            .unwrap_or_else(|| req.app_config().host().to_owned());
+
+        Origin {
+            scheme: scheme.to_ascii_lowercase(),
+            host,
+        }
+    }
+
+    /// Returns the absolute URL of `path_and_query` on the origin of the request.
+    pub(crate) fn absolute_url(&self, req: &HttpRequest, path_and_query: &str) -> String {
+        let origin = self.origin(req);
+        format!("{}://{}{}", origin.scheme, origin.host, path_and_query)
+    }
+}
+
+/// Returns the `proto` and `host` of the first element of the `Forwarded` header.
+fn forwarded(req: &HttpRequest) -> (Option<String>, Option<String>) {
+    let (mut proto, mut host) = (None, None);
+    let value = match req
+        .headers()
+        .get(header::FORWARDED)
+        .and_then(|v| v.to_str().ok())
+    {
+        Some(value) => value,
+        None => return (proto, host),
+    };
+
+    let first = value.split(',').next().unwrap_or_default();
+    for pair in first.split(';') {
+        if let Some((name, value)) = pair.trim().split_once('=') {
+            let value = value.trim().trim_matches('"').to_owned();
+            match name.trim().to_ascii_lowercase().as_str() {
+                "proto" => proto = Some(value),
+                "host" => host = Some(value),
+                _ => {}
+            }
+        }
+    }
+    (proto, host)
+}
+
+fn first_header_value(req: &HttpRequest, name: &str) -> Option<String> {
+    let value = req.headers().get(name)?.to_str().ok()?;
+    let first = value.split(',').next()?.trim();
+    (!first.is_empty()).then(|| first.to_owned())
+}
@@ -0,0 +1,163 @@
+/// A glob pattern matched against embedded file paths.
+///
+/// - `?` matches any character except `/`;
+/// - `*` matches any sequence of characters except `/`;
+/// - `**` matches any sequence of characters, `**/` also matches no directory at all.
+///
+/// Leading slashes of patterns are ignored, since embedded file paths are relative.
+///
+/// With the `compact-globs` feature, patterns with a single `*`, `**` or `**/`, or with
+/// `**/*`, e.g. `assets/**`, `**/*.css` or `vendor/*.js`, are compiled to a literal prefix
+/// and suffix around the wildcard, matched in linear time without backtracking or
+/// allocating. Other patterns, e.g. with `?` or wildcards in multiple segments, still match
+/// as above, and are reported by [Embed::issues](crate::Embed::issues), so they are rejected
+/// by [Embed::try_build](crate::Embed::try_build).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Glob {
+    pattern: String,
+    /// The compiled pattern, `None` if it has no compact form.
+    #[cfg(feature = "compact-globs")]
+    compact: Option<Compact>,
+}
+
+impl Glob {
+    pub(crate) fn new<P: AsRef<str>>(pattern: P) -> Self {
+        let pattern = pattern.as_ref().trim_start_matches('/').to_owned();
+        Glob {
+            #[cfg(feature = "compact-globs")]
+            compact: Compact::new(&pattern),
+            pattern,
+        }
+    }
+
+    pub(crate) fn as_str(&self) -> &str {
+        &self.pattern
+    }
+
+    #[cfg(not(feature = "compact-globs"))]
+    pub(crate) fn matches(&self, path: &str) -> bool {
+        matches(
+            self.pattern.as_bytes(),
+            path.trim_start_matches('/').as_bytes(),
+        )
+    }
+
+    #[cfg(feature = "compact-globs")]
+    pub(crate) fn matches(&self, path: &str) -> bool {
+        let path = path.trim_start_matches('/');
+        match &self.compact {
+            Some(compact) => compact.matches(&self.pattern, path),
+            None => matches(self.pattern.as_bytes(), path.as_bytes()),
+        }
+    }
+
+    /// Returns `true` if the pattern is matched by its compact form, without backtracking.
+    #[cfg(feature = "compact-globs")]
+    pub(crate) fn is_compact(&self) -> bool {
+        self.compact.is_some()
+    }
+}
+
+fn matches(pattern: &[u8], path: &[u8]) -> bool {
+    match pattern {
+        [] => path.is_empty(),
+        [b'*', b'*', b'/', rest @ ..] => {
+            // `**/` matches zero or more directories.
+            matches(rest, path)
+                || path
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, &c)| c == b'/')
+                    .any(|(i, _)| matches(rest, &path[i + 1..]))
+        }
+        [b'*', b'*', rest @ ..] => (0..=path.len()).any(|i| matches(rest, &path[i..])),
+        [b'*', rest @ ..] => {
+            let segment = path.iter().position(|&c| c == b'/').unwrap_or(path.len());
+            (0..=segment).any(|i| matches(rest, &path[i..]))
+        }
+        [b'?', rest @ ..] => matches!(path, [c, ..] if *c != b'/') && matches(rest, &path[1..]),
+        [c, rest @ ..] => path.first() == Some(c) && matches(rest, &path[1..]),
+    }
+}
+
+/// What a compact glob matches between its literal prefix and suffix.
+#[cfg(feature = "compact-globs")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Wildcard {
+    /// No wildcard, the path is the pattern.
+    None,
+    /// `*`, characters except `/`.
+    Segment,
+    /// `**` or `**/*`, any characters.
+    Any,
+    /// `**/`, nothing or directories.
+    Directories,
+}
+
+/// A glob compiled to the byte offsets of its prefix and suffix in the pattern.
+#[cfg(feature = "compact-globs")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Compact {
+    prefix: usize,
+    suffix: usize,
+    wildcard: Wildcard,
+}
+
+#[cfg(feature = "compact-globs")]
+impl Compact {
+    /// Returns the compiled `pattern`, `None` if it cannot be matched exactly by a prefix and
+    /// suffix.
+    fn new(pattern: &str) -> Option<Self> {
+        let is_wildcard = |c| c == '*' || c == '?';
+        let prefix = match pattern.find(is_wildcard) {
+            Some(prefix) => prefix,
+            None => {
+                return Some(Compact {
+                    prefix: pattern.len(),
+                    suffix: pattern.len(),
+                    wildcard: Wildcard::None,
+                })
+            }
+        };
+        let suffix = pattern.rfind(is_wildcard).map_or(prefix, |last| last + 1);
+        let (suffix, wildcard) = match &pattern[prefix..suffix] {
+            "*" => (suffix, Wildcard::Segment),
+            "**" if pattern[suffix..].starts_with('/') => (suffix + 1, Wildcard::Directories),
+            "**" | "**/*" => (suffix, Wildcard::Any),
+            _ => return None,
+        };
+        Some(Compact {
+            prefix,
+            suffix,
+            wildcard,
+        })
+    }
+
+    fn matches(&self, pattern: &str, path: &str) -> bool {
+        let (prefix, suffix) = (&pattern[..self.prefix], &pattern[self.suffix..]);
+        if self.wildcard == Wildcard::None {
+            return path == pattern;
+        }
+        let middle = match path
+            .strip_prefix(prefix)
+            .and_then(|rest| rest.strip_suffix(suffix))
+        {
+            Some(middle) => middle,
+            None => return false,
+        };
+        match self.wildcard {
+            Wildcard::Segment => !middle.contains('/'),
+            Wildcard::Directories => middle.is_empty() || middle.ends_with('/'),
+            Wildcard::None | Wildcard::Any => true,
+        }
+    }
+}
+
+/// Converts a list of patterns to globs.
+pub(crate) fn globs<I>(patterns: I) -> Vec<Glob>
+where
+    I: IntoIterator,
+    I::Item: AsRef<str>,
+{
+    patterns.into_iter().map(Glob::new).collect()
+}
@@ -0,0 +1,57 @@
+#[cfg(feature = "hashed-filename-pattern")]
+use regex::Regex;
+
+/// The pattern of content hashes in file names by default, e.g. `app.3f2a9c1d.js`.
+pub(crate) const DEFAULT_PATTERN: &str = r"\.[0-9a-f]{8,}\.";
+
+/// Detects content hashes in file names, see
+/// [Embed::hashed_filenames](crate::Embed::hashed_filenames).
+#[derive(Debug, Clone)]
+pub(crate) enum HashedNames {
+    /// Names matching [DEFAULT_PATTERN], matched without a regular expression.
+    Default,
+    /// Names matching a regular expression, see
+    /// [Embed::hashed_filename_pattern](crate::Embed::hashed_filename_pattern).
+    #[cfg(feature = "hashed-filename-pattern")]
+    Pattern(Regex),
+}
+
+impl HashedNames {
+    /// Returns the detector of file names matching `pattern`, or the error of the pattern.
+    #[cfg(feature = "hashed-filename-pattern")]
+    pub(crate) fn new(pattern: &str) -> Result<Self, String> {
+        let pattern = Regex::new(pattern).map_err(|err| err.to_string())?;
+        Ok(HashedNames::Pattern(pattern))
+    }
+
+    /// Returns the pattern file names are matched with.
+    pub(crate) fn pattern(&self) -> &str {
+        match self {
+            HashedNames::Default => DEFAULT_PATTERN,
+            #[cfg(feature = "hashed-filename-pattern")]
+            HashedNames::Pattern(pattern) => pattern.as_str(),
+        }
+    }
+
+    /// Returns `true` if the name of the file at `path` contains a content hash, so its
+    /// content never changes at this path.
+    pub(crate) fn is_hashed(&self, path: &str) -> bool {
+        let name = path.rsplit('/').next().unwrap_or(path);
+        match self {
+            HashedNames::Default => has_default_hash(name.as_bytes()),
+            #[cfg(feature = "hashed-filename-pattern")]
+            HashedNames::Pattern(pattern) => pattern.is_match(name),
+        }
+    }
+}
+
+/// Returns `true` if `name` contains a `.` followed by at least 8 lowercase hexadecimal digits
+/// and another `.`, as matched by [DEFAULT_PATTERN].
+fn has_default_hash(name: &[u8]) -> bool {
+    let is_hex = |b: &u8| b.is_ascii_digit() || (b'a'..=b'f').contains(b);
+    name.iter().enumerate().any(|(i, &b)| {
+        let rest = &name[i + 1..];
+        let digits = rest.iter().take_while(|b| is_hex(b)).count();
+        b == b'.' && digits >= 8 && rest.get(digits) == Some(&b'.')
+    })
+}
@@ -0,0 +1,164 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use rust_embed::EmbeddedFile;
+use sha2::Digest;
+
+use crate::parallel;
+
+/// Computes the content hashes of embedded files, used as validators in entity tags.
+///
+/// Hashes of embedded files are cached per path, so each file is hashed at most once per
+/// service, or per [DerivedCache](crate::DerivedCache) shared by services. See
+/// [Embed::hasher](crate::Embed::hasher).
+pub trait Hasher: 'static {
+    /// Returns the hash of `file`.
+    fn hash(&self, file: &EmbeddedFile) -> Vec<u8>;
+
+    /// Returns whether hashes are cheap enough to be computed for each request instead of
+    /// being cached.
+    fn is_precomputed(&self) -> bool {
+        false
+    }
+
+    /// Returns the hasher if it can be shared between threads, so files are hashed on all
+    /// cores when services are created with [Preparation::Eager](crate::Preparation::Eager).
+    ///
+    /// Returns `None` by default, files are then hashed on the thread creating the service.
+    fn as_sync(&self) -> Option<&(dyn Hasher + Sync)> {
+        None
+    }
+}
+
+/// SHA-256, the default [Hasher].
+///
+/// The hashes are computed by `rust_embed` at compile time.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Sha256;
+
+impl Hasher for Sha256 {
+    fn hash(&self, file: &EmbeddedFile) -> Vec<u8> {
+        file.metadata.sha256_hash().to_vec()
+    }
+
+    fn is_precomputed(&self) -> bool {
+        true
+    }
+}
+
+/// BLAKE3, much faster than SHA-256 for large files.
+///
+/// Requires the `hash-blake3` feature.
+#[cfg(feature = "hash-blake3")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Blake3;
+
+#[cfg(feature = "hash-blake3")]
+impl Hasher for Blake3 {
+    fn hash(&self, file: &EmbeddedFile) -> Vec<u8> {
+        blake3::hash(&file.data).as_bytes().to_vec()
+    }
+
+    fn as_sync(&self) -> Option<&(dyn Hasher + Sync)> {
+        Some(self)
+    }
+}
+
+/// XXH3 with 128-bit output. It is not a cryptographic hash, but fast and sufficient to
+/// tell versions of files apart.
+///
+/// Requires the `hash-xxhash` feature.
+#[cfg(feature = "hash-xxhash")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Xxh3;
+
+#[cfg(feature = "hash-xxhash")]
+impl Hasher for Xxh3 {
+    fn hash(&self, file: &EmbeddedFile) -> Vec<u8> {
+        xxhash_rust::xxh3::xxh3_128(&file.data)
+            .to_be_bytes()
+            .to_vec()
+    }
+
+    fn as_sync(&self) -> Option<&(dyn Hasher + Sync)> {
+        Some(self)
+    }
+}
+
+/// Hex-encoded hashes of embedded files, keyed by file path.
+pub(crate) struct ContentHashes {
+    hasher: Rc<dyn Hasher>,
+    hashes: RefCell<HashMap<String, Rc<str>>>,
+}
+
+impl ContentHashes {
+    pub(crate) fn new(hasher: Rc<dyn Hasher>) -> Self {
+        ContentHashes {
+            hasher,
+            hashes: RefCell::new(HashMap::new()),
+        }
+    }
+
+    pub(crate) fn get(&self, path: &str, file: &EmbeddedFile) -> Rc<str> {
+        if self.hasher.is_precomputed() {
+            return hex::encode(self.hasher.hash(file)).into();
+        }
+        self.hashes
+            .borrow_mut()
+            .entry(path.to_owned())
+            .or_insert_with(|| hex::encode(self.hasher.hash(file)).into())
+            .clone()
+    }
+
+    /// Hashes `files`, keyed by path, on up to `threads` threads if the hasher can be shared
+    /// between them, reporting the number of files hashed to `progress`.
+    ///
+    /// Nothing is hashed if hashes are precomputed.
+    pub(crate) fn prefill<P>(&self, files: &[(String, EmbeddedFile)], threads: usize, progress: P)
+    where
+        P: FnMut(usize),
+    {
+        if self.hasher.is_precomputed() {
+            return;
+        }
+        let hashes = match self.hasher.as_sync() {
+            Some(hasher) => parallel::map(files, threads, |(_, file)| hasher.hash(file), progress),
+            None => {
+                let mut progress = progress;
+                let hash = |(i, (_, file)): (usize, &(String, EmbeddedFile))| {
+                    let hash = self.hasher.hash(file);
+                    progress(i + 1);
+                    hash
+                };
+                files.iter().enumerate().map(hash).collect()
+            }
+        };
+        let mut cached = self.hashes.borrow_mut();
+        for ((path, _), hash) in files.iter().zip(hashes) {
+            cached.insert(path.clone(), hex::encode(hash).into());
+        }
+    }
+
+    /// Returns the mismatch if `recorded`, the hash served with the file at `path`, is not
+    /// the hash of its content, forgetting the cached hash. See
+    /// [Embed::verify_hashes](crate::Embed::verify_hashes).
+    pub(crate) fn verify(&self, path: &str, recorded: &str, file: &EmbeddedFile) -> Option<String> {
+        if sha2::Sha256::digest(&file.data).as_slice() != file.metadata.sha256_hash() {
+            self.hashes.borrow_mut().remove(path);
+            return Some(format!(
+                "the embedded metadata of `{}` does not match its content",
+                path
+            ));
+        }
+        let actual = hex::encode(self.hasher.hash(file));
+        if actual == recorded {
+            return None;
+        }
+        self.hashes.borrow_mut().remove(path);
+        Some(format!(
+            "`{}` is served with the hash {} of its previous content, its content hashes to {}",
+            path, recorded, actual
+        ))
+    }
+}
@@ -0,0 +1,260 @@
+use std::collections::HashMap;
+
+use actix_web::http::header::{self, HeaderMap, HeaderName, HeaderValue};
+use actix_web::HttpResponseBuilder;
+use mime_guess::MimeGuess;
+
+use crate::glob::Glob;
+
+/// Extra headers inserted into responses for files with specific extensions, or matching
+/// globs.
+///
+/// Rules without extensions and globs apply to all files.
+#[derive(Clone)]
+pub(crate) struct HeaderRule {
+    pub(crate) extensions: &'static [&'static str],
+    pub(crate) globs: Vec<Glob>,
+    pub(crate) headers: Vec<(HeaderName, HeaderValue)>,
+}
+
+impl HeaderRule {
+    pub(crate) fn matches(&self, path: &str) -> bool {
+        if !self.globs.is_empty() && !self.globs.iter().any(|glob| glob.matches(path)) {
+            return false;
+        }
+        if self.extensions.is_empty() {
+            return true;
+        }
+        extension(path).is_some_and(|ext| {
+            self.extensions
+                .iter()
+                .any(|candidate| candidate.eq_ignore_ascii_case(ext))
+        })
+    }
+
+    pub(crate) fn apply(&self, builder: &mut HttpResponseBuilder) {
+        for header in &self.headers {
+            builder.insert_header(header.clone());
+        }
+    }
+}
+
+/// Whether range requests are honored, and advertised with `Accept-Ranges`.
+///
+/// See [Embed::accept_ranges](crate::Embed::accept_ranges).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AcceptRanges {
+    /// No `Accept-Ranges` header is sent. Range requests are honored.
+    #[default]
+    Unadvertised,
+    /// `Accept-Ranges: bytes` is sent, and range requests are honored.
+    Bytes,
+    /// `Accept-Ranges: none` is sent, and range requests are answered with the whole file,
+    /// e.g. so download managers do not split downloads.
+    None,
+}
+
+impl AcceptRanges {
+    /// Returns the value of the `Accept-Ranges` header, `None` if it is not sent.
+    pub(crate) fn header_value(self) -> Option<&'static str> {
+        match self {
+            AcceptRanges::Unadvertised => None,
+            AcceptRanges::Bytes => Some("bytes"),
+            AcceptRanges::None => Some("none"),
+        }
+    }
+
+    /// Returns `true` if range requests for files are honored.
+    pub(crate) fn honors_ranges(self) -> bool {
+        self != AcceptRanges::None
+    }
+}
+
+/// Which pages may embed files in frames, see [Embed::frame_policy](crate::Embed::frame_policy).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FramePolicy {
+    /// No page may embed the files, sent as `X-Frame-Options: DENY` and
+    /// `Content-Security-Policy: frame-ancestors 'none'`.
+    Deny,
+    /// Only pages of the origins may embed the files, e.g. `https://partner.example`, sent as
+    /// `Content-Security-Policy: frame-ancestors <origins>`. Any source expression of
+    /// `frame-ancestors` is accepted, e.g. `'self'` or `https://*.partner.example`.
+    AllowFrom(Vec<String>),
+}
+
+impl FramePolicy {
+    /// Returns the headers sending the policy, or an error naming an invalid origin.
+    pub(crate) fn headers(&self) -> Result<Vec<(HeaderName, HeaderValue)>, String> {
+        let ancestors = match self {
+            FramePolicy::Deny => "'none'".to_owned(),
+            FramePolicy::AllowFrom(origins) if origins.is_empty() => "'none'".to_owned(),
+            FramePolicy::AllowFrom(origins) => {
+                // source expressions are separated by spaces, and directives by semicolons
+                let invalid = origins.iter().find(|origin| {
+                    origin.is_empty()
+                        || !origin
+                            .bytes()
+                            .all(|b| b.is_ascii_graphic() && b != b';' && b != b',')
+                });
+                if let Some(origin) = invalid {
+                    return Err(format!("invalid origin {:?}", origin));
+                }
+                origins.join(" ")
+            }
+        };
+        let csp = HeaderValue::from_str(&format!("frame-ancestors {}", ancestors))
+            .map_err(|err| err.to_string())?;
+        let mut headers = vec![(header::CONTENT_SECURITY_POLICY, csp)];
+        // `ALLOW-FROM` is obsolete and takes a single origin, so browsers which do not know
+        // `frame-ancestors` are left without a policy rather than blocking partners
+        if *self == FramePolicy::Deny {
+            headers.push((header::X_FRAME_OPTIONS, HeaderValue::from_static("DENY")));
+        }
+        Ok(headers)
+    }
+}
+
+/// The `Priority` header of RFC 9218.
+const PRIORITY: HeaderName = HeaderName::from_static("priority");
+
+/// The priority clients and proxies give to the responses for files, sent as the `Priority`
+/// header of RFC 9218, see [Embed::priority](crate::Embed::priority).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Priority {
+    /// Sent as `u=1`, ahead of the default urgency of 3, e.g. for stylesheets and scripts
+    /// blocking the first render.
+    High,
+    /// Sent as `u=5`, e.g. for images below the fold or prefetched scripts.
+    Low,
+    /// Sent as `u=<urgency>`, with `i` if incremental.
+    Urgency {
+        /// The urgency from 0, the most urgent, to 7.
+        urgency: u8,
+        /// Whether clients process the response as it arrives, e.g. progressive images, so
+        /// it is worth sending alongside other responses of the same urgency.
+        incremental: bool,
+    },
+}
+
+impl Priority {
+    /// Returns the header sending the priority, or an error if the urgency is out of range.
+    pub(crate) fn header(self) -> Result<(HeaderName, HeaderValue), String> {
+        let value = match self {
+            Priority::High => "u=1".to_owned(),
+            Priority::Low => "u=5".to_owned(),
+            Priority::Urgency { urgency, .. } if urgency > 7 => {
+                return Err(format!("urgency {} is not within 0 to 7", urgency))
+            }
+            Priority::Urgency {
+                urgency,
+                incremental: false,
+            } => format!("u={}", urgency),
+            Priority::Urgency {
+                urgency,
+                incremental: true,
+            } => format!("u={}, i", urgency),
+        };
+        let value = HeaderValue::from_str(&value).map_err(|err| err.to_string())?;
+        Ok((PRIORITY, value))
+    }
+}
+
+/// Headers whose values are comma-separated lists of elements which can be reordered, merged
+/// by [canonicalize].
+static CANONICAL_LISTS: [HeaderName; 6] = [
+    header::VARY,
+    header::CACHE_CONTROL,
+    header::ACCESS_CONTROL_ALLOW_HEADERS,
+    header::ACCESS_CONTROL_ALLOW_METHODS,
+    header::ACCESS_CONTROL_EXPOSE_HEADERS,
+    HeaderName::from_static("accept-ch"),
+];
+
+/// Merges the values of each list header of `headers` into a single value with the elements
+/// sorted and without duplicates, e.g. `Vary: Accept-Encoding, Cookie`, see
+/// [Embed::canonical_headers](crate::Embed::canonical_headers).
+pub(crate) fn canonicalize(headers: &mut HeaderMap) {
+    for name in &CANONICAL_LISTS {
+        let mut elements = headers
+            .get_all(name)
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(str::trim)
+            .filter(|element| !element.is_empty())
+            .collect::<Vec<_>>();
+        // quoted strings may contain commas, e.g. `no-cache="Set-Cookie, Link"`
+        if elements.is_empty() || elements.iter().any(|element| element.contains('"')) {
+            continue;
+        }
+        elements.sort_by_cached_key(|element| element.to_ascii_lowercase());
+        elements.dedup_by(|a, b| a.eq_ignore_ascii_case(b));
+        if let Ok(value) = HeaderValue::from_str(&elements.join(", ")) {
+            headers.insert(name.clone(), value);
+        }
+    }
+}
+
+/// Inserts `name: value` into `headers` unless they already have a value for `name`, e.g. set
+/// by a fallback handler.
+pub(crate) fn insert_default(headers: &mut HeaderMap, name: HeaderName, value: &HeaderValue) {
+    if !headers.contains_key(&name) {
+        headers.insert(name, value.clone());
+    }
+}
+
+/// Returns the media type of the file at `path`, from its extension in `mime_types` or
+/// guessed from the extension.
+pub(crate) fn content_type(path: &str, mime_types: &HashMap<String, String>) -> String {
+    extension(path)
+        .and_then(|ext| mime_types.get(&ext.to_ascii_lowercase()))
+        .cloned()
+        .unwrap_or_else(|| {
+            MimeGuess::from_path(path)
+                .first_or_octet_stream()
+                .to_string()
+        })
+}
+
+/// Returns the extension of the file name in `path`.
+pub(crate) fn extension(path: &str) -> Option<&str> {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    name.rsplit_once('.').map(|(_, ext)| ext)
+}
+
+/// The `Cache-Control` value for files that never change.
+pub(crate) const IMMUTABLE: &str = "public, max-age=31536000, immutable";
+
+/// The `Cache-Control` value for files that must be revalidated before each use.
+pub(crate) const NO_CACHE: &str = "no-cache";
+
+/// The `Cache-Control` value for responses that must not be stored.
+pub(crate) const NO_STORE: &str = "no-store";
+
+/// Extensions of documents which can run scripts when opened by browsers.
+pub(crate) const ACTIVE_EXTENSIONS: &[&str] = &["html", "htm", "xhtml", "xht", "svg", "svgz"];
+
+pub(crate) const FONT_EXTENSIONS: &[&str] = &["woff", "woff2", "ttf", "otf"];
+
+/// Extensions of data files read in ranges by clients, see
+/// [Embed::byte_serving_preset](crate::Embed::byte_serving_preset).
+pub(crate) const DATA_EXTENSIONS: &[&str] = &["sqlite", "sqlite3", "db", "wasm"];
+
+/// Content types of data files, some of which `mime_guess` does not know.
+pub(crate) const DATA_MIME_TYPES: &[(&str, &str)] = &[
+    ("sqlite", "application/vnd.sqlite3"),
+    ("sqlite3", "application/vnd.sqlite3"),
+    ("db", "application/vnd.sqlite3"),
+    ("wasm", "application/wasm"),
+];
+
+/// The `Cross-Origin-Resource-Policy` header, which `http` does not define.
+pub(crate) const CROSS_ORIGIN_RESOURCE_POLICY: HeaderName =
+    HeaderName::from_static("cross-origin-resource-policy");
+
+/// Content types of fonts defined by RFC 8081, some of which `mime_guess` does not know.
+pub(crate) const FONT_MIME_TYPES: &[(&str, &str)] = &[
+    ("woff", "font/woff"),
+    ("woff2", "font/woff2"),
+    ("ttf", "font/ttf"),
+    ("otf", "font/otf"),
+];
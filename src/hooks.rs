@@ -0,0 +1,178 @@
+use std::any::Any;
+use std::fmt::{Debug, Display, Formatter};
+use std::future::Future;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use actix_web::http::StatusCode;
+
+/// Describes a panic caught while calling a user hook.
+///
+/// See [Embed::on_hook_panic](crate::Embed::on_hook_panic).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HookPanic {
+    /// The name of the hook, e.g. `fallback_handler`.
+    pub hook: &'static str,
+    /// The panic message, if it is a string.
+    pub message: Option<String>,
+}
+
+impl Display for HookPanic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.message {
+            Some(message) => write!(f, "{} panicked: {}", self.hook, message),
+            None => write!(f, "{} panicked", self.hook),
+        }
+    }
+}
+
+/// What happens when a hook guarding requests fails to decide, by panicking or reporting an
+/// internal error.
+///
+/// See [Embed::rate_limiter_failure](crate::Embed::rate_limiter_failure).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailurePolicy {
+    /// Serve the request as if the hook allowed it.
+    Open,
+    /// Reject the request with the status code, e.g. `403 Forbidden` or
+    /// `503 Service Unavailable`.
+    Closed(StatusCode),
+}
+
+impl Default for FailurePolicy {
+    fn default() -> Self {
+        FailurePolicy::Closed(StatusCode::INTERNAL_SERVER_ERROR)
+    }
+}
+
+/// Describes a hook guarding a request which failed to decide, and how the failure was
+/// handled.
+///
+/// See [ServeRecord::hook_failure](crate::ServeRecord::hook_failure).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HookFailure {
+    /// The name of the hook, e.g. `rate_limiter`.
+    pub hook: &'static str,
+    /// The panic message, if the hook panicked with a string.
+    pub message: Option<String>,
+    /// The policy applied, [FailurePolicy::Open] if the request was served anyway.
+    pub policy: FailurePolicy,
+}
+
+/// Why an async hook failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum HookError {
+    Panicked(HookPanic),
+    /// The hook did not complete within the [timeout](crate::Embed::hook_timeout).
+    TimedOut,
+}
+
+type PanicHook = dyn Fn(&HookPanic);
+
+/// Determines how panics and slow async hooks are handled.
+#[derive(Clone)]
+pub(crate) struct HookPolicy {
+    pub(crate) catch_panics: bool,
+    pub(crate) on_panic: Option<Rc<PanicHook>>,
+    /// The time after which async hooks are abandoned.
+    pub(crate) timeout: Option<Duration>,
+}
+
+impl Default for HookPolicy {
+    fn default() -> Self {
+        HookPolicy {
+            catch_panics: true,
+            on_panic: None,
+            timeout: None,
+        }
+    }
+}
+
+impl Debug for HookPolicy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HookPolicy")
+            .field("catch_panics", &self.catch_panics)
+            .field("timeout", &self.timeout)
+            .finish_non_exhaustive()
+    }
+}
+
+impl HookPolicy {
+    /// Calls the hook named `hook`, returning `Err` if it panicked and panics are caught.
+    pub(crate) fn call<R>(
+        &self,
+        hook: &'static str,
+        f: impl FnOnce() -> R,
+    ) -> Result<R, HookPanic> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::trace_span!("hook", name = hook).entered();
+        if !self.catch_panics {
+            return Ok(f());
+        }
+        catch_unwind(AssertUnwindSafe(f)).map_err(|payload| {
+            let panic = HookPanic {
+                hook,
+                message: panic_message(payload.as_ref()),
+            };
+            if let Some(on_panic) = &self.on_panic {
+                on_panic(&panic);
+            }
+            panic
+        })
+    }
+
+    /// Calls the async hook named `hook` and awaits its future, returning `Err` if it
+    /// panicked and panics are caught, or if it did not complete within the timeout.
+    pub(crate) async fn call_async<F: Future>(
+        &self,
+        hook: &'static str,
+        f: impl FnOnce() -> F,
+    ) -> Result<F::Output, HookError> {
+        let future = self.call(hook, f).map_err(HookError::Panicked)?;
+        let guarded = Guarded {
+            policy: self,
+            hook,
+            future: Box::pin(future),
+        };
+        let result = match self.timeout {
+            Some(timeout) => actix_web::rt::time::timeout(timeout, guarded)
+                .await
+                .map_err(|_| HookError::TimedOut)?,
+            None => guarded.await,
+        };
+        result.map_err(HookError::Panicked)
+    }
+}
+
+/// Polls the future of an async hook, catching panics like [HookPolicy::call].
+struct Guarded<'a, F> {
+    policy: &'a HookPolicy,
+    hook: &'static str,
+    future: Pin<Box<F>>,
+}
+
+impl<F: Future> Future for Guarded<'_, F> {
+    type Output = Result<F::Output, HookPanic>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        match this
+            .policy
+            .call(this.hook, || this.future.as_mut().poll(cx))
+        {
+            Ok(Poll::Pending) => Poll::Pending,
+            Ok(Poll::Ready(output)) => Poll::Ready(Ok(output)),
+            Err(panic) => Poll::Ready(Err(panic)),
+        }
+    }
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> Option<String> {
+    payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+}
@@ -0,0 +1,53 @@
+use std::time::{Duration, SystemTime};
+
+use actix_web::http::header::{self, HeaderMap, HeaderValue};
+use actix_web::http::Version;
+use actix_web::HttpRequest;
+
+use crate::http_util;
+
+/// Returns whether `req` is sent by an HTTP/1.0 client, see
+/// [Embed::http10_compat](crate::Embed::http10_compat).
+pub(crate) fn is_http10(req: &HttpRequest) -> bool {
+    req.version() == Version::HTTP_10
+}
+
+/// Returns whether `req` names no host, neither in a `Host` header nor in its URI.
+pub(crate) fn missing_host(req: &HttpRequest) -> bool {
+    !req.headers().contains_key(header::HOST) && req.uri().authority().is_none()
+}
+
+/// Adds the `Expires` and `Pragma` headers HTTP/1.0 caches understand, equivalent to the
+/// `Cache-Control` header of the response.
+///
+/// Responses which must be revalidated expire immediately, responses with `max-age` expire
+/// after it. Responses without `Cache-Control`, or with `Expires` already, are left as is.
+pub(crate) fn add_expires(headers: &mut HeaderMap, now: SystemTime) {
+    if headers.contains_key(header::EXPIRES) {
+        return;
+    }
+    let cache_control = match headers.get(header::CACHE_CONTROL) {
+        Some(value) => value.to_str().unwrap_or_default().to_ascii_lowercase(),
+        None => return,
+    };
+    let directives = cache_control.split(',').map(str::trim).collect::<Vec<_>>();
+    let expires = if directives
+        .iter()
+        .any(|directive| *directive == "no-cache" || *directive == "no-store")
+    {
+        headers.insert(header::PRAGMA, HeaderValue::from_static("no-cache"));
+        SystemTime::UNIX_EPOCH
+    } else {
+        let max_age = directives
+            .iter()
+            .find_map(|directive| directive.strip_prefix("max-age=")?.parse::<u64>().ok());
+        match max_age {
+            Some(max_age) => now + Duration::from_secs(max_age),
+            None => return,
+        }
+    };
+    headers.insert(
+        header::EXPIRES,
+        http_util::header_value(&http_util::http_date(expires)),
+    );
+}
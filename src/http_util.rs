@@ -0,0 +1,264 @@
+//! Formatting of the HTTP header values sent by [Embed](crate::Embed).
+//!
+//! Custom handlers serving the embedded files next to an [Embed](crate::Embed), e.g. a
+//! [fallback handler](crate::FallbackHandler) or a download route, produce byte-compatible
+//! headers with these functions, so caches and clients treat their responses alike.
+//!
+//! Values reflecting request data, e.g. paths in `Location` headers, go through
+//! [header_value], so they cannot inject headers or fail to build the response.
+//!
+//! # Entity tags
+//! Each representation of an embedded file has an entity tag of its own, derived from the
+//! content hash of the file, so `If-None-Match` and `If-Range` never match a representation
+//! with another encoding:
+//!
+//! | Representation | `ETag` |
+//! |---|---|
+//! | the file as embedded | `"<hash>"` |
+//! | compressed with brotli | `"<hash>-br"` |
+//! | compressed with gzip | `"<hash>-gzip"` |
+//! | minified | `"<hash>-min-<digest>"` |
+//!
+//! Tags are weak, e.g. `W/"<hash>"`, if compressing middleware may still encode the body, see
+//! [OuterCompression::Allow]. CDNs normalizing `Accept-Encoding` in their cache keys can map
+//! tags to encodings with [split_entity_tag].
+//!
+//! # Examples
+//! ```
+//! use actix_embed::http_util;
+//! use actix_web::http::header;
+//! use actix_web::HttpResponse;
+//!
+//! let etag = http_util::entity_tag("e3b0c442", None);
+//! let resp = HttpResponse::Ok()
+//!     .insert_header(header::ETag(etag))
+//!     .insert_header((header::CONTENT_RANGE, http_util::content_range(0, 99, 1000)))
+//!     .finish();
+//! ```
+
+use std::time::SystemTime;
+
+use actix_web::http::header::{EntityTag, HeaderValue, HttpDate};
+use sha2::{Digest, Sha256};
+
+use crate::encoding::{Encoding, OuterCompression};
+
+/// Returns the entity tag of the file with the content hash `hash`, as sent with the file or
+/// its variant compressed with `encoding`, e.g. `"<hash>-br"`.
+pub fn entity_tag(hash: &str, encoding: Option<Encoding>) -> EntityTag {
+    match encoding {
+        Some(encoding) => EntityTag::new_strong(format!("{}-{}", hash, encoding)),
+        None => EntityTag::new_strong(hash.to_owned()),
+    }
+}
+
+/// Returns the content hash and the encoding of the representation with `etag`, the inverse
+/// of [entity_tag], e.g. `("<hash>", Some(Encoding::Brotli))` for `"<hash>-br"`.
+///
+/// Tags of uncompressed representations are returned whole, e.g. `"<hash>-min-<digest>"` of
+/// minified files.
+///
+/// # Examples
+/// ```
+/// use actix_embed::http_util;
+/// use actix_embed::Encoding;
+/// use actix_web::http::header::EntityTag;
+///
+/// let etag = EntityTag::new_strong("e3b0c442-gzip".to_owned());
+/// assert_eq!(http_util::split_entity_tag(&etag), ("e3b0c442", Some(Encoding::Gzip)));
+/// ```
+pub fn split_entity_tag(etag: &EntityTag) -> (&str, Option<Encoding>) {
+    let tag = etag.tag();
+    [Encoding::Brotli, Encoding::Gzip]
+        .into_iter()
+        .find_map(|encoding| {
+            let hash = tag.strip_suffix(encoding.as_str())?.strip_suffix('-')?;
+            Some((hash, Some(encoding)))
+        })
+        .unwrap_or((tag, None))
+}
+
+/// Returns the entity tag of the minified variant of the file with the content hash `hash`,
+/// `digest` being a digest of the minified content.
+pub(crate) fn minified_entity_tag(hash: &str, digest: &str) -> EntityTag {
+    EntityTag::new_strong(format!("{}-min-{}", hash, digest))
+}
+
+/// Returns `etag` as sent with a response compressing middleware may still encode, weak if it
+/// is allowed to, see [OuterCompression].
+pub(crate) fn outer_entity_tag(etag: EntityTag, outer: OuterCompression) -> EntityTag {
+    match outer {
+        OuterCompression::Allow => EntityTag::new_weak(etag.tag().to_owned()),
+        OuterCompression::Absent | OuterCompression::Bypass => etag,
+    }
+}
+
+/// Returns the value of an `ETag` header with `tag`, e.g. `"abc"`, or `W/"abc"` if `weak`.
+pub fn quote_etag(tag: &str, weak: bool) -> String {
+    let etag = if weak {
+        EntityTag::new_weak(tag.to_owned())
+    } else {
+        EntityTag::new_strong(tag.to_owned())
+    };
+    etag.to_string()
+}
+
+/// Returns `time` formatted as an HTTP date, e.g. `Sun, 06 Nov 1994 08:49:37 GMT`, for
+/// `Last-Modified`, `Expires` and `Date` headers.
+pub fn http_date(time: SystemTime) -> String {
+    HttpDate::from(time).to_string()
+}
+
+/// Returns the value of a `Content-Range` header for the bytes `start` to `end` inclusive of
+/// a body of `total` bytes, e.g. `bytes 0-99/1000`.
+pub fn content_range(start: u64, end: u64, total: u64) -> String {
+    format!("bytes {}-{}/{}", start, end, total)
+}
+
+/// Returns the value of a `Content-Range` header of a `416 Range Not Satisfiable` response
+/// for a body of `total` bytes, e.g. `bytes */1000`.
+pub fn unsatisfied_range(total: u64) -> String {
+    format!("bytes */{}", total)
+}
+
+/// Returns the boundary of `multipart/byteranges` bodies of the representation with `etag`.
+///
+/// The boundary only depends on the entity tag, so responses for the same ranges are
+/// byte-identical across workers and restarts.
+pub fn multipart_boundary(etag: &EntityTag) -> String {
+    let digest = Sha256::digest(etag.to_string().as_bytes());
+    format!("embed-{}", hex::encode(&digest[..12]))
+}
+
+/// Returns `value` as a header value, with the bytes which are not allowed or not safe in
+/// header values percent-encoded.
+///
+/// Carriage returns, line feeds and other control characters are encoded, so the value cannot
+/// end the header and start another one (response splitting). Non-ASCII characters are
+/// encoded as their UTF-8 bytes, as in URLs. Tabs and visible ASCII characters are kept.
+///
+/// # Examples
+/// ```
+/// use actix_embed::http_util;
+///
+/// let value = http_util::header_value("/a\r\nSet-Cookie: x=1");
+/// assert_eq!(value, "/a%0D%0ASet-Cookie: x=1");
+/// ```
+pub fn header_value(value: &str) -> HeaderValue {
+    let mut sanitized = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        if byte == b'\t' || (b' '..=b'~').contains(&byte) {
+            sanitized.push(byte as char);
+        } else {
+            sanitized.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    HeaderValue::from_str(&sanitized).expect("sanitized header value")
+}
+
+/// Returns the value of a `Content-Disposition` header downloading the body as `filename`,
+/// e.g. `attachment; filename="site.tar"`.
+///
+/// Names which are not ASCII are sent as by [Disposition].
+pub fn attachment(filename: &str) -> HeaderValue {
+    Disposition::attachment(filename).header_value()
+}
+
+/// A `Content-Disposition` header value naming the file a body is saved as, for names which
+/// may not be ASCII.
+///
+/// Clients disagree on the encoding of non-ASCII bytes in the `filename` parameter, so such
+/// names are sent as recommended by RFC 6266: in a `filename*` parameter percent-encoded as
+/// UTF-8 (RFC 8187), which clients prefer, and in `filename` as an ASCII fallback for clients
+/// which do not know `filename*`. The fallback is the name with the characters which are not
+/// printable ASCII replaced by `_`, unless [set](Self::fallback).
+///
+/// The value is a header value, e.g. for [Embed::header](crate::Embed::header).
+///
+/// # Examples
+/// ```
+/// use actix_embed::http_util::Disposition;
+/// use actix_embed::Embed;
+/// use actix_web::http::header;
+/// use rust_embed::RustEmbed;
+///
+/// #[derive(RustEmbed)]
+/// #[folder = "testdata/"]
+/// struct Assets;
+///
+/// let name = Disposition::attachment("報告.pdf").fallback("report.pdf");
+/// assert_eq!(
+///     name.header_value(),
+///     "attachment; filename=\"report.pdf\"; filename*=UTF-8''%E5%A0%B1%E5%91%8A.pdf"
+/// );
+/// let embed = Embed::downloads("/files", &Assets)
+///     .header(["reports/q1.pdf"], header::CONTENT_DISPOSITION, name);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Disposition {
+    inline: bool,
+    filename: String,
+    fallback: Option<String>,
+}
+
+impl Disposition {
+    /// The body is downloaded and saved as `filename`.
+    pub fn attachment<N: Into<String>>(filename: N) -> Self {
+        Disposition {
+            inline: false,
+            filename: filename.into(),
+            fallback: None,
+        }
+    }
+
+    /// The body is displayed, and saved as `filename` if the user saves it.
+    pub fn inline<N: Into<String>>(filename: N) -> Self {
+        Disposition {
+            inline: true,
+            ..Self::attachment(filename)
+        }
+    }
+
+    /// Set the name used by clients which do not know `filename*`, e.g. a transliteration.
+    ///
+    /// Characters of `fallback` which are not printable ASCII are replaced by `_`.
+    pub fn fallback<F: Into<String>>(mut self, fallback: F) -> Self {
+        self.fallback = Some(fallback.into());
+        self
+    }
+
+    /// Returns the header value, e.g. `attachment; filename="site.tar"`.
+    pub fn header_value(&self) -> HeaderValue {
+        let ascii = self.filename.bytes().all(|b| (b' '..=b'~').contains(&b));
+        let fallback = self.fallback.as_deref().unwrap_or(&self.filename);
+        let fallback = fallback
+            .chars()
+            .map(|c| match c {
+                ' '..='~' => c,
+                _ => '_',
+            })
+            .collect::<String>()
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"");
+        let kind = if self.inline { "inline" } else { "attachment" };
+        let mut value = format!("{}; filename=\"{}\"", kind, fallback);
+        if !ascii || self.fallback.is_some() {
+            value.push_str("; filename*=UTF-8''");
+            for byte in self.filename.bytes() {
+                // the `attr-char` of RFC 8187
+                if byte.is_ascii_alphanumeric() || b"!#$&+-.^_`|~".contains(&byte) {
+                    value.push(byte as char);
+                } else {
+                    value.push_str(&format!("%{:02X}", byte));
+                }
+            }
+        }
+        header_value(&value)
+    }
+}
+
+impl From<Disposition> for HeaderValue {
+    fn from(disposition: Disposition) -> Self {
+        disposition.header_value()
+    }
+}
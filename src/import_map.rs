@@ -0,0 +1,110 @@
+use std::collections::BTreeMap;
+
+use actix_web::http::header::{self, EntityTag};
+use actix_web::{HttpRequest, HttpResponse};
+use sha2::{Digest, Sha256};
+
+use crate::conditional::{self, Precondition};
+use crate::content_address;
+use crate::file_index::{FileIndex, KeyMap, Visibility};
+use crate::glob::Glob;
+use crate::headers;
+use crate::packages::PackageExports;
+
+#[derive(Debug, Clone)]
+pub(crate) struct ImportMapEndpoint {
+    /// Relative to the mount path, without leading and trailing slashes.
+    pub(crate) path: String,
+    pub(crate) globs: Vec<Glob>,
+}
+
+/// The entries of the import map, relative to the mount path, see
+/// [Embed::import_map](crate::Embed::import_map).
+#[derive(Debug)]
+pub(crate) struct ImportMap {
+    /// Bare specifiers and the paths and URLs of the files they resolve to.
+    packages: BTreeMap<String, (String, String)>,
+    /// Paths of files and the URLs they are imported from.
+    files: BTreeMap<String, String>,
+}
+
+impl ImportMap {
+    pub(crate) fn build<E>(
+        endpoint: &ImportMapEndpoint,
+        index: &FileIndex,
+        keys: &KeyMap,
+        packages: Option<(&str, &PackageExports)>,
+        content_addressed: bool,
+    ) -> Self
+    where
+        E: 'static + rust_embed::RustEmbed,
+    {
+        let url = |path: &str| match keys.get::<E>(path) {
+            Some((_, file)) if content_addressed => content_address::url(path, &file),
+            _ => path.to_owned(),
+        };
+        let files = index
+            .paths()
+            .iter()
+            .filter(|path| endpoint.globs.iter().any(|glob| glob.matches(path)))
+            .map(|path| (path.clone(), url(path)))
+            .collect();
+        let packages = match packages {
+            Some((dir, exports)) => exports
+                .iter()
+                .filter_map(|(specifier, path)| {
+                    let name = specifier.strip_prefix(dir)?.trim_start_matches('/');
+                    Some((name.to_owned(), (path.to_owned(), url(path))))
+                })
+                .collect(),
+            None => BTreeMap::new(),
+        };
+        ImportMap { packages, files }
+    }
+
+    /// Returns the import map as served at the mount path `base`, ending with `/`, with the
+    /// entries resolving to files shown by `visibility`.
+    fn render(&self, base: &str, visibility: &Visibility<'_>) -> String {
+        let imports = self
+            .packages
+            .iter()
+            .filter(|(_, (path, _))| visibility.shows(path))
+            .map(|(specifier, (_, url))| (specifier.clone(), format!("{}{}", base, url)))
+            .chain(
+                self.files
+                    .iter()
+                    .filter(|(path, _)| visibility.shows(path))
+                    .map(|(path, url)| (format!("{}{}", base, path), format!("{}{}", base, url))),
+            )
+            .collect::<BTreeMap<_, _>>();
+        let mut map = BTreeMap::new();
+        map.insert("imports", imports);
+        serde_json::to_string(&map).unwrap_or_default()
+    }
+
+    /// Returns the response of the import map endpoint, honoring conditional requests.
+    pub(crate) fn response(
+        &self,
+        req: &HttpRequest,
+        base: &str,
+        visibility: &Visibility<'_>,
+    ) -> HttpResponse {
+        let body = self.render(base, visibility);
+        let etag = EntityTag::new_strong(hex::encode(Sha256::digest(body.as_bytes())));
+        let mut builder = match conditional::evaluate(req, &etag, None) {
+            Precondition::Passed => HttpResponse::Ok(),
+            Precondition::NotModified => {
+                return HttpResponse::NotModified()
+                    .insert_header((header::CACHE_CONTROL, headers::NO_CACHE))
+                    .insert_header(header::ETag(etag))
+                    .finish()
+            }
+            Precondition::Failed => return HttpResponse::PreconditionFailed().finish(),
+        };
+        builder
+            .content_type("application/importmap+json")
+            .insert_header((header::CACHE_CONTROL, headers::NO_CACHE))
+            .insert_header(header::ETag(etag))
+            .body(body)
+    }
+}
@@ -0,0 +1,205 @@
+use actix_web::http::header;
+use actix_web::HttpRequest;
+
+/// Returns the language ranges of the `Accept-Language` header, most preferred first.
+///
+/// Ranges with a quality of zero and the wildcard `*` are dropped.
+pub(crate) fn accepted_languages(req: &HttpRequest) -> Vec<String> {
+    let mut ranges = req
+        .headers()
+        .get_all(header::ACCEPT_LANGUAGE)
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|item| {
+            let mut parts = item.split(';');
+            let range = parts.next()?.trim();
+            let quality = parts
+                .filter_map(|param| param.trim().strip_prefix("q="))
+                .find_map(|q| q.trim().parse::<f32>().ok())
+                .unwrap_or(1.0);
+            (!range.is_empty() && range != "*" && quality > 0.0)
+                .then(|| (range.to_ascii_lowercase(), quality))
+        })
+        .collect::<Vec<_>>();
+    // stable sort keeps the order of ranges with the same quality
+    ranges.sort_by(|(_, a), (_, b)| b.partial_cmp(a).unwrap_or(std::cmp::Ordering::Equal));
+    ranges.into_iter().map(|(range, _)| range).collect()
+}
+
+/// Returns `true` if `tag` is matched by the language `range`, e.g. `de` matches `de-AT`.
+pub(crate) fn matches(range: &str, tag: &str) -> bool {
+    let (range, tag) = (range.as_bytes(), tag.as_bytes());
+    tag.len() >= range.len()
+        && tag[..range.len()].eq_ignore_ascii_case(range)
+        && (tag.len() == range.len() || tag[range.len()] == b'-')
+}
+
+/// Returns the value of the cookie `name` sent with the request.
+pub(crate) fn cookie<'a>(req: &'a HttpRequest, name: &str) -> Option<&'a str> {
+    req.headers()
+        .get_all(header::COOKIE)
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value.trim_matches('"'))
+}
+
+/// The two-letter language codes of ISO 639-1, which locale directories are named after.
+const LANGUAGE_CODES: &str = "aa ab ae af ak am an ar as av ay az ba be bg bh bi bm bn bo br bs \
+    ca ce ch co cr cs cu cv cy da de dv dz ee el en eo es et eu fa ff fi fj fo fr fy ga gd gl gn \
+    gu gv ha he hi ho hr ht hu hy hz ia id ie ig ii ik io is it iu ja jv ka kg ki kj kk kl km kn \
+    ko kr ks ku kv kw ky la lb lg li ln lo lt lu lv mg mh mi mk ml mn mr ms mt my na nb nd ne ng \
+    nl nn no nr nv ny oc oj om or os pa pi pl ps pt qu rm rn ro ru rw sa sc sd se sg si sk sl sm \
+    sn so sq sr ss st su sv sw ta te tg th ti tk tl tn to tr ts tt tw ty ug uk ur uz ve vi vo wa \
+    wo xh yi yo za zh zu";
+
+/// Returns the language of the locale directory a file is in, e.g. `de` for `de/guide.html`
+/// or `pt-BR` for `pt-BR/index.html`, see
+/// [Embed::content_language_from_path](crate::Embed::content_language_from_path).
+///
+/// Only the first directory is considered, named after an ISO 639-1 code, optionally
+/// followed by a script or region subtag.
+pub(crate) fn from_path(path: &str) -> Option<&str> {
+    let (dir, _) = path.split_once('/')?;
+    let (code, subtag) = match dir.split_once('-') {
+        Some((code, subtag)) => (code, Some(subtag)),
+        None => (dir, None),
+    };
+    let known = code.len() == 2
+        && code.bytes().all(|c| c.is_ascii_lowercase())
+        && LANGUAGE_CODES.split_whitespace().any(|known| known == code);
+    let subtag_valid = subtag.is_none_or(|subtag| match subtag.len() {
+        // regions, e.g. `BR` or `419`
+        2 => subtag.bytes().all(|c| c.is_ascii_alphabetic()),
+        3 => subtag.bytes().all(|c| c.is_ascii_digit()),
+        // scripts, e.g. `Hant`
+        4 => subtag.bytes().all(|c| c.is_ascii_alphabetic()),
+        _ => false,
+    });
+    (known && subtag_valid).then_some(dir)
+}
+
+/// Returns `true` if `tag` is a plausible language tag, e.g. `de` or `pt-BR`.
+fn is_language_tag(tag: &str) -> bool {
+    !tag.is_empty()
+        && tag.len() <= 35
+        && tag.bytes().all(|c| c.is_ascii_alphanumeric() || c == b'-')
+}
+
+/// Languages tried when a language range is not available, e.g. `pt-BR -> pt -> en`.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct FallbackChains {
+    /// `(range, fallbacks)`, all in lowercase.
+    pub(crate) chains: Vec<(String, Vec<String>)>,
+}
+
+impl FallbackChains {
+    /// Returns the ranges tried for `range`, most preferred first.
+    ///
+    /// Ranges without a configured chain fall back to their primary language.
+    fn chain<'a>(&'a self, range: &'a str) -> Vec<&'a str> {
+        let mut chain = vec![range];
+        match self.chains.iter().find(|(from, _)| from == range) {
+            Some((_, fallbacks)) => chain.extend(fallbacks.iter().map(String::as_str)),
+            None => chain.extend(range.split_once('-').map(|(primary, _)| primary)),
+        }
+        chain
+    }
+}
+
+/// Lets users choose the language with a query parameter, remembered in a cookie.
+///
+/// See [Embed::language_override](crate::Embed::language_override).
+#[derive(Debug, Clone)]
+pub(crate) struct LanguageOverride {
+    pub(crate) param: String,
+    pub(crate) cookie: String,
+}
+
+impl LanguageOverride {
+    /// Returns the language chosen with the query parameter.
+    fn query<'a>(&self, req: &'a HttpRequest) -> Option<&'a str> {
+        req.query_string()
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .find(|(key, _)| *key == self.param)
+            .map(|(_, value)| value)
+            .filter(|value| is_language_tag(value))
+    }
+
+    /// Returns the language remembered in the cookie.
+    fn remembered<'a>(&self, req: &'a HttpRequest) -> Option<&'a str> {
+        cookie(req, &self.cookie).filter(|value| is_language_tag(value))
+    }
+}
+
+/// The index file selected by [LocalizedIndex::select].
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct Selection<'a> {
+    pub(crate) path: &'a str,
+    /// The language chosen with the query parameter, to be remembered in the cookie.
+    pub(crate) remember: Option<String>,
+}
+
+/// Index files of different languages served at the root directory.
+#[derive(Debug, Clone)]
+pub(crate) struct LocalizedIndex {
+    /// `(language, path)`, the language is the first segment of the path.
+    pub(crate) candidates: Vec<(String, String)>,
+    pub(crate) default: String,
+}
+
+impl LocalizedIndex {
+    /// Selects the index file for the request.
+    ///
+    /// The language chosen with the query parameter of `choice` takes precedence over the
+    /// one remembered in its cookie, which takes precedence over `Accept-Language`.
+    ///
+    /// Each language range, most preferred first, is matched against the languages of the
+    /// candidates, followed by its fallback chain, e.g. `de-AT` matches `de` unless a chain
+    /// is configured for it.
+    pub(crate) fn select(
+        &self,
+        req: &HttpRequest,
+        fallbacks: &FallbackChains,
+        choice: Option<&LanguageOverride>,
+    ) -> Selection<'_> {
+        if let Some(choice) = choice {
+            if let Some(chosen) = choice.query(req) {
+                if let Some(path) = self.find(&chosen.to_ascii_lowercase(), fallbacks) {
+                    let remember = choice.remembered(req) != Some(chosen);
+                    return Selection {
+                        path,
+                        remember: remember.then(|| chosen.to_owned()),
+                    };
+                }
+            }
+            let remembered = choice.remembered(req).map(str::to_ascii_lowercase);
+            if let Some(path) = remembered.and_then(|lang| self.find(&lang, fallbacks)) {
+                return Selection {
+                    path,
+                    remember: None,
+                };
+            }
+        }
+        let path = accepted_languages(req)
+            .iter()
+            .find_map(|range| self.find(range, fallbacks))
+            .unwrap_or(&self.default);
+        Selection {
+            path,
+            remember: None,
+        }
+    }
+
+    /// Returns the index file matching the language `range` or its fallback chain.
+    fn find(&self, range: &str, fallbacks: &FallbackChains) -> Option<&str> {
+        fallbacks.chain(range).into_iter().find_map(|range| {
+            self.candidates
+                .iter()
+                .find(|(lang, _)| matches(range, lang))
+                .map(|(_, path)| path.as_str())
+        })
+    }
+}
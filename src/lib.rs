@@ -17,26 +17,162 @@
 #![warn(missing_docs, missing_debug_implementations)]
 #![allow(dead_code)]
 
+pub use admin::EmbedAdmin;
+pub use availability::Clock;
+pub use canonical::Scheme;
+pub use circuit_breaker::CircuitBreaker;
+pub use conditional::{RevalidationDecision, Validators};
+pub use content_address::ContentAddresses;
+pub use context::{PolicyDecision, ServeContext};
+pub use cookie_gate::CookieVerifier;
+#[cfg(feature = "secure-cookies")]
+pub use cookie_gate::{PrivateCookie, SignedCookie};
+pub use deprecation::Sunset;
+pub use derived_cache::DerivedCache;
+pub use describe::{Description, Value};
+pub use encoding::{Compression, Encoding, OuterCompression};
+pub use error::{ConfigIssue, Severity};
+pub use events::{ServeOutcome, ServeRecord, ServeStage, ServedAsset, SlowServe};
 pub use fallback_handler::{DefaultFallbackHandler, FallbackHandler};
-pub use service::Embed;
+pub use forwarded::ForwardedPolicy;
+#[cfg(feature = "hash-blake3")]
+pub use hasher::Blake3;
+#[cfg(feature = "hash-xxhash")]
+pub use hasher::Xxh3;
+pub use hasher::{Hasher, Sha256};
+pub use headers::{AcceptRanges, FramePolicy, Priority};
+pub use hooks::{FailurePolicy, HookFailure, HookPanic};
+pub use maintenance::EmbedHandle;
+pub use metadata::{FileMetadata, MetadataRegistry};
+pub use metrics::{CacheSizes, MetricsSnapshot, ServeMetrics};
+pub use mount_registry::{MountConflict, MountRegistry, Tracked};
+pub use options::EmbedOptions;
+pub use plugin::EmbedPlugin;
+pub use prepare::{Preparation, PrepareProgress, PrepareStage};
+pub use query::QueryRule;
+pub use rate_limiter::{RateLimitDecision, RateLimiter};
+pub use registry::{AssetCollision, CollisionPolicy, EmbedRegistry};
+pub use report::{embed_report, DirectorySize, EmbedReport, FileSize};
+pub use ruleset::{Rule, RuleKind, Ruleset};
+pub use service::{Embed, MountKind};
+pub use shared::SharedState;
+pub use sniff::SniffAction;
+pub use spa::{spa, Spa};
+pub use suggest::Suggestions;
+pub use timeout::TimeoutAction;
+pub use tolerance::PathTolerance;
+pub use version::VersionInfo;
 
+mod admin;
+mod archive;
+mod availability;
+mod cache_status;
+mod canary;
+mod canonical;
+mod charset;
+mod chunked;
+mod circuit_breaker;
+pub mod codegen;
+pub mod compliance;
+mod conditional;
+mod content_address;
+mod context;
+mod cookie_gate;
+mod cors;
+pub mod decision;
+mod deprecation;
+mod derived_cache;
+mod describe;
+mod disk_cache;
+mod encoding;
+mod endpoint_cache;
+mod error;
+mod events;
 mod fallback_handler;
+mod file_index;
+mod forwarded;
+mod glob;
+mod hashed_names;
+mod hasher;
+mod headers;
+mod hooks;
+mod http10;
+pub mod http_util;
+mod import_map;
+mod language;
+mod listing;
+mod macros;
+mod maintenance;
+mod metadata;
+mod metrics;
+mod mime_allowlist;
+#[cfg(feature = "minify")]
+mod minify;
+mod mount_registry;
+mod options;
+mod packages;
+mod parallel;
+mod plugin;
+mod precompress;
+mod prepare;
+mod query;
+mod range;
+mod rate_limiter;
+mod registry;
+mod report;
+mod responsive;
+mod revalidate;
+#[cfg(debug_assertions)]
+mod rules_page;
+mod ruleset;
+#[cfg(feature = "sanitize-svg")]
+mod sanitize;
 mod service;
+mod shared;
+mod sniff;
+mod spa;
+mod stages;
+#[cfg(debug_assertions)]
+mod staleness;
+mod suggest;
+mod throttle;
+mod timeout;
+mod tolerance;
+mod trace;
+mod variant_cache;
+mod version;
 
 #[cfg(test)]
 mod tests {
-    use actix_web::http::StatusCode;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::time::Duration;
+
+    use actix_web::http::{header, StatusCode};
     use actix_web::test::TestRequest;
-    use actix_web::{test, App, HttpResponse};
+    use actix_web::{guard, test, web, App, HttpMessage, HttpRequest, HttpResponse};
     use bytes::Bytes;
     use rust_embed::RustEmbed;
 
-    use crate::Embed;
+    use crate::{
+        compliance, CollisionPolicy, DefaultFallbackHandler, Embed, EmbedAdmin, EmbedOptions,
+        EmbedRegistry, ForwardedPolicy, HookPanic, MetricsSnapshot, MountConflict, MountKind,
+        MountRegistry, RateLimitDecision, Scheme, ServeMetrics, ServeOutcome, ServeRecord,
+        ServeStage, Suggestions, VersionInfo,
+    };
 
     #[derive(RustEmbed)]
     #[folder = "testdata/"]
     struct Assets;
 
+    #[derive(RustEmbed)]
+    #[folder = "testdata/fonts/"]
+    struct Fonts;
+
+    #[derive(RustEmbed)]
+    #[folder = "testdata/well-known/"]
+    struct WellKnown;
+
     #[actix_web::test]
     async fn test_basic() {
         let srv = test::init_service(App::new().service(Embed::new("/", &Assets))).await;
@@ -123,4 +259,5663 @@ mod tests {
             assert_eq!(test::read_body(resp_a).await, test::read_body(resp_b).await);
         }
     }
+
+    #[actix_web::test]
+    async fn test_mount_kind() {
+        let embed = |kind| {
+            Embed::new("/static", &Assets)
+                .mount_kind(kind)
+                .index_file("index.html")
+        };
+
+        let srv = test::init_service(
+            App::new()
+                .service(web::scope("/prefix").service(embed(MountKind::Prefix)))
+                .service(web::scope("/exact").service(embed(MountKind::Exact)))
+                .service(web::scope("/tail").service(embed(MountKind::Tail)))
+                .service(
+                    web::scope("/nested").service(
+                        web::scope("/guarded")
+                            .guard(guard::Header("x-guard", "1"))
+                            .service(embed(MountKind::Tail)),
+                    ),
+                )
+                .service(embed(MountKind::Prefix)),
+        )
+        .await;
+
+        let cases = [
+            ("/static", StatusCode::OK),
+            ("/static/", StatusCode::OK),
+            ("/static/assets/index.css", StatusCode::OK),
+            ("/staticx/index.html", StatusCode::NOT_FOUND),
+            ("/prefix/static", StatusCode::OK),
+            ("/prefix/static/assets/index.css", StatusCode::OK),
+            ("/exact/static", StatusCode::OK),
+            ("/exact/static/assets/index.css", StatusCode::NOT_FOUND),
+            ("/tail/static", StatusCode::NOT_FOUND),
+            ("/tail/static/", StatusCode::OK),
+            ("/tail/static/assets/index.css", StatusCode::OK),
+            (
+                "/nested/guarded/static/assets/index.css",
+                StatusCode::NOT_FOUND,
+            ),
+        ];
+
+        for (path, status) in cases {
+            let req = TestRequest::get().uri(path).to_request();
+            let resp = test::call_service(&srv, req).await;
+            assert_eq!(resp.status(), status, "{}", path);
+        }
+
+        let req = TestRequest::get()
+            .uri("/nested/guarded/static/assets/index.css")
+            .insert_header(("x-guard", "1"))
+            .to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        // exact mount at the root
+        let srv = test::init_service(
+            App::new().service(
+                Embed::new("/", &Assets)
+                    .mount_kind(MountKind::Exact)
+                    .index_file("index.html"),
+            ),
+        )
+        .await;
+        let req = TestRequest::get().uri("/").to_request();
+        assert_eq!(test::call_service(&srv, req).await.status(), StatusCode::OK);
+        let req = TestRequest::get().uri("/index.html").to_request();
+        assert_eq!(
+            test::call_service(&srv, req).await.status(),
+            StatusCode::NOT_FOUND
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_rate_limiter() {
+        let limiter = |key: &str| {
+            if key == "blocked" {
+                RateLimitDecision::RetryAfter(Duration::from_secs(30))
+            } else {
+                RateLimitDecision::Allow
+            }
+        };
+        let srv = test::init_service(App::new().service(
+            Embed::new("/", &Assets).rate_limiter_with_key(limiter, |req: &HttpRequest| {
+                req.headers()
+                    .get("x-client")
+                    .and_then(|v| v.to_str().ok())
+                    .map(str::to_owned)
+            }),
+        ))
+        .await;
+
+        let req = TestRequest::get()
+            .uri("/index.html")
+            .insert_header(("x-client", "blocked"))
+            .to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(resp.headers().get(header::RETRY_AFTER).unwrap(), "30");
+
+        let req = TestRequest::get()
+            .uri("/index.html")
+            .insert_header(("x-client", "other"))
+            .to_request();
+        assert_eq!(test::call_service(&srv, req).await.status(), StatusCode::OK);
+
+        // requests without a key are not limited
+        let req = TestRequest::get().uri("/index.html").to_request();
+        assert_eq!(test::call_service(&srv, req).await.status(), StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn test_conditional_compliance() {
+        compliance::check_conditional_requests(Embed::new("/", &Assets), "/index.html")
+            .await
+            .assert_compliant();
+        compliance::check_conditional_requests(
+            Embed::new("/static", &Assets).index_file("index.html"),
+            "/static/",
+        )
+        .await
+        .assert_compliant();
+    }
+
+    #[actix_web::test]
+    async fn test_on_serve() {
+        let records = Rc::new(RefCell::new(Vec::new()));
+        let srv = test::init_service(App::new().service({
+            let records = records.clone();
+            Embed::new("/static", &Assets)
+                .archive("fonts.tar", "fonts")
+                .on_serve(move |record: ServeRecord| records.borrow_mut().push(record))
+        }))
+        .await;
+
+        for path in ["/static/assets/index.css", "/static/missing"] {
+            let req = TestRequest::get().uri(path).to_request();
+            test::call_and_read_body(&srv, req).await;
+        }
+        // records are emitted once the body is sent, with the bytes sent
+        let req = TestRequest::get().uri("/static/fonts.tar").to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(records.borrow().len(), 2);
+        let archive = test::read_body(resp).await;
+        let req = TestRequest::get().uri("/static/fonts.tar").to_request();
+        drop(test::call_service(&srv, req).await);
+
+        let records = records.borrow();
+        assert_eq!(records.len(), 4);
+        assert_eq!(records[2].bytes, archive.len() as u64);
+        assert_eq!(records[3].bytes, 0);
+        assert_eq!(records[0].path.as_deref(), Some("assets/index.css"));
+        assert_eq!(records[0].status, StatusCode::OK);
+        assert_eq!(
+            records[0].bytes,
+            Assets::get("assets/index.css").unwrap().data.len() as u64
+        );
+        assert_eq!(records[0].outcome, ServeOutcome::Ok);
+        assert_eq!(records[1].path, None);
+        assert_eq!(records[1].status, StatusCode::NOT_FOUND);
+        assert_eq!(records[1].outcome, ServeOutcome::Fallback);
+    }
+
+    #[actix_web::test]
+    async fn test_serve_context() {
+        use actix_web::http::Method;
+
+        use crate::{
+            Encoding, PathTolerance, PolicyDecision, RevalidationDecision, ServeContext, Validators,
+        };
+
+        let records = Rc::new(RefCell::new(Vec::new()));
+        let encodings = Rc::new(RefCell::new(Vec::new()));
+        let srv = test::init_service(App::new().service({
+            let records = records.clone();
+            let encodings = encodings.clone();
+            Embed::new("/", &Assets)
+                .use_precompressed(&[Encoding::Gzip])
+                .path_tolerance(PathTolerance::Trim)
+                .on_serve(move |record: ServeRecord| records.borrow_mut().push(record))
+                .revalidation_hook(move |_: &HttpRequest, validators: &Validators| {
+                    encodings.borrow_mut().push(validators.context.encoding());
+                    RevalidationDecision::Evaluate
+                })
+                .fallback_handler(|req: &HttpRequest| {
+                    let extensions = req.extensions();
+                    let context = extensions.get::<ServeContext>().unwrap();
+                    HttpResponse::NotFound().body(context.path().to_owned())
+                })
+        }))
+        .await;
+
+        let req = TestRequest::get()
+            .uri("/precompressed/app.js.")
+            .insert_header((header::ACCEPT_ENCODING, "gzip"))
+            .to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let context = resp.request().extensions().get::<ServeContext>().cloned();
+        test::read_body(resp).await;
+        let req = TestRequest::get().uri("/missing").to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(test::read_body(resp).await, "missing");
+
+        let records = records.borrow();
+        let served = &records[0].context;
+        assert_eq!(context.as_ref(), Some(served));
+        assert_eq!(served.method(), Method::GET);
+        assert_eq!(served.path(), "precompressed/app.js");
+        assert_eq!(served.file(), Some("precompressed/app.js"));
+        assert_eq!(served.encoding(), Some(Encoding::Gzip));
+        assert_eq!(
+            served.decisions(),
+            [PolicyDecision {
+                option: "path_tolerance",
+                outcome: "trim"
+            }]
+        );
+        assert_eq!(served.decision("path_tolerance"), Some("trim"));
+        assert_eq!(*encodings.borrow(), [Some(Encoding::Gzip)]);
+        let missing = &records[1].context;
+        assert_eq!(missing.file(), None);
+        assert_eq!(missing.compression(), None);
+    }
+
+    #[actix_web::test]
+    async fn test_deprecated() {
+        use std::time::SystemTime;
+
+        use crate::Sunset;
+
+        let removal = SystemTime::UNIX_EPOCH + Duration::from_secs(1_900_000_000);
+        let since = SystemTime::UNIX_EPOCH + Duration::from_secs(1_800_000_000);
+        let records = Rc::new(RefCell::new(Vec::new()));
+        let srv = test::init_service(App::new().service({
+            let records = records.clone();
+            Embed::new("/", &Assets)
+                .deprecated(
+                    ["images/hero@*"],
+                    Sunset::date(removal).deprecated_since(since),
+                )
+                .deprecated(["fonts/**"], Sunset::unscheduled())
+                .on_serve(move |record: ServeRecord| records.borrow_mut().push(record))
+        }))
+        .await;
+        let get = |uri: &'static str| {
+            let req = TestRequest::get().uri(uri).to_request();
+            test::call_service(&srv, req)
+        };
+
+        let resp = get("/images/hero@1x.png").await;
+        assert_eq!(resp.headers().get("deprecation").unwrap(), "@1800000000");
+        assert_eq!(
+            resp.headers().get("sunset").unwrap(),
+            "Sun, 17 Mar 2030 17:46:40 GMT"
+        );
+        test::read_body(resp).await;
+        let resp = get("/fonts/icons.woff").await;
+        assert!(resp.headers().get("deprecation").is_some());
+        assert!(resp.headers().get("sunset").is_none());
+        test::read_body(resp).await;
+        let resp = get("/images/banner@640w.png").await;
+        assert!(resp.headers().get("deprecation").is_none());
+        test::read_body(resp).await;
+
+        let deprecated = records
+            .borrow()
+            .iter()
+            .map(|record| record.deprecated)
+            .collect::<Vec<_>>();
+        assert_eq!(deprecated, [true, true, false]);
+    }
+
+    #[actix_web::test]
+    async fn test_available() {
+        use std::cell::Cell;
+        use std::time::SystemTime;
+
+        let launch = SystemTime::UNIX_EPOCH + Duration::from_secs(1_900_000_000);
+        let end = launch + Duration::from_secs(60);
+        let now = Rc::new(Cell::new(launch - Duration::from_secs(1)));
+        let clock = now.clone();
+        let srv = test::init_service(
+            App::new().service(
+                Embed::new("/", &Assets)
+                    .variant_cache(4)
+                    .available("en/**", launch..end)
+                    .clock(move || clock.get()),
+            ),
+        )
+        .await;
+        let status = |uri: &'static str| {
+            let srv = &srv;
+            async move {
+                let req = TestRequest::get().uri(uri).to_request();
+                test::call_service(srv, req).await.status()
+            }
+        };
+
+        assert_eq!(status("/en/index.html").await, StatusCode::NOT_FOUND);
+        assert_eq!(status("/de/index.html").await, StatusCode::OK);
+        now.set(launch);
+        assert_eq!(status("/en/index.html").await, StatusCode::OK);
+        // the response kept by the variant cache is not reused after the window
+        now.set(end);
+        assert_eq!(status("/en/index.html").await, StatusCode::GONE);
+        assert_eq!(status("/de/index.html").await, StatusCode::OK);
+
+        let issues = Embed::new("/", &Assets)
+            .available("en/**", end..launch)
+            .issues();
+        assert_eq!(issues[0].option, "availability");
+    }
+
+    #[cfg(feature = "tracing")]
+    #[actix_web::test]
+    async fn test_hooks_in_request_span() {
+        use std::sync::{Arc, Mutex};
+
+        use actix_web::dev::Service;
+        use tracing::span::{Attributes, Id, Record};
+        use tracing::{Event, Metadata, Subscriber};
+
+        /// Records the spans with their parents, the id of a span is its index plus one.
+        #[derive(Default)]
+        struct Spans {
+            spans: Mutex<Vec<(&'static Metadata<'static>, Option<u64>)>>,
+            entered: Mutex<Vec<u64>>,
+        }
+
+        impl Spans {
+            fn chain(&self, id: u64) -> Vec<&'static str> {
+                let spans = self.spans.lock().unwrap();
+                let mut chain = Vec::new();
+                let mut current = Some(id);
+                while let Some(id) = current {
+                    let (metadata, parent) = spans[id as usize - 1];
+                    chain.push(metadata.name());
+                    current = parent;
+                }
+                chain
+            }
+        }
+
+        impl Subscriber for Spans {
+            fn enabled(&self, _: &Metadata<'_>) -> bool {
+                true
+            }
+            fn new_span(&self, span: &Attributes<'_>) -> Id {
+                let parent = match span.parent() {
+                    Some(parent) => Some(parent.into_u64()),
+                    None if span.is_contextual() => self.entered.lock().unwrap().last().copied(),
+                    None => None,
+                };
+                let mut spans = self.spans.lock().unwrap();
+                spans.push((span.metadata(), parent));
+                Id::from_u64(spans.len() as u64)
+            }
+            fn record(&self, _: &Id, _: &Record<'_>) {}
+            fn record_follows_from(&self, _: &Id, _: &Id) {}
+            fn event(&self, _: &Event<'_>) {}
+            fn enter(&self, span: &Id) {
+                self.entered.lock().unwrap().push(span.into_u64());
+            }
+            fn exit(&self, _: &Id) {
+                self.entered.lock().unwrap().pop();
+            }
+        }
+
+        let spans = Arc::new(Spans::default());
+        let _default = tracing::subscriber::set_default(spans.clone());
+        let upstream = Rc::new(RefCell::new(Vec::new()));
+        let srv = test::init_service(App::new().service({
+            let upstream = upstream.clone();
+            // a span of an upstream call made from the hook
+            Embed::new("/static", &Assets).on_serve(move |_: ServeRecord| {
+                upstream
+                    .borrow_mut()
+                    .push(tracing::info_span!("upstream").id());
+            })
+        }))
+        .await;
+
+        let root = tracing::info_span!("request");
+        // the service is called, not awaited, in the span, as by tracing middleware
+        let resp = {
+            let _entered = root.enter();
+            let req = TestRequest::get().uri("/static/index.html").to_request();
+            srv.call(req)
+        }
+        .await
+        .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        test::read_body(resp).await;
+
+        let id = upstream.borrow()[0].clone().unwrap();
+        assert_eq!(
+            spans.chain(id.into_u64()),
+            ["upstream", "hook", "actix_embed::serve", "request"]
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_fonts_preset() {
+        let srv =
+            test::init_service(App::new().service(Embed::new("/", &Assets).fonts_preset())).await;
+
+        for (path, mime) in [
+            ("/fonts/icons.woff", "font/woff"),
+            ("/fonts/icons.woff2", "font/woff2"),
+        ] {
+            let req = TestRequest::get().uri(path).to_request();
+            let resp = test::call_service(&srv, req).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+            let headers = resp.headers();
+            assert_eq!(headers.get(header::CONTENT_TYPE).unwrap(), mime);
+            assert_eq!(
+                headers.get(header::ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(),
+                "*"
+            );
+            assert_eq!(
+                headers.get(header::CACHE_CONTROL).unwrap(),
+                "public, max-age=31536000, immutable"
+            );
+        }
+
+        let req = TestRequest::get().uri("/index.html").to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert!(resp
+            .headers()
+            .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+            .is_none());
+    }
+
+    #[cfg(feature = "minify")]
+    #[actix_web::test]
+    async fn test_minify() {
+        use sha2::Digest;
+
+        let srv = test::init_service(
+            App::new().service(Embed::new("/", &Assets).minify(true).serve_originals(true)),
+        )
+        .await;
+        let original = Assets::get("index.html").unwrap().data;
+
+        let req = TestRequest::get().uri("/index.html").to_request();
+        let resp = test::call_service(&srv, req).await;
+        let etag = resp.headers().get(header::ETAG).unwrap().clone();
+        let minified = test::read_body(resp).await;
+        assert!(minified.len() < original.len());
+        // derived from the original file and the minified output
+        let hash = Assets::get("index.html").unwrap().metadata.sha256_hash();
+        let digest = sha2::Sha256::digest(&minified);
+        let expected = format!(
+            "\"{}-min-{}\"",
+            hex::encode(hash),
+            hex::encode(&digest[..8])
+        );
+        assert_eq!(etag.to_str().unwrap(), expected);
+
+        let req = TestRequest::get()
+            .uri("/index.html")
+            .insert_header((header::IF_NONE_MATCH, etag.clone()))
+            .to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+
+        let req = TestRequest::get().uri("/index.html?original").to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(test::read_body(resp).await, original.as_ref());
+    }
+
+    #[cfg(feature = "compress-brotli")]
+    #[actix_web::test]
+    async fn test_precompress() {
+        use crate::Encoding;
+
+        let srv = test::init_service(
+            App::new().service(
+                Embed::new("/", &Assets)
+                    .precompress(Encoding::Brotli, 11, ["**/*.html"])
+                    .precompress_blocking(true),
+            ),
+        )
+        .await;
+
+        let req = TestRequest::get()
+            .uri("/index.html")
+            .insert_header((header::ACCEPT_ENCODING, "gzip, br"))
+            .to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.headers().get(header::CONTENT_ENCODING).unwrap(), "br");
+        assert_eq!(resp.headers().get(header::VARY).unwrap(), "Accept-Encoding");
+        let etag = resp.headers().get(header::ETAG).unwrap().clone();
+        assert!(etag.to_str().unwrap().ends_with("-br\""));
+        let compressed = test::read_body(resp).await;
+        assert!(compressed.len() < Assets::get("index.html").unwrap().data.len());
+
+        let req = TestRequest::get()
+            .uri("/index.html")
+            .insert_header((header::ACCEPT_ENCODING, "gzip"))
+            .insert_header((header::IF_NONE_MATCH, etag))
+            .to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(resp.headers().get(header::CONTENT_ENCODING).is_none());
+        assert_eq!(resp.headers().get(header::VARY).unwrap(), "Accept-Encoding");
+
+        let req = TestRequest::get()
+            .uri("/assets/index.css")
+            .insert_header((header::ACCEPT_ENCODING, "br"))
+            .to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert!(resp.headers().get(header::CONTENT_ENCODING).is_none());
+        assert!(resp.headers().get(header::VARY).is_none());
+    }
+
+    #[cfg(feature = "compress-gzip")]
+    #[actix_web::test]
+    async fn test_derived_cache_dir() {
+        use crate::Encoding;
+
+        let dir = std::env::temp_dir().join(format!("actix-embed-test-{}", std::process::id()));
+        let embed = || {
+            Embed::new("/", &Assets)
+                .precompress(Encoding::Gzip, 9, ["*.html"])
+                .derived_cache_dir(&dir)
+        };
+        let request = || {
+            TestRequest::get()
+                .uri("/index.html")
+                .insert_header((header::ACCEPT_ENCODING, "gzip"))
+                .to_request()
+        };
+
+        let srv = test::init_service(App::new().service(embed())).await;
+        let resp = test::call_service(&srv, request()).await;
+        assert_eq!(
+            resp.headers().get(header::CONTENT_ENCODING).unwrap(),
+            "gzip"
+        );
+        let body = test::read_body(resp).await;
+
+        let artifacts = std::fs::read_dir(&dir).unwrap().count();
+        assert_eq!(artifacts, 2);
+
+        // corrupted artifacts are recreated
+        let artifact = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .find(|path| path.extension().unwrap() != "sha256")
+            .unwrap();
+        std::fs::write(&artifact, b"corrupted").unwrap();
+
+        let srv = test::init_service(App::new().service(embed())).await;
+        let resp = test::call_service(&srv, request()).await;
+        assert_eq!(test::read_body(resp).await, body);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[actix_web::test]
+    async fn test_localized_index() {
+        let srv = test::init_service(
+            App::new().service(
+                Embed::new("/", &Assets)
+                    .localized_index(["en/index.html", "de/index.html"], "en/index.html"),
+            ),
+        )
+        .await;
+
+        let cases = [
+            (Some("de-AT, en;q=0.5"), "de"),
+            (Some("fr, en;q=0.8, de;q=0.9"), "de"),
+            (Some("fr"), "en"),
+            (None, "en"),
+        ];
+        for (accept_language, expected) in cases {
+            let mut req = TestRequest::get().uri("/");
+            if let Some(accept_language) = accept_language {
+                req = req.insert_header((header::ACCEPT_LANGUAGE, accept_language));
+            }
+            let resp = test::call_service(&srv, req.to_request()).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+            assert_eq!(
+                resp.headers().get(header::CONTENT_LANGUAGE).unwrap(),
+                expected
+            );
+            assert_eq!(resp.headers().get(header::VARY).unwrap(), "Accept-Language");
+            let expected_body = Assets::get(&format!("{}/index.html", expected))
+                .unwrap()
+                .data;
+            assert_eq!(test::read_body(resp).await, expected_body.as_ref());
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_auto_correct() {
+        let srv = test::init_service(
+            App::new()
+                .service(Embed::new("/docs", &Assets).auto_correct(true))
+                .service(Embed::new("/plain", &Assets)),
+        )
+        .await;
+        let get = |uri: &'static str| {
+            let srv = &srv;
+            async move {
+                let req = TestRequest::get().uri(uri).to_request();
+                let resp = test::call_service(srv, req).await;
+                let location = resp
+                    .headers()
+                    .get(header::LOCATION)
+                    .map(|value| value.to_str().unwrap().to_owned());
+                (resp.status(), location)
+            }
+        };
+        let redirected =
+            |location: &str| (StatusCode::MOVED_PERMANENTLY, Some(location.to_owned()));
+
+        assert_eq!(
+            get("/docs/Index.HTML").await,
+            redirected("/docs/index.html")
+        );
+        assert_eq!(
+            get("/docs/Index.HTML?lang=de").await,
+            redirected("/docs/index.html?lang=de")
+        );
+        assert_eq!(
+            get("/docs/assets/index").await,
+            redirected("/docs/assets/index.css")
+        );
+        assert_eq!(
+            get("/docs/assets/index.css.map").await,
+            redirected("/docs/assets/index.css")
+        );
+        assert_eq!(
+            get("/docs/assets/index.scss").await,
+            redirected("/docs/assets/index.css")
+        );
+        assert_eq!(
+            get("/docs/Precompressed/App").await,
+            redirected("/docs/precompressed/app.js")
+        );
+        // `icons.woff` and `icons.woff2` are as close
+        assert_eq!(
+            get("/docs/fonts/icons").await,
+            (StatusCode::NOT_FOUND, None)
+        );
+        assert_eq!(
+            get("/docs/assets/main.css").await,
+            (StatusCode::NOT_FOUND, None)
+        );
+        assert_eq!(
+            get("/plain/Index.HTML").await,
+            (StatusCode::NOT_FOUND, None)
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_redirect_and_suggestions() {
+        let srv = test::init_service(
+            App::new().service(
+                Embed::new("/docs", &Assets)
+                    .redirect("style.css", "assets/index.css")
+                    .suggestions(3),
+            ),
+        )
+        .await;
+
+        let req = TestRequest::get().uri("/docs/style.css").to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::MOVED_PERMANENTLY);
+        assert_eq!(
+            resp.headers().get(header::LOCATION).unwrap(),
+            "/docs/assets/index.css"
+        );
+
+        let req = TestRequest::get()
+            .uri("/docs/assets/Index.css")
+            .to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        let body = test::read_body(resp).await;
+        assert!(std::str::from_utf8(&body)
+            .unwrap()
+            .contains("<a href=\"/docs/assets/index.css\">"));
+
+        let srv = test::init_service(
+            App::new().service(Embed::new("/", &Assets).suggestions(2).fallback_handler(
+                |req: &HttpRequest| {
+                    let suggestions = req.extensions().get::<Suggestions>().cloned().unwrap();
+                    HttpResponse::NotFound().body(suggestions.urls.join(","))
+                },
+            )),
+        )
+        .await;
+        let req = TestRequest::get().uri("/indx.html").to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(test::read_body(resp).await, Bytes::from("/index.html"));
+    }
+
+    #[actix_web::test]
+    async fn test_canonical_host() {
+        let embed = || Embed::new("/", &Assets).canonical_host("www.example.com", Scheme::Https);
+        let srv = test::init_service(
+            App::new()
+                .service(web::scope("/ignore").service(embed()))
+                .service(embed().forwarded_policy(ForwardedPolicy::Trust)),
+        )
+        .await;
+
+        let req = TestRequest::get()
+            .uri("/index.html?a=1")
+            .insert_header((header::HOST, "example.com"))
+            .to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::MOVED_PERMANENTLY);
+        assert_eq!(
+            resp.headers().get(header::LOCATION).unwrap(),
+            "https://www.example.com/index.html?a=1"
+        );
+
+        for (forwarded, path) in [
+            ("x-forwarded-proto", "https"),
+            ("forwarded", "for=10.0.0.1;proto=https"),
+        ] {
+            let req = TestRequest::get()
+                .uri("/index.html")
+                .insert_header((header::HOST, "www.example.com"))
+                .insert_header((forwarded, path))
+                .to_request();
+            let resp = test::call_service(&srv, req).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+        }
+
+        // forwarded headers are ignored by default
+        let req = TestRequest::get()
+            .uri("/ignore/index.html")
+            .insert_header((header::HOST, "www.example.com"))
+            .insert_header(("x-forwarded-proto", "https"))
+            .to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::MOVED_PERMANENTLY);
+    }
+
+    #[actix_web::test]
+    async fn test_metrics() {
+        let metrics = ServeMetrics::new();
+        let srv = test::init_service(
+            App::new().service(Embed::new("/static", &Assets).metrics(metrics.clone())),
+        )
+        .await;
+
+        let req = TestRequest::get().uri("/static/index.html").to_request();
+        let resp = test::call_service(&srv, req).await;
+        let etag = resp.headers().get(header::ETAG).unwrap().clone();
+        test::read_body(resp).await;
+
+        let req = TestRequest::get()
+            .uri("/static/index.html")
+            .insert_header((header::IF_NONE_MATCH, etag))
+            .to_request();
+        test::call_service(&srv, req).await;
+
+        let req = TestRequest::get().uri("/static/missing").to_request();
+        test::call_service(&srv, req).await;
+
+        let req = TestRequest::default()
+            .method(actix_web::http::Method::HEAD)
+            .uri("/static/index.html")
+            .to_request();
+        test::call_service(&srv, req).await;
+
+        assert_eq!(
+            metrics.snapshot(),
+            MetricsSnapshot {
+                ok: 2,
+                not_modified: 1,
+                fallback: 1,
+                head: 1,
+                bytes_served: Assets::get("index.html").unwrap().data.len() as u64,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_embed_routes() {
+        let srv = test::init_service(App::new().service(crate::embed_routes! {
+            |embed| embed.index_file("index.html");
+            "/static" => Assets,
+            "/docs" => Assets,
+        }))
+        .await;
+
+        for path in ["/static", "/docs/", "/docs/assets/index.css"] {
+            let req = TestRequest::get().uri(path).to_request();
+            let resp = test::call_service(&srv, req).await;
+            assert_eq!(resp.status(), StatusCode::OK, "{}", path);
+        }
+
+        let srv = test::init_service(App::new().service(crate::embed_routes! {
+            "/static" => Assets,
+        }))
+        .await;
+        let req = TestRequest::get().uri("/static").to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn test_embed_options() {
+        let options = EmbedOptions::new().index_file("index.html").fonts_preset();
+        let srv = test::init_service(
+            App::new()
+                .service(Embed::with_options("/a", &Assets, options.clone()).strict_slash(true))
+                .service(Embed::with_options("/b", &Assets, options)),
+        )
+        .await;
+
+        for path in ["/a", "/b"] {
+            let req = TestRequest::get().uri(path).to_request();
+            let resp = test::call_service(&srv, req).await;
+            assert_eq!(resp.status(), StatusCode::OK, "{}", path);
+
+            let req = TestRequest::get()
+                .uri(&format!("{}/fonts/icons.woff", path))
+                .to_request();
+            let resp = test::call_service(&srv, req).await;
+            assert_eq!(
+                resp.headers().get(header::CONTENT_TYPE).unwrap(),
+                "font/woff"
+            );
+        }
+
+        // settings applied to one instance don't affect the other
+        let req = TestRequest::get().uri("/a/index.html/").to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        let req = TestRequest::get().uri("/b/index.html/").to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn test_mount_registry() {
+        let conflicts = Rc::new(RefCell::new(Vec::new()));
+        let mounts = MountRegistry::new().on_conflict({
+            let conflicts = conflicts.clone();
+            move |conflict: &MountConflict| conflicts.borrow_mut().push(conflict.clone())
+        });
+
+        let srv = test::init_service(
+            App::new()
+                .service(Embed::new("static//", &Assets).mount_registry(&mounts))
+                .service(Embed::new("/static/fonts", &Assets).mount_registry(&mounts))
+                .service(Embed::new("/", &Assets).mount_registry(&mounts))
+                .service(mounts.track(
+                    "/api",
+                    web::resource("/api").to(|| async { HttpResponse::Ok().finish() }),
+                )),
+        )
+        .await;
+
+        assert_eq!(mounts.mounts(), ["/static", "/static/fonts", "/", "/api"]);
+        assert_eq!(
+            *conflicts.borrow(),
+            [
+                MountConflict {
+                    path: "/static/fonts".to_owned(),
+                    shadowed_by: "/static".to_owned(),
+                },
+                MountConflict {
+                    path: "/api".to_owned(),
+                    shadowed_by: "/".to_owned(),
+                },
+            ]
+        );
+
+        // the normalized mount path is served
+        let req = TestRequest::get().uri("/static/index.html").to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn test_well_known() {
+        let srv = test::init_service(
+            App::new()
+                .service(Embed::well_known(&Assets, "well-known/"))
+                .service(Embed::favicon(&Assets, "favicon.ico"))
+                .route(
+                    "/.well-known/acme-challenge/{token}",
+                    web::get().to(|| async { HttpResponse::Ok().body("acme") }),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::get()
+            .uri("/.well-known/security.txt")
+            .to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get(header::CACHE_CONTROL).unwrap(),
+            "no-cache"
+        );
+
+        let req = TestRequest::get()
+            .uri("/.well-known/acme-challenge/token")
+            .to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(test::read_body(resp).await, Bytes::from("acme"));
+
+        let req = TestRequest::get()
+            .uri("/.well-known/index.html")
+            .to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        let req = TestRequest::get().uri("/favicon.ico").to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(resp.headers().get(header::CACHE_CONTROL).is_none());
+    }
+
+    #[actix_web::test]
+    async fn test_exclude() {
+        let srv = test::init_service(
+            App::new()
+                .service(
+                    Embed::new("/static", &Assets)
+                        .clean_urls(true)
+                        .exclude(["index.html"]),
+                )
+                .default_service(web::to(HttpResponse::Gone)),
+        )
+        .await;
+
+        for uri in ["/static/index.html", "/static/ind%65x.html"] {
+            let req = TestRequest::get().uri(uri).to_request();
+            let resp = test::call_service(&srv, req).await;
+            assert_eq!(resp.status(), StatusCode::GONE, "{}", uri);
+        }
+
+        // the file is not served at its clean URL either
+        let req = TestRequest::get().uri("/static/index").to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        let req = TestRequest::get().uri("/static/favicon.ico").to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn test_default_fallback_handler() {
+        let srv = test::init_service(
+            App::new()
+                .service(
+                    Embed::new("/gone", &Assets).fallback_handler(
+                        DefaultFallbackHandler::new()
+                            .status(StatusCode::GONE)
+                            .content_type("text/plain")
+                            .body("gone"),
+                    ),
+                )
+                .service(Embed::new("/", &Assets).fallback_handler(
+                    DefaultFallbackHandler::new().file::<Assets, _>("index.html"),
+                )),
+        )
+        .await;
+
+        let req = TestRequest::get().uri("/gone/missing").to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::GONE);
+        assert_eq!(
+            resp.headers().get(header::CONTENT_TYPE).unwrap(),
+            "text/plain"
+        );
+        assert_eq!(test::read_body(resp).await, Bytes::from("gone"));
+
+        let req = TestRequest::get().uri("/missing").to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        assert_eq!(
+            resp.headers().get(header::CONTENT_TYPE).unwrap(),
+            "text/html"
+        );
+        assert_eq!(
+            test::read_body(resp).await,
+            Assets::get("index.html").unwrap().data.as_ref()
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_fallback_responder() {
+        let srv = test::init_service(App::new().service(
+            Embed::new("/", &Assets).fallback(|req| (req.path().to_owned(), StatusCode::GONE)),
+        ))
+        .await;
+
+        let req = TestRequest::get().uri("/missing").to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::GONE);
+        assert_eq!(test::read_body(resp).await, Bytes::from("/missing"));
+    }
+
+    #[actix_web::test]
+    async fn test_hook_panics() {
+        let panics = Rc::new(RefCell::new(Vec::new()));
+        let srv = test::init_service(
+            App::new().service(
+                Embed::new("/", &Assets)
+                    .on_hook_panic({
+                        let panics = panics.clone();
+                        move |panic: &HookPanic| panics.borrow_mut().push(panic.clone())
+                    })
+                    .on_serve(|record: ServeRecord| {
+                        if record.path.as_deref() == Some("index.html") {
+                            panic!("on_serve failed");
+                        }
+                    })
+                    .fallback(|_| -> HttpResponse { panic!("fallback failed") }),
+            ),
+        )
+        .await;
+
+        let req = TestRequest::get().uri("/missing").to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+        let req = TestRequest::get().uri("/index.html").to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        test::read_body(resp).await;
+
+        assert_eq!(
+            *panics.borrow(),
+            [
+                HookPanic {
+                    hook: "fallback_handler",
+                    message: Some("fallback failed".to_owned()),
+                },
+                HookPanic {
+                    hook: "on_serve",
+                    message: Some("on_serve failed".to_owned()),
+                },
+            ]
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_async_hooks() {
+        use futures_core::future::LocalBoxFuture;
+
+        use crate::FallbackHandler;
+
+        #[derive(Clone)]
+        struct Upstream(Duration);
+
+        impl FallbackHandler for Upstream {
+            fn execute(&self, _: &HttpRequest) -> HttpResponse {
+                HttpResponse::BadGateway().finish()
+            }
+
+            fn execute_async(&self, _: HttpRequest) -> LocalBoxFuture<'static, HttpResponse> {
+                let delay = self.0;
+                Box::pin(async move {
+                    actix_web::rt::time::sleep(delay).await;
+                    if delay.is_zero() {
+                        panic!("upstream failed");
+                    }
+                    HttpResponse::Ok().body("upstream")
+                })
+            }
+        }
+
+        // panics while the future is polled are caught too
+        for (delay, status) in [
+            (Duration::from_millis(1), StatusCode::OK),
+            (Duration::from_secs(1), StatusCode::GATEWAY_TIMEOUT),
+            (Duration::ZERO, StatusCode::INTERNAL_SERVER_ERROR),
+        ] {
+            let srv = test::init_service(
+                App::new().service(
+                    Embed::new("/", &Assets)
+                        .hook_timeout(Duration::from_millis(20))
+                        .fallback_handler(Upstream(delay)),
+                ),
+            )
+            .await;
+            let req = TestRequest::get().uri("/missing").to_request();
+            assert_eq!(test::call_service(&srv, req).await.status(), status);
+        }
+    }
+
+    #[cfg(feature = "minify")]
+    #[actix_web::test]
+    async fn test_serve_timeout() {
+        use crate::TimeoutAction;
+
+        let embed = |action| {
+            Embed::new("/", &Assets)
+                .minify(true)
+                .serve_timeout(Duration::ZERO, action)
+        };
+
+        let srv = test::init_service(App::new().service(embed(TimeoutAction::Identity))).await;
+        let req = TestRequest::get().uri("/index.html").to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            test::read_body(resp).await,
+            Assets::get("index.html").unwrap().data.as_ref()
+        );
+
+        let srv =
+            test::init_service(App::new().service(embed(TimeoutAction::ServiceUnavailable))).await;
+        let req = TestRequest::get().uri("/index.html").to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        // files without derived work are not affected
+        let req = TestRequest::get().uri("/fonts/icons.woff").to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn test_hasher() {
+        struct Length;
+
+        impl crate::Hasher for Length {
+            fn hash(&self, file: &rust_embed::EmbeddedFile) -> Vec<u8> {
+                (file.data.len() as u32).to_be_bytes().to_vec()
+            }
+        }
+
+        let srv =
+            test::init_service(App::new().service(Embed::new("/", &Assets).hasher(Length))).await;
+
+        let req = TestRequest::get().uri("/index.html").to_request();
+        let resp = test::call_service(&srv, req).await;
+        let len = Assets::get("index.html").unwrap().data.len() as u32;
+        assert_eq!(
+            resp.headers().get(header::ETAG).unwrap(),
+            format!("\"{}\"", hex::encode(len.to_be_bytes())).as_str()
+        );
+
+        compliance::check_conditional_requests(
+            Embed::new("/", &Assets).hasher(Length),
+            "/index.html",
+        )
+        .await
+        .assert_compliant();
+    }
+
+    #[actix_web::test]
+    async fn test_version_endpoint() {
+        let srv = test::init_service(
+            App::new().service(
+                Embed::new("/", &Assets)
+                    .version_endpoint("/__version", VersionInfo::new().build("abc")),
+            ),
+        )
+        .await;
+
+        let req = TestRequest::get().uri("/__version").to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let etag = resp.headers().get(header::ETAG).unwrap().clone();
+        let body = test::read_body(resp).await;
+        let body = std::str::from_utf8(&body).unwrap();
+        let content_hash = etag.to_str().unwrap().trim_matches('"').to_owned();
+        assert_eq!(
+            body,
+            format!(
+                "{{\"build\":\"abc\",\"content_hash\":\"{}\",\"files\":{}}}",
+                content_hash,
+                Assets::iter().count()
+            )
+        );
+
+        let req = TestRequest::get()
+            .uri("/__version")
+            .insert_header((header::IF_NONE_MATCH, etag))
+            .to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+
+        // the content hash changes with the served files
+        let srv = test::init_service(
+            App::new().service(
+                Embed::new("/", &Assets)
+                    .folder("assets")
+                    .version_endpoint("__version", VersionInfo::new()),
+            ),
+        )
+        .await;
+        let req = TestRequest::get().uri("/__version").to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_ne!(
+            resp.headers().get(header::ETAG).unwrap().to_str().unwrap(),
+            format!("\"{}\"", content_hash)
+        );
+        let body = test::read_body(resp).await;
+        assert!(std::str::from_utf8(&body)
+            .unwrap()
+            .starts_with("{\"build\":null,"));
+    }
+
+    #[actix_web::test]
+    async fn test_responsive_images() {
+        let srv = test::init_service(
+            App::new().service(Embed::new("/", &Assets).responsive_images(true)),
+        )
+        .await;
+
+        let request = |dpr: Option<&str>, width: Option<&str>, uri: &str| {
+            let mut req = TestRequest::get().uri(uri);
+            if let Some(dpr) = dpr {
+                req = req.insert_header(("Sec-CH-DPR", dpr));
+            }
+            if let Some(width) = width {
+                req = req.insert_header(("Sec-CH-Width", width));
+            }
+            req.to_request()
+        };
+
+        let resp = test::call_service(&srv, request(None, None, "/images/hero.png")).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(resp
+            .headers()
+            .get_all(header::VARY)
+            .any(|v| v == "Sec-CH-DPR, Sec-CH-Width"));
+        assert_eq!(test::read_body(resp).await, "hero-1x");
+
+        let resp = test::call_service(&srv, request(Some("1.5"), None, "/images/hero.png")).await;
+        assert_eq!(test::read_body(resp).await, "hero-2x");
+
+        // the largest variant is served if none is large enough
+        let resp = test::call_service(&srv, request(Some("3"), None, "/images/hero.png")).await;
+        assert_eq!(test::read_body(resp).await, "hero-2x");
+
+        let resp =
+            test::call_service(&srv, request(Some("2"), Some("600"), "/images/banner.png")).await;
+        assert_eq!(test::read_body(resp).await, "hero-640w");
+        let resp =
+            test::call_service(&srv, request(None, Some("1000"), "/images/banner.png")).await;
+        assert_eq!(test::read_body(resp).await, "hero-1280w");
+
+        // variants are still served by their own paths
+        let resp = test::call_service(&srv, request(Some("2"), None, "/images/hero@1x.png")).await;
+        assert_eq!(test::read_body(resp).await, "hero-1x");
+
+        let resp = test::call_service(&srv, request(None, None, "/index.html")).await;
+        assert_eq!(
+            resp.headers().get("accept-ch").unwrap(),
+            "Sec-CH-DPR, Sec-CH-Width"
+        );
+
+        // disabled by default
+        let srv = test::init_service(App::new().service(Embed::new("/", &Assets))).await;
+        let resp = test::call_service(&srv, request(Some("2"), None, "/images/hero.png")).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[cfg(all(feature = "compress-brotli", feature = "compress-gzip"))]
+    #[actix_web::test]
+    async fn test_deterministic_responses() {
+        use crate::Encoding;
+
+        compliance::check_deterministic_responses(
+            || {
+                Embed::new("/", &Assets)
+                    .precompress(Encoding::Brotli, 11, ["**/*.html"])
+                    .precompress(Encoding::Gzip, 9, ["**/*.html", "**/*.css"])
+            },
+            &["/index.html", "/assets/index.css", "/images/hero@2x.png"],
+        )
+        .await
+        .assert_compliant();
+
+        // responses differing between services are reported
+        let build = std::cell::Cell::new(0);
+        let report = compliance::check_deterministic_responses(
+            || {
+                build.set(build.get() + 1);
+                Embed::new("/", &Assets).index_file(if build.get() == 1 {
+                    "index.html"
+                } else {
+                    "de/index.html"
+                })
+            },
+            &["/"],
+        )
+        .await;
+        assert!(!report.is_compliant());
+    }
+
+    #[actix_web::test]
+    async fn test_replay_decisions() {
+        use crate::decision::Decision;
+
+        let recorded = Rc::new(RefCell::new(Vec::new()));
+        let sink = recorded.clone();
+        let embed = || {
+            Embed::new("/static", &Assets)
+                .index_file("index.html")
+                .redirect("old.css", "assets/index.css")
+        };
+        let srv = test::init_service(App::new().service(
+            embed().record_decisions(move |decision: Decision| sink.borrow_mut().push(decision)),
+        ))
+        .await;
+        for (uri, accept_language) in [
+            ("/static/", "de"),
+            ("/static/old.css", "en"),
+            ("/static/missing.js", "en"),
+        ] {
+            let req = TestRequest::get()
+                .uri(uri)
+                .insert_header((header::ACCEPT_LANGUAGE, accept_language))
+                .insert_header((header::COOKIE, "session=secret"))
+                .to_request();
+            test::call_service(&srv, req).await;
+        }
+
+        let recorded = recorded.borrow().clone();
+        assert_eq!(
+            recorded[0].headers,
+            [("accept-language".to_owned(), "de".to_owned())]
+        );
+        assert_eq!(recorded[0].outcome.file.as_deref(), Some("index.html"));
+        assert_eq!(
+            recorded[1].outcome.location.as_deref(),
+            Some("/static/assets/index.css")
+        );
+        assert!(recorded[2].outcome.fallback);
+        let json = serde_json::to_string(&recorded).unwrap();
+        let decisions = serde_json::from_str::<Vec<Decision>>(&json).unwrap();
+        compliance::replay_decisions(embed(), &decisions)
+            .await
+            .assert_compliant();
+
+        // drifted decisions are reported
+        let report = compliance::replay_decisions(Embed::new("/static", &Assets), &decisions).await;
+        assert_eq!(report.failures.len(), 2);
+        assert!(report.failures[0].actual.ends_with("-> 404 fallback"));
+    }
+
+    #[actix_web::test]
+    async fn test_globs() {
+        use crate::glob::Glob;
+
+        for (pattern, path, expected) in [
+            ("index.html", "index.html", true),
+            ("index.html", "en/index.html", false),
+            ("/assets/**", "assets/fonts/a.woff2", true),
+            ("assets/**", "assets", false),
+            ("**/*.css", "index.css", true),
+            ("**/*.css", "assets/vendor/index.css", true),
+            ("**/*.css", "index.css.map", false),
+            ("de/**.html", "de/a/index.html", true),
+            ("vendor/*.js", "vendor/a.js", true),
+            ("vendor/*.js", "vendor/lodash/a.js", false),
+            ("images/hero@*", "images/hero@2x.png", true),
+            ("*", "favicon.ico", true),
+            ("*", "images/a.png", false),
+            ("**/index.html", "index.html", true),
+            ("**/index.html", "de/index.html", true),
+            ("**/index.html", "de/old-index.html", false),
+            ("en/**/*", "en/index.html", true),
+        ] {
+            assert_eq!(
+                Glob::new(pattern).matches(path),
+                expected,
+                "{} {}",
+                pattern,
+                path
+            );
+        }
+
+        // patterns beyond a single wildcard match exactly, and have no compact form
+        let glob = Glob::new("assets/?/*.css");
+        assert!(glob.matches("assets/a/index.css"));
+        assert!(!glob.matches("assets/ab/c/index.css"));
+        #[cfg(feature = "compact-globs")]
+        {
+            use crate::Severity;
+
+            assert!(!glob.is_compact());
+            assert!(Glob::new("**/*.css").is_compact());
+            let issues = Embed::new("/", &Assets).exclude(["a?c/**"]).issues();
+            assert_eq!(issues[0].severity, Severity::Error);
+            assert_eq!(issues[0].option, "exclude");
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_preparation() {
+        use std::cell::Cell;
+        use std::rc::Rc;
+
+        #[derive(Clone, Default)]
+        struct Counting(Rc<Cell<usize>>);
+
+        impl crate::Hasher for Counting {
+            fn hash(&self, file: &rust_embed::EmbeddedFile) -> Vec<u8> {
+                self.0.set(self.0.get() + 1);
+                file.metadata.sha256_hash().to_vec()
+            }
+        }
+
+        let hasher = Counting::default();
+        let srv =
+            test::init_service(App::new().service(Embed::new("/", &Assets).hasher(hasher.clone())))
+                .await;
+        assert_eq!(hasher.0.get(), 0);
+        for _ in 0..2 {
+            let req = TestRequest::get().uri("/index.html").to_request();
+            let resp = test::call_service(&srv, req).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+        }
+        assert_eq!(hasher.0.get(), 1);
+
+        let hasher = Counting::default();
+        let srv = test::init_service(
+            App::new().service(
+                Embed::new("/", &Assets)
+                    .folder("assets")
+                    .hasher(hasher.clone())
+                    .preparation(crate::Preparation::Eager),
+            ),
+        )
+        .await;
+        assert_eq!(hasher.0.get(), 1);
+        let req = TestRequest::get().uri("/index.css").to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(hasher.0.get(), 1);
+    }
+
+    #[cfg(all(feature = "compress-gzip", feature = "hash-blake3"))]
+    #[actix_web::test]
+    async fn test_prepare_progress() {
+        use std::sync::{Arc, Mutex};
+
+        use crate::{Blake3, Encoding, Preparation, PrepareProgress, PrepareStage};
+
+        let progress = Arc::new(Mutex::new(Vec::new()));
+        let embed = |preparation| {
+            let progress = progress.clone();
+            Embed::new("/", &Assets)
+                .hasher(Blake3)
+                .precompress(Encoding::Gzip, 9, ["**/*"])
+                .preparation(preparation)
+                .on_prepare_progress(move |report: PrepareProgress| {
+                    progress.lock().unwrap().push(report)
+                })
+        };
+        let etag = |preparation| async move {
+            let srv = test::init_service(App::new().service(embed(preparation))).await;
+            let req = TestRequest::get()
+                .uri("/index.html")
+                .insert_header((header::ACCEPT_ENCODING, "gzip"))
+                .to_request();
+            let resp = test::call_service(&srv, req).await;
+            assert_eq!(
+                resp.headers().get(header::CONTENT_ENCODING).unwrap(),
+                "gzip"
+            );
+            resp.headers().get(header::ETAG).unwrap().clone()
+        };
+
+        // files hashed and compressed on all cores are served as when prepared lazily
+        let lazy = etag(Preparation::Lazy).await;
+        let reports = std::mem::take(&mut *progress.lock().unwrap());
+        assert!(reports
+            .iter()
+            .all(|report| report.stage == PrepareStage::Compression));
+        assert_eq!(etag(Preparation::Eager).await, lazy);
+
+        let reports = progress.lock().unwrap();
+        for stage in [PrepareStage::Compression, PrepareStage::Hashing] {
+            let reports = reports
+                .iter()
+                .filter(|report| report.stage == stage)
+                .collect::<Vec<_>>();
+            let total = reports[0].total;
+            assert!(total > 0);
+            assert_eq!(
+                reports.iter().map(|report| report.done).collect::<Vec<_>>(),
+                (1..=total).collect::<Vec<_>>()
+            );
+        }
+        let hashed = reports
+            .iter()
+            .find(|report| report.stage == PrepareStage::Hashing)
+            .unwrap();
+        assert_eq!(hashed.total, Assets::iter().count());
+    }
+
+    #[actix_web::test]
+    async fn test_file_index() {
+        use crate::file_index::{Entry, FileIndex, KeyMap};
+
+        let index = FileIndex::build::<Assets>(&KeyMap::default());
+        assert!(index.contains("images/hero@2x.png"));
+        assert!(!index.contains("images"));
+        assert_eq!(index.under("/well-known/"), index.under("well-known"));
+        assert_eq!(
+            index.under("well-known"),
+            ["well-known/acme-challenge/token", "well-known/security.txt"]
+        );
+        assert!(index.under("well").is_empty());
+        assert_eq!(
+            index.entries("well-known"),
+            [Entry::Dir("acme-challenge"), Entry::File("security.txt")]
+        );
+        assert!(index.entries("").contains(&Entry::File("index.html")));
+        assert!(index.entries("").contains(&Entry::Dir("images")));
+
+        assert_eq!(
+            index.nearest("de/missing/page", "index.html").unwrap(),
+            "de/index.html"
+        );
+        assert_eq!(index.nearest("de", "index.html").unwrap(), "de/index.html");
+        assert_eq!(
+            index.nearest("fonts/x.woff2", "index.html").unwrap(),
+            "index.html"
+        );
+        assert_eq!(index.nearest("images", "missing.html"), None);
+
+        let keys = KeyMap::new(None, Some("assets"));
+        let index = FileIndex::build::<Assets>(&keys);
+        assert_eq!(index.paths(), ["index.css"]);
+        assert_eq!(
+            keys.get::<Assets>("index.css").unwrap().0,
+            "assets/index.css"
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_strip_embed_prefix() {
+        let srv = test::init_service(
+            App::new().service(Embed::new("/", &Assets).strip_embed_prefix("de/")),
+        )
+        .await;
+
+        // files with the prefix take precedence
+        let req = TestRequest::get().uri("/index.html").to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let expected = Assets::get("de/index.html").unwrap();
+        assert_eq!(test::read_body(resp).await, expected.data.as_ref());
+
+        // and are not served with it
+        let req = TestRequest::get().uri("/de/index.html").to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        // keys without the prefix are served as they are
+        let req = TestRequest::get().uri("/en/index.html").to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let srv = test::init_service(
+            App::new().service(
+                Embed::new("/", &Assets)
+                    .strip_embed_prefix("/well-known/")
+                    .folder("acme-challenge"),
+            ),
+        )
+        .await;
+        let req = TestRequest::get().uri("/token").to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn test_hide_missing_paths() {
+        let srv = test::init_service(App::new().service(Embed::new("/", &Assets))).await;
+        let req = TestRequest::post().uri("/missing.html").to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(resp.headers().get(header::ALLOW).unwrap(), "GET, HEAD");
+
+        let srv = test::init_service(
+            App::new().service(
+                Embed::new("/", &Assets)
+                    .hide_missing_paths(true)
+                    .redirect("old.html", "index.html"),
+            ),
+        )
+        .await;
+        for (uri, status) in [
+            ("/missing.html", StatusCode::NOT_FOUND),
+            ("/index.html", StatusCode::METHOD_NOT_ALLOWED),
+            ("/old.html", StatusCode::METHOD_NOT_ALLOWED),
+        ] {
+            let req = TestRequest::post().uri(uri).to_request();
+            let resp = test::call_service(&srv, req).await;
+            assert_eq!(resp.status(), status, "{}", uri);
+        }
+
+        let req = TestRequest::get().uri("/index.html").to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn test_describe() {
+        use crate::Value;
+
+        let embed = Embed::new("static/", &Assets)
+            .index_file("index.html")
+            .redirect("b.html", "index.html")
+            .redirect("a.html", "index.html")
+            .exclude(["*.map"])
+            .on_serve(|_| {});
+        let description = embed.describe();
+        assert_eq!(description.get("mount_path"), Some(&Value::from("/static")));
+        assert_eq!(description.get("folder"), Some(&Value::Null));
+        assert_eq!(description.get("on_serve"), Some(&Value::Bool(true)));
+        assert_eq!(description.get("rate_limiter"), Some(&Value::Bool(false)));
+        assert_eq!(description.get("missing"), None);
+
+        let json = description.to_string();
+        assert!(json.starts_with("{\"mount_path\":\"/static\",\"fallback_handler\":"));
+        assert!(json.contains("\"exclude\":[\"*.map\"]"));
+        assert!(
+            json.contains("\"redirects\":{\"a.html\":\"index.html\",\"b.html\":\"index.html\"}")
+        );
+        assert_eq!(json, embed.describe().to_string());
+    }
+
+    #[actix_web::test]
+    async fn test_variant_cache() {
+        let metrics = ServeMetrics::new();
+        let srv = test::init_service(
+            App::new().service(
+                Embed::new("/", &Assets)
+                    .variant_cache(2)
+                    .metrics(metrics.clone()),
+            ),
+        )
+        .await;
+        let request = |language: &str| {
+            TestRequest::get()
+                .uri("/index.html")
+                .insert_header((header::ACCEPT_LANGUAGE, language))
+                .to_request()
+        };
+
+        let resp = test::call_service(&srv, request("de")).await;
+        let etag = resp.headers().get(header::ETAG).unwrap().clone();
+        let body = test::read_body(resp).await;
+        let resp = test::call_service(&srv, request("de")).await;
+        assert_eq!(resp.headers().get(header::ETAG), Some(&etag));
+        assert_eq!(test::read_body(resp).await, body);
+        assert_eq!(metrics.snapshot().cached_variants, 1);
+
+        let req = TestRequest::get()
+            .uri("/index.html")
+            .insert_header((header::ACCEPT_LANGUAGE, "de"))
+            .insert_header((header::IF_NONE_MATCH, etag.clone()))
+            .to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(resp.headers().get(header::ETAG), Some(&etag));
+
+        test::call_service(&srv, request("en")).await;
+        test::call_service(&srv, request("fr")).await;
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.cached_variants, 2);
+        assert_eq!(snapshot.uncached_variants, 1);
+    }
+
+    #[cfg(feature = "compress-gzip")]
+    #[actix_web::test]
+    async fn test_cache_sizes() {
+        use crate::{CacheSizes, EmbedAdmin, Encoding, ServeMetrics};
+
+        let metrics = ServeMetrics::new();
+        let admin = EmbedAdmin::new();
+        let srv = test::init_service(
+            App::new().service(
+                Embed::new("/", &Assets)
+                    .precompress(Encoding::Gzip, 6, ["**/*.html"])
+                    .precompress_blocking(true)
+                    .variant_cache(4)
+                    .listing_endpoint("__listing")
+                    .compress_endpoints(Encoding::Gzip, 6)
+                    .with_admin(&admin)
+                    .metrics(metrics.clone()),
+            ),
+        )
+        .await;
+        let get = |uri: &'static str| {
+            let req = TestRequest::get()
+                .uri(uri)
+                .insert_header((header::ACCEPT_ENCODING, "gzip"))
+                .to_request();
+            test::call_and_read_body(&srv, req)
+        };
+
+        let sizes = metrics.cache_sizes();
+        assert!(sizes.compressed_variants > 0);
+        assert_eq!(sizes.variant_cache, 0);
+        assert_eq!(sizes.endpoints, 0);
+
+        let body = get("/index.html").await;
+        let listing = get("/__listing").await;
+        let sizes = metrics.cache_sizes();
+        assert_eq!(sizes.variant_cache, body.len() as u64);
+        // the identity body and the gzip variant
+        assert!(sizes.endpoints > listing.len() as u64);
+
+        // purges apply to the next request
+        admin.bust_derived_caches();
+        get("/missing").await;
+        let purged = metrics.cache_sizes();
+        assert_eq!(purged.variant_cache, 0);
+        assert_eq!(purged.endpoints, 0);
+        assert_eq!(purged.compressed_variants, sizes.compressed_variants);
+
+        drop(srv);
+        assert_eq!(metrics.cache_sizes(), CacheSizes::default());
+    }
+
+    #[actix_web::test]
+    async fn test_match_query() {
+        use crate::QueryRule;
+
+        let srv = test::init_service(App::new().service(Embed::new("/", &Assets).match_query([
+            QueryRule::value("lang", "german", "de"),
+            QueryRule::subtree("lang"),
+        ])))
+        .await;
+        let body = |uri: &str| {
+            let uri = uri.to_owned();
+            let srv = &srv;
+            async move {
+                let resp = test::call_service(srv, TestRequest::get().uri(&uri).to_request()).await;
+                assert_eq!(resp.status(), StatusCode::OK, "{}", uri);
+                test::read_body(resp).await
+            }
+        };
+        let de = Assets::get("de/index.html").unwrap();
+        let en = Assets::get("en/index.html").unwrap();
+        let root = Assets::get("index.html").unwrap();
+
+        assert_eq!(body("/index.html?lang=de").await, de.data.as_ref());
+        assert_eq!(body("/index.html?x=1&lang=en").await, en.data.as_ref());
+        assert_eq!(body("/index.html?lang=german").await, de.data.as_ref());
+        // files missing in the selected folder are looked up as usual
+        assert_eq!(body("/index.html?lang=fr").await, root.data.as_ref());
+        assert_eq!(body("/index.html?lang=..").await, root.data.as_ref());
+        assert_eq!(body("/index.html").await, root.data.as_ref());
+
+        // query strings are ignored by default
+        let srv = test::init_service(App::new().service(Embed::new("/", &Assets))).await;
+        let req = TestRequest::get().uri("/index.html?lang=de").to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(test::read_body(resp).await, root.data.as_ref());
+    }
+
+    #[actix_web::test]
+    async fn test_rate_limiter_failure() {
+        use crate::{FailurePolicy, HookFailure};
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        let failures = Rc::new(RefCell::new(Vec::new()));
+        let embed = |policy| {
+            let failures = failures.clone();
+            Embed::new("/", &Assets)
+                .rate_limiter(|ip: &str| match ip {
+                    "10.0.0.1" => RateLimitDecision::Unavailable,
+                    _ => panic!("limiter broken"),
+                })
+                .rate_limiter_failure(policy)
+                .on_serve(move |record| failures.borrow_mut().push(record.hook_failure))
+        };
+        let request = |ip: &str| {
+            TestRequest::get()
+                .uri("/index.html")
+                .peer_addr(format!("{}:1234", ip).parse().unwrap())
+                .to_request()
+        };
+
+        let srv = test::init_service(App::new().service(embed(FailurePolicy::default()))).await;
+        let resp = test::call_service(&srv, request("10.0.0.1")).await;
+        assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        drop(resp);
+
+        let closed = FailurePolicy::Closed(StatusCode::SERVICE_UNAVAILABLE);
+        let srv = test::init_service(App::new().service(embed(closed))).await;
+        let resp = test::call_service(&srv, request("10.0.0.2")).await;
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+        drop(resp);
+
+        let srv = test::init_service(App::new().service(embed(FailurePolicy::Open))).await;
+        let resp = test::call_service(&srv, request("10.0.0.1")).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        drop(resp);
+
+        assert_eq!(
+            *failures.borrow(),
+            [
+                Some(HookFailure {
+                    hook: "rate_limiter",
+                    message: None,
+                    policy: FailurePolicy::default(),
+                }),
+                Some(HookFailure {
+                    hook: "rate_limiter",
+                    message: Some("limiter broken".to_owned()),
+                    policy: closed,
+                }),
+                Some(HookFailure {
+                    hook: "rate_limiter",
+                    message: None,
+                    policy: FailurePolicy::Open,
+                }),
+            ]
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_shared_state() {
+        use crate::SharedState;
+
+        let state = SharedState::new();
+        assert!(!state.is_built());
+        let embed = || Embed::new("/", &Assets).suggestions(1).shared_state(&state);
+        let first = test::init_service(App::new().service(embed())).await;
+        assert!(state.is_built());
+        let second = test::init_service(App::new().service(embed())).await;
+
+        for srv in [&first, &second] {
+            let req = TestRequest::get().uri("/index.html").to_request();
+            let resp = test::call_service(srv, req).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+        }
+
+        // services of other threads share the state too
+        let handle = std::thread::spawn({
+            let state = state.clone();
+            move || {
+                actix_web::rt::System::new().block_on(async move {
+                    let srv = test::init_service(
+                        App::new().service(Embed::new("/", &Assets).shared_state(&state)),
+                    )
+                    .await;
+                    let req = TestRequest::get().uri("/index.html").to_request();
+                    test::call_service(&srv, req).await.status()
+                })
+            }
+        });
+        assert_eq!(handle.join().unwrap(), StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn test_maintenance() {
+        use crate::EmbedHandle;
+
+        let handle = EmbedHandle::new();
+        let srv = test::init_service(
+            App::new().service(
+                Embed::new("/", &Assets)
+                    .variant_cache(4)
+                    .version_endpoint("__version", VersionInfo::new())
+                    .listing_endpoint("__listing")
+                    .revalidation_endpoint("__revalidate")
+                    .maintenance(&handle, "/en/index.html", Duration::from_secs(120))
+                    .maintenance_allow(["assets/**", "__listing"]),
+            ),
+        )
+        .await;
+        let status = |uri: &'static str| {
+            let srv = &srv;
+            async move {
+                let req = TestRequest::get().uri(uri).to_request();
+                test::call_service(srv, req).await.status()
+            }
+        };
+
+        assert_eq!(status("/index.html").await, StatusCode::OK);
+
+        handle.set_maintenance(true);
+        let req = TestRequest::get().uri("/index.html").to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(resp.headers().get(header::RETRY_AFTER).unwrap(), "120");
+        assert_eq!(
+            resp.headers().get(header::CACHE_CONTROL).unwrap(),
+            "no-cache"
+        );
+        assert_eq!(
+            resp.headers().get(header::CONTENT_TYPE).unwrap(),
+            "text/html"
+        );
+        let body = test::read_body(resp).await;
+        assert_eq!(body, Assets::get("en/index.html").unwrap().data.as_ref());
+
+        assert_eq!(status("/missing").await, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(status("/assets/index.css").await, StatusCode::OK);
+
+        // endpoints are unavailable too, unless allowed
+        assert_eq!(status("/__version").await, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(status("/__listing").await, StatusCode::OK);
+        let req = TestRequest::post()
+            .uri("/__revalidate")
+            .set_payload("[]")
+            .to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        handle.set_maintenance(false);
+        assert_eq!(status("/index.html").await, StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn test_require_signed_cookie() {
+        let srv = test::init_service(
+            App::new()
+                .service(
+                    Embed::new("/dashboard", &Assets)
+                        .require_signed_cookie("session", |value: &str| value == "valid")
+                        .login_redirect("/login?return_to={return_to}"),
+                )
+                .service(
+                    Embed::new("/internal", &Assets)
+                        .require_signed_cookie("session", |_: &str| -> bool { panic!("down") }),
+                ),
+        )
+        .await;
+        let call = |uri: &'static str, cookie: Option<&'static str>| {
+            let srv = &srv;
+            async move {
+                let mut req = TestRequest::get().uri(uri);
+                if let Some(cookie) = cookie {
+                    req = req.insert_header((header::COOKIE, cookie));
+                }
+                test::call_service(srv, req.to_request()).await
+            }
+        };
+
+        let resp = call("/dashboard/index.html?tab=1", None).await;
+        assert_eq!(resp.status(), StatusCode::SEE_OTHER);
+        assert_eq!(
+            resp.headers().get(header::LOCATION).unwrap(),
+            "/login?return_to=%2Fdashboard%2Findex.html%3Ftab%3D1"
+        );
+        assert_eq!(
+            resp.headers().get(header::CACHE_CONTROL).unwrap(),
+            "no-store"
+        );
+        let resp = call("/dashboard/index.html", Some("session=forged")).await;
+        assert_eq!(resp.status(), StatusCode::SEE_OTHER);
+
+        let resp = call("/dashboard/index.html", Some("theme=dark; session=valid")).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(resp
+            .headers()
+            .get_all(header::VARY)
+            .any(|value| value == "Cookie"));
+        let cache_control = resp.headers().get(header::CACHE_CONTROL).unwrap();
+        assert!(cache_control.to_str().unwrap().starts_with("private"));
+
+        // without a login URL, and with a verifier that panics
+        let resp = call("/internal/index.html", None).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        let resp = call("/internal/index.html", Some("session=valid")).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[cfg(feature = "secure-cookies")]
+    #[actix_web::test]
+    async fn test_require_signed_cookie_with_key() {
+        use actix_web::cookie::{Cookie, CookieJar, Key};
+
+        use crate::{PrivateCookie, SignedCookie};
+
+        let key = Key::generate();
+        let srv = test::init_service(
+            App::new()
+                .service(
+                    Embed::new("/signed", &Assets)
+                        .require_signed_cookie("session", SignedCookie(key.clone())),
+                )
+                .service(
+                    Embed::new("/private", &Assets)
+                        .require_signed_cookie("session", PrivateCookie(key.clone())),
+                ),
+        )
+        .await;
+        let mut jar = CookieJar::new();
+        jar.signed_mut(&key).add(Cookie::new("session", "alice"));
+        let signed = jar.get("session").unwrap().value().to_owned();
+        let mut jar = CookieJar::new();
+        jar.private_mut(&key).add(Cookie::new("session", "alice"));
+        let private = jar.get("session").unwrap().value().to_owned();
+
+        for (uri, value, expected) in [
+            ("/signed/index.html", &signed, StatusCode::OK),
+            ("/signed/index.html", &private, StatusCode::UNAUTHORIZED),
+            ("/private/index.html", &private, StatusCode::OK),
+            ("/private/index.html", &signed, StatusCode::UNAUTHORIZED),
+        ] {
+            let req = TestRequest::get()
+                .uri(uri)
+                .insert_header((header::COOKIE, format!("session={}", value)))
+                .to_request();
+            let resp = test::call_service(&srv, req).await;
+            assert_eq!(resp.status(), expected, "{}", uri);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_served_asset() {
+        use crate::ServedAsset;
+
+        let srv = test::init_service(App::new().service(Embed::new("/", &Assets))).await;
+
+        let req = TestRequest::get().uri("/assets/index.css").to_request();
+        let resp = test::call_service(&srv, req).await;
+        let asset = resp
+            .request()
+            .extensions()
+            .get::<ServedAsset>()
+            .cloned()
+            .unwrap();
+        assert_eq!(asset.path, "assets/index.css");
+        assert_eq!(
+            asset.size,
+            Assets::get("assets/index.css").unwrap().data.len() as u64
+        );
+        assert_eq!(asset.mime, "text/css");
+        assert_eq!(
+            resp.headers().get(header::ETAG).unwrap().to_str().unwrap(),
+            asset.etag.to_string()
+        );
+        assert_eq!(
+            resp.response().extensions().get::<ServedAsset>(),
+            Some(&asset)
+        );
+
+        let req = TestRequest::get()
+            .uri("/assets/index.css")
+            .insert_header((header::IF_NONE_MATCH, asset.etag.to_string()))
+            .to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(
+            resp.request().extensions().get::<ServedAsset>(),
+            Some(&asset)
+        );
+
+        let req = TestRequest::get().uri("/missing").to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert!(resp.request().extensions().get::<ServedAsset>().is_none());
+    }
+
+    // the trace is never sent by release builds
+    #[cfg(debug_assertions)]
+    #[actix_web::test]
+    async fn test_resolution_trace() {
+        let srv = test::init_service(
+            App::new().service(
+                Embed::new("/", &Assets)
+                    .index_file("index.html")
+                    .redirect("old.html", "index.html")
+                    .resolution_trace(true),
+            ),
+        )
+        .await;
+        let trace = |uri: &'static str| {
+            let srv = &srv;
+            async move {
+                let req = TestRequest::get().uri(uri).to_request();
+                let resp = test::call_service(srv, req).await;
+                resp.headers()
+                    .get("x-embed-resolution")
+                    .map(|value| value.to_str().unwrap().to_owned())
+            }
+        };
+
+        assert_eq!(
+            trace("/").await.as_deref(),
+            Some(r#"lookup ""; index file "index.html"; found "index.html"; encoding identity"#)
+        );
+        assert_eq!(
+            trace("/old.html").await.as_deref(),
+            Some(r#"lookup "old.html"; no file found; redirect to "index.html""#)
+        );
+        assert_eq!(
+            trace("/missing").await.as_deref(),
+            Some(r#"lookup "missing"; no file found; fallback handler"#)
+        );
+
+        let srv = test::init_service(App::new().service(Embed::new("/", &Assets))).await;
+        let req = TestRequest::get().uri("/index.html").to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert!(resp.headers().get("x-embed-resolution").is_none());
+    }
+
+    #[actix_web::test]
+    async fn test_content_addressed() {
+        let embed = Embed::new("/static", &Assets).content_addressed(true);
+        let addresses = embed.content_addresses();
+        let url = addresses.get("/assets/index.css").unwrap().to_owned();
+        let hash = hex::encode(
+            Assets::get("assets/index.css")
+                .unwrap()
+                .metadata
+                .sha256_hash(),
+        );
+        assert_eq!(url, format!("/static/_by-hash/{}/assets/index.css", hash));
+        assert_eq!(addresses.iter().count(), Assets::iter().count());
+
+        let srv = test::init_service(App::new().service(embed)).await;
+        let req = TestRequest::get().uri(&url).to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get(header::CACHE_CONTROL).unwrap(),
+            "public, max-age=31536000, immutable"
+        );
+        assert_eq!(
+            resp.headers().get(header::CONTENT_TYPE).unwrap(),
+            "text/css"
+        );
+
+        let req = TestRequest::get()
+            .uri("/static/assets/index.css")
+            .to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(resp.headers().get(header::CACHE_CONTROL).is_none());
+
+        let outdated = format!("/static/_by-hash/{}/assets/index.css", "0".repeat(64));
+        let req = TestRequest::get().uri(&outdated).to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn test_revalidation_endpoint() {
+        let srv = test::init_service(
+            App::new()
+                .service(Embed::new("/static", &Assets).revalidation_endpoint("/__revalidate/")),
+        )
+        .await;
+
+        let req = TestRequest::get().uri("/static/index.html").to_request();
+        let etag = test::call_service(&srv, req)
+            .await
+            .headers()
+            .get(header::ETAG)
+            .unwrap()
+            .clone();
+        let etag = etag.to_str().unwrap();
+        let body = format!(
+            r#"[{{"path":"index.html","etag":{:?}}},{{"path":"/assets/index.css","etag":"\"outdated\""}},{{"path":"gone.js","etag":"\"x\""}}]"#,
+            etag
+        );
+        let req = TestRequest::post()
+            .uri("/static/__revalidate")
+            .set_payload(body)
+            .to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let css = test::call_service(
+            &srv,
+            TestRequest::get()
+                .uri("/static/assets/index.css")
+                .to_request(),
+        )
+        .await;
+        let expected = format!(
+            r#"{{"stale":[{{"path":"/assets/index.css","etag":{:?}}},{{"path":"gone.js","etag":null}}]}}"#,
+            css.headers().get(header::ETAG).unwrap().to_str().unwrap()
+        );
+        assert_eq!(test::read_body(resp).await, expected);
+
+        let req = TestRequest::post()
+            .uri("/static/__revalidate")
+            .set_payload("{")
+            .to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        let req = TestRequest::get().uri("/static/__revalidate").to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(resp.headers().get(header::ALLOW).unwrap(), "POST");
+
+        // files outside their availability window are not served anymore
+        let launch = std::time::SystemTime::now() + Duration::from_secs(3600);
+        let srv = test::init_service(
+            App::new().service(
+                Embed::new("/", &Assets)
+                    .available("index.html", launch..)
+                    .revalidation_endpoint("__revalidate"),
+            ),
+        )
+        .await;
+        let req = TestRequest::post()
+            .uri("/__revalidate")
+            .set_payload(format!(r#"[{{"path":"index.html","etag":{:?}}}]"#, etag))
+            .to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(
+            test::read_body(resp).await,
+            r#"{"stale":[{"path":"index.html","etag":null}]}"#
+        );
+
+        // excluded files are not served, so their entity tags are not told
+        let srv = test::init_service(
+            App::new().service(
+                Embed::new("/", &Assets)
+                    .exclude(["index.html"])
+                    .revalidation_endpoint("__revalidate"),
+            ),
+        )
+        .await;
+        let req = TestRequest::post()
+            .uri("/__revalidate")
+            .set_payload(r#"[{"path":"index.html","etag":"\"0\""}]"#)
+            .to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(
+            test::read_body(resp).await,
+            r#"{"stale":[{"path":"index.html","etag":null}]}"#
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_packages() {
+        let srv = test::init_service(
+            App::new().service(
+                Embed::new("/static", &Assets)
+                    .packages("/vendor/")
+                    .package_export("vendor/signals", "vendor/@preact/signals/dist/signals.mjs"),
+            ),
+        )
+        .await;
+
+        let cases = [
+            (
+                "/static/vendor/lodash",
+                Some("/static/vendor/lodash/lodash.js"),
+            ),
+            (
+                "/static/vendor/@preact/signals",
+                Some("/static/vendor/@preact/signals/dist/signals.mjs"),
+            ),
+            (
+                "/static/vendor/@preact/signals/utils",
+                Some("/static/vendor/@preact/signals/dist/utils.mjs"),
+            ),
+            (
+                "/static/vendor/signals",
+                Some("/static/vendor/@preact/signals/dist/signals.mjs"),
+            ),
+            ("/static/vendor/@preact/signals/dist/signals.cjs", None),
+        ];
+        for (uri, location) in cases {
+            let req = TestRequest::get().uri(uri).to_request();
+            let resp = test::call_service(&srv, req).await;
+            match location {
+                Some(location) => {
+                    assert_eq!(resp.status(), StatusCode::FOUND, "{}", uri);
+                    assert_eq!(resp.headers().get(header::LOCATION).unwrap(), location);
+                }
+                None => assert_eq!(resp.status(), StatusCode::NOT_FOUND, "{}", uri),
+            }
+        }
+
+        let req = TestRequest::get()
+            .uri("/static/vendor/lodash/lodash.js")
+            .to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn test_import_map() {
+        let embed = || {
+            Embed::new("/static", &Assets)
+                .packages("vendor")
+                .import_map("/importmap.json", ["vendor/lodash/*.js"])
+        };
+        let srv = test::init_service(App::new().service(embed())).await;
+        let req = TestRequest::get()
+            .uri("/static/importmap.json")
+            .to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get(header::CONTENT_TYPE).unwrap(),
+            "application/importmap+json"
+        );
+        let etag = resp.headers().get(header::ETAG).unwrap().clone();
+        assert_eq!(
+            test::read_body(resp).await,
+            concat!(
+                r#"{"imports":{"/static/vendor/lodash/lodash.js":"/static/vendor/lodash/lodash.js","#,
+                r#""@preact/signals":"/static/vendor/@preact/signals/dist/signals.mjs","#,
+                r#""@preact/signals/utils":"/static/vendor/@preact/signals/dist/utils.mjs","#,
+                r#""lodash":"/static/vendor/lodash/lodash.js"}}"#
+            )
+        );
+        let req = TestRequest::get()
+            .uri("/static/importmap.json")
+            .insert_header((header::IF_NONE_MATCH, etag))
+            .to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+
+        // modules are mapped to their content-addressed URLs
+        let embed = embed().content_addressed(true);
+        let addresses = embed.content_addresses();
+        let srv = test::init_service(App::new().service(embed)).await;
+        let req = TestRequest::get()
+            .uri("/static/importmap.json")
+            .to_request();
+        let body = test::call_and_read_body(&srv, req).await;
+        let body = std::str::from_utf8(&body).unwrap();
+        let lodash = addresses.get("vendor/lodash/lodash.js").unwrap();
+        assert!(body.contains(&format!(r#""lodash":"{}""#, lodash)));
+        assert!(body.contains(&format!(
+            r#""/static/vendor/lodash/lodash.js":"{}""#,
+            lodash
+        )));
+    }
+
+    #[actix_web::test]
+    async fn test_import_map_availability() {
+        let launch = std::time::SystemTime::now() + Duration::from_secs(3600);
+        let srv = test::init_service(
+            App::new().service(
+                Embed::new("/static", &Assets)
+                    .packages("vendor")
+                    .import_map("/importmap.json", ["vendor/lodash/*.js"])
+                    .available("vendor/lodash/**", launch..)
+                    .exclude(["vendor/@preact/signals/dist/utils.mjs"]),
+            ),
+        )
+        .await;
+        let req = TestRequest::get()
+            .uri("/static/importmap.json")
+            .to_request();
+        assert_eq!(
+            test::call_and_read_body(&srv, req).await,
+            r#"{"imports":{"@preact/signals":"/static/vendor/@preact/signals/dist/signals.mjs"}}"#
+        );
+    }
+
+    #[cfg(feature = "compress-gzip")]
+    #[actix_web::test]
+    async fn test_skip_compression() {
+        use crate::{Compression, Encoding};
+
+        let records = Rc::new(RefCell::new(Vec::new()));
+        let srv = test::init_service(
+            App::new().service(
+                Embed::new("/", &Assets)
+                    .precompress(Encoding::Gzip, 9, ["**/*"])
+                    .skip_compression([".ICO"])
+                    .on_serve({
+                        let records = records.clone();
+                        move |record| records.borrow_mut().push(record.compression)
+                    }),
+            ),
+        )
+        .await;
+
+        let cases = [
+            ("/index.html", "gzip"),
+            ("/index.html", "identity"),
+            ("/favicon.ico", "gzip"),
+            ("/fonts/icons.woff2", "gzip"),
+            ("/well-known/acme-challenge/token", "gzip"),
+            ("/missing", "gzip"),
+        ];
+        for (uri, accept) in cases {
+            let req = TestRequest::get()
+                .uri(uri)
+                .insert_header((header::ACCEPT_ENCODING, accept))
+                .to_request();
+            test::call_service(&srv, req).await;
+        }
+        assert_eq!(
+            *records.borrow(),
+            [
+                Some(Compression::Compressed(Encoding::Gzip)),
+                Some(Compression::NotAccepted),
+                Some(Compression::Incompressible),
+                Some(Compression::Incompressible),
+                Some(Compression::Unavailable),
+                None,
+            ]
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_outer_compression() {
+        use actix_web::middleware::Compress;
+
+        use crate::OuterCompression;
+
+        let original = Assets::get("index.html").unwrap().data;
+        let request = || {
+            TestRequest::get()
+                .uri("/index.html")
+                .insert_header((header::ACCEPT_ENCODING, "gzip"))
+        };
+
+        let srv = test::init_service(
+            App::new()
+                .wrap(Compress::default())
+                .service(Embed::new("/", &Assets).outer_compression(OuterCompression::Bypass)),
+        )
+        .await;
+        let resp = test::call_service(&srv, request().to_request()).await;
+        assert_eq!(
+            resp.headers().get(header::CONTENT_ENCODING).unwrap(),
+            "identity"
+        );
+        assert!(!resp
+            .headers()
+            .get(header::ETAG)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .starts_with("W/"));
+        assert_eq!(test::read_body(resp).await, original.as_ref());
+
+        let srv = test::init_service(
+            App::new()
+                .wrap(Compress::default())
+                .service(Embed::new("/", &Assets).outer_compression(OuterCompression::Allow)),
+        )
+        .await;
+        let resp = test::call_service(&srv, request().to_request()).await;
+        assert_eq!(
+            resp.headers().get(header::CONTENT_ENCODING).unwrap(),
+            "gzip"
+        );
+        assert!(resp.headers().get_all(header::VARY).any(|vary| vary
+            .to_str()
+            .unwrap()
+            .eq_ignore_ascii_case("accept-encoding")));
+        let etag = resp.headers().get(header::ETAG).unwrap().clone();
+        assert!(etag.to_str().unwrap().starts_with("W/"));
+        assert_ne!(test::read_body(resp).await, original.as_ref());
+
+        let req = request()
+            .insert_header((header::IF_NONE_MATCH, etag))
+            .to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+    }
+
+    #[actix_web::test]
+    async fn test_language_override() {
+        let srv = test::init_service(
+            App::new().service(
+                Embed::new("/site", &Assets)
+                    .localized_index(["en/index.html", "de/index.html"], "en/index.html")
+                    .language_fallback("lb", ["de"])
+                    .language_fallback("de-CH", ["fr", "en"])
+                    .language_override("lang", "site-lang")
+                    .variant_cache(8),
+            ),
+        )
+        .await;
+        let language = |uri: &'static str, accept: &'static str, cookie: Option<&'static str>| {
+            let srv = &srv;
+            async move {
+                let mut req = TestRequest::get()
+                    .uri(uri)
+                    .insert_header((header::ACCEPT_LANGUAGE, accept));
+                if let Some(cookie) = cookie {
+                    req = req.insert_header((header::COOKIE, cookie));
+                }
+                let resp = test::call_service(srv, req.to_request()).await;
+                let set_cookie = resp
+                    .headers()
+                    .get(header::SET_COOKIE)
+                    .map(|value| value.to_str().unwrap().to_owned());
+                let language = resp
+                    .headers()
+                    .get(header::CONTENT_LANGUAGE)
+                    .unwrap()
+                    .clone();
+                (language.to_str().unwrap().to_owned(), set_cookie)
+            }
+        };
+
+        // fallback chains
+        assert_eq!(
+            language("/site/", "lb", None).await,
+            ("de".to_owned(), None)
+        );
+        assert_eq!(
+            language("/site/", "de-CH", None).await,
+            ("en".to_owned(), None)
+        );
+        assert_eq!(
+            language("/site/", "de-AT", None).await,
+            ("de".to_owned(), None)
+        );
+
+        // the query parameter beats the cookie, which beats Accept-Language
+        assert_eq!(
+            language("/site/?lang=de", "en", None).await,
+            (
+                "de".to_owned(),
+                Some("site-lang=de; Path=/site/; Max-Age=31536000; SameSite=Lax".to_owned())
+            )
+        );
+        assert_eq!(
+            language("/site/?lang=de", "en", Some("site-lang=de")).await,
+            ("de".to_owned(), None)
+        );
+        assert_eq!(
+            language("/site/", "en", Some("other=1; site-lang=de")).await,
+            ("de".to_owned(), None)
+        );
+        assert_eq!(
+            language("/site/?lang=en", "de", Some("site-lang=de"))
+                .await
+                .0,
+            "en"
+        );
+        // unavailable choices are ignored
+        assert_eq!(
+            language("/site/?lang=fr", "de", Some("site-lang=xx")).await,
+            ("de".to_owned(), None)
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_registry() {
+        let registry = EmbedRegistry::new()
+            .register("/plugins/fonts/", &Fonts)
+            .register("", &WellKnown);
+        assert_eq!(
+            registry.prefixes().collect::<Vec<_>>(),
+            ["plugins/fonts", ""]
+        );
+        let embed = Embed::new("/static", &Assets)
+            .folder("assets")
+            .registry(registry);
+        // registered files are indexed
+        let addresses = embed.content_addresses();
+        assert!(addresses.get("plugins/fonts/icons.woff").is_some());
+        assert!(addresses.get("security.txt").is_some());
+        let srv = test::init_service(App::new().service(embed)).await;
+        let get = |uri: &'static str| {
+            let srv = &srv;
+            async move {
+                let req = TestRequest::get().uri(uri).to_request();
+                let resp = test::call_service(srv, req).await;
+                let status = resp.status();
+                (status, test::read_body(resp).await)
+            }
+        };
+
+        let (status, body) = get("/static/index.css").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, Assets::get("assets/index.css").unwrap().data.as_ref());
+        let (status, body) = get("/static/plugins/fonts/icons.woff").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, Fonts::get("icons.woff").unwrap().data.as_ref());
+        let (status, body) = get("/static/security.txt").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, WellKnown::get("security.txt").unwrap().data.as_ref());
+        assert_eq!(get("/static/icons.woff").await.0, StatusCode::NOT_FOUND);
+        assert_eq!(
+            get("/static/plugins/fonts/security.txt").await.0,
+            StatusCode::NOT_FOUND
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_registry_collisions() {
+        let registry = || {
+            EmbedRegistry::new()
+                .register("", &Fonts)
+                .register("fonts", &Fonts)
+        };
+        let embed = |policy| Embed::new("/", &Assets).registry(registry().collision_policy(policy));
+        let get = |embed: Embed<Assets, DefaultFallbackHandler>| async move {
+            let srv = test::init_service(App::new().service(embed)).await;
+            let req = TestRequest::get().uri("/fonts/icons.woff").to_request();
+            test::call_service(&srv, req).await.status()
+        };
+
+        let collisions = embed(CollisionPolicy::FirstWins).collisions();
+        let paths = collisions
+            .iter()
+            .map(|c| c.path.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(paths, ["fonts/icons.woff", "fonts/icons.woff2"]);
+        assert!(collisions[0].served.ends_with("Assets"));
+        assert!(collisions[0].shadowed[0].ends_with("Fonts"));
+        assert_eq!(get(embed(CollisionPolicy::FirstWins)).await, StatusCode::OK);
+
+        let collisions = embed(CollisionPolicy::LastWins).collisions();
+        assert!(collisions[0].served.ends_with("Fonts"));
+        assert!(collisions[0].shadowed[0].ends_with("Assets"));
+        assert_eq!(get(embed(CollisionPolicy::LastWins)).await, StatusCode::OK);
+
+        // files of different sources at different paths do not collide
+        let separate = Embed::new("/", &Assets)
+            .registry(registry().collision_policy(CollisionPolicy::Error))
+            .folder("assets");
+        assert!(separate.collisions().is_empty());
+        assert_eq!(get(separate).await, StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    #[should_panic(expected = "colliding embedded files: `fonts/icons.woff` of")]
+    async fn test_registry_collision_error() {
+        let registry = EmbedRegistry::new()
+            .register("fonts", &Fonts)
+            .collision_policy(CollisionPolicy::Error);
+        test::init_service(App::new().service(Embed::new("/", &Assets).registry(registry))).await;
+    }
+
+    #[actix_web::test]
+    async fn test_namespace() {
+        use crate::Value;
+
+        // `Assets` also has `fonts/icons.woff`, which the namespace hides
+        let embed = Embed::new("/static", &Assets)
+            .namespace("/fonts/", &WellKnown)
+            .namespace("plugins", &Fonts)
+            .registry(EmbedRegistry::new().register("plugins", &WellKnown));
+        let namespaces = Value::List(vec!["fonts".into(), "plugins".into()]);
+        assert_eq!(embed.describe().get("namespaces"), Some(&namespaces));
+        assert!(embed.collisions().is_empty());
+        let addresses = embed.content_addresses();
+        assert!(addresses.get("fonts/security.txt").is_some());
+        assert!(addresses.get("fonts/icons.woff").is_none());
+        assert!(addresses.get("plugins/security.txt").is_none());
+        let srv = test::init_service(App::new().service(embed)).await;
+        let get = |uri: &'static str| {
+            let srv = &srv;
+            async move {
+                let req = TestRequest::get().uri(uri).to_request();
+                let resp = test::call_service(srv, req).await;
+                let status = resp.status();
+                (status, test::read_body(resp).await)
+            }
+        };
+
+        let (status, body) = get("/static/fonts/security.txt").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, WellKnown::get("security.txt").unwrap().data.as_ref());
+        let (status, body) = get("/static/plugins/icons.woff").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, Fonts::get("icons.woff").unwrap().data.as_ref());
+        assert_eq!(
+            get("/static/fonts/icons.woff").await.0,
+            StatusCode::NOT_FOUND
+        );
+        assert_eq!(
+            get("/static/plugins/security.txt").await.0,
+            StatusCode::NOT_FOUND
+        );
+        assert_eq!(get("/static/index.html").await.0, StatusCode::OK);
+
+        let issues = Embed::new("/", &Assets)
+            .namespace("", &Fonts)
+            .namespace("fonts", &Fonts)
+            .namespace("fonts/", &WellKnown)
+            .issues();
+        assert_eq!(
+            issues
+                .iter()
+                .filter(|issue| issue.option == "namespace")
+                .count(),
+            2
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_allowed_mime_types() {
+        let get = |embed: Embed<Assets, DefaultFallbackHandler>, uri: &'static str| async move {
+            let srv = test::init_service(App::new().service(embed)).await;
+            let req = TestRequest::get().uri(uri).to_request();
+            test::call_service(&srv, req).await.status()
+        };
+        let embed = |refused| {
+            Embed::new("/", &Assets)
+                .index_file("index.html")
+                .allowed_mime_types(["TEXT/HTML", "text/css", "font/*"], refused)
+                .fonts_preset()
+        };
+
+        let addresses = embed(StatusCode::FORBIDDEN).content_addresses();
+        assert!(addresses.get("index.html").is_some());
+        assert!(addresses.get("favicon.ico").is_none());
+        assert_eq!(get(embed(StatusCode::FORBIDDEN), "/").await, StatusCode::OK);
+        assert_eq!(
+            get(embed(StatusCode::FORBIDDEN), "/assets/index.css").await,
+            StatusCode::OK
+        );
+        // the media types set after the allowlist apply
+        assert_eq!(
+            get(embed(StatusCode::FORBIDDEN), "/fonts/icons.woff2").await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            get(embed(StatusCode::FORBIDDEN), "/favicon.ico").await,
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            get(embed(StatusCode::NOT_FOUND), "/favicon.ico").await,
+            StatusCode::NOT_FOUND
+        );
+        assert_eq!(
+            get(embed(StatusCode::FORBIDDEN), "/missing.ico").await,
+            StatusCode::NOT_FOUND
+        );
+
+        let issues = Embed::new("/", &Assets)
+            .allowed_mime_types(["text"], StatusCode::OK)
+            .issues();
+        assert_eq!(
+            issues
+                .iter()
+                .filter(|issue| issue.option == "allowed_mime_types")
+                .count(),
+            2
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_admin() {
+        let admin = EmbedAdmin::new();
+        let metrics = ServeMetrics::new();
+        let embed = Embed::new("/", &Assets)
+            .suggestions(2)
+            .variant_cache(4)
+            .metrics(metrics.clone())
+            .fallback_handler(|req: &HttpRequest| {
+                let suggestions = req.extensions().get::<Suggestions>().cloned();
+                let urls = suggestions.map(|suggestions| suggestions.urls.join(","));
+                HttpResponse::NotFound().body(urls.unwrap_or_default())
+            })
+            .with_admin(&admin);
+        assert_eq!(format!("{:?}", embed.admin()), format!("{:?}", admin));
+        let srv = test::init_service(App::new().service(embed)).await;
+        let get = |uri: &'static str| {
+            let srv = &srv;
+            async move {
+                let req = TestRequest::get().uri(uri).to_request();
+                test::read_body(test::call_service(srv, req).await).await
+            }
+        };
+
+        assert_eq!(get("/indx.html").await, Bytes::from("/index.html"));
+        admin.set_suggestions(false);
+        assert_eq!(get("/indx.html").await, Bytes::new());
+        admin.set_suggestions(true);
+        assert_eq!(get("/indx.html").await, Bytes::from("/index.html"));
+
+        get("/index.html").await;
+        get("/index.html").await;
+        assert_eq!(metrics.snapshot().cached_variants, 1);
+        admin.bust_derived_caches();
+        get("/index.html").await;
+        get("/index.html").await;
+        assert_eq!(metrics.snapshot().cached_variants, 2);
+
+        #[cfg(debug_assertions)]
+        {
+            let trace = || async {
+                let req = TestRequest::get().uri("/index.html").to_request();
+                let resp = test::call_service(&srv, req).await;
+                resp.headers().contains_key("x-embed-resolution")
+            };
+            assert!(!trace().await);
+            admin.set_resolution_trace(true);
+            assert!(trace().await);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_purge() {
+        let metrics = ServeMetrics::new();
+        let embed = Embed::new("/", &Assets)
+            .variant_cache(4)
+            .metrics(metrics.clone());
+        let admin = embed.admin();
+        let srv = test::init_service(App::new().service(embed)).await;
+        let get = |uri: &'static str| {
+            let srv = &srv;
+            async move {
+                let req = TestRequest::get().uri(uri).to_request();
+                test::call_service(srv, req).await.status()
+            }
+        };
+
+        for _ in 0..2 {
+            assert_eq!(get("/index.html").await, StatusCode::OK);
+            assert_eq!(get("/assets/index.css").await, StatusCode::OK);
+        }
+        assert_eq!(metrics.snapshot().cached_variants, 2);
+
+        admin.purge("/**/*.css");
+        assert_eq!(get("/index.html").await, StatusCode::OK);
+        assert_eq!(get("/assets/index.css").await, StatusCode::OK);
+        assert_eq!(metrics.snapshot().cached_variants, 3);
+
+        admin.purge("*.txt");
+        admin.bust_derived_caches();
+        assert_eq!(get("/index.html").await, StatusCode::OK);
+        assert_eq!(get("/assets/index.css").await, StatusCode::OK);
+        assert_eq!(metrics.snapshot().cached_variants, 5);
+    }
+
+    #[actix_web::test]
+    async fn test_http_util() {
+        use std::time::{Duration, SystemTime};
+
+        use crate::{http_util, Encoding};
+
+        let srv = test::init_service(App::new().service(Embed::new("/", &Assets))).await;
+        let hash = hex::encode(Assets::get("index.html").unwrap().metadata.sha256_hash());
+        let req = TestRequest::get().uri("/index.html").to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(
+            resp.headers().get(header::ETAG).unwrap().to_str().unwrap(),
+            http_util::entity_tag(&hash, None).to_string()
+        );
+        assert_eq!(
+            http_util::quote_etag(&hash, false),
+            http_util::entity_tag(&hash, None).to_string()
+        );
+        assert_eq!(
+            http_util::entity_tag(&hash, Some(Encoding::Gzip)).to_string(),
+            format!("\"{}-gzip\"", hash)
+        );
+        assert_eq!(http_util::quote_etag("abc", true), "W/\"abc\"");
+        for encoding in [None, Some(Encoding::Brotli), Some(Encoding::Gzip)] {
+            let etag = http_util::entity_tag(&hash, encoding);
+            assert_eq!(
+                http_util::split_entity_tag(&etag),
+                (hash.as_str(), encoding)
+            );
+        }
+        let weak = header::EntityTag::new_weak(format!("{}-br", hash));
+        assert_eq!(
+            http_util::split_entity_tag(&weak),
+            (hash.as_str(), Some(Encoding::Brotli))
+        );
+        let minified = header::EntityTag::new_strong("abc-min-def".to_owned());
+        assert_eq!(
+            http_util::split_entity_tag(&minified),
+            ("abc-min-def", None)
+        );
+
+        let time = SystemTime::UNIX_EPOCH + Duration::from_secs(784111777);
+        assert_eq!(http_util::http_date(time), "Sun, 06 Nov 1994 08:49:37 GMT");
+        assert_eq!(http_util::content_range(0, 99, 1000), "bytes 0-99/1000");
+        assert_eq!(http_util::unsatisfied_range(1000), "bytes */1000");
+
+        let etag = http_util::entity_tag(&hash, None);
+        let boundary = http_util::multipart_boundary(&etag);
+        assert_eq!(boundary, http_util::multipart_boundary(&etag.clone()));
+        assert_ne!(
+            boundary,
+            http_util::multipart_boundary(&http_util::entity_tag(&hash, Some(Encoding::Gzip)))
+        );
+
+        assert_eq!(
+            http_util::attachment("site \"v2\".tar"),
+            "attachment; filename=\"site \\\"v2\\\".tar\""
+        );
+        assert_eq!(
+            http_util::attachment("資料.zip"),
+            "attachment; filename=\"__.zip\"; filename*=UTF-8''%E8%B3%87%E6%96%99.zip"
+        );
+        assert_eq!(
+            http_util::Disposition::inline("🎉 party.txt").header_value(),
+            "inline; filename=\"_ party.txt\"; filename*=UTF-8''%F0%9F%8E%89%20party.txt"
+        );
+        assert_eq!(
+            http_util::Disposition::attachment("日本語.txt")
+                .fallback("nihongo\r\n.txt")
+                .header_value(),
+            "attachment; filename=\"nihongo__.txt\"; \
+             filename*=UTF-8''%E6%97%A5%E6%9C%AC%E8%AA%9E.txt"
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_slow_serve_threshold() {
+        let records = Rc::new(RefCell::new(Vec::new()));
+        let embed = |threshold| {
+            let records = records.clone();
+            Embed::new("/", &Assets)
+                .slow_serve_threshold(threshold)
+                .on_serve(move |record: ServeRecord| records.borrow_mut().push(record))
+        };
+        let srv = test::init_service(
+            App::new()
+                .service(web::scope("/fast").service(embed(Duration::from_secs(60))))
+                .service(embed(Duration::ZERO)),
+        )
+        .await;
+
+        let req = TestRequest::get().uri("/fast/index.html").to_request();
+        test::call_service(&srv, req).await;
+        let req = TestRequest::get().uri("/index.html").to_request();
+        test::call_service(&srv, req).await;
+        let req = TestRequest::get().uri("/missing").to_request();
+        test::call_service(&srv, req).await;
+
+        let records = records.borrow();
+        assert_eq!(records[0].slow, None);
+        let slow = records[1].slow.as_ref().unwrap();
+        assert_eq!(slow.threshold, Duration::ZERO);
+        let stages = slow
+            .stages
+            .iter()
+            .map(|(stage, _)| *stage)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            stages,
+            [
+                ServeStage::Lookup,
+                ServeStage::Negotiation,
+                ServeStage::Compression,
+                ServeStage::Write
+            ]
+        );
+        assert!(slow.dominant().is_some());
+        let slow = records[2].slow.as_ref().unwrap();
+        let stages = slow
+            .stages
+            .iter()
+            .map(|(stage, _)| *stage)
+            .collect::<Vec<_>>();
+        assert_eq!(stages, [ServeStage::Lookup, ServeStage::Write]);
+    }
+
+    #[actix_web::test]
+    async fn test_archive() {
+        let srv = test::init_service(
+            App::new().service(
+                Embed::new("/", &Assets)
+                    .archive("fonts.tar", "fonts")
+                    .archive("/all.tar", "/"),
+            ),
+        )
+        .await;
+
+        let req = TestRequest::get().uri("/fonts.tar").to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get(header::CONTENT_TYPE).unwrap(),
+            "application/x-tar"
+        );
+        assert_eq!(
+            resp.headers().get(header::CONTENT_DISPOSITION).unwrap(),
+            "attachment; filename=\"fonts.tar\""
+        );
+        let body = test::read_body(resp).await;
+        assert_eq!(body.len() % 512, 0);
+        let mut offset = 0;
+        let mut names = Vec::new();
+        while body[offset] != 0 {
+            let header = &body[offset..offset + 512];
+            let name = std::str::from_utf8(&header[..100])
+                .unwrap()
+                .trim_end_matches('\0');
+            let size = std::str::from_utf8(&header[124..135]).unwrap();
+            let size = usize::from_str_radix(size, 8).unwrap();
+            let checksum = std::str::from_utf8(&header[148..154]).unwrap();
+            let sum = header[..148].iter().chain(&[b' '; 8]).chain(&header[156..]);
+            assert_eq!(
+                u32::from_str_radix(checksum, 8).unwrap(),
+                sum.map(|&b| u32::from(b)).sum::<u32>()
+            );
+            let data = &body[offset + 512..offset + 512 + size];
+            let expected = Assets::get(&format!("fonts/{}", name)).unwrap().data;
+            assert_eq!(data, expected.as_ref());
+            names.push(name.to_owned());
+            offset += 512 + size.div_ceil(512) * 512;
+        }
+        assert_eq!(names, ["icons.woff", "icons.woff2"]);
+        assert_eq!(body.len(), offset + 1024);
+
+        let req = TestRequest::get().uri("/all.tar").to_request();
+        let body = test::read_body(test::call_service(&srv, req).await).await;
+        assert_eq!(&body[..9], b"assets/in");
+
+        let req = TestRequest::post().uri("/fonts.tar").to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+
+        // names too long for ustar headers are stored in pax headers
+        let srv = test::init_service(
+            App::new().service(Embed::new("/", &Assets).archive("long.tar", "long")),
+        )
+        .await;
+        let req = TestRequest::get().uri("/long.tar").to_request();
+        let body = test::read_body(test::call_service(&srv, req).await).await;
+        let path = format!("{}/{}/long.txt", "a".repeat(120), "b".repeat(120));
+        let record = format!("{} path={}\n", path.len() + 10, path);
+        assert_eq!(body[156], b'x');
+        assert_eq!(&body[512..512 + record.len()], record.as_bytes());
+        assert_eq!(body[1024 + 156], b'0');
+        assert_eq!(&body[1536..1541], b"long\n");
+
+        // excluded files and files outside their availability windows are left out
+        let launch = std::time::SystemTime::now() + Duration::from_secs(3600);
+        let srv = test::init_service(
+            App::new().service(
+                Embed::new("/", &Assets)
+                    .archive("fonts.tar", "fonts")
+                    .exclude(["fonts/icons.woff"])
+                    .available("fonts/icons.woff2", launch..),
+            ),
+        )
+        .await;
+        let req = TestRequest::get().uri("/fonts.tar").to_request();
+        let body = test::read_body(test::call_service(&srv, req).await).await;
+        assert_eq!(body, [0; 1024].as_ref());
+    }
+
+    #[actix_web::test]
+    async fn test_accept_charset() {
+        let srv = test::init_service(
+            App::new().service(
+                Embed::new("/", &Assets)
+                    .accept_charset(StatusCode::NOT_ACCEPTABLE)
+                    .variant_cache(4),
+            ),
+        )
+        .await;
+        let get = |uri: &'static str, accept: Option<&'static str>| {
+            let srv = &srv;
+            async move {
+                let mut req = TestRequest::get().uri(uri);
+                if let Some(accept) = accept {
+                    req = req.insert_header((header::ACCEPT_CHARSET, accept));
+                }
+                test::call_service(srv, req.to_request()).await
+            }
+        };
+
+        let resp = get("/index.html", None).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get(header::CONTENT_TYPE).unwrap(),
+            "text/html; charset=utf-8"
+        );
+        assert_eq!(resp.headers().get(header::VARY).unwrap(), "Accept-Charset");
+        for accept in [
+            "utf-8",
+            "iso-8859-1, UTF-8;q=0.5",
+            "*",
+            "iso-8859-1;q=0.5, *;q=0.1",
+        ] {
+            let resp = get("/index.html", Some(accept)).await;
+            assert_eq!(resp.status(), StatusCode::OK, "{}", accept);
+        }
+        for accept in ["iso-8859-1", "utf-8;q=0, *", "*;q=0"] {
+            let resp = get("/index.html", Some(accept)).await;
+            assert_eq!(resp.status(), StatusCode::NOT_ACCEPTABLE, "{}", accept);
+        }
+
+        // binary files are not concerned
+        let resp = get("/favicon.ico", Some("iso-8859-1")).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(!resp
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .contains("charset"));
+    }
+
+    #[actix_web::test]
+    async fn test_header() {
+        let srv = test::init_service(
+            App::new().service(
+                Embed::new("/", &Assets)
+                    .header(["*.html"], "x-robots-tag", "noindex")
+                    .header(
+                        Vec::<&str>::new(),
+                        header::X_CONTENT_TYPE_OPTIONS,
+                        "nosniff",
+                    )
+                    .try_build()
+                    .unwrap(),
+            ),
+        )
+        .await;
+        let req = TestRequest::get().uri("/index.html").to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.headers().get("x-robots-tag").unwrap(), "noindex");
+        assert_eq!(
+            resp.headers().get(header::X_CONTENT_TYPE_OPTIONS).unwrap(),
+            "nosniff"
+        );
+        let req = TestRequest::get().uri("/favicon.ico").to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert!(resp.headers().get("x-robots-tag").is_none());
+        assert_eq!(
+            resp.headers().get(header::X_CONTENT_TYPE_OPTIONS).unwrap(),
+            "nosniff"
+        );
+
+        let issues = Embed::new("/", &Assets)
+            .header(["**"], "x-build", "line\nbreak")
+            .try_build()
+            .unwrap_err();
+        assert_eq!(issues[0].option, "header_rules");
+        let issues = Embed::new("/", &Assets)
+            .header(["**"], "bad name", "value")
+            .try_build()
+            .unwrap_err();
+        assert_eq!(issues[0].option, "header_rules");
+        let issues = Embed::new("/", &Assets)
+            .language_override("lang", "bad;cookie")
+            .try_build()
+            .unwrap_err();
+        assert_eq!(issues[0].option, "language_override");
+    }
+
+    #[cfg(debug_assertions)]
+    #[actix_web::test]
+    async fn test_source_folder() {
+        use crate::{staleness, Severity};
+        use std::path::Path;
+
+        // debug builds of rust_embed serve the files on disk
+        let testdata = Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata");
+        assert_eq!(staleness::compare::<Assets>(&testdata), None);
+
+        let dir = std::env::temp_dir().join(format!("actix-embed-source-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("assets")).unwrap();
+        std::fs::write(dir.join("assets/app.js"), b"console.log(1)").unwrap();
+        let difference = staleness::compare::<Assets>(&dir).unwrap();
+        assert!(difference.contains("(1 files, 14 bytes)"), "{}", difference);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let missing = staleness::compare::<Assets>(&dir).unwrap();
+        assert!(missing.starts_with("cannot read"), "{}", missing);
+
+        let issues = |folder: &Path| {
+            let embed = Embed::new("/", &Assets).source_folder(folder);
+            embed
+                .issues()
+                .into_iter()
+                .filter(|issue| issue.option == "source_folder")
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(issues(&testdata), []);
+        let stale = issues(&dir);
+        assert_eq!(stale.len(), 1);
+        assert_eq!(stale[0].severity, Severity::Warning);
+        assert!(stale[0].message.starts_with("cannot read"), "{:?}", stale);
+    }
+
+    #[actix_web::test]
+    async fn test_revalidation_hook() {
+        use crate::{RevalidationDecision, Validators};
+
+        for cached in [false, true] {
+            let mut embed = Embed::new("/", &Assets).revalidation_hook(
+                |req: &HttpRequest, validators: &Validators| {
+                    assert_eq!(validators.path, "index.html");
+                    match req.headers().get(header::USER_AGENT) {
+                        Some(agent) if agent == "LegacyTV" => RevalidationDecision::Full,
+                        Some(agent) if agent == "Prefetcher" => RevalidationDecision::NotModified,
+                        _ => RevalidationDecision::Evaluate,
+                    }
+                },
+            );
+            if cached {
+                embed = embed.variant_cache(4);
+            }
+            let srv = test::init_service(App::new().service(embed)).await;
+            let req = TestRequest::get().uri("/index.html").to_request();
+            let resp = test::call_service(&srv, req).await;
+            let etag = resp.headers().get(header::ETAG).unwrap().clone();
+
+            let status = |agent: &'static str, etag: Option<header::HeaderValue>| {
+                let srv = &srv;
+                async move {
+                    let mut req = TestRequest::get()
+                        .uri("/index.html")
+                        .insert_header((header::USER_AGENT, agent));
+                    if let Some(etag) = etag {
+                        req = req.insert_header((header::IF_NONE_MATCH, etag));
+                    }
+                    let resp = test::call_service(srv, req.to_request()).await;
+                    assert!(resp.headers().contains_key(header::ETAG));
+                    resp.status()
+                }
+            };
+            let current = Some(etag);
+            assert_eq!(
+                status("Browser", current.clone()).await,
+                StatusCode::NOT_MODIFIED
+            );
+            assert_eq!(status("LegacyTV", current.clone()).await, StatusCode::OK);
+            assert_eq!(status("Browser", None).await, StatusCode::OK);
+            assert_eq!(status("Prefetcher", None).await, StatusCode::NOT_MODIFIED);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_verify_hashes() {
+        // hashes differently each time, as if the file changed between requests
+        struct Drifting(Rc<std::cell::Cell<u32>>);
+        impl crate::Hasher for Drifting {
+            fn hash(&self, _: &rust_embed::EmbeddedFile) -> Vec<u8> {
+                self.0.set(self.0.get() + 1);
+                self.0.get().to_be_bytes().to_vec()
+            }
+        }
+
+        for verify in [false, true] {
+            let records = Rc::new(RefCell::new(Vec::new()));
+            let srv = test::init_service(App::new().service({
+                let records = records.clone();
+                Embed::new("/", &Assets)
+                    .hasher(Drifting(Default::default()))
+                    .verify_hashes(verify)
+                    .on_serve(move |record: ServeRecord| records.borrow_mut().push(record))
+            }))
+            .await;
+            let mut etags = Vec::new();
+            for _ in 0..2 {
+                let req = TestRequest::get().uri("/index.html").to_request();
+                let resp = test::call_service(&srv, req).await;
+                assert_eq!(resp.status(), StatusCode::OK);
+                etags.push(resp.headers().get(header::ETAG).unwrap().clone());
+                test::read_body(resp).await;
+            }
+            assert_eq!(etags[0] != etags[1], verify);
+            let stale = records
+                .borrow()
+                .iter()
+                .map(|record| record.context.decision("verify_hashes"))
+                .collect::<Vec<_>>();
+            // the file is hashed again to verify it, so each request sees a mismatch
+            assert_eq!(stale, [verify.then_some("stale"); 2]);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_not_found_cache_control() {
+        let srv = test::init_service(
+            App::new()
+                .service(
+                    Embed::new("/custom", &Assets)
+                        .not_found_cache_control("public, max-age=60")
+                        .fallback(|_: &HttpRequest| {
+                            HttpResponse::NotFound()
+                                .insert_header((header::CACHE_CONTROL, "no-store"))
+                                .finish()
+                        }),
+                )
+                .service(Embed::new("/", &Assets).not_found_cache_control("public, max-age=60")),
+        )
+        .await;
+        let get = |uri: &'static str| {
+            let srv = &srv;
+            async move {
+                let req = TestRequest::get().uri(uri).to_request();
+                test::call_service(srv, req).await
+            }
+        };
+
+        let resp = get("/missing.js").await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        assert_eq!(
+            resp.headers().get(header::CACHE_CONTROL).unwrap(),
+            "public, max-age=60"
+        );
+        let resp = get("/index.html").await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(resp.headers().get(header::CACHE_CONTROL).is_none());
+        let resp = get("/custom/missing.js").await;
+        assert_eq!(
+            resp.headers().get(header::CACHE_CONTROL).unwrap(),
+            "no-store"
+        );
+
+        let issues = Embed::new("/", &Assets)
+            .not_found_cache_control("max-age=60\r\n")
+            .try_build()
+            .unwrap_err();
+        assert_eq!(issues[0].option, "not_found_cache_control");
+    }
+
+    #[actix_web::test]
+    async fn test_age_headers() {
+        let srv = test::init_service(
+            App::new()
+                .service(Embed::new("/plain", &Assets).variant_cache(4))
+                .service(Embed::new("/", &Assets).variant_cache(4).age_headers(true)),
+        )
+        .await;
+        let get = |uri: &'static str| {
+            let srv = &srv;
+            async move {
+                let req = TestRequest::get().uri(uri).to_request();
+                test::call_service(srv, req).await
+            }
+        };
+
+        let first = get("/index.html").await;
+        assert_eq!(first.headers().get(header::AGE).unwrap(), "0");
+        std::thread::sleep(Duration::from_millis(1100));
+        let cached = get("/index.html").await;
+        assert_eq!(cached.headers().get(header::AGE).unwrap(), "1");
+        assert_eq!(
+            cached.headers().get(header::DATE).unwrap(),
+            first.headers().get(header::DATE).unwrap()
+        );
+
+        let resp = get("/plain/index.html").await;
+        assert!(resp.headers().get(header::AGE).is_none());
+        assert!(resp.headers().get(header::DATE).is_none());
+    }
+
+    #[actix_web::test]
+    async fn test_config_epoch() {
+        let admin = EmbedAdmin::new();
+        let epochs = Rc::new(RefCell::new(Vec::new()));
+        let srv = test::init_service(App::new().service({
+            let epochs = epochs.clone();
+            Embed::new("/", &Assets)
+                .variant_cache(4)
+                .cache_status(true)
+                .with_admin(&admin)
+                .on_serve(move |record: ServeRecord| {
+                    epochs.borrow_mut().push(record.context.epoch())
+                })
+        }))
+        .await;
+        let status = || {
+            let srv = &srv;
+            async move {
+                let req = TestRequest::get().uri("/index.html").to_request();
+                let resp = test::call_service(srv, req).await;
+                let value = resp.headers().get("cache-status").unwrap();
+                value.to_str().unwrap().to_owned()
+            }
+        };
+
+        let generated = "actix-embed-derived; fwd=miss; stored, actix-embed; fwd=uri-miss; stored";
+        assert_eq!(status().await, generated);
+        assert_eq!(status().await, "actix-embed; hit");
+        admin.bump_epoch();
+        assert_eq!(admin.epoch(), 1);
+        // the stale response and prepared file are replaced when next used
+        assert_eq!(status().await, generated);
+        assert_eq!(status().await, "actix-embed; hit");
+        admin.set_suggestions(false);
+        assert_eq!(status().await, generated);
+        assert_eq!(*epochs.borrow(), [0, 0, 1, 1, 2]);
+        assert!(format!("{:?}", admin).contains("epoch: 2"));
+    }
+
+    #[actix_web::test]
+    async fn test_cache_status() {
+        let srv = test::init_service(
+            App::new()
+                .service(Embed::new("/plain", &Assets).cache_status(true))
+                .service(
+                    Embed::new("/", &Assets)
+                        .variant_cache(4)
+                        .cache_status(true)
+                        .header(["**"], header::CACHE_CONTROL, "public, max-age=60")
+                        .cdn_cache_control(["assets/**"], "max-age=86400"),
+                ),
+        )
+        .await;
+        let get = |uri: &'static str| {
+            let srv = &srv;
+            async move {
+                let req = TestRequest::get().uri(uri).to_request();
+                test::call_service(srv, req).await
+            }
+        };
+        let status = |resp: &actix_web::dev::ServiceResponse| {
+            let value = resp.headers().get("cache-status").unwrap();
+            value.to_str().unwrap().to_owned()
+        };
+
+        let resp = get("/index.html").await;
+        assert_eq!(
+            status(&resp),
+            "actix-embed-derived; fwd=miss; stored, actix-embed; fwd=uri-miss; stored"
+        );
+        assert!(resp.headers().get("cdn-cache-control").is_none());
+        assert_eq!(status(&get("/index.html").await), "actix-embed; hit");
+
+        let resp = get("/assets/index.css").await;
+        assert_eq!(
+            resp.headers().get("cdn-cache-control").unwrap(),
+            "max-age=86400"
+        );
+        assert_eq!(
+            resp.headers().get(header::CACHE_CONTROL).unwrap(),
+            "public, max-age=60"
+        );
+
+        let resp = get("/plain/index.html").await;
+        assert_eq!(status(&resp), "actix-embed-derived; fwd=miss; stored");
+        let resp = get("/plain/index.html").await;
+        assert_eq!(status(&resp), "actix-embed-derived; hit");
+
+        let issues = Embed::new("/", &Assets)
+            .cdn_cache_control(["**"], "max-age=60\r\n")
+            .issues();
+        assert_eq!(issues[0].option, "cdn_cache_control");
+    }
+
+    #[cfg(feature = "compress-gzip")]
+    #[actix_web::test]
+    async fn test_precompress_levels() {
+        use std::io::Write;
+
+        use crate::Encoding;
+
+        let srv = test::init_service(
+            App::new().service(
+                Embed::new("/", &Assets)
+                    .precompress(Encoding::Gzip, 9, ["assets/**"])
+                    .precompress(Encoding::Gzip, 1, ["**/*.css", "**/*.html"])
+                    .precompress_blocking(true),
+            ),
+        )
+        .await;
+        let gzip = |path: &str, level: u32| {
+            let mut encoder = flate2::GzBuilder::new()
+                .mtime(0)
+                .write(Vec::new(), flate2::Compression::new(level));
+            encoder.write_all(&Assets::get(path).unwrap().data).unwrap();
+            encoder.finish().unwrap()
+        };
+
+        for (path, level) in [("assets/index.css", 9), ("index.html", 1)] {
+            let req = TestRequest::get()
+                .uri(&format!("/{}", path))
+                .insert_header((header::ACCEPT_ENCODING, "gzip"))
+                .to_request();
+            let resp = test::call_service(&srv, req).await;
+            assert_eq!(
+                resp.headers().get(header::CONTENT_ENCODING).unwrap(),
+                "gzip"
+            );
+            let body = test::read_body(resp).await;
+            assert_eq!(body, gzip(path, level), "{}", path);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_embed_report() {
+        let report = crate::embed_report::<Assets>();
+        let total = Assets::iter()
+            .map(|path| Assets::get(&path).unwrap().data.len() as u64)
+            .sum::<u64>();
+        assert_eq!(report.size, total);
+        assert_eq!(report.files.len(), Assets::iter().count());
+        assert!(report.estimated_compressed <= report.size);
+
+        let largest = report.largest(2);
+        assert_eq!(largest.len(), 2);
+        assert!(largest[0].size >= largest[1].size);
+        assert_eq!(report.largest(usize::MAX).len(), report.files.len());
+
+        let assets = report
+            .directories
+            .iter()
+            .find(|dir| dir.path == "assets")
+            .unwrap();
+        let expected = report
+            .files
+            .iter()
+            .filter(|file| file.path.starts_with("assets/"))
+            .map(|file| file.size)
+            .sum::<u64>();
+        assert_eq!(assets.size, expected);
+        assert!(report
+            .directories
+            .iter()
+            .any(|dir| dir.path == "vendor/lodash"));
+        assert!(report.to_string().contains("largest files:"));
+    }
+
+    #[actix_web::test]
+    async fn test_header_sanitizer() {
+        use crate::http_util;
+
+        assert_eq!(http_util::header_value("/a\r\nb"), "/a%0D%0Ab");
+        assert_eq!(http_util::header_value("\u{0}\t~ü"), "%00\t~%C3%BC");
+        assert_eq!(http_util::header_value("plain value"), "plain value");
+        assert_eq!(
+            http_util::attachment("a\"b\\c\nd.tar"),
+            "attachment; filename=\"a\\\"b\\\\c_d.tar\"; filename*=UTF-8''a%22b%5Cc%0Ad.tar"
+        );
+
+        let srv = test::init_service(
+            App::new().service(
+                Embed::new("/", &Assets)
+                    .redirect("old.html", "über\r\nset-cookie: a=b")
+                    .archive("dl/\"x\".tar", "assets"),
+            ),
+        )
+        .await;
+        let req = TestRequest::get().uri("/old.html").to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::MOVED_PERMANENTLY);
+        assert_eq!(
+            resp.headers().get(header::LOCATION).unwrap(),
+            "/%C3%BCber%0D%0Aset-cookie: a=b"
+        );
+        assert!(resp.headers().get(header::SET_COOKIE).is_none());
+
+        let req = TestRequest::get().uri("/dl/%22x%22.tar").to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get(header::CONTENT_DISPOSITION).unwrap(),
+            "attachment; filename=\"\\\"x\\\".tar\""
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_presets() {
+        let srv = test::init_service(
+            App::new()
+                .service(Embed::spa("/app", &Assets))
+                .service(Embed::docs_site("/docs", &Assets))
+                .service(Embed::downloads("/files", &Assets)),
+        )
+        .await;
+        let get = |uri: &'static str| {
+            let srv = &srv;
+            async move {
+                let req = TestRequest::get().uri(uri).to_request();
+                test::call_service(srv, req).await
+            }
+        };
+        let index = Assets::get("index.html").unwrap().data;
+
+        for uri in ["/app", "/app/settings/profile"] {
+            let resp = get(uri).await;
+            assert_eq!(resp.status(), StatusCode::OK, "{}", uri);
+            assert_eq!(test::read_body(resp).await, index.as_ref(), "{}", uri);
+        }
+
+        let de = Assets::get("de/index.html").unwrap().data;
+        for uri in [
+            "/docs/de",
+            "/docs/de/",
+            "/docs/de/index",
+            "/docs/de/index.html",
+        ] {
+            let resp = get(uri).await;
+            assert_eq!(resp.status(), StatusCode::OK, "{}", uri);
+            assert_eq!(test::read_body(resp).await, de.as_ref(), "{}", uri);
+        }
+        assert_eq!(get("/docs/fr").await.status(), StatusCode::NOT_FOUND);
+
+        let resp = get("/files/assets/index.css").await;
+        assert_eq!(
+            resp.headers().get(header::CONTENT_DISPOSITION).unwrap(),
+            "attachment"
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_http10_compat() {
+        use actix_web::body::{BodySize, MessageBody};
+        use actix_web::http::Version;
+
+        let srv = test::init_service(
+            App::new().service(
+                Embed::new("/", &Assets)
+                    .http10_compat(true)
+                    .canonical_host("www.example.com", Scheme::Http)
+                    .header(["assets/**"], header::CACHE_CONTROL, "public, max-age=600")
+                    .archive("assets.tar", "assets"),
+            ),
+        )
+        .await;
+        let get = |uri: &'static str, version: Version| {
+            let srv = &srv;
+            async move {
+                let req = TestRequest::get().uri(uri).version(version).to_request();
+                test::call_service(srv, req).await
+            }
+        };
+
+        let resp = get("/assets/index.css", Version::HTTP_10).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let expires = resp
+            .headers()
+            .get(header::EXPIRES)
+            .unwrap()
+            .to_str()
+            .unwrap();
+        let expires = expires.parse::<header::HttpDate>().unwrap();
+        let expires = std::time::SystemTime::from(expires);
+        let now = std::time::SystemTime::now();
+        assert!(expires > now + Duration::from_secs(500));
+        assert!(expires <= now + Duration::from_secs(600));
+
+        let resp = get("/assets.tar", Version::HTTP_10).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get(header::EXPIRES).unwrap(),
+            "Thu, 01 Jan 1970 00:00:00 GMT"
+        );
+        assert_eq!(resp.headers().get(header::PRAGMA).unwrap(), "no-cache");
+        let size = match resp.response().body().size() {
+            BodySize::Sized(size) => size,
+            size => panic!("unexpected body size {:?}", size),
+        };
+        assert_eq!(test::read_body(resp).await.len() as u64, size);
+
+        let req = TestRequest::get()
+            .uri("/assets.tar")
+            .insert_header((header::HOST, "www.example.com"))
+            .to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(resp.headers().get(header::EXPIRES).is_none());
+        assert_eq!(resp.response().body().size(), BodySize::Stream);
+
+        let req = TestRequest::get()
+            .uri("/index.html")
+            .version(Version::HTTP_10)
+            .insert_header((header::HOST, "example.com"))
+            .to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::MOVED_PERMANENTLY);
+        let resp = get("/index.html", Version::HTTP_11).await;
+        assert_eq!(resp.status(), StatusCode::MOVED_PERMANENTLY);
+    }
+
+    #[actix_web::test]
+    async fn test_canary() {
+        let srv = test::init_service(
+            App::new()
+                .service(Embed::new("/all", &Assets).canary(100, &WellKnown))
+                .service(Embed::new("/none", &Assets).canary(0, &WellKnown)),
+        )
+        .await;
+        let get = |uri: &'static str, cookie: Option<&'static str>| {
+            let srv = &srv;
+            async move {
+                let mut req = TestRequest::get().uri(uri);
+                if let Some(cookie) = cookie {
+                    req = req.insert_header((header::COOKIE, cookie));
+                }
+                test::call_service(srv, req.to_request()).await
+            }
+        };
+
+        let resp = get("/all/security.txt", None).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers().get(header::VARY).unwrap(), "Cookie");
+        assert_eq!(
+            resp.headers().get(header::SET_COOKIE).unwrap(),
+            "embed-canary=canary; Path=/all/; Max-Age=2592000; SameSite=Lax"
+        );
+        let resp = get("/all/index.html", None).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        // remembered cohorts are kept whatever the percentage
+        let resp = get("/all/index.html", Some("embed-canary=stable")).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(resp.headers().get(header::SET_COOKIE).is_none());
+        let resp = get("/none/security.txt", Some("embed-canary=canary")).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let resp = get("/none/index.html", None).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get(header::SET_COOKIE).unwrap(),
+            "embed-canary=stable; Path=/none/; Max-Age=2592000; SameSite=Lax"
+        );
+
+        let issues = Embed::new("/", &Assets)
+            .canary(101, &WellKnown)
+            .try_build()
+            .unwrap_err();
+        assert_eq!(issues[0].option, "canary");
+    }
+
+    #[actix_web::test]
+    async fn test_header_only_responses() {
+        use crate::ServedAsset;
+
+        let srv = test::init_service(
+            App::new()
+                .service(Embed::new("/plain", &Assets))
+                .service(Embed::new("/cached", &Assets).variant_cache(4))
+                .service(
+                    Embed::new("/hidden", &Assets)
+                        .hide_missing_paths(true)
+                        .clean_urls(true),
+                ),
+        )
+        .await;
+        let req = TestRequest::get().uri("/plain/index.html").to_request();
+        let resp = test::call_service(&srv, req).await;
+        let etag = resp.headers().get(header::ETAG).unwrap().clone();
+        let size = Assets::get("index.html").unwrap().data.len() as u64;
+
+        for uri in ["/plain/index.html", "/cached/index.html"] {
+            let req = TestRequest::get()
+                .uri(uri)
+                .insert_header((header::IF_NONE_MATCH, etag.clone()))
+                .to_request();
+            let resp = test::call_service(&srv, req).await;
+            assert_eq!(resp.status(), StatusCode::NOT_MODIFIED, "{}", uri);
+            assert_eq!(resp.headers().get(header::ETAG), Some(&etag));
+            let asset = resp.request().extensions().get::<ServedAsset>().cloned();
+            assert_eq!(asset.unwrap().size, size);
+        }
+
+        // responses without their body are not cached
+        let req = TestRequest::get().uri("/cached/index.html").to_request();
+        let body = test::call_and_read_body(&srv, req).await;
+        assert_eq!(body, Assets::get("index.html").unwrap().data.as_ref());
+
+        for (uri, status) in [
+            ("/hidden/index", StatusCode::METHOD_NOT_ALLOWED),
+            ("/hidden/missing", StatusCode::NOT_FOUND),
+        ] {
+            let req = TestRequest::post().uri(uri).to_request();
+            let resp = test::call_service(&srv, req).await;
+            assert_eq!(resp.status(), status, "{}", uri);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_yield_every() {
+        use std::cell::Cell;
+        use std::rc::Rc;
+
+        for (every, yields) in [(0, false), (4, true)] {
+            let srv =
+                test::init_service(App::new().service(Embed::new("/", &Assets).yield_every(every)))
+                    .await;
+            let ran = Rc::new(Cell::new(false));
+            actix_web::rt::spawn({
+                let ran = ran.clone();
+                async move { ran.set(true) }
+            });
+            for _ in 0..4 {
+                let req = TestRequest::get().uri("/index.html").to_request();
+                let resp = test::call_service(&srv, req).await;
+                assert_eq!(resp.status(), StatusCode::OK);
+            }
+            // the other task only runs if the service yields
+            assert_eq!(ran.get(), yields, "{}", every);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_accept_ranges() {
+        use crate::AcceptRanges;
+
+        let srv = test::init_service(
+            App::new()
+                .service(Embed::new("/default", &Assets))
+                .service(
+                    Embed::new("/bytes", &Assets)
+                        .accept_ranges(AcceptRanges::Bytes)
+                        .archive("all.tar", ""),
+                )
+                .service(
+                    Embed::new("/none", &Assets)
+                        .accept_ranges(AcceptRanges::None)
+                        .archive("all.tar", ""),
+                ),
+        )
+        .await;
+        for (uri, status, expected) in [
+            ("/default/index.html", StatusCode::PARTIAL_CONTENT, None),
+            (
+                "/bytes/index.html",
+                StatusCode::PARTIAL_CONTENT,
+                Some("bytes"),
+            ),
+            ("/bytes/all.tar", StatusCode::OK, Some("none")),
+            ("/none/index.html", StatusCode::OK, Some("none")),
+            ("/none/all.tar", StatusCode::OK, Some("none")),
+        ] {
+            let req = TestRequest::get()
+                .uri(uri)
+                .insert_header((header::RANGE, "bytes=0-9"))
+                .to_request();
+            let resp = test::call_service(&srv, req).await;
+            assert_eq!(resp.status(), status, "{}", uri);
+            let accept_ranges = resp.headers().get(header::ACCEPT_RANGES);
+            assert_eq!(
+                accept_ranges.map(|v| v.to_str().unwrap()),
+                expected,
+                "{}",
+                uri
+            );
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_directory_index_file() {
+        let srv = test::init_service(
+            App::new()
+                .service(Embed::new("/en", &Assets).index_file("en/"))
+                .service(
+                    Embed::new("/de", &Assets)
+                        .index_file("de")
+                        .directory_index("missing.html"),
+                )
+                .service(Embed::spa("/", &Assets).index_file("de/")),
+        )
+        .await;
+        let read = |uri: &'static str| {
+            let srv = &srv;
+            async move {
+                let req = TestRequest::get().uri(uri).to_request();
+                let resp = test::call_service(srv, req).await;
+                (resp.status(), test::read_body(resp).await)
+            }
+        };
+        let file = |path: &str| Bytes::from(Assets::get(path).unwrap().data.into_owned());
+
+        assert_eq!(read("/en").await, (StatusCode::OK, file("en/index.html")));
+        // the directory index takes the place of `index.html`
+        assert_eq!(read("/de").await.0, StatusCode::NOT_FOUND);
+        assert_eq!(read("/").await, (StatusCode::OK, file("de/index.html")));
+        // the fallback handler comes last
+        assert_eq!(read("/missing").await, (StatusCode::OK, file("index.html")));
+    }
+
+    #[actix_web::test]
+    async fn test_metadata_registry() {
+        use crate::{FileMetadata, MetadataRegistry};
+
+        let records = Rc::new(RefCell::new(Vec::new()));
+        let srv = test::init_service(
+            App::new().service(
+                Embed::new("/", &Assets)
+                    .metadata_registry(true)
+                    .on_serve({
+                        let records = records.clone();
+                        move |record: ServeRecord| records.borrow_mut().push(record)
+                    })
+                    .fallback_handler(|req: &HttpRequest| {
+                        let extensions = req.extensions();
+                        let registry = extensions.get::<MetadataRegistry>().unwrap();
+                        let index = registry.get("index.html").unwrap();
+                        HttpResponse::NotFound().body(format!("{} {}", registry.len(), index.mime))
+                    }),
+            ),
+        )
+        .await;
+
+        let req = TestRequest::get().uri("/assets/index.css").to_request();
+        test::call_service(&srv, req).await;
+        let file = Assets::get("assets/index.css").unwrap();
+        let expected = FileMetadata {
+            size: file.data.len() as u64,
+            hash: hex::encode(file.metadata.sha256_hash()),
+            mime: "text/css".to_owned(),
+            last_modified: file.metadata.last_modified(),
+        };
+        assert_eq!(records.borrow()[0].metadata, Some(expected));
+
+        let req = TestRequest::get().uri("/missing").to_request();
+        let body = test::call_and_read_body(&srv, req).await;
+        assert_eq!(body, format!("{} text/html", Assets::iter().count()));
+        assert_eq!(records.borrow()[1].metadata, None);
+    }
+
+    #[actix_web::test]
+    async fn test_concurrent_construction() {
+        use crate::SharedState;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static LISTED: AtomicUsize = AtomicUsize::new(0);
+
+        // counts how often the files are listed to build the state
+        struct Counted;
+
+        impl RustEmbed for Counted {
+            fn get(file_path: &str) -> Option<rust_embed::EmbeddedFile> {
+                <Assets as RustEmbed>::get(file_path)
+            }
+
+            fn iter() -> rust_embed::Filenames {
+                LISTED.fetch_add(1, Ordering::SeqCst);
+                <Assets as RustEmbed>::iter()
+            }
+        }
+
+        let state = SharedState::new();
+        let embed = || Embed::new("/", &Counted).suggestions(1);
+        let first = test::init_service(App::new().service(embed().shared_state(&state))).await;
+        let listed = LISTED.load(Ordering::SeqCst);
+        assert!(listed > 0);
+
+        // services of other workers created at the same time wait for one of them to build
+        // the state
+        let other = SharedState::new();
+        let barrier = std::sync::Arc::new(std::sync::Barrier::new(4));
+        let handles = (0..4)
+            .map(|_| {
+                let other = other.clone();
+                let barrier = barrier.clone();
+                std::thread::spawn(move || {
+                    actix_web::rt::System::new().block_on(async move {
+                        let embed = embed().shared_state(&other);
+                        barrier.wait();
+                        let srv = test::init_service(App::new().service(embed)).await;
+                        let req = TestRequest::get().uri("/index.html").to_request();
+                        test::call_service(&srv, req).await.status()
+                    })
+                })
+            })
+            .collect::<Vec<_>>();
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), StatusCode::OK);
+        }
+        assert_eq!(LISTED.load(Ordering::SeqCst), 2 * listed);
+
+        // services of the first state reuse it
+        let _same = test::init_service(App::new().service(embed().shared_state(&state))).await;
+        assert_eq!(LISTED.load(Ordering::SeqCst), 2 * listed);
+
+        // services without a shared state build their own
+        let _own = test::init_service(App::new().service(embed())).await;
+        assert_eq!(LISTED.load(Ordering::SeqCst), 3 * listed);
+
+        let req = TestRequest::get().uri("/index.html").to_request();
+        assert_eq!(
+            test::call_service(&first, req).await.status(),
+            StatusCode::OK
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_embed_path() {
+        let index = crate::embed_path!("testdata/", "de/index.html");
+        assert_eq!(index, "de/index.html");
+
+        let srv = test::init_service(App::new().service(
+            Embed::new("/", &Assets).index_file(index).redirect(
+                "style.css",
+                crate::embed_path!("testdata", "assets/index.css"),
+            ),
+        ))
+        .await;
+        let req = TestRequest::get().uri("/").to_request();
+        let body = test::call_and_read_body(&srv, req).await;
+        assert_eq!(body, Assets::get(index).unwrap().data.as_ref());
+
+        let req = TestRequest::get().uri("/style.css").to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(
+            resp.headers().get(header::LOCATION).unwrap(),
+            "/assets/index.css"
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_ruleset() {
+        use crate::{Rule, RuleKind};
+
+        let embed = Embed::new("/static", &Assets)
+            .index_file("index.html")
+            .redirect("old.css", "assets/index.css")
+            .header(["**/*.css"], "cache-control", "max-age=3600, immutable");
+        let ruleset = embed.ruleset();
+        assert_eq!(
+            ruleset.rules,
+            vec![
+                Rule {
+                    kind: RuleKind::Rewrite,
+                    pattern: "/static/".to_owned(),
+                    status: None,
+                    header: None,
+                    value: "/static/index.html".to_owned(),
+                },
+                Rule {
+                    kind: RuleKind::Redirect,
+                    pattern: "/static/old.css".to_owned(),
+                    status: Some(301),
+                    header: None,
+                    value: "/static/assets/index.css".to_owned(),
+                },
+                Rule {
+                    kind: RuleKind::Header,
+                    pattern: "/static/**/*.css".to_owned(),
+                    status: None,
+                    header: Some("cache-control".to_owned()),
+                    value: "max-age=3600, immutable".to_owned(),
+                },
+            ]
+        );
+
+        assert_eq!(
+            ruleset.to_csv(),
+            "kind,pattern,status,header,value\r\n\
+             rewrite,/static/,,,/static/index.html\r\n\
+             redirect,/static/old.css,301,,/static/assets/index.css\r\n\
+             header,/static/**/*.css,,cache-control,\"max-age=3600, immutable\"\r\n"
+        );
+        let json = ruleset.to_string();
+        assert!(
+            json.starts_with("[{\"kind\":\"rewrite\",\"pattern\":\"/static/\",\"status\":null,")
+        );
+        assert!(json.contains("\"status\":301"));
+    }
+
+    #[actix_web::test]
+    async fn test_throttle() {
+        use actix_web::body::{BodySize, MessageBody};
+        use std::time::{Duration, Instant};
+
+        let srv = test::init_service(
+            App::new().service(Embed::new("/", &Assets).throttle("*.html", 1000)),
+        )
+        .await;
+
+        // 341 bytes at 1000 bytes per second, in chunks of 100 bytes
+        let start = Instant::now();
+        let req = TestRequest::get().uri("/index.html").to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        // the length is still known up front
+        assert_eq!(resp.response().body().size(), BodySize::Sized(341));
+        let body = test::read_body(resp).await;
+        assert_eq!(body.len(), 341);
+        assert!(start.elapsed() >= Duration::from_millis(300));
+
+        let req = TestRequest::get().uri("/assets/index.css").to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.response().body().size(), BodySize::Sized(347));
+
+        let issues = Embed::new("/", &Assets)
+            .throttle("**", 0)
+            .try_build()
+            .unwrap_err();
+        assert_eq!(issues[0].option, "throttle");
+    }
+
+    #[actix_web::test]
+    async fn test_canonical_headers() {
+        let embed = |canonical| {
+            Embed::new("/", &Assets)
+                .localized_index(["en/index.html", "de/index.html"], "en/index.html")
+                .language_override("lang", "site-lang")
+                .require_signed_cookie("session", |value: &str| value == "ok")
+                .header(["**"], header::CACHE_CONTROL, "public, max-age=60")
+                .canonical_headers(canonical)
+        };
+        let headers = |canonical| async move {
+            let srv = test::init_service(App::new().service(embed(canonical))).await;
+            let req = TestRequest::get()
+                .uri("/")
+                .insert_header((header::ACCEPT_LANGUAGE, "de"))
+                .insert_header((header::COOKIE, "session=ok"))
+                .to_request();
+            let resp = test::call_service(&srv, req).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+            [header::VARY, header::CACHE_CONTROL].map(|name| {
+                resp.headers()
+                    .get_all(name)
+                    .map(|value| value.to_str().unwrap().to_owned())
+                    .collect::<Vec<_>>()
+            })
+        };
+
+        // values are added in the same order by independently created services
+        let added = headers(false).await;
+        assert_eq!(added, headers(false).await);
+        assert_eq!(added[0], ["Accept-Language", "Cookie", "Cookie"]);
+
+        let [vary, cache_control] = headers(true).await;
+        assert_eq!(vary, ["Accept-Language, Cookie"]);
+        assert_eq!(cache_control, ["max-age=60, private"]);
+    }
+
+    #[actix_web::test]
+    async fn test_stream_chunk_size() {
+        use std::future::poll_fn;
+        use std::pin::Pin;
+
+        use actix_web::body::{BodySize, MessageBody};
+
+        let srv = test::init_service(
+            App::new()
+                .service(
+                    web::scope("/whole").service(Embed::new("/", &Assets).stream_chunk_size(0)),
+                )
+                .service(Embed::new("/", &Assets).stream_chunk_size(100)),
+        )
+        .await;
+        let chunks = |uri: &'static str| {
+            let srv = &srv;
+            async move {
+                let req = TestRequest::get().uri(uri).to_request();
+                let resp = test::call_service(srv, req).await;
+                // the length is still known up front
+                assert_eq!(resp.response().body().size(), BodySize::Sized(341));
+                let mut body = resp.into_body();
+                let mut chunks = Vec::new();
+                while let Some(chunk) = poll_fn(|cx| Pin::new(&mut body).poll_next(cx)).await {
+                    chunks.push(chunk.unwrap().len());
+                }
+                chunks
+            }
+        };
+
+        // 341 bytes in chunks of 100 bytes
+        assert_eq!(chunks("/index.html").await, [100, 100, 100, 41]);
+        assert_eq!(chunks("/whole/index.html").await, [341]);
+    }
+
+    #[actix_web::test]
+    async fn test_derived_cache() {
+        use std::cell::Cell;
+        use std::rc::Rc;
+
+        use crate::DerivedCache;
+
+        #[derive(Clone, Default)]
+        struct Counting(Rc<Cell<usize>>);
+
+        impl crate::Hasher for Counting {
+            fn hash(&self, file: &rust_embed::EmbeddedFile) -> Vec<u8> {
+                self.0.set(self.0.get() + 1);
+                file.metadata.sha256_hash().to_vec()
+            }
+        }
+
+        for (shared, hashed) in [(false, 2), (true, 1)] {
+            let hasher = Counting::default();
+            let cache = DerivedCache::new();
+            let embed = |path| {
+                let embed = Embed::new(path, &Assets).hasher(hasher.clone());
+                match shared {
+                    true => embed.derived_cache(&cache),
+                    false => embed,
+                }
+            };
+            let srv =
+                test::init_service(App::new().service(embed("/static")).service(embed("/cdn")))
+                    .await;
+            let mut etags = Vec::new();
+            for uri in ["/static/index.html", "/cdn/index.html"] {
+                let req = TestRequest::get().uri(uri).to_request();
+                let resp = test::call_service(&srv, req).await;
+                assert_eq!(resp.status(), StatusCode::OK);
+                etags.push(resp.headers().get(header::ETAG).unwrap().clone());
+            }
+            assert_eq!(etags[0], etags[1]);
+            assert_eq!(hasher.0.get(), hashed, "{}", shared);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_byte_serving_preset() {
+        use crate::{AcceptRanges, OuterCompression};
+
+        let embed = |globs: &[&str]| {
+            Embed::new("/", &Assets)
+                .accept_ranges(AcceptRanges::None)
+                .outer_compression(OuterCompression::Allow)
+                .byte_serving_preset(globs.to_vec())
+        };
+        let get = |embed: Embed<Assets, DefaultFallbackHandler>, uri: &'static str| async move {
+            let srv = test::init_service(App::new().service(embed)).await;
+            let req = TestRequest::get()
+                .uri(uri)
+                .insert_header((header::RANGE, "bytes=0-15"))
+                .insert_header((header::ACCEPT_ENCODING, "gzip"))
+                .to_request();
+            let resp = test::call_service(&srv, req).await;
+            let status = resp.status();
+            let headers = resp.headers().clone();
+            (status, headers, test::read_body(resp).await)
+        };
+
+        let (status, headers, body) = get(embed(&[]), "/data/notes.sqlite").await;
+        assert_eq!(status, StatusCode::PARTIAL_CONTENT);
+        assert_eq!(body, "SQLite format 3\0");
+        assert_eq!(headers.get(header::ACCEPT_RANGES).unwrap(), "bytes");
+        assert_eq!(headers.get(header::CONTENT_ENCODING).unwrap(), "identity");
+        assert_eq!(
+            headers.get(header::CONTENT_TYPE).unwrap(),
+            "application/vnd.sqlite3"
+        );
+        assert_eq!(
+            headers.get(header::CACHE_CONTROL).unwrap(),
+            "public, max-age=31536000, immutable"
+        );
+        assert_eq!(
+            headers.get("cross-origin-resource-policy").unwrap(),
+            "same-origin"
+        );
+        assert!(headers.get(header::VARY).is_none());
+
+        // other files keep the options of the service
+        let (status, headers, _) = get(embed(&[]), "/index.html").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(headers.get(header::ACCEPT_RANGES).unwrap(), "none");
+        let (status, headers, _) = get(embed(&["db/**"]), "/data/notes.sqlite").await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(headers.get("cross-origin-resource-policy").is_none());
+    }
+
+    #[actix_web::test]
+    async fn test_untrusted_preset() {
+        let embed = Embed::new("/", &Assets).untrusted_preset(["de/**", "assets/*"]);
+        let exported = embed
+            .ruleset()
+            .rules
+            .into_iter()
+            .filter(|rule| rule.header.as_deref() == Some("content-disposition"))
+            .map(|rule| rule.pattern)
+            .collect::<Vec<_>>();
+        assert!(exported.contains(&"/de/**.html".to_owned()));
+        assert!(exported.contains(&"/assets/*.svg".to_owned()));
+
+        let srv = test::init_service(App::new().service(embed)).await;
+        for (path, untrusted, attachment) in [
+            ("/de/index.html", true, true),
+            ("/assets/index.css", true, false),
+            ("/index.html", false, false),
+        ] {
+            let req = TestRequest::get().uri(path).to_request();
+            let resp = test::call_service(&srv, req).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+            let headers = resp.headers();
+            assert_eq!(
+                headers.get(header::CONTENT_SECURITY_POLICY).is_some(),
+                untrusted,
+                "{}",
+                path
+            );
+            if untrusted {
+                assert_eq!(
+                    headers.get(header::CONTENT_SECURITY_POLICY).unwrap(),
+                    "sandbox"
+                );
+                assert_eq!(
+                    headers.get(header::X_CONTENT_TYPE_OPTIONS).unwrap(),
+                    "nosniff"
+                );
+            }
+            assert_eq!(
+                headers
+                    .get(header::CONTENT_DISPOSITION)
+                    .map(|v| v.to_str().unwrap()),
+                attachment.then_some("attachment"),
+                "{}",
+                path
+            );
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_frame_policy() {
+        use crate::FramePolicy;
+
+        let partners = vec!["https://partner.example".to_owned(), "'self'".to_owned()];
+        let srv = test::init_service(
+            App::new().service(
+                Embed::new("/", &Assets)
+                    .frame_policy("en/**", FramePolicy::AllowFrom(partners))
+                    .frame_policy("de/**", FramePolicy::Deny),
+            ),
+        )
+        .await;
+        for (path, csp, x_frame_options) in [
+            (
+                "/en/index.html",
+                Some("frame-ancestors https://partner.example 'self'"),
+                None,
+            ),
+            (
+                "/de/index.html",
+                Some("frame-ancestors 'none'"),
+                Some("DENY"),
+            ),
+            ("/index.html", None, None),
+        ] {
+            let req = TestRequest::get().uri(path).to_request();
+            let resp = test::call_service(&srv, req).await;
+            let header = |name| resp.headers().get(name).map(|v| v.to_str().unwrap());
+            assert_eq!(header(header::CONTENT_SECURITY_POLICY), csp, "{}", path);
+            assert_eq!(header(header::X_FRAME_OPTIONS), x_frame_options, "{}", path);
+        }
+
+        let origins = vec!["https://a.example; script-src *".to_owned()];
+        let issues = Embed::new("/", &Assets)
+            .frame_policy("**", FramePolicy::AllowFrom(origins))
+            .issues();
+        assert_eq!(issues[0].option, "frame_policy");
+    }
+
+    #[actix_web::test]
+    async fn test_priority() {
+        use crate::Priority;
+
+        let srv = test::init_service(
+            App::new().service(
+                Embed::new("/", &Assets)
+                    .priority("assets/*.css", Priority::High)
+                    .priority("en/**", Priority::Low)
+                    .priority(
+                        "images/**",
+                        Priority::Urgency {
+                            urgency: 4,
+                            incremental: true,
+                        },
+                    ),
+            ),
+        )
+        .await;
+        for (path, priority) in [
+            ("/assets/index.css", Some("u=1")),
+            ("/en/index.html", Some("u=5")),
+            ("/images/hero@2x.png", Some("u=4, i")),
+            ("/index.html", None),
+        ] {
+            let req = TestRequest::get().uri(path).to_request();
+            let resp = test::call_service(&srv, req).await;
+            let header = resp.headers().get("priority");
+            assert_eq!(header.map(|v| v.to_str().unwrap()), priority, "{}", path);
+        }
+
+        let urgent = Priority::Urgency {
+            urgency: 8,
+            incremental: false,
+        };
+        let issues = Embed::new("/", &Assets).priority("**", urgent).issues();
+        assert_eq!(issues[0].option, "priority");
+    }
+
+    #[cfg(feature = "sanitize-svg")]
+    #[actix_web::test]
+    async fn test_sanitize_svg() {
+        let srv = test::init_service(
+            App::new()
+                .service(Embed::new("/sanitized", &Assets).sanitize_svg(true))
+                .service(Embed::new("/original", &Assets)),
+        )
+        .await;
+
+        let req = TestRequest::get()
+            .uri("/sanitized/icons/badge.svg")
+            .to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let etag = resp.headers().get(header::ETAG).unwrap().to_str().unwrap();
+        assert!(etag.ends_with("-sanitized\""), "{}", etag);
+        let body = test::read_body(resp).await;
+        let svg = std::str::from_utf8(&body).unwrap();
+        for removed in [
+            "script",
+            "alert",
+            "foreignObject",
+            "iframe",
+            "onload",
+            "onclick",
+        ] {
+            assert!(!svg.contains(removed), "{}: {}", removed, svg);
+        }
+        assert!(svg.contains("<a><circle cx=\"8\" cy=\"8\" r=\"8\" fill=\"#4a90d9\"/></a>"));
+        assert!(svg.contains("<a href=\"https://example.com/\"><path d=\"M4 8h8\"/></a>"));
+        assert!(svg.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<svg "));
+
+        let req = TestRequest::get()
+            .uri("/original/icons/badge.svg")
+            .to_request();
+        let body = test::call_and_read_body(&srv, req).await;
+        assert_eq!(&body[..], &Assets::get("icons/badge.svg").unwrap().data[..]);
+    }
+
+    #[cfg(debug_assertions)]
+    #[actix_web::test]
+    async fn test_rules_endpoint() {
+        let srv = test::init_service(
+            App::new().service(
+                Embed::new("/static", &Assets)
+                    .redirect("old.css", "assets/index.css")
+                    .header(["assets/**"], header::CACHE_CONTROL, "max-age=60")
+                    .rules_endpoint("/__embed/rules/"),
+            ),
+        )
+        .await;
+
+        let req = TestRequest::get().uri("/static/__embed/rules").to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get(header::CONTENT_TYPE).unwrap(),
+            "application/json"
+        );
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["mount_path"], "/static");
+        assert_eq!(body["rules_endpoint"], "__embed/rules");
+        assert_eq!(body["redirects"]["old.css"], "assets/index.css");
+
+        let req = TestRequest::get()
+            .uri("/static/__embed/rules")
+            .insert_header((header::ACCEPT, "text/html,application/xhtml+xml,*/*;q=0.8"))
+            .to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(
+            resp.headers().get(header::CONTENT_TYPE).unwrap(),
+            "text/html; charset=utf-8"
+        );
+        let body = test::read_body(resp).await;
+        let body = std::str::from_utf8(&body).unwrap();
+        assert!(body.contains("<th>redirects</th>"));
+        assert!(body.contains("max-age=60"));
+
+        let req = TestRequest::post()
+            .uri("/static/__embed/rules")
+            .to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+    }
+
+    #[actix_web::test]
+    async fn test_listing_endpoint() {
+        use serde_json::Value;
+
+        let srv = test::init_service(
+            App::new().service(
+                Embed::new("/static", &Assets)
+                    .listing_endpoint("/__listing/")
+                    .exclude(["favicon.ico"]),
+            ),
+        )
+        .await;
+        let list = |query: &str| {
+            let req = TestRequest::get()
+                .uri(&format!("/static/__listing?{}", query))
+                .to_request();
+            test::call_service(&srv, req)
+        };
+        let json =
+            |resp| async { serde_json::from_slice::<Value>(&test::read_body(resp).await).unwrap() };
+        let names = |listing: &Value| {
+            listing["entries"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|entry| entry["name"].as_str().unwrap().to_owned())
+                .collect::<Vec<_>>()
+        };
+
+        let all = json(list("limit=1000").await).await;
+        let all_names = names(&all);
+        assert_eq!(all["total"], all_names.len());
+        assert!(all_names.contains(&"index.html".to_owned()));
+        assert!(!all_names.contains(&"favicon.ico".to_owned()));
+        let mut sorted = all_names.clone();
+        sorted.sort();
+        assert_eq!(all_names, sorted);
+
+        // pages neither overlap nor miss entries
+        let mut paged = Vec::new();
+        let mut offset = Some(0);
+        while let Some(current) = offset {
+            let page = json(list(&format!("limit=2&offset={}", current)).await).await;
+            paged.extend(names(&page));
+            offset = page["next_offset"].as_u64();
+        }
+        assert_eq!(paged, all_names);
+
+        let images = json(list("dir=images&sort=size&order=desc").await).await;
+        let sizes = images["entries"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|entry| entry["size"].as_u64().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(sizes.len(), 4);
+        assert!(sizes.windows(2).all(|pair| pair[0] >= pair[1]));
+
+        // directories have no size, so they go last
+        let root = json(list("sort=size&limit=1000").await).await;
+        let kinds = root["entries"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|entry| entry["kind"].as_str().unwrap())
+            .collect::<Vec<_>>();
+        assert!(kinds.ends_with(&["dir"]));
+        assert!(kinds.windows(2).all(|pair| pair != ["dir", "file"]));
+
+        assert_eq!(list("dir=missing").await.status(), StatusCode::NOT_FOUND);
+        assert_eq!(list("sort=color").await.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_web::test]
+    async fn test_listing_availability() {
+        use std::cell::Cell;
+        use std::rc::Rc;
+
+        let launch = std::time::SystemTime::now() + Duration::from_secs(3600);
+        let now = Rc::new(Cell::new(launch - Duration::from_secs(60)));
+        let clock = now.clone();
+        let srv = test::init_service(
+            App::new().service(
+                Embed::new("/static", &Assets)
+                    .listing_endpoint("/__listing/")
+                    .available("fonts/**", launch..)
+                    .clock(move || clock.get()),
+            ),
+        )
+        .await;
+        let list = |query: &str| {
+            let req = TestRequest::get()
+                .uri(&format!("/static/__listing?{}", query))
+                .to_request();
+            test::call_service(&srv, req)
+        };
+
+        let body = test::read_body(list("").await).await;
+        assert!(!std::str::from_utf8(&body).unwrap().contains(r#""fonts""#));
+        assert_eq!(list("dir=fonts").await.status(), StatusCode::NOT_FOUND);
+
+        // cached listings change once the window opens
+        now.set(launch);
+        let body = test::read_body(list("").await).await;
+        assert!(std::str::from_utf8(&body).unwrap().contains(r#""fonts""#));
+        assert_eq!(list("dir=fonts").await.status(), StatusCode::OK);
+    }
+
+    #[cfg(feature = "compress-gzip")]
+    #[actix_web::test]
+    async fn test_compress_endpoints() {
+        use std::io::Read;
+
+        use crate::Encoding;
+
+        let srv = test::init_service(
+            App::new().service(
+                Embed::new("/static", &Assets)
+                    .listing_endpoint("__listing")
+                    .compress_endpoints(Encoding::Gzip, 6),
+            ),
+        )
+        .await;
+        let list = |accept: &'static str| {
+            let req = TestRequest::get()
+                .uri("/static/__listing?limit=1000")
+                .insert_header((header::ACCEPT_ENCODING, accept))
+                .to_request();
+            test::call_service(&srv, req)
+        };
+
+        let plain = list("identity").await;
+        assert!(plain.headers().get(header::CONTENT_ENCODING).is_none());
+        let plain_etag = plain.headers().get(header::ETAG).unwrap().clone();
+        let plain = test::read_body(plain).await;
+
+        let gzip = list("gzip").await;
+        assert_eq!(
+            gzip.headers().get(header::CONTENT_ENCODING).unwrap(),
+            "gzip"
+        );
+        assert_eq!(gzip.headers().get(header::VARY).unwrap(), "Accept-Encoding");
+        let gzip_etag = gzip.headers().get(header::ETAG).unwrap().clone();
+        assert_ne!(gzip_etag, plain_etag);
+        let compressed = test::read_body(gzip).await;
+        assert!(compressed.len() < plain.len());
+        let mut decoded = Vec::new();
+        flate2::read::GzDecoder::new(&compressed[..])
+            .read_to_end(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, plain);
+
+        // the cached variant is revalidated with its own entity tag
+        let req = TestRequest::get()
+            .uri("/static/__listing?limit=1000")
+            .insert_header((header::ACCEPT_ENCODING, "gzip"))
+            .insert_header((header::IF_NONE_MATCH, gzip_etag))
+            .to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+    }
+
+    #[actix_web::test]
+    async fn test_cors() {
+        use crate::Severity;
+        use actix_web::http::Method;
+
+        let srv = test::init_service(
+            App::new().service(
+                Embed::new("/", &Assets)
+                    .cors(["https://intranet.example.com/"])
+                    .cors_credentials(true)
+                    .cors_private_network(true),
+            ),
+        )
+        .await;
+
+        let req = TestRequest::get()
+            .uri("/index.html")
+            .insert_header((header::ORIGIN, "https://intranet.example.com"))
+            .to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let headers = resp.headers();
+        assert_eq!(
+            headers.get(header::ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(),
+            "https://intranet.example.com"
+        );
+        assert_eq!(
+            headers
+                .get(header::ACCESS_CONTROL_ALLOW_CREDENTIALS)
+                .unwrap(),
+            "true"
+        );
+        assert!(headers
+            .get_all(header::VARY)
+            .any(|value| value.to_str().unwrap().contains("Origin")));
+
+        let req = TestRequest::get()
+            .uri("/index.html")
+            .insert_header((header::ORIGIN, "https://evil.example.com"))
+            .to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert!(!resp
+            .headers()
+            .contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+        assert_eq!(resp.headers().get(header::VARY).unwrap(), "Origin");
+
+        let req = TestRequest::get().uri("/index.html").to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.headers().get(header::VARY).unwrap(), "Origin");
+
+        let req = TestRequest::default()
+            .method(Method::OPTIONS)
+            .uri("/index.html")
+            .insert_header((header::ORIGIN, "https://intranet.example.com"))
+            .insert_header((header::ACCESS_CONTROL_REQUEST_METHOD, "GET"))
+            .insert_header(("access-control-request-private-network", "true"))
+            .to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        let headers = resp.headers();
+        assert_eq!(
+            headers.get(header::ACCESS_CONTROL_ALLOW_METHODS).unwrap(),
+            "GET, HEAD"
+        );
+        assert_eq!(
+            headers.get("access-control-allow-private-network").unwrap(),
+            "true"
+        );
+        assert_eq!(
+            headers
+                .get(header::ACCESS_CONTROL_ALLOW_CREDENTIALS)
+                .unwrap(),
+            "true"
+        );
+
+        // credentials are never allowed for origins only matching `*`
+        let embed = Embed::new("/", &Assets).cors(["*"]).cors_credentials(true);
+        let issues = embed.issues();
+        assert_eq!(issues[0].severity, Severity::Error);
+        assert_eq!(issues[0].option, "cors");
+        let srv = test::init_service(App::new().service(embed)).await;
+        let req = TestRequest::get()
+            .uri("/index.html")
+            .insert_header((header::ORIGIN, "https://evil.example"))
+            .to_request();
+        let resp = test::call_service(&srv, req).await;
+        let headers = resp.headers();
+        assert_eq!(
+            headers.get(header::ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(),
+            "*"
+        );
+        assert!(!headers.contains_key(header::ACCESS_CONTROL_ALLOW_CREDENTIALS));
+
+        // any origin, without credentials nor private network access
+        let srv =
+            test::init_service(App::new().service(Embed::new("/", &Assets).cors(["*"]))).await;
+        let req = TestRequest::default()
+            .method(Method::OPTIONS)
+            .uri("/index.html")
+            .insert_header((header::ORIGIN, "https://public.example.com"))
+            .insert_header((header::ACCESS_CONTROL_REQUEST_METHOD, "GET"))
+            .insert_header(("access-control-request-private-network", "true"))
+            .to_request();
+        let resp = test::call_service(&srv, req).await;
+        let headers = resp.headers();
+        assert_eq!(
+            headers.get(header::ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(),
+            "*"
+        );
+        assert!(!headers.contains_key(header::ACCESS_CONTROL_ALLOW_CREDENTIALS));
+        assert!(!headers.contains_key("access-control-allow-private-network"));
+
+        // preflights are only answered for paths which are served
+        let srv = test::init_service(
+            App::new().service(
+                Embed::new("/", &Assets)
+                    .cors(["*"])
+                    .clean_urls(true)
+                    .exclude(["index.html"]),
+            ),
+        )
+        .await;
+        for uri in ["/missing.html", "/index.html", "/index"] {
+            let req = TestRequest::default()
+                .method(Method::OPTIONS)
+                .uri(uri)
+                .insert_header((header::ORIGIN, "https://public.example.com"))
+                .insert_header((header::ACCESS_CONTROL_REQUEST_METHOD, "GET"))
+                .to_request();
+            let resp = test::call_service(&srv, req).await;
+            assert_eq!(resp.status(), StatusCode::NOT_FOUND, "{}", uri);
+            assert!(!resp
+                .headers()
+                .contains_key(header::ACCESS_CONTROL_ALLOW_METHODS));
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_content_language_from_path() {
+        let srv = test::init_service(
+            App::new().service(Embed::new("/", &Assets).content_language_from_path(true)),
+        )
+        .await;
+        let content_language = |uri: &'static str| {
+            let srv = &srv;
+            async move {
+                let req = TestRequest::get().uri(uri).to_request();
+                let resp = test::call_service(srv, req).await;
+                assert_eq!(resp.status(), StatusCode::OK);
+                assert!(resp.headers().get(header::VARY).is_none());
+                resp.headers()
+                    .get(header::CONTENT_LANGUAGE)
+                    .map(|value| value.to_str().unwrap().to_owned())
+            }
+        };
+        assert_eq!(content_language("/de/index.html").await.unwrap(), "de");
+        assert_eq!(content_language("/en/index.html").await.unwrap(), "en");
+        assert_eq!(content_language("/assets/index.css").await, None);
+        assert_eq!(content_language("/index.html").await, None);
+
+        let srv = test::init_service(App::new().service(Embed::new("/", &Assets))).await;
+        let req = TestRequest::get().uri("/de/index.html").to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert!(resp.headers().get(header::CONTENT_LANGUAGE).is_none());
+    }
+
+    #[actix_web::test]
+    async fn test_content_location() {
+        let embed = |enabled| {
+            Embed::new("/site", &Assets)
+                .localized_index(["en/index.html", "de/index.html"], "en/index.html")
+                .responsive_images(true)
+                .content_location(enabled)
+        };
+        let srv = test::init_service(App::new().service(embed(true))).await;
+        let content_location = |uri: &'static str, name, value| {
+            let srv = &srv;
+            async move {
+                let req = TestRequest::get()
+                    .uri(uri)
+                    .insert_header((name, value))
+                    .to_request();
+                let resp = test::call_service(srv, req).await;
+                assert_eq!(resp.status(), StatusCode::OK);
+                resp.headers()
+                    .get(header::CONTENT_LOCATION)
+                    .map(|value| value.to_str().unwrap().to_owned())
+            }
+        };
+        let de = content_location("/site/", header::ACCEPT_LANGUAGE, "de").await;
+        assert_eq!(de.unwrap(), "/site/de/index.html");
+        let en = content_location("/site/", header::ACCEPT_LANGUAGE, "fr").await;
+        assert_eq!(en.unwrap(), "/site/en/index.html");
+        let hero = content_location(
+            "/site/images/hero.png",
+            header::HeaderName::from_static("sec-ch-dpr"),
+            "2",
+        )
+        .await;
+        assert_eq!(hero.unwrap(), "/site/images/hero@2x.png");
+
+        // files selected by their path have no other URL
+        let variant = content_location("/site/images/hero@2x.png", header::ACCEPT, "*/*").await;
+        assert_eq!(variant, None);
+        let file = content_location("/site/de/index.html", header::ACCEPT_LANGUAGE, "en").await;
+        assert_eq!(file, None);
+
+        let srv = test::init_service(App::new().service(embed(false))).await;
+        let req = TestRequest::get().uri("/site/").to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert!(resp.headers().get(header::CONTENT_LOCATION).is_none());
+    }
+
+    #[actix_web::test]
+    async fn test_circuit_breaker() {
+        use futures_core::future::LocalBoxFuture;
+
+        use crate::{CircuitBreaker, FallbackHandler};
+
+        let calls = Rc::new(RefCell::new(0));
+        let up = Rc::new(RefCell::new(false));
+        let upstream = {
+            let (calls, up) = (calls.clone(), up.clone());
+            move |_: &HttpRequest| {
+                *calls.borrow_mut() += 1;
+                match *up.borrow() {
+                    true => HttpResponse::Ok().body("upstream"),
+                    false => HttpResponse::BadGateway().finish(),
+                }
+            }
+        };
+        let local = |_: &HttpRequest| HttpResponse::NotFound().body("local");
+        let breaker = CircuitBreaker::new(upstream, local)
+            .failure_threshold(2)
+            .open_for(Duration::from_millis(50));
+        let srv = test::init_service(
+            App::new().service(Embed::new("/", &Assets).fallback_handler(breaker.clone())),
+        )
+        .await;
+        let miss = || async {
+            let req = TestRequest::get().uri("/missing").to_request();
+            let resp = test::call_service(&srv, req).await;
+            (resp.status(), test::read_body(resp).await)
+        };
+
+        // failures are answered locally, and open the circuit at the threshold
+        assert_eq!(miss().await, (StatusCode::NOT_FOUND, Bytes::from("local")));
+        assert!(!breaker.is_open());
+        assert_eq!(miss().await.1, "local");
+        assert!(breaker.is_open());
+        assert_eq!(*calls.borrow(), 2);
+        assert_eq!(miss().await.1, "local");
+        assert_eq!(*calls.borrow(), 2);
+
+        // a failed probe opens the circuit again
+        actix_web::rt::time::sleep(Duration::from_millis(60)).await;
+        assert_eq!(miss().await.1, "local");
+        assert_eq!(*calls.borrow(), 3);
+        assert!(breaker.is_open());
+
+        // a successful probe closes it
+        actix_web::rt::time::sleep(Duration::from_millis(60)).await;
+        *up.borrow_mut() = true;
+        assert_eq!(miss().await, (StatusCode::OK, Bytes::from("upstream")));
+        assert!(!breaker.is_open());
+        assert_eq!(miss().await.1, "upstream");
+        assert_eq!(*calls.borrow(), 5);
+
+        // stalled upstreams time out, and count as failures
+        #[derive(Clone)]
+        struct Stalled;
+
+        impl FallbackHandler for Stalled {
+            fn execute(&self, _: &HttpRequest) -> HttpResponse {
+                HttpResponse::BadGateway().finish()
+            }
+
+            fn execute_async(&self, _: HttpRequest) -> LocalBoxFuture<'static, HttpResponse> {
+                Box::pin(async {
+                    actix_web::rt::time::sleep(Duration::from_secs(60)).await;
+                    HttpResponse::Ok().finish()
+                })
+            }
+        }
+
+        let breaker = CircuitBreaker::new(Stalled, local)
+            .failure_threshold(1)
+            .timeout(Duration::from_millis(10));
+        let srv = test::init_service(
+            App::new().service(Embed::new("/", &Assets).fallback_handler(breaker.clone())),
+        )
+        .await;
+        let req = TestRequest::get().uri("/missing").to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(test::read_body(resp).await, "local");
+        assert!(breaker.is_open());
+    }
+
+    #[actix_web::test]
+    async fn test_issues() {
+        use crate::Severity;
+
+        let issues = Embed::new("/", &Assets)
+            .header(["**"], "x-build", "line\nbreak")
+            .throttle("**", 0)
+            .index_file("missing.html")
+            .try_build()
+            .unwrap_err();
+        let found = issues
+            .iter()
+            .map(|issue| (issue.severity, issue.option))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            [
+                (Severity::Error, "header_rules"),
+                (Severity::Error, "throttle"),
+                (Severity::Warning, "index_file"),
+            ]
+        );
+
+        // warnings alone do not fail the build
+        let embed = Embed::new("/", &Assets)
+            .header(["**/*.html"], "x-frame-options", "DENY")
+            .header(["en/**"], "x-frame-options", "SAMEORIGIN")
+            .header(["**"], "x-robots-tag", "noindex")
+            .throttle("images/**", 1 << 20)
+            .throttle("**/*.png", 2 << 20)
+            .archive("assets.tar", "missing")
+            .localized_index(["en/index.html", "fr/index.html"], "en/index.html");
+        let issues = embed.issues();
+        assert!(issues
+            .iter()
+            .all(|issue| issue.severity == Severity::Warning));
+        let options = issues.iter().map(|issue| issue.option).collect::<Vec<_>>();
+        assert_eq!(
+            options,
+            ["localized_index", "archives", "header_rules", "throttles"]
+        );
+        assert!(issues[2].message.contains("x-frame-options"));
+        assert!(embed.try_build().is_ok());
+
+        let issues = Embed::new("/", &Assets)
+            .redirect("old.html", "missing.html")
+            .redirect("new", "index.html?from=old")
+            .redirect("docs", "en/")
+            .package_export("lodash", "vendor/lodash/missing.js")
+            .issues();
+        let options = issues.iter().map(|issue| issue.option).collect::<Vec<_>>();
+        assert_eq!(options, ["redirects", "package_exports"]);
+        assert!(issues[0].message.contains("missing.html"));
+
+        assert!(Embed::new("/", &Assets)
+            .index_file("index.html")
+            .issues()
+            .is_empty());
+    }
+
+    #[actix_web::test]
+    async fn test_claim_only_known_paths() {
+        let app = |claim_only_known_paths| {
+            App::new()
+                .service(
+                    Embed::new("/", &Assets)
+                        .index_file("index.html")
+                        .clean_urls(true)
+                        .listing_endpoint("__listing")
+                        .content_addressed(true)
+                        .claim_only_known_paths(claim_only_known_paths),
+                )
+                .route("/api/hello", web::get().to(|| async { "hello" }))
+        };
+        let url = Embed::new("/", &Assets)
+            .content_addresses()
+            .get("index.html")
+            .unwrap()
+            .to_owned();
+        let srv = test::init_service(app(true)).await;
+        let req = TestRequest::get().uri(&url).to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK, "{}", url);
+        for (uri, expected) in [
+            ("/", StatusCode::OK),
+            ("/index.html", StatusCode::OK),
+            // segments are known by their decoded names
+            ("/ind%65x.html", StatusCode::OK),
+            ("/index", StatusCode::OK),
+            ("/de/index.html", StatusCode::OK),
+            ("/de/missing.html", StatusCode::NOT_FOUND),
+            ("/__listing", StatusCode::OK),
+            ("/api/hello", StatusCode::OK),
+        ] {
+            let req = TestRequest::get().uri(uri).to_request();
+            let resp = test::call_service(&srv, req).await;
+            assert_eq!(resp.status(), expected, "{}", uri);
+        }
+        for uri in ["/api/hello", "/%61pi/hello"] {
+            let req = TestRequest::get().uri(uri).to_request();
+            assert_eq!(
+                test::call_and_read_body(&srv, req).await,
+                "hello",
+                "{}",
+                uri
+            );
+        }
+
+        let srv = test::init_service(app(false)).await;
+        let req = TestRequest::get().uri("/api/hello").to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        let issues = Embed::new("/", &Assets)
+            .spa_index("index.html")
+            .claim_only_known_paths(true)
+            .issues();
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].option, "claim_only_known_paths");
+    }
+
+    #[actix_web::test]
+    async fn test_ranges() {
+        use actix_web::http::header::HttpDate;
+        use actix_web::http::Method;
+
+        let srv = test::init_service(App::new().service(Embed::new("/", &Assets))).await;
+        let file = Assets::get("index.html").unwrap();
+        let len = file.data.len();
+        let get = |headers: Vec<(header::HeaderName, String)>| {
+            let mut req = TestRequest::get().uri("/index.html");
+            for header in headers {
+                req = req.insert_header(header);
+            }
+            test::call_service(&srv, req.to_request())
+        };
+
+        let resp = get(vec![]).await;
+        let etag = resp
+            .headers()
+            .get(header::ETAG)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_owned();
+        let last_modified = resp.headers().get(header::LAST_MODIFIED).unwrap();
+        let last_modified = last_modified.to_str().unwrap().to_owned();
+
+        let resp = get(vec![(header::RANGE, "bytes=0-9".to_owned())]).await;
+        assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(
+            resp.headers()
+                .get(header::CONTENT_RANGE)
+                .unwrap()
+                .to_str()
+                .unwrap(),
+            format!("bytes 0-9/{}", len)
+        );
+        assert_eq!(test::read_body(resp).await, file.data[..10]);
+
+        let resp = get(vec![(header::RANGE, "bytes=-5".to_owned())]).await;
+        assert_eq!(test::read_body(resp).await, file.data[len - 5..]);
+
+        let resp = get(vec![(header::RANGE, "bytes=0-1,4-5".to_owned())]).await;
+        assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+        let content_type = resp.headers().get(header::CONTENT_TYPE).unwrap();
+        assert!(content_type
+            .to_str()
+            .unwrap()
+            .starts_with("multipart/byteranges; boundary="));
+        let body = test::read_body(resp).await;
+        let body = String::from_utf8_lossy(&body);
+        assert!(body.contains(&format!("Content-Range: bytes 4-5/{}", len)));
+        assert!(body.contains("Content-Type: text/html"));
+
+        let resp = get(vec![(header::RANGE, format!("bytes={}-", len))]).await;
+        assert_eq!(resp.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(
+            resp.headers()
+                .get(header::CONTENT_RANGE)
+                .unwrap()
+                .to_str()
+                .unwrap(),
+            format!("bytes */{}", len)
+        );
+
+        // If-Range with the current entity tag or date honors the range, otherwise not
+        for (if_range, status) in [
+            (etag.clone(), StatusCode::PARTIAL_CONTENT),
+            (last_modified.clone(), StatusCode::PARTIAL_CONTENT),
+            ("\"outdated\"".to_owned(), StatusCode::OK),
+            ("Sun, 06 Nov 1994 08:49:37 GMT".to_owned(), StatusCode::OK),
+        ] {
+            let resp = get(vec![
+                (header::RANGE, "bytes=0-9".to_owned()),
+                (header::IF_RANGE, if_range.clone()),
+            ])
+            .await;
+            assert_eq!(resp.status(), status, "{}", if_range);
+        }
+
+        let resp = get(vec![(header::IF_MODIFIED_SINCE, last_modified.clone())]).await;
+        assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+        let earlier = HttpDate::from(std::time::SystemTime::UNIX_EPOCH).to_string();
+        let resp = get(vec![(header::IF_MODIFIED_SINCE, earlier.clone())]).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        // If-None-Match takes precedence
+        let resp = get(vec![
+            (header::IF_MODIFIED_SINCE, last_modified.clone()),
+            (header::IF_NONE_MATCH, "\"outdated\"".to_owned()),
+        ])
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let resp = get(vec![(header::IF_UNMODIFIED_SINCE, earlier)]).await;
+        assert_eq!(resp.status(), StatusCode::PRECONDITION_FAILED);
+
+        let req = TestRequest::default()
+            .method(Method::HEAD)
+            .uri("/index.html")
+            .insert_header((header::RANGE, "bytes=0-9"))
+            .to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get(header::ETAG).unwrap().to_str().unwrap(),
+            etag
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_use_precompressed() {
+        use crate::Encoding;
+
+        let srv = test::init_service(
+            App::new()
+                .service(
+                    Embed::new("/br", &Assets)
+                        .use_precompressed(&[Encoding::Brotli, Encoding::Gzip]),
+                )
+                .service(Embed::new("/gzip", &Assets).use_precompressed(&[Encoding::Gzip]))
+                .service(Embed::new("/off", &Assets)),
+        )
+        .await;
+        let file = |path: &str| Bytes::from(Assets::get(path).unwrap().data.into_owned());
+        let get = |uri: &'static str, accept_encoding: Option<&'static str>| {
+            let srv = &srv;
+            async move {
+                let mut req = TestRequest::get().uri(uri);
+                if let Some(accept_encoding) = accept_encoding {
+                    req = req.insert_header((header::ACCEPT_ENCODING, accept_encoding));
+                }
+                let resp = test::call_service(srv, req.to_request()).await;
+                assert_eq!(resp.status(), StatusCode::OK);
+                let headers = resp.headers().clone();
+                (headers, test::read_body(resp).await)
+            }
+        };
+
+        let (headers, body) = get("/br/precompressed/app.js", Some("gzip, br")).await;
+        assert_eq!(body, file("precompressed/app.js.br"));
+        assert_eq!(headers.get(header::CONTENT_ENCODING).unwrap(), "br");
+        assert_eq!(
+            headers.get(header::CONTENT_TYPE).unwrap(),
+            "text/javascript"
+        );
+        assert_eq!(headers.get(header::VARY).unwrap(), "Accept-Encoding");
+        let br_etag = headers.get(header::ETAG).unwrap().clone();
+
+        let (headers, body) = get("/gzip/precompressed/app.js", Some("gzip, br")).await;
+        assert_eq!(body, file("precompressed/app.js.gz"));
+        assert_eq!(headers.get(header::CONTENT_ENCODING).unwrap(), "gzip");
+        assert_ne!(headers.get(header::ETAG).unwrap(), br_etag);
+
+        let (headers, body) = get("/br/precompressed/app.js", Some("gzip;q=1, br;q=0.5")).await;
+        assert_eq!(body, file("precompressed/app.js.gz"));
+        assert_eq!(headers.get(header::CONTENT_ENCODING).unwrap(), "gzip");
+
+        let (headers, body) = get("/br/precompressed/app.js", None).await;
+        assert_eq!(body, file("precompressed/app.js"));
+        assert!(headers.get(header::CONTENT_ENCODING).is_none());
+        assert_eq!(headers.get(header::VARY).unwrap(), "Accept-Encoding");
+
+        // variants remain available at their own paths
+        let (_, body) = get("/br/precompressed/app.js.gz", Some("br")).await;
+        assert_eq!(body, file("precompressed/app.js.gz"));
+
+        let (headers, body) = get("/off/precompressed/app.js", Some("br")).await;
+        assert_eq!(body, file("precompressed/app.js"));
+        assert!(headers.get(header::CONTENT_ENCODING).is_none());
+    }
+
+    #[actix_web::test]
+    async fn test_ranges_of_encodings() {
+        use crate::{AcceptRanges, Encoding, OuterCompression};
+
+        let srv = test::init_service(
+            App::new()
+                .service(
+                    Embed::new("/static", &Assets)
+                        .use_precompressed(&[Encoding::Brotli])
+                        .accept_ranges(AcceptRanges::Bytes),
+                )
+                .service(
+                    Embed::new("/outer", &Assets)
+                        .use_precompressed(&[Encoding::Brotli])
+                        .accept_ranges(AcceptRanges::Bytes)
+                        .outer_compression(OuterCompression::Allow),
+                ),
+        )
+        .await;
+        let plain = Assets::get("precompressed/app.js").unwrap().data;
+        let brotli = Assets::get("precompressed/app.js.br").unwrap().data;
+        let get = |uri: &'static str, headers: Vec<(header::HeaderName, String)>| {
+            let mut req = TestRequest::get().uri(uri);
+            for header in headers {
+                req = req.insert_header(header);
+            }
+            test::call_service(&srv, req.to_request())
+        };
+        let header = |resp: &actix_web::dev::ServiceResponse, name| {
+            resp.headers()
+                .get(name)
+                .map(|value: &header::HeaderValue| value.to_str().unwrap().to_owned())
+        };
+        let br = (header::ACCEPT_ENCODING, "br".to_owned());
+        let range = (header::RANGE, "bytes=0-9".to_owned());
+        let uri = "/static/precompressed/app.js";
+
+        // ranges are taken from the selected representation
+        let resp = get(uri, vec![br.clone(), range.clone()]).await;
+        assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(header(&resp, header::CONTENT_ENCODING).unwrap(), "br");
+        assert_eq!(header(&resp, header::VARY).unwrap(), "Accept-Encoding");
+        assert_eq!(
+            header(&resp, header::CONTENT_RANGE).unwrap(),
+            format!("bytes 0-9/{}", brotli.len())
+        );
+        let br_etag = header(&resp, header::ETAG).unwrap();
+        assert_eq!(test::read_body(resp).await, brotli[..10]);
+
+        let resp = get(uri, vec![range.clone()]).await;
+        assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+        assert!(resp.headers().get(header::CONTENT_ENCODING).is_none());
+        assert_eq!(
+            header(&resp, header::CONTENT_RANGE).unwrap(),
+            format!("bytes 0-9/{}", plain.len())
+        );
+        let plain_etag = header(&resp, header::ETAG).unwrap();
+        assert_ne!(plain_etag, br_etag);
+        assert_eq!(test::read_body(resp).await, plain[..10]);
+
+        // an If-Range of another encoding yields the whole representation
+        let resp = get(
+            uri,
+            vec![br.clone(), range.clone(), (header::IF_RANGE, plain_etag)],
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(test::read_body(resp).await, brotli[..]);
+        let resp = get(
+            uri,
+            vec![br.clone(), range.clone(), (header::IF_RANGE, br_etag)],
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+
+        // unsatisfiable in the compressed length, and cached per encoding
+        let beyond = (header::RANGE, format!("bytes={}-", brotli.len()));
+        let resp = get(uri, vec![br.clone(), beyond]).await;
+        assert_eq!(resp.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(
+            header(&resp, header::CONTENT_RANGE).unwrap(),
+            format!("bytes */{}", brotli.len())
+        );
+        assert_eq!(header(&resp, header::VARY).unwrap(), "Accept-Encoding");
+
+        // uncompressed responses middleware may compress are sent whole
+        let outer = "/outer/precompressed/app.js";
+        let resp = get(outer, vec![range.clone()]).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(header(&resp, header::ACCEPT_RANGES).unwrap(), "none");
+        let resp = get(outer, vec![br, range]).await;
+        assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(header(&resp, header::ACCEPT_RANGES).unwrap(), "bytes");
+    }
+
+    #[actix_web::test]
+    async fn test_path_tolerance() {
+        use crate::PathTolerance;
+
+        let srv = test::init_service(
+            App::new()
+                .service(Embed::new("/trim", &Assets).path_tolerance(PathTolerance::Trim))
+                .service(Embed::new("/redirect", &Assets).path_tolerance(PathTolerance::Redirect))
+                .service(Embed::new("/strict", &Assets)),
+        )
+        .await;
+
+        for uri in [
+            "/trim/index.html.",
+            "/trim/index.html%20",
+            "/trim/index.html.%20.",
+        ] {
+            let req = TestRequest::get().uri(uri).to_request();
+            let resp = test::call_service(&srv, req).await;
+            assert_eq!(resp.status(), StatusCode::OK, "{}", uri);
+        }
+
+        let req = TestRequest::get()
+            .uri("/redirect/index.html.?v=1")
+            .to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::MOVED_PERMANENTLY);
+        assert_eq!(
+            resp.headers().get(header::LOCATION).unwrap(),
+            "/redirect/index.html?v=1"
+        );
+
+        let req = TestRequest::get().uri("/redirect/index.html").to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let req = TestRequest::get().uri("/strict/index.html.").to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        // dot-segments are not names with trailing dots
+        for uri in ["/redirect/assets/..", "/redirect/..", "/redirect/assets/."] {
+            let req = TestRequest::get().uri(uri).to_request();
+            let resp = test::call_service(&srv, req).await;
+            assert_ne!(resp.status(), StatusCode::MOVED_PERMANENTLY, "{}", uri);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_url_constants() {
+        use crate::codegen::UrlConstants;
+
+        let constants = UrlConstants::new("testdata/", "static/")
+            .file("assets/index.css")
+            .file("/favicon.ico");
+        let source = constants.generate().unwrap();
+        let addresses = Embed::new("/static", &Assets).content_addresses();
+        assert!(source.contains(&format!(
+            "pub const ASSETS_INDEX_CSS_URL: &str = {:?};",
+            addresses.get("assets/index.css").unwrap()
+        )));
+        assert!(source.contains(&format!(
+            "pub const FAVICON_ICO_URL: &str = {:?};",
+            addresses.get("favicon.ico").unwrap()
+        )));
+
+        let missing = UrlConstants::new("testdata/", "/").file("missing.js");
+        assert!(missing.generate().is_err());
+        let duplicate = UrlConstants::new("testdata/", "/")
+            .file("index.html")
+            .file("index-html");
+        assert!(duplicate.generate().is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_spa_index() {
+        let srv = test::init_service(
+            App::new().service(
+                Embed::new("/", &Assets)
+                    .index_file("index.html")
+                    .spa_index("index.html")
+                    .directory_index("index.html")
+                    .redirect("old", "index.html")
+                    .header(["assets/**"], "cache-control", "public, immutable")
+                    .header(["index.html"], "cache-control", "no-cache"),
+            ),
+        )
+        .await;
+        let index = Bytes::from(Assets::get("index.html").unwrap().data.into_owned());
+
+        let req = TestRequest::get().uri("/settings/profile").to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get(header::CACHE_CONTROL).unwrap(),
+            "no-cache"
+        );
+        assert_eq!(test::read_body(resp).await, index);
+
+        let req = TestRequest::get().uri("/en/").to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = test::read_body(resp).await;
+        assert_eq!(body, Assets::get("en/index.html").unwrap().data.as_ref());
+
+        let req = TestRequest::get().uri("/assets/index.css").to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(
+            resp.headers().get(header::CACHE_CONTROL).unwrap(),
+            "public, immutable"
+        );
+
+        let req = TestRequest::get().uri("/assets/missing.js").to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        let req = TestRequest::get().uri("/old").to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::MOVED_PERMANENTLY);
+    }
+
+    #[actix_web::test]
+    async fn test_plugin() {
+        use crate::{EmbedPlugin, Value};
+
+        struct NoSniff;
+
+        impl EmbedPlugin for NoSniff {
+            fn apply(self, options: EmbedOptions) -> EmbedOptions {
+                options
+                    .header(["**"], "x-content-type-options", "nosniff")
+                    .exclude(["private/**"])
+            }
+
+            fn name(&self) -> &'static str {
+                "nosniff"
+            }
+        }
+
+        let embed = Embed::new("/", &Assets)
+            .with(NoSniff)
+            .with(|options: EmbedOptions| options.index_file("index.html"));
+        let plugins = embed.describe().get("plugins").cloned().unwrap();
+        let Value::List(plugins) = plugins else {
+            panic!("plugins are not a list");
+        };
+        assert_eq!(plugins.len(), 2);
+        assert_eq!(plugins[0], Value::from("nosniff"));
+        let srv = test::init_service(
+            App::new()
+                .service(embed)
+                .route("/private/key", web::get().to(HttpResponse::Forbidden)),
+        )
+        .await;
+
+        let req = TestRequest::get().uri("/").to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get(header::X_CONTENT_TYPE_OPTIONS).unwrap(),
+            "nosniff"
+        );
+
+        let req = TestRequest::get().uri("/private/key").to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[actix_web::test]
+    async fn test_content_sniffing() {
+        use crate::{Severity, SniffAction};
+
+        let embed = Embed::new("/", &Assets).content_sniffing(SniffAction::Report);
+        let issues = embed.issues();
+        let mut mislabeled = issues
+            .iter()
+            .filter(|issue| issue.option == "content_sniffing")
+            .inspect(|issue| assert_eq!(issue.severity, Severity::Warning))
+            .map(|issue| issue.message.as_str())
+            .collect::<Vec<_>>();
+        mislabeled.sort();
+        assert_eq!(
+            mislabeled,
+            [
+                "\"mislabeled/notes.txt\" is served as text/plain but its content is text/html",
+                "\"mislabeled/photo.jpg\" is served as image/jpeg but its content is image/png",
+            ]
+        );
+
+        let srv = test::init_service(
+            App::new()
+                .service(
+                    Embed::new("/report", &Assets)
+                        .folder("mislabeled")
+                        .content_sniffing(SniffAction::Report),
+                )
+                .service(
+                    Embed::new("/correct", &Assets)
+                        .folder("mislabeled")
+                        .content_sniffing(SniffAction::Correct),
+                ),
+        )
+        .await;
+        let content_type = |resp: &actix_web::dev::ServiceResponse| {
+            let value = resp.headers().get(header::CONTENT_TYPE).unwrap();
+            value.to_str().unwrap().to_owned()
+        };
+
+        let req = TestRequest::get().uri("/report/photo.jpg").to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(content_type(&resp), "image/jpeg");
+
+        let req = TestRequest::get().uri("/correct/photo.jpg").to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(content_type(&resp), "image/png");
+
+        let req = TestRequest::get().uri("/correct/notes.txt").to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(content_type(&resp), "text/html");
+    }
+
+    #[actix_web::test]
+    async fn test_hashed_filenames() {
+        let srv = test::init_service(App::new().service(
+            Embed::new("/", &Assets).hashed_filenames(true).header(
+                ["favicon.ico"],
+                "cache-control",
+                "max-age=3600",
+            ),
+        ))
+        .await;
+        let cache_control = |resp: &actix_web::dev::ServiceResponse| {
+            let value = resp.headers().get(header::CACHE_CONTROL);
+            value.map(|value| value.to_str().unwrap().to_owned())
+        };
+
+        let req = TestRequest::get()
+            .uri("/hashed/app.3f2a9c1d.js")
+            .to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            cache_control(&resp).as_deref(),
+            Some("public, max-age=31536000, immutable")
+        );
+
+        for uri in ["/assets/index.css", "/index.html"] {
+            let req = TestRequest::get().uri(uri).to_request();
+            let resp = test::call_service(&srv, req).await;
+            assert_eq!(cache_control(&resp).as_deref(), Some("no-cache"), "{}", uri);
+        }
+
+        // header rules setting the caching win
+        let req = TestRequest::get().uri("/favicon.ico").to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(cache_control(&resp).as_deref(), Some("max-age=3600"));
+
+        let hashed = crate::hashed_names::HashedNames::Default;
+        for (name, expected) in [
+            ("app.3f2a9c1d.js", true),
+            ("app.3f2a9c1d0b.chunk.js", true),
+            ("v1.2.3f2a9c1d.js", true),
+            ("app.3f2a9c1.js", false),
+            ("app.3F2A9C1D.js", false),
+            ("app.3f2a9c1dz.js", false),
+            ("app-3f2a9c1d.js", false),
+            ("3f2a9c1d/app.js", false),
+        ] {
+            assert_eq!(hashed.is_hashed(name), expected, "{}", name);
+        }
+
+        #[cfg(feature = "hashed-filename-pattern")]
+        {
+            let srv = test::init_service(
+                App::new().service(
+                    Embed::new("/images", &Assets)
+                        .folder("images")
+                        .hashed_filename_pattern(r"@\d+x\."),
+                ),
+            )
+            .await;
+            let req = TestRequest::get().uri("/images/hero@2x.png").to_request();
+            let resp = test::call_service(&srv, req).await;
+            assert_eq!(
+                cache_control(&resp).as_deref(),
+                Some("public, max-age=31536000, immutable")
+            );
+
+            let result = Embed::new("/", &Assets)
+                .hashed_filename_pattern("[0-9a-f")
+                .try_build();
+            assert!(result.is_err());
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_spa_builder() {
+        let srv = test::init_service(
+            App::new()
+                .service(
+                    crate::spa(&Assets)
+                        .index_file("./index.html")
+                        .static_resources_mount("/static")
+                        .static_resources_location("./assets")
+                        .exclude_prefixes(["/api"])
+                        .finish(),
+                )
+                .route("/api/users", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+        let index = Bytes::from(Assets::get("index.html").unwrap().data.into_owned());
+
+        let req = TestRequest::get().uri("/static/index.css").to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = test::read_body(resp).await;
+        assert_eq!(body, Assets::get("assets/index.css").unwrap().data.as_ref());
+
+        for uri in ["/", "/settings/profile", "/static/missing.js"] {
+            let req = TestRequest::get().uri(uri).to_request();
+            let resp = test::call_service(&srv, req).await;
+            assert_eq!(resp.status(), StatusCode::OK, "{}", uri);
+            assert_eq!(test::read_body(resp).await, index, "{}", uri);
+        }
+
+        let req = TestRequest::get().uri("/api/users").to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(test::read_body(resp).await.is_empty());
+
+        let req = TestRequest::get().uri("/api/missing").to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        // static resources at the root, with the index file served by the pipeline
+        let srv = test::init_service(
+            App::new()
+                .service(crate::spa(&Assets).exclude_prefixes(["/api"]).finish())
+                .route("/api/users", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+
+        let req = TestRequest::get().uri("/settings").to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(resp.headers().contains_key(header::ETAG));
+        assert_eq!(test::read_body(resp).await, index);
+
+        let req = TestRequest::get().uri("/assets/index.css").to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let req = TestRequest::get().uri("/api/users").to_request();
+        let resp = test::call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(test::read_body(resp).await.is_empty());
+    }
 }
@@ -0,0 +1,161 @@
+use std::cmp::Ordering;
+
+use actix_web::http::header;
+use actix_web::web::Query;
+use actix_web::{HttpRequest, HttpResponse};
+use serde::{Deserialize, Serialize};
+
+use crate::file_index::{Entry, FileIndex, KeyMap, Visibility};
+use crate::headers;
+
+/// The number of entries of a page if the request does not set a limit.
+const DEFAULT_LIMIT: usize = 100;
+
+/// The maximum number of entries of a page.
+const MAX_LIMIT: usize = 1000;
+
+/// The query of listing requests.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ListingQuery {
+    #[serde(default)]
+    dir: String,
+    #[serde(default)]
+    sort: SortKey,
+    #[serde(default)]
+    order: SortOrder,
+    #[serde(default)]
+    offset: usize,
+    limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum SortKey {
+    #[default]
+    Name,
+    Size,
+    Mtime,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+#[derive(Debug, Serialize)]
+struct Listing<'a> {
+    dir: &'a str,
+    /// The number of entries of the directory, on all pages.
+    total: usize,
+    offset: usize,
+    /// The offset of the next page, `None` on the last page.
+    next_offset: Option<usize>,
+    entries: &'a [ListedEntry<'a>],
+}
+
+#[derive(Debug, Serialize)]
+struct ListedEntry<'a> {
+    name: &'a str,
+    kind: &'static str,
+    /// The size of files in bytes, `None` for directories.
+    size: Option<u64>,
+    /// The modification time of files in seconds since the epoch, if embedded.
+    mtime: Option<u64>,
+}
+
+impl ListedEntry<'_> {
+    fn value(&self, key: SortKey) -> Option<u64> {
+        match key {
+            SortKey::Name => None,
+            SortKey::Size => self.size,
+            SortKey::Mtime => self.mtime,
+        }
+    }
+}
+
+/// Returns the response listing a page of the entries of the directory requested by `req`,
+/// see [Embed::listing_endpoint](crate::Embed::listing_endpoint).
+///
+/// Only the files shown by `visibility` are listed, and the directories containing any which
+/// are not excluded themselves.
+pub(crate) fn response<E>(
+    req: &HttpRequest,
+    index: &FileIndex,
+    keys: &KeyMap,
+    visibility: &Visibility<'_>,
+) -> HttpResponse
+where
+    E: 'static + rust_embed::RustEmbed,
+{
+    let query = match Query::<ListingQuery>::from_query(req.query_string()) {
+        Ok(query) => query.into_inner(),
+        Err(err) => return HttpResponse::BadRequest().body(format!("invalid query: {}", err)),
+    };
+    let dir = query.dir.trim_matches('/');
+    if !dir.is_empty() && index.visible_under(dir, visibility).next().is_none() {
+        return HttpResponse::NotFound().finish();
+    }
+    let path = |name: &str| match dir {
+        "" => name.to_owned(),
+        dir => format!("{}/{}", dir, name),
+    };
+
+    let mut entries = index
+        .visible_entries(dir, visibility)
+        .into_iter()
+        .filter(|entry| match entry {
+            Entry::File(_) => true,
+            Entry::Dir(name) => !visibility.excludes(&path(name)),
+        })
+        .map(|entry| match entry {
+            Entry::File(name) => {
+                let file = keys.get::<E>(&path(name)).map(|(_, file)| file);
+                ListedEntry {
+                    name,
+                    kind: "file",
+                    size: file.as_ref().map(|file| file.data.len() as u64),
+                    mtime: file.and_then(|file| file.metadata.last_modified()),
+                }
+            }
+            Entry::Dir(name) => ListedEntry {
+                name,
+                kind: "dir",
+                size: None,
+                mtime: None,
+            },
+        })
+        .collect::<Vec<_>>();
+    // entries without the value go last, names are unique and break ties
+    entries.sort_by(|a, b| {
+        let ordered = |ordering: Ordering| match query.order {
+            SortOrder::Asc => ordering,
+            SortOrder::Desc => ordering.reverse(),
+        };
+        match (query.sort, a.value(query.sort), b.value(query.sort)) {
+            (SortKey::Name, ..) => ordered(a.name.cmp(b.name)),
+            (_, Some(x), Some(y)) => ordered(x.cmp(&y)).then_with(|| a.name.cmp(b.name)),
+            (_, Some(_), None) => Ordering::Less,
+            (_, None, Some(_)) => Ordering::Greater,
+            (_, None, None) => a.name.cmp(b.name),
+        }
+    });
+
+    let total = entries.len();
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+    let start = query.offset.min(total);
+    let end = start.saturating_add(limit).min(total);
+    let listing = Listing {
+        dir,
+        total,
+        offset: start,
+        next_offset: (end < total).then_some(end),
+        entries: &entries[start..end],
+    };
+    HttpResponse::Ok()
+        .insert_header((header::CACHE_CONTROL, headers::NO_CACHE))
+        .json(listing)
+}
@@ -0,0 +1,94 @@
+/// Creates [Embed](crate::Embed) services for multiple mount paths at once.
+///
+/// Each `mount_path => assets` pair is expanded into `Embed::new(mount_path, &assets)`, and the
+/// services are grouped into a tuple which can be registered with `App::service()`. At most 12
+/// services can be grouped together.
+///
+/// Settings shared by all services can be given as a closure-like expression before the pairs,
+/// which is applied to each of them.
+///
+/// # Examples
+/// ```
+/// use actix_embed::embed_routes;
+/// use actix_web::App;
+/// use rust_embed::RustEmbed;
+///
+/// #[derive(RustEmbed)]
+/// #[folder = "testdata/"]
+/// struct Assets;
+///
+/// #[derive(RustEmbed)]
+/// #[folder = "testdata/assets/"]
+/// struct Styles;
+///
+/// let app = App::new().service(embed_routes! {
+///     "/static" => Assets,
+///     "/styles" => Styles,
+/// });
+///
+/// // with shared settings
+/// let app = App::new().service(embed_routes! {
+///     |embed| embed.index_file("index.html").strict_slash(true);
+///     "/static" => Assets,
+///     "/styles" => Styles,
+/// });
+/// ```
+#[macro_export]
+macro_rules! embed_routes {
+    (|$embed:ident| $config:expr; $($mount_path:expr => $assets:expr),+ $(,)?) => {
+        ($({
+            let $embed = $crate::Embed::new($mount_path, &$assets);
+            $config
+        },)+)
+    };
+    ($($mount_path:expr => $assets:expr),+ $(,)?) => {
+        ($($crate::Embed::new($mount_path, &$assets),)+)
+    };
+}
+
+/// Checks at compile time that a file exists in the folder of embedded files, and expands to
+/// its path as a `&'static str`.
+///
+/// `folder` is the folder given to `#[folder = "..."]` of the embedded files, relative to the
+/// root of the crate, and `path` is the path of the file in it. Both must be string literals.
+/// The build fails if there is no file at `path`, so typos in paths given to
+/// [index_file](crate::Embed::index_file), [redirect](crate::Embed::redirect) or
+/// [favicon](crate::Embed::favicon) are caught before the application runs.
+///
+/// The file is read by the compiler, like with `include_bytes!`, but nothing is added to the
+/// binary. The folder is not taken from the embedded files type, since only its derive
+/// knows it, so keep both in sync, e.g. by reusing the macro for all paths of a folder.
+///
+/// # Examples
+/// ```
+/// use actix_embed::{embed_path, Embed};
+/// use rust_embed::RustEmbed;
+///
+/// #[derive(RustEmbed)]
+/// #[folder = "testdata/"]
+/// struct Assets;
+///
+/// let embed = Embed::new("/", &Assets)
+///     .index_file(embed_path!("testdata/", "index.html"))
+///     .redirect("style.css", embed_path!("testdata/", "assets/index.css"));
+/// ```
+///
+/// Missing files fail the build:
+/// ```compile_fail
+/// use actix_embed::embed_path;
+///
+/// let path = embed_path!("testdata/", "assets/missing.js");
+/// ```
+#[macro_export]
+macro_rules! embed_path {
+    ($folder:literal, $path:literal) => {{
+        const _: &[u8] = include_bytes!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/",
+            $folder,
+            "/",
+            $path
+        ));
+        $path
+    }};
+}
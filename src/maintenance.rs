@@ -0,0 +1,49 @@
+use std::fmt::{Debug, Formatter};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Controls [Embed](crate::Embed) services at runtime, e.g. from an admin endpoint or a
+/// signal handler.
+///
+/// `EmbedHandle` is a handle, clones control the same services. It can be shared by the
+/// services of all workers.
+///
+/// See [Embed::maintenance](crate::Embed::maintenance).
+#[derive(Clone, Default)]
+pub struct EmbedHandle {
+    maintenance: Arc<AtomicBool>,
+}
+
+impl Debug for EmbedHandle {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EmbedHandle")
+            .field("maintenance", &self.is_maintenance())
+            .finish()
+    }
+}
+
+impl EmbedHandle {
+    /// Create new [EmbedHandle], with maintenance mode off.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Switch maintenance mode on or off, taking effect for the next requests.
+    pub fn set_maintenance(&self, enabled: bool) {
+        self.maintenance.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Returns `true` if maintenance mode is on.
+    pub fn is_maintenance(&self) -> bool {
+        self.maintenance.load(Ordering::Relaxed)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Maintenance {
+    pub(crate) handle: EmbedHandle,
+    /// Relative to the embedded files, without leading slashes.
+    pub(crate) page: String,
+    pub(crate) retry_after: Duration,
+}
@@ -0,0 +1,102 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use crate::file_index::{FileIndex, KeyMap};
+use crate::headers;
+
+/// The metadata of the served files, by path relative to the mount path.
+///
+/// When enabled with [Embed::metadata_registry](crate::Embed::metadata_registry), it is
+/// inserted into the request extensions before the request is served, so hooks such as the
+/// [fallback handler](crate::FallbackHandler) or the key function of the
+/// [rate limiter](crate::Embed::rate_limiter_with_key) can decide without reading or hashing files.
+/// The metadata of a served file is also passed to
+/// [on_serve](crate::Embed::on_serve) in [ServeRecord::metadata](crate::ServeRecord::metadata).
+///
+/// The registry is built once per service, or once for all workers with a
+/// [SharedState](crate::SharedState). Clones share the same registry.
+///
+/// # Examples
+/// ```
+/// use actix_embed::MetadataRegistry;
+/// use actix_web::{HttpMessage, HttpRequest, HttpResponse};
+///
+/// fn fallback(req: &HttpRequest) -> HttpResponse {
+///     let extensions = req.extensions();
+///     let registry = extensions.get::<MetadataRegistry>();
+///     match registry.and_then(|registry| registry.get("index.html")) {
+///         Some(index) => HttpResponse::NotFound().body(format!("see index ({})", index.mime)),
+///         None => HttpResponse::NotFound().finish(),
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetadataRegistry {
+    files: Arc<BTreeMap<String, FileMetadata>>,
+}
+
+/// The metadata of a served file, see [MetadataRegistry].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileMetadata {
+    /// The size of the file in bytes.
+    pub size: u64,
+    /// The SHA-256 hash of the file, hex encoded.
+    pub hash: String,
+    /// The media type of the file, e.g. `text/css`.
+    pub mime: String,
+    /// The modification time of the file in seconds since the Unix epoch, if it is known.
+    pub last_modified: Option<u64>,
+}
+
+impl MetadataRegistry {
+    /// Collects the metadata of the files in `index`, the embedded metadata is used so no
+    /// file is hashed.
+    pub(crate) fn build<E>(
+        index: &FileIndex,
+        keys: &KeyMap,
+        mime_types: &HashMap<String, String>,
+    ) -> Self
+    where
+        E: 'static + rust_embed::RustEmbed,
+    {
+        let files = index
+            .paths()
+            .iter()
+            .filter_map(|path| {
+                let (_, file) = keys.get::<E>(path)?;
+                let metadata = FileMetadata {
+                    size: file.data.len() as u64,
+                    hash: hex::encode(file.metadata.sha256_hash()),
+                    mime: headers::content_type(path, mime_types),
+                    last_modified: file.metadata.last_modified(),
+                };
+                Some((path.clone(), metadata))
+            })
+            .collect();
+        MetadataRegistry {
+            files: Arc::new(files),
+        }
+    }
+
+    /// Returns the metadata of the file at `path`, relative to the mount path.
+    pub fn get(&self, path: &str) -> Option<&FileMetadata> {
+        self.files.get(path.trim_start_matches('/'))
+    }
+
+    /// Returns the paths and metadata of all files, ordered by path.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &FileMetadata)> {
+        self.files
+            .iter()
+            .map(|(path, metadata)| (path.as_str(), metadata))
+    }
+
+    /// Returns the number of files.
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Returns `true` if no file is served.
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+}
@@ -0,0 +1,202 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError, Weak};
+
+use actix_web::http::Method;
+
+use crate::events::{ServeOutcome, ServeRecord};
+
+/// Counters of the responses generated by [Embed](crate::Embed).
+///
+/// `ServeMetrics` is a handle, clones share the same counters. It can be shared by the
+/// services of all workers.
+///
+/// See [Embed::metrics](crate::Embed::metrics).
+#[derive(Debug, Clone, Default)]
+pub struct ServeMetrics {
+    counters: Arc<Counters>,
+    /// The gauges of the caches of the services, dropped caches are skipped.
+    gauges: Arc<Mutex<Gauges>>,
+}
+
+type Gauges = Vec<(CacheKind, Weak<AtomicU64>)>;
+
+#[derive(Debug, Default)]
+struct Counters {
+    ok: AtomicU64,
+    partial_content: AtomicU64,
+    not_modified: AtomicU64,
+    fallback: AtomicU64,
+    other: AtomicU64,
+    head: AtomicU64,
+    bytes_served: AtomicU64,
+    cached_variants: AtomicU64,
+    uncached_variants: AtomicU64,
+}
+
+/// A snapshot of [ServeMetrics].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MetricsSnapshot {
+    /// Number of `200 OK` responses with an embedded file.
+    pub ok: u64,
+    /// Number of `206 Partial Content` responses with a range of an embedded file.
+    pub partial_content: u64,
+    /// Number of `304 Not Modified` responses.
+    pub not_modified: u64,
+    /// Number of responses generated by the fallback handler.
+    pub fallback: u64,
+    /// Number of other responses, e.g. redirects and rejected requests.
+    pub other: u64,
+    /// Number of `HEAD` requests, they are also counted by their outcome.
+    pub head: u64,
+    /// Number of body bytes sent in `200` and `206` responses.
+    ///
+    /// Responses to `HEAD` requests have no body and are not counted, ranged responses are
+    /// counted by the size of the range.
+    pub bytes_served: u64,
+    /// Number of negotiated responses cached, see
+    /// [Embed::variant_cache](crate::Embed::variant_cache).
+    ///
+    /// A steady increase means requests carry ever new combinations of headers.
+    pub cached_variants: u64,
+    /// Number of negotiated responses not cached, because their path already had as many
+    /// cached responses as allowed.
+    pub uncached_variants: u64,
+}
+
+/// The bytes held by the caches of values derived from embedded files, summed over the
+/// services using the [ServeMetrics].
+///
+/// Bodies of cached responses may share memory with the variants they were made of, so the
+/// sizes are those retained while the caches hold them rather than allocated by them.
+///
+/// See [ServeMetrics::cache_sizes].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CacheSizes {
+    /// Bytes of the compressed variants kept in memory, see
+    /// [Embed::precompress](crate::Embed::precompress). Variants embedded in the binary or
+    /// stored in the [derived cache directory](crate::Embed::derived_cache_dir) are not counted.
+    pub compressed_variants: u64,
+    /// Bytes of the minified variants, see [Embed::minify](crate::Embed::minify).
+    pub minified: u64,
+    /// Bytes of the bodies of negotiated responses, see
+    /// [Embed::variant_cache](crate::Embed::variant_cache).
+    pub variant_cache: u64,
+    /// Bytes of the generated bodies of endpoints and their compressed variants, see
+    /// [Embed::compress_endpoints](crate::Embed::compress_endpoints).
+    pub endpoints: u64,
+}
+
+/// The caches reported in [CacheSizes].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CacheKind {
+    CompressedVariants,
+    Minified,
+    VariantCache,
+    Endpoints,
+}
+
+/// The number of bytes held by a cache.
+///
+/// Caches shared by services, like compressed variants, have a single gauge, so they are
+/// counted once.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct CacheGauge(Arc<AtomicU64>);
+
+impl CacheGauge {
+    pub(crate) fn add(&self, bytes: u64) {
+        self.0.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub(crate) fn sub(&self, bytes: u64) {
+        self.0.fetch_sub(bytes, Ordering::Relaxed);
+    }
+}
+
+impl ServeMetrics {
+    /// Create new counters, all starting at zero.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the current values of the counters.
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        let c = &self.counters;
+        MetricsSnapshot {
+            ok: c.ok.load(Ordering::Relaxed),
+            partial_content: c.partial_content.load(Ordering::Relaxed),
+            not_modified: c.not_modified.load(Ordering::Relaxed),
+            fallback: c.fallback.load(Ordering::Relaxed),
+            other: c.other.load(Ordering::Relaxed),
+            head: c.head.load(Ordering::Relaxed),
+            bytes_served: c.bytes_served.load(Ordering::Relaxed),
+            cached_variants: c.cached_variants.load(Ordering::Relaxed),
+            uncached_variants: c.uncached_variants.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Returns the bytes currently held by the caches of the services.
+    ///
+    /// The caches of services which have been dropped are not counted.
+    pub fn cache_sizes(&self) -> CacheSizes {
+        let mut sizes = CacheSizes::default();
+        let gauges = self.gauges.lock().unwrap_or_else(PoisonError::into_inner);
+        for (kind, gauge) in gauges.iter() {
+            let bytes = gauge
+                .upgrade()
+                .map_or(0, |gauge| gauge.load(Ordering::Relaxed));
+            let size = match kind {
+                CacheKind::CompressedVariants => &mut sizes.compressed_variants,
+                CacheKind::Minified => &mut sizes.minified,
+                CacheKind::VariantCache => &mut sizes.variant_cache,
+                CacheKind::Endpoints => &mut sizes.endpoints,
+            };
+            *size += bytes;
+        }
+        sizes
+    }
+
+    /// Adds `gauge` to the [cache sizes](Self::cache_sizes), unless it was added already.
+    pub(crate) fn register(&self, kind: CacheKind, gauge: &CacheGauge) {
+        let mut gauges = self.gauges.lock().unwrap_or_else(PoisonError::into_inner);
+        gauges.retain(|(_, registered)| registered.strong_count() > 0);
+        if !gauges
+            .iter()
+            .any(|(_, registered)| registered.as_ptr() == Arc::as_ptr(&gauge.0))
+        {
+            gauges.push((kind, Arc::downgrade(&gauge.0)));
+        }
+    }
+
+    pub(crate) fn record(&self, record: &ServeRecord) {
+        let c = &self.counters;
+        let counter = match record.outcome {
+            ServeOutcome::Ok => &c.ok,
+            ServeOutcome::PartialContent => &c.partial_content,
+            ServeOutcome::NotModified => &c.not_modified,
+            ServeOutcome::Fallback => &c.fallback,
+            ServeOutcome::Other => &c.other,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        if record.method == Method::HEAD {
+            c.head.fetch_add(1, Ordering::Relaxed);
+        }
+        if matches!(
+            record.outcome,
+            ServeOutcome::Ok | ServeOutcome::PartialContent
+        ) {
+            c.bytes_served.fetch_add(record.bytes, Ordering::Relaxed);
+        }
+    }
+
+    pub(crate) fn record_variant_cached(&self) {
+        self.counters
+            .cached_variants
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_variant_rejected(&self) {
+        self.counters
+            .uncached_variants
+            .fetch_add(1, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,71 @@
+use std::collections::HashMap;
+
+use actix_web::http::StatusCode;
+
+use crate::headers;
+
+/// The media types of the files which may be served, see
+/// [Embed::allowed_mime_types](crate::Embed::allowed_mime_types).
+#[derive(Debug, Clone)]
+pub(crate) struct MimeAllowlist {
+    /// Lowercase `type/subtype` or `type/*`, in the order they were allowed.
+    types: Vec<String>,
+    /// The status of responses to requests for refused files.
+    pub(crate) refused: StatusCode,
+}
+
+impl MimeAllowlist {
+    /// Returns the allowlist of `types`, or an error naming a type which is not of the form
+    /// `type/subtype` or `type/*`.
+    pub(crate) fn new<I>(types: I, refused: StatusCode) -> Result<Self, String>
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        let mut allowed = Vec::new();
+        for mime in types {
+            let mime = mime.as_ref().trim();
+            let valid = mime.split_once('/').is_some_and(|(top, sub)| {
+                let token = |part: &str| {
+                    !part.is_empty()
+                        && part
+                            .bytes()
+                            .all(|b| b.is_ascii_alphanumeric() || b"!#$&-^_.+".contains(&b))
+                };
+                token(top) && (sub == "*" || token(sub))
+            });
+            if !valid {
+                return Err(format!("invalid media type {:?}", mime));
+            }
+            allowed.push(mime.to_ascii_lowercase());
+        }
+        Ok(MimeAllowlist {
+            types: allowed,
+            refused,
+        })
+    }
+
+    /// Returns the allowed media types.
+    pub(crate) fn types(&self) -> impl Iterator<Item = &str> {
+        self.types.iter().map(String::as_str)
+    }
+
+    /// Returns `true` if the file at `path` may be served, by its media type resolved from
+    /// its extension and `mime_types`.
+    pub(crate) fn allows(&self, path: &str, mime_types: &HashMap<String, String>) -> bool {
+        let content_type = headers::content_type(path, mime_types);
+        let essence = content_type
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase();
+        let top = essence.split('/').next().unwrap_or_default();
+        self.types
+            .iter()
+            .any(|allowed| match allowed.strip_suffix("/*") {
+                Some(allowed) => allowed == top,
+                None => *allowed == essence,
+            })
+    }
+}
@@ -0,0 +1,165 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use actix_web::http::header::EntityTag;
+use actix_web::web::Bytes;
+use actix_web::HttpRequest;
+use lightningcss::stylesheet::{MinifyOptions, ParserOptions, PrinterOptions, StyleSheet};
+use sha2::{Digest, Sha256};
+
+use crate::glob::Glob;
+use crate::headers;
+use crate::http_util;
+use crate::metrics::CacheGauge;
+use crate::timeout::{Deadline, TimedOut};
+
+/// The query parameter used to request the original file when originals are kept accessible.
+const ORIGINAL_QUERY: &str = "original";
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct MinifyConfig {
+    pub(crate) serve_originals: bool,
+}
+
+/// The number of bytes of the digest of minified output in entity tags.
+const OUTPUT_DIGEST_LEN: usize = 8;
+
+/// A minified variant of an embedded file.
+pub(crate) struct Minified {
+    pub(crate) data: Bytes,
+    /// The hash of the file, so the revalidation endpoint recognizes the variant, followed by
+    /// a digest of the minified output, so the tag changes with the output of the minifiers,
+    /// e.g. when they are upgraded.
+    pub(crate) etag: EntityTag,
+}
+
+/// Minifies files on first access and keeps the results for later requests.
+pub(crate) struct MinifyCache {
+    config: MinifyConfig,
+    variants: RefCell<HashMap<String, Option<Rc<Minified>>>>,
+    /// The bytes of the minified variants.
+    pub(crate) gauge: CacheGauge,
+}
+
+impl MinifyCache {
+    pub(crate) fn new(config: MinifyConfig) -> Self {
+        MinifyCache {
+            config,
+            variants: RefCell::new(HashMap::new()),
+            gauge: CacheGauge::default(),
+        }
+    }
+
+    /// Returns `true` if the file at `path` may have a minified variant.
+    pub(crate) fn may_minify(&self, path: &str) -> bool {
+        minifiable(path)
+    }
+
+    /// Drops the minified variants of files matching `glob`, all of them if it is `None`.
+    pub(crate) fn purge(&self, glob: Option<&Glob>) {
+        let mut variants = self.variants.borrow_mut();
+        variants.retain(|path, minified| {
+            let purged = glob.is_none_or(|glob| glob.matches(path));
+            if let (true, Some(minified)) = (purged, minified) {
+                self.gauge.sub(minified.data.len() as u64);
+            }
+            !purged
+        });
+    }
+
+    /// Returns the minified variant of the file at `path`, or `None` if the original file
+    /// should be served.
+    ///
+    /// Files not minified yet are not minified once `deadline` has passed.
+    pub(crate) fn get(
+        &self,
+        req: &HttpRequest,
+        path: &str,
+        data: &[u8],
+        hash: &str,
+        deadline: &Deadline,
+    ) -> Result<Option<Rc<Minified>>, TimedOut> {
+        if self.config.serve_originals
+            && req
+                .query_string()
+                .split('&')
+                .any(|pair| pair.split('=').next() == Some(ORIGINAL_QUERY))
+        {
+            return Ok(None);
+        }
+
+        if let Some(minified) = self.variants.borrow().get(path) {
+            return Ok(minified.clone());
+        }
+        if deadline.passed() && minifiable(path) {
+            return Err(TimedOut);
+        }
+
+        let minified = minify(path, data).map(|data| {
+            let digest = Sha256::digest(&data);
+            let digest = hex::encode(&digest[..OUTPUT_DIGEST_LEN]);
+            Rc::new(Minified {
+                data: Bytes::from(data),
+                etag: http_util::minified_entity_tag(hash, &digest),
+            })
+        });
+        if let Some(minified) = &minified {
+            self.gauge.add(minified.data.len() as u64);
+        }
+        self.variants
+            .borrow_mut()
+            .insert(path.to_owned(), minified.clone());
+        Ok(minified)
+    }
+}
+
+fn minifiable(path: &str) -> bool {
+    headers::extension(path).is_some_and(|ext| {
+        matches!(
+            ext.to_ascii_lowercase().as_str(),
+            "html" | "htm" | "css" | "js" | "mjs"
+        )
+    })
+}
+
+/// Minifies HTML, CSS and JavaScript files, returns `None` for other files or if the file
+/// could not be parsed.
+fn minify(path: &str, data: &[u8]) -> Option<Vec<u8>> {
+    match headers::extension(path)?.to_ascii_lowercase().as_str() {
+        "html" | "htm" => Some(minify_html::minify(
+            data,
+            &minify_html::Cfg {
+                keep_closing_tags: true,
+                keep_html_and_head_opening_tags: true,
+                minify_css: true,
+                minify_js: true,
+                ..minify_html::Cfg::default()
+            },
+        )),
+        "css" => {
+            let source = std::str::from_utf8(data).ok()?;
+            let mut sheet = StyleSheet::parse(source, ParserOptions::default()).ok()?;
+            sheet.minify(MinifyOptions::default()).ok()?;
+            let css = sheet
+                .to_css(PrinterOptions {
+                    minify: true,
+                    ..PrinterOptions::default()
+                })
+                .ok()?;
+            Some(css.code.into_bytes())
+        }
+        ext @ ("js" | "mjs") => {
+            let mode = if ext == "mjs" {
+                minify_js::TopLevelMode::Module
+            } else {
+                minify_js::TopLevelMode::Global
+            };
+            let session = minify_js::Session::new();
+            let mut out = Vec::new();
+            minify_js::minify(&session, mode, data, &mut out).ok()?;
+            Some(out)
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,196 @@
+use std::cell::RefCell;
+use std::fmt::{Debug, Display, Formatter};
+use std::rc::Rc;
+
+use actix_web::dev::{AppService, HttpServiceFactory};
+
+use crate::service::MountKind;
+
+/// A mount which can never be reached, because an earlier one matches all its requests.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MountConflict {
+    /// The path of the mount registered later.
+    pub path: String,
+    /// The path of the mount registered earlier, which shadows `path`.
+    pub shadowed_by: String,
+}
+
+impl Display for MountConflict {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.path == self.shadowed_by {
+            write!(f, "`{}` is mounted more than once", self.path)
+        } else {
+            write!(
+                f,
+                "`{}` is shadowed by `{}`, which is registered before it",
+                self.path, self.shadowed_by
+            )
+        }
+    }
+}
+
+type ConflictHook = dyn Fn(&MountConflict);
+
+/// Records the paths at which services are registered on the application root, and reports
+/// mounts shadowed by earlier ones.
+///
+/// Conflicts are reported when a service is registered, by panicking unless a hook is set
+/// with [on_conflict](Self::on_conflict). [Embed](crate::Embed) instances record their mount
+/// paths with [Embed::mount_registry](crate::Embed::mount_registry), other services can be
+/// recorded with [track](Self::track). Services registered in scopes are not recorded.
+///
+/// `MountRegistry` is a handle, clones share the same records. Create one per application.
+///
+/// # Examples
+/// ```
+/// use actix_embed::{Embed, MountRegistry};
+/// use actix_web::{web, App, HttpResponse};
+/// use rust_embed::RustEmbed;
+///
+/// #[derive(RustEmbed)]
+/// #[folder = "testdata/"]
+/// struct Assets;
+///
+/// let mounts = MountRegistry::new()
+///     .on_conflict(|conflict| eprintln!("warning: {}", conflict));
+///
+/// let app = App::new()
+///     .service(mounts.track(
+///         "/api",
+///         web::scope("/api").route("", web::get().to(HttpResponse::Ok)),
+///     ))
+///     .service(Embed::new("/", &Assets).mount_registry(&mounts));
+/// ```
+#[derive(Clone, Default)]
+pub struct MountRegistry {
+    inner: Rc<RefCell<Inner>>,
+}
+
+#[derive(Default)]
+struct Inner {
+    mounts: Vec<(String, MountKind)>,
+    on_conflict: Option<Rc<ConflictHook>>,
+}
+
+impl Debug for MountRegistry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("MountRegistry")
+    }
+}
+
+impl MountRegistry {
+    /// Create new [MountRegistry].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the hook called with each conflict, instead of panicking.
+    pub fn on_conflict<H>(self, hook: H) -> Self
+    where
+        H: Fn(&MountConflict) + 'static,
+    {
+        self.inner.borrow_mut().on_conflict = Some(Rc::new(hook));
+        self
+    }
+
+    /// Record `service` as mounted at `path` when it is registered.
+    ///
+    /// The service is treated as matching `path` and every path under it, like a scope.
+    pub fn track<P, S>(&self, path: P, service: S) -> Tracked<S>
+    where
+        P: AsRef<str>,
+        S: HttpServiceFactory,
+    {
+        Tracked {
+            registry: self.clone(),
+            path: normalize(path.as_ref()),
+            service,
+        }
+    }
+
+    /// Returns the recorded mount paths, in registration order.
+    pub fn mounts(&self) -> Vec<String> {
+        let inner = self.inner.borrow();
+        inner.mounts.iter().map(|(path, _)| display(path)).collect()
+    }
+
+    pub(crate) fn record(&self, config: &AppService, path: &str, kind: MountKind) {
+        if !config.is_root() {
+            return;
+        }
+        let path = normalize(path);
+        let (conflict, hook) = {
+            let mut inner = self.inner.borrow_mut();
+            let conflict = inner
+                .mounts
+                .iter()
+                .find(|(earlier, earlier_kind)| shadows(earlier, *earlier_kind, &path, kind))
+                .map(|(earlier, _)| MountConflict {
+                    path: display(&path),
+                    shadowed_by: display(earlier),
+                });
+            inner.mounts.push((path, kind));
+            (conflict, inner.on_conflict.clone())
+        };
+
+        if let Some(conflict) = conflict {
+            match hook {
+                Some(hook) => hook(&conflict),
+                None => panic!("conflicting mounts: {}", conflict),
+            }
+        }
+    }
+}
+
+/// Normalizes a mount path to start with `/` and contain no empty segments.
+pub(crate) fn normalize(path: &str) -> String {
+    let segments = path.split('/').filter(|s| !s.is_empty());
+    let mut normalized = String::new();
+    for segment in segments {
+        normalized.push('/');
+        normalized.push_str(segment);
+    }
+    normalized
+}
+
+/// Returns the mount path as reported to users, the root is reported as `/`.
+pub(crate) fn display(path: &str) -> String {
+    if path.is_empty() {
+        "/".to_owned()
+    } else {
+        path.to_owned()
+    }
+}
+
+/// Returns `true` if the mount at `earlier` matches every request for the mount at `path`.
+fn shadows(earlier: &str, earlier_kind: MountKind, path: &str, kind: MountKind) -> bool {
+    let under = |path: &str| {
+        earlier.is_empty()
+            || path
+                .strip_prefix(earlier)
+                .is_some_and(|rest| rest.starts_with('/'))
+    };
+    match earlier_kind {
+        MountKind::Prefix => path == earlier || under(path),
+        MountKind::Exact => path == earlier && kind == MountKind::Exact,
+        MountKind::Tail => under(path) && kind != MountKind::Exact,
+    }
+}
+
+/// A service recorded by a [MountRegistry] when registered, see [MountRegistry::track].
+#[derive(Debug)]
+pub struct Tracked<S> {
+    registry: MountRegistry,
+    path: String,
+    service: S,
+}
+
+impl<S> HttpServiceFactory for Tracked<S>
+where
+    S: HttpServiceFactory,
+{
+    fn register(self, config: &mut AppService) {
+        self.registry.record(config, &self.path, MountKind::Prefix);
+        self.service.register(config)
+    }
+}
@@ -0,0 +1,1872 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::{Debug, Display, Formatter};
+use std::ops::RangeBounds;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::time::{Duration, SystemTime};
+
+use actix_web::http::header::{self, HeaderName, HeaderValue};
+use actix_web::http::StatusCode;
+use actix_web::HttpRequest;
+
+use crate::admin::EmbedAdmin;
+use crate::availability::{Availability, Clock};
+use crate::cache_status::CDN_CACHE_CONTROL;
+use crate::canary::Canary;
+use crate::canonical::{CanonicalHost, Scheme};
+use crate::chunked::DEFAULT_CHUNK;
+use crate::conditional::{RevalidationDecision, RevalidationHook, Validators};
+use crate::cookie_gate::{CookieGate, CookieVerifier};
+use crate::cors::Cors;
+use crate::decision::Decision;
+use crate::deprecation::Sunset;
+use crate::derived_cache::DerivedCache;
+use crate::describe::Value;
+use crate::disk_cache::DiskCache;
+use crate::encoding::{Encoding, OuterCompression};
+use crate::error::ConfigIssue;
+use crate::events::ServeRecord;
+use crate::file_index::{FileIndex, KeyMap};
+use crate::forwarded::ForwardedPolicy;
+use crate::glob::{self, Glob};
+use crate::hashed_names::HashedNames;
+use crate::hasher::Hasher;
+use crate::headers::{self, AcceptRanges, FramePolicy, HeaderRule, Priority};
+use crate::hooks::{FailurePolicy, HookPanic, HookPolicy};
+use crate::import_map::ImportMapEndpoint;
+use crate::language::{FallbackChains, LanguageOverride, LocalizedIndex};
+use crate::maintenance::{EmbedHandle, Maintenance};
+use crate::metrics::ServeMetrics;
+use crate::mime_allowlist::MimeAllowlist;
+#[cfg(feature = "minify")]
+use crate::minify::MinifyConfig;
+use crate::mount_registry::MountRegistry;
+use crate::parallel;
+use crate::plugin::EmbedPlugin;
+use crate::precompress::PrecompressRule;
+use crate::prepare::{Preparation, PrepareProgress, ProgressHook};
+use crate::query::QueryRule;
+use crate::rate_limiter::{peer_ip, RateLimit, RateLimiter};
+use crate::registry::{CollisionPolicy, EmbedRegistry, Source};
+use crate::service::MountKind;
+use crate::shared::SharedState;
+use crate::sniff::{self, SniffAction};
+#[cfg(debug_assertions)]
+use crate::staleness;
+use crate::throttle::Throttle;
+use crate::timeout::{ServeTimeout, TimeoutAction};
+use crate::tolerance::PathTolerance;
+use crate::version::{VersionEndpoint, VersionInfo};
+
+/// Settings of [Embed](crate::Embed), which can be shared by multiple instances.
+///
+/// `EmbedOptions` has the same builder methods as `Embed`, except for the fallback handler.
+///
+/// # Examples
+/// ```
+/// use actix_embed::{Embed, EmbedOptions};
+/// use actix_web::App;
+/// use rust_embed::RustEmbed;
+///
+/// #[derive(RustEmbed)]
+/// #[folder = "testdata/"]
+/// struct Assets;
+///
+/// #[derive(RustEmbed)]
+/// #[folder = "testdata/assets/"]
+/// struct Styles;
+///
+/// let options = EmbedOptions::new()
+///     .index_file("index.html")
+///     .strict_slash(true);
+///
+/// let app = App::new()
+///     .service(Embed::with_options("/static", &Assets, options.clone()))
+///     .service(Embed::with_options("/styles", &Styles, options));
+/// ```
+#[derive(Clone, Default)]
+pub struct EmbedOptions {
+    pub(crate) mount_kind: MountKind,
+    pub(crate) index_file_path: Option<String>,
+    pub(crate) directory_index: Option<String>,
+    pub(crate) spa_index: Option<String>,
+    pub(crate) clean_urls: bool,
+    pub(crate) localized_index: Option<LocalizedIndex>,
+    pub(crate) language_fallbacks: FallbackChains,
+    pub(crate) language_override: Option<LanguageOverride>,
+    pub(crate) content_language_from_path: bool,
+    pub(crate) content_location: bool,
+    pub(crate) strict_slash: bool,
+    pub(crate) path_tolerance: PathTolerance,
+    pub(crate) hide_missing_paths: bool,
+    pub(crate) header_rules: Vec<HeaderRule>,
+    /// The indexes of the header rules of files served as bytes, see
+    /// [Embed::byte_serving_preset](crate::Embed::byte_serving_preset).
+    pub(crate) byte_serving: Vec<usize>,
+    /// Files served with `Deprecation` headers, see [Embed::deprecated](crate::Embed::deprecated).
+    pub(crate) deprecated: Vec<Glob>,
+    /// See [Embed::available](crate::Embed::available).
+    pub(crate) availability: Vec<Availability>,
+    pub(crate) clock: Option<Rc<dyn Clock>>,
+    pub(crate) not_found_cache_control: Option<HeaderValue>,
+    /// The invalid options, see [Embed::try_build](crate::Embed::try_build).
+    pub(crate) errors: Vec<ConfigIssue>,
+    pub(crate) mime_types: HashMap<String, String>,
+    /// The status of responses to requests excluding UTF-8 for text files, if the UTF-8
+    /// charset is advertised.
+    pub(crate) accept_charset: Option<StatusCode>,
+    /// See [Embed::allowed_mime_types](crate::Embed::allowed_mime_types).
+    pub(crate) mime_allowlist: Option<MimeAllowlist>,
+    /// See [Embed::content_sniffing](crate::Embed::content_sniffing).
+    pub(crate) content_sniffing: Option<SniffAction>,
+    /// See [Embed::hashed_filenames](crate::Embed::hashed_filenames).
+    pub(crate) hashed_names: Option<HashedNames>,
+    /// The names of the applied plugins, in order.
+    pub(crate) plugins: Vec<&'static str>,
+    #[cfg(feature = "minify")]
+    pub(crate) minify: Option<MinifyConfig>,
+    #[cfg(feature = "sanitize-svg")]
+    pub(crate) sanitize_svg: bool,
+    /// The encodings of the embedded variants served, in order of preference.
+    pub(crate) precompressed_siblings: Vec<Encoding>,
+    pub(crate) precompress: Vec<PrecompressRule>,
+    pub(crate) precompress_blocking: bool,
+    pub(crate) endpoint_compression: Vec<(Encoding, u32)>,
+    /// Extensions skipped by compression in addition to the built-in ones, in lowercase.
+    pub(crate) incompressible: Vec<String>,
+    pub(crate) outer_compression: OuterCompression,
+    pub(crate) accept_ranges: AcceptRanges,
+    pub(crate) disk_cache: Option<DiskCache>,
+    pub(crate) redirects: HashMap<String, String>,
+    pub(crate) suggestions: Option<usize>,
+    /// See [Embed::auto_correct](crate::Embed::auto_correct).
+    pub(crate) auto_correct: bool,
+    pub(crate) canonical_host: Option<CanonicalHost>,
+    pub(crate) cors: Cors,
+    pub(crate) http10_compat: bool,
+    pub(crate) yield_every: Option<u32>,
+    pub(crate) throttles: Vec<Throttle>,
+    /// See [Embed::stream_chunk_size](crate::Embed::stream_chunk_size).
+    pub(crate) stream_chunk_size: Option<usize>,
+    pub(crate) canonical_headers: bool,
+    pub(crate) forwarded_policy: ForwardedPolicy,
+    pub(crate) rate_limit: Option<Rc<RateLimit>>,
+    pub(crate) rate_limit_failure: FailurePolicy,
+    pub(crate) on_serve: Option<Rc<OnServe>>,
+    pub(crate) revalidation_hook: Option<Rc<RevalidationHook>>,
+    pub(crate) on_decision: Option<Rc<OnDecision>>,
+    pub(crate) metadata_registry: bool,
+    pub(crate) slow_serve_threshold: Option<Duration>,
+    pub(crate) verify_hashes: bool,
+    pub(crate) source_folder: Option<PathBuf>,
+    pub(crate) metrics: Option<ServeMetrics>,
+    pub(crate) mount_registry: Option<MountRegistry>,
+    pub(crate) folder: Option<String>,
+    pub(crate) strip_embed_prefix: Option<String>,
+    pub(crate) registry: EmbedRegistry,
+    pub(crate) excluded: Vec<Glob>,
+    pub(crate) claim_only_known_paths: bool,
+    pub(crate) hooks: HookPolicy,
+    pub(crate) serve_timeout: Option<ServeTimeout>,
+    pub(crate) hasher: Option<Rc<dyn Hasher>>,
+    pub(crate) version_endpoint: Option<VersionEndpoint>,
+    pub(crate) responsive_images: bool,
+    pub(crate) preparation: Preparation,
+    pub(crate) on_prepare_progress: Option<ProgressHook>,
+    pub(crate) variant_cache: Option<usize>,
+    pub(crate) age_headers: bool,
+    pub(crate) cache_status: bool,
+    pub(crate) query_rules: Vec<QueryRule>,
+    pub(crate) shared_state: Option<SharedState>,
+    pub(crate) derived_cache: Option<DerivedCache>,
+    pub(crate) maintenance: Option<Maintenance>,
+    pub(crate) maintenance_allowed: Vec<Glob>,
+    pub(crate) cookie_gate: Option<Rc<CookieGate>>,
+    pub(crate) login_redirect: Option<String>,
+    pub(crate) resolution_trace: bool,
+    pub(crate) admin: EmbedAdmin,
+    pub(crate) content_addressed: bool,
+    /// Relative to the mount path, without leading and trailing slashes.
+    pub(crate) revalidation_endpoint: Option<String>,
+    pub(crate) listing_endpoint: Option<String>,
+    /// Relative to the mount path, without leading and trailing slashes.
+    pub(crate) rules_endpoint: Option<String>,
+    /// The directory of the packages, without leading and trailing slashes.
+    pub(crate) packages: Option<String>,
+    pub(crate) package_exports: HashMap<String, String>,
+    pub(crate) import_map: Option<ImportMapEndpoint>,
+    /// Paths of archives and the directories they contain.
+    pub(crate) archives: HashMap<String, String>,
+    pub(crate) canary: Option<Canary>,
+}
+
+pub(crate) type OnServe = dyn Fn(ServeRecord);
+
+pub(crate) type OnDecision = dyn Fn(Decision);
+
+impl Debug for EmbedOptions {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("EmbedOptions")
+    }
+}
+
+impl EmbedOptions {
+    /// Create new [EmbedOptions] with default settings.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// See [Embed::strict_slash](crate::Embed::strict_slash).
+    pub fn strict_slash(mut self, strict_slash: bool) -> Self {
+        self.strict_slash = strict_slash;
+        self
+    }
+
+    /// See [Embed::path_tolerance](crate::Embed::path_tolerance).
+    pub fn path_tolerance(mut self, tolerance: PathTolerance) -> Self {
+        self.path_tolerance = tolerance;
+        self
+    }
+
+    /// See [Embed::mount_kind](crate::Embed::mount_kind).
+    pub fn mount_kind(mut self, mount_kind: MountKind) -> Self {
+        self.mount_kind = mount_kind;
+        self
+    }
+
+    /// See [Embed::index_file](crate::Embed::index_file).
+    pub fn index_file<P: AsRef<str>>(mut self, path: P) -> Self {
+        self.index_file_path = Some(
+            path.as_ref()
+                .trim_end_matches('/')
+                .trim_start_matches('/')
+                .to_string(),
+        );
+        self
+    }
+
+    /// See [Embed::directory_index](crate::Embed::directory_index).
+    pub fn directory_index<N: AsRef<str>>(mut self, file_name: N) -> Self {
+        self.directory_index = Some(file_name.as_ref().trim_matches('/').to_owned());
+        self
+    }
+
+    /// See [Embed::spa_index](crate::Embed::spa_index).
+    pub fn spa_index<P: AsRef<str>>(mut self, path: P) -> Self {
+        self.spa_index = Some(path.as_ref().trim_start_matches('/').to_owned());
+        self
+    }
+
+    /// See [Embed::clean_urls](crate::Embed::clean_urls).
+    pub fn clean_urls(mut self, enabled: bool) -> Self {
+        self.clean_urls = enabled;
+        self
+    }
+
+    /// See [Embed::localized_index](crate::Embed::localized_index).
+    pub fn localized_index<I, P>(mut self, paths: I, default: P) -> Self
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+        P: AsRef<str>,
+    {
+        let normalize = |path: &str| path.trim_start_matches('/').to_owned();
+        let candidates = paths
+            .into_iter()
+            .map(|path| {
+                let path = normalize(path.as_ref());
+                let language = path.split('/').next().unwrap_or_default().to_owned();
+                (language, path)
+            })
+            .collect();
+        self.localized_index = Some(LocalizedIndex {
+            candidates,
+            default: normalize(default.as_ref()),
+        });
+        self
+    }
+
+    /// See [Embed::language_fallback](crate::Embed::language_fallback).
+    pub fn language_fallback<R, I>(mut self, range: R, fallbacks: I) -> Self
+    where
+        R: AsRef<str>,
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        let range = range.as_ref().to_ascii_lowercase();
+        let fallbacks = fallbacks
+            .into_iter()
+            .map(|fallback| fallback.as_ref().to_ascii_lowercase())
+            .collect();
+        let chains = &mut self.language_fallbacks.chains;
+        chains.retain(|(from, _)| *from != range);
+        chains.push((range, fallbacks));
+        self
+    }
+
+    /// See [Embed::language_override](crate::Embed::language_override).
+    pub fn language_override<P: AsRef<str>, C: AsRef<str>>(mut self, param: P, cookie: C) -> Self {
+        let name = cookie.as_ref();
+        if name.is_empty() || HeaderName::from_bytes(name.as_bytes()).is_err() {
+            let message = format!("{:?} is not a valid cookie name", name);
+            self.errors
+                .push(ConfigIssue::error("language_override", message));
+            return self;
+        }
+        self.language_override = Some(LanguageOverride {
+            param: param.as_ref().to_owned(),
+            cookie: cookie.as_ref().to_owned(),
+        });
+        self
+    }
+
+    /// See [Embed::content_language_from_path](crate::Embed::content_language_from_path).
+    pub fn content_language_from_path(mut self, enabled: bool) -> Self {
+        self.content_language_from_path = enabled;
+        self
+    }
+
+    /// See [Embed::content_location](crate::Embed::content_location).
+    pub fn content_location(mut self, enabled: bool) -> Self {
+        self.content_location = enabled;
+        self
+    }
+
+    /// See [Embed::header](crate::Embed::header).
+    pub fn header<I, K, V>(mut self, globs: I, name: K, value: V) -> Self
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+        K: TryInto<HeaderName>,
+        K::Error: Display,
+        V: TryInto<HeaderValue>,
+        V::Error: Display,
+    {
+        let name = name.try_into();
+        let value = value.try_into();
+        match (name, value) {
+            (Ok(name), Ok(value)) => self.header_rules.push(HeaderRule {
+                extensions: &[],
+                globs: glob::globs(globs),
+                headers: vec![(name, value)],
+            }),
+            (Err(err), _) => self.errors.push(ConfigIssue::error(
+                "header_rules",
+                format!("invalid name: {}", err),
+            )),
+            (Ok(name), Err(err)) => self.errors.push(ConfigIssue::error(
+                "header_rules",
+                format!("invalid value of {}: {}", name, err),
+            )),
+        }
+        self
+    }
+
+    /// See [Embed::deprecated](crate::Embed::deprecated).
+    pub fn deprecated<I>(mut self, globs: I, sunset: Sunset) -> Self
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        let globs = glob::globs(globs);
+        self.deprecated.extend(globs.iter().cloned());
+        self.header_rules.push(HeaderRule {
+            extensions: &[],
+            globs,
+            headers: sunset.headers(SystemTime::now()),
+        });
+        self
+    }
+
+    /// See [Embed::available](crate::Embed::available).
+    pub fn available<G, R>(mut self, glob: G, window: R) -> Self
+    where
+        G: AsRef<str>,
+        R: RangeBounds<SystemTime>,
+    {
+        let glob = Glob::new(glob);
+        self.availability.push(Availability::new(glob, window));
+        self
+    }
+
+    /// See [Embed::clock](crate::Embed::clock).
+    pub fn clock<C: Clock>(mut self, clock: C) -> Self {
+        self.clock = Some(Rc::new(clock));
+        self
+    }
+
+    /// See [Embed::not_found_cache_control](crate::Embed::not_found_cache_control).
+    pub fn not_found_cache_control<V>(mut self, value: V) -> Self
+    where
+        V: TryInto<HeaderValue>,
+        V::Error: Display,
+    {
+        match value.try_into() {
+            Ok(value) => self.not_found_cache_control = Some(value),
+            Err(err) => self
+                .errors
+                .push(ConfigIssue::error("not_found_cache_control", err)),
+        }
+        self
+    }
+
+    /// See [Embed::cdn_cache_control](crate::Embed::cdn_cache_control).
+    pub fn cdn_cache_control<I, V>(mut self, globs: I, value: V) -> Self
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+        V: TryInto<HeaderValue>,
+        V::Error: Display,
+    {
+        match value.try_into() {
+            Ok(value) => self.header_rules.push(HeaderRule {
+                extensions: &[],
+                globs: glob::globs(globs),
+                headers: vec![(CDN_CACHE_CONTROL, value)],
+            }),
+            Err(err) => self
+                .errors
+                .push(ConfigIssue::error("cdn_cache_control", err)),
+        }
+        self
+    }
+
+    /// See [Embed::frame_policy](crate::Embed::frame_policy).
+    pub fn frame_policy<G: AsRef<str>>(mut self, glob: G, policy: FramePolicy) -> Self {
+        match policy.headers() {
+            Ok(headers) => self.header_rules.push(HeaderRule {
+                extensions: &[],
+                globs: glob::globs([glob]),
+                headers,
+            }),
+            Err(err) => self.errors.push(ConfigIssue::error("frame_policy", err)),
+        }
+        self
+    }
+
+    /// See [Embed::priority](crate::Embed::priority).
+    pub fn priority<G: AsRef<str>>(mut self, glob: G, priority: Priority) -> Self {
+        match priority.header() {
+            Ok(header) => self.header_rules.push(HeaderRule {
+                extensions: &[],
+                globs: glob::globs([glob]),
+                headers: vec![header],
+            }),
+            Err(err) => self.errors.push(ConfigIssue::error("priority", err)),
+        }
+        self
+    }
+
+    /// See [Embed::fonts_preset](crate::Embed::fonts_preset).
+    pub fn fonts_preset(mut self) -> Self {
+        self.header_rules.push(HeaderRule {
+            extensions: headers::FONT_EXTENSIONS,
+            globs: Vec::new(),
+            headers: vec![
+                (
+                    header::ACCESS_CONTROL_ALLOW_ORIGIN,
+                    HeaderValue::from_static("*"),
+                ),
+                (
+                    header::CACHE_CONTROL,
+                    HeaderValue::from_static(headers::IMMUTABLE),
+                ),
+            ],
+        });
+        for (ext, mime) in headers::FONT_MIME_TYPES {
+            self.mime_types.insert(ext.to_string(), mime.to_string());
+        }
+        self
+    }
+
+    /// See [Embed::sanitize_svg](crate::Embed::sanitize_svg).
+    #[cfg(feature = "sanitize-svg")]
+    pub fn sanitize_svg(mut self, enabled: bool) -> Self {
+        self.sanitize_svg = enabled;
+        self
+    }
+
+    /// See [Embed::untrusted_preset](crate::Embed::untrusted_preset).
+    pub fn untrusted_preset<I>(mut self, globs: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        let globs = glob::globs(globs);
+        self.header_rules.push(HeaderRule {
+            extensions: &[],
+            globs: globs.clone(),
+            headers: vec![
+                (
+                    header::CONTENT_SECURITY_POLICY,
+                    HeaderValue::from_static("sandbox"),
+                ),
+                (
+                    header::X_CONTENT_TYPE_OPTIONS,
+                    HeaderValue::from_static("nosniff"),
+                ),
+            ],
+        });
+        self.header_rules.push(HeaderRule {
+            extensions: headers::ACTIVE_EXTENSIONS,
+            globs,
+            headers: vec![(
+                header::CONTENT_DISPOSITION,
+                HeaderValue::from_static("attachment"),
+            )],
+        });
+        self
+    }
+
+    /// See [Embed::byte_serving_preset](crate::Embed::byte_serving_preset).
+    pub fn byte_serving_preset<I>(mut self, globs: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        self.byte_serving.push(self.header_rules.len());
+        self.header_rules.push(HeaderRule {
+            extensions: headers::DATA_EXTENSIONS,
+            globs: glob::globs(globs),
+            headers: vec![
+                (header::ACCEPT_RANGES, HeaderValue::from_static("bytes")),
+                (
+                    header::CACHE_CONTROL,
+                    HeaderValue::from_static(headers::IMMUTABLE),
+                ),
+                (
+                    headers::CROSS_ORIGIN_RESOURCE_POLICY,
+                    HeaderValue::from_static("same-origin"),
+                ),
+            ],
+        });
+        for (ext, mime) in headers::DATA_MIME_TYPES {
+            self.mime_types.insert(ext.to_string(), mime.to_string());
+        }
+        self
+    }
+
+    /// See [Embed::content_sniffing](crate::Embed::content_sniffing).
+    pub fn content_sniffing(mut self, action: SniffAction) -> Self {
+        self.content_sniffing = Some(action);
+        self
+    }
+
+    /// See [Embed::hashed_filenames](crate::Embed::hashed_filenames).
+    pub fn hashed_filenames(mut self, enabled: bool) -> Self {
+        self.hashed_names = enabled.then_some(HashedNames::Default);
+        self
+    }
+
+    /// See [Embed::hashed_filename_pattern](crate::Embed::hashed_filename_pattern).
+    #[cfg(feature = "hashed-filename-pattern")]
+    pub fn hashed_filename_pattern<P: AsRef<str>>(mut self, pattern: P) -> Self {
+        match HashedNames::new(pattern.as_ref()) {
+            Ok(hashed_names) => self.hashed_names = Some(hashed_names),
+            Err(err) => self
+                .errors
+                .push(ConfigIssue::error("hashed_filenames", err)),
+        }
+        self
+    }
+
+    /// See [Embed::allowed_mime_types](crate::Embed::allowed_mime_types).
+    pub fn allowed_mime_types<I>(mut self, types: I, refused: StatusCode) -> Self
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        if !refused.is_client_error() {
+            let message = format!("{} is not a client error status", refused);
+            self.errors
+                .push(ConfigIssue::error("allowed_mime_types", message));
+        }
+        match MimeAllowlist::new(types, refused) {
+            Ok(allowlist) => self.mime_allowlist = Some(allowlist),
+            Err(err) => self
+                .errors
+                .push(ConfigIssue::error("allowed_mime_types", err)),
+        }
+        self
+    }
+
+    /// See [Embed::accept_charset](crate::Embed::accept_charset).
+    pub fn accept_charset(mut self, not_acceptable: StatusCode) -> Self {
+        self.accept_charset = Some(not_acceptable);
+        self
+    }
+
+    /// See [Embed::minify](crate::Embed::minify).
+    #[cfg(feature = "minify")]
+    pub fn minify(mut self, minify: bool) -> Self {
+        self.minify = minify.then_some(MinifyConfig {
+            serve_originals: false,
+        });
+        self
+    }
+
+    /// See [Embed::serve_originals](crate::Embed::serve_originals).
+    #[cfg(feature = "minify")]
+    pub fn serve_originals(mut self, serve_originals: bool) -> Self {
+        if let Some(minify) = &mut self.minify {
+            minify.serve_originals = serve_originals;
+        }
+        self
+    }
+
+    /// See [Embed::use_precompressed](crate::Embed::use_precompressed).
+    pub fn use_precompressed(mut self, encodings: &[Encoding]) -> Self {
+        self.precompressed_siblings.clear();
+        for &encoding in encodings {
+            if !self.precompressed_siblings.contains(&encoding) {
+                self.precompressed_siblings.push(encoding);
+            }
+        }
+        self
+    }
+
+    /// See [Embed::precompress](crate::Embed::precompress).
+    #[cfg(any(feature = "compress-brotli", feature = "compress-gzip"))]
+    pub fn precompress<I>(mut self, encoding: Encoding, level: u32, globs: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        self.precompress.push(PrecompressRule {
+            encoding,
+            level,
+            globs: glob::globs(globs),
+        });
+        self
+    }
+
+    /// See [Embed::compress_endpoints](crate::Embed::compress_endpoints).
+    #[cfg(any(feature = "compress-brotli", feature = "compress-gzip"))]
+    pub fn compress_endpoints(mut self, encoding: Encoding, level: u32) -> Self {
+        self.endpoint_compression.push((encoding, level));
+        self
+    }
+
+    /// See [Embed::precompress_blocking](crate::Embed::precompress_blocking).
+    #[cfg(any(feature = "compress-brotli", feature = "compress-gzip"))]
+    pub fn precompress_blocking(mut self, blocking: bool) -> Self {
+        self.precompress_blocking = blocking;
+        self
+    }
+
+    /// See [Embed::skip_compression](crate::Embed::skip_compression).
+    #[cfg(any(feature = "compress-brotli", feature = "compress-gzip"))]
+    pub fn skip_compression<I>(mut self, extensions: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        self.incompressible.extend(
+            extensions
+                .into_iter()
+                .map(|ext| ext.as_ref().trim_start_matches('.').to_ascii_lowercase()),
+        );
+        self
+    }
+
+    /// See [Embed::accept_ranges](crate::Embed::accept_ranges).
+    pub fn accept_ranges(mut self, accept_ranges: AcceptRanges) -> Self {
+        self.accept_ranges = accept_ranges;
+        self
+    }
+
+    /// See [Embed::outer_compression](crate::Embed::outer_compression).
+    pub fn outer_compression(mut self, outer: OuterCompression) -> Self {
+        self.outer_compression = outer;
+        self
+    }
+
+    /// See [Embed::derived_cache_dir](crate::Embed::derived_cache_dir).
+    pub fn derived_cache_dir<P: Into<PathBuf>>(mut self, dir: P) -> Self {
+        self.disk_cache = Some(DiskCache::new(dir));
+        self
+    }
+
+    /// See [Embed::redirect](crate::Embed::redirect).
+    pub fn redirect<P: AsRef<str>, Q: AsRef<str>>(mut self, from: P, to: Q) -> Self {
+        self.redirects.insert(
+            from.as_ref().trim_matches('/').to_owned(),
+            to.as_ref().trim_start_matches('/').to_owned(),
+        );
+        self
+    }
+
+    /// See [Embed::auto_correct](crate::Embed::auto_correct).
+    pub fn auto_correct(mut self, enabled: bool) -> Self {
+        self.auto_correct = enabled;
+        self
+    }
+
+    /// See [Embed::suggestions](crate::Embed::suggestions).
+    pub fn suggestions(mut self, limit: usize) -> Self {
+        self.suggestions = (limit > 0).then_some(limit);
+        self
+    }
+
+    /// See [Embed::http10_compat](crate::Embed::http10_compat).
+    pub fn http10_compat(mut self, enabled: bool) -> Self {
+        self.http10_compat = enabled;
+        self
+    }
+
+    /// See [Embed::yield_every](crate::Embed::yield_every).
+    pub fn yield_every(mut self, responses: u32) -> Self {
+        self.yield_every = (responses > 0).then_some(responses);
+        self
+    }
+
+    /// See [Embed::throttle](crate::Embed::throttle).
+    pub fn throttle<G: AsRef<str>>(mut self, glob: G, bytes_per_sec: u64) -> Self {
+        if bytes_per_sec == 0 {
+            let message = format!("{:?} is throttled to 0 bytes per second", glob.as_ref());
+            self.errors.push(ConfigIssue::error("throttle", message));
+            return self;
+        }
+        self.throttles.push(Throttle {
+            glob: Glob::new(glob),
+            bytes_per_sec,
+        });
+        self
+    }
+
+    /// See [Embed::stream_chunk_size](crate::Embed::stream_chunk_size).
+    pub fn stream_chunk_size(mut self, bytes: usize) -> Self {
+        self.stream_chunk_size = Some(bytes);
+        self
+    }
+
+    /// See [Embed::canonical_headers](crate::Embed::canonical_headers).
+    pub fn canonical_headers(mut self, enabled: bool) -> Self {
+        self.canonical_headers = enabled;
+        self
+    }
+
+    /// See [Embed::canonical_host](crate::Embed::canonical_host).
+    pub fn canonical_host<H: AsRef<str>>(mut self, host: H, scheme: Scheme) -> Self {
+        self.canonical_host = Some(CanonicalHost {
+            host: host.as_ref().to_owned(),
+            scheme,
+        });
+        self
+    }
+
+    /// See [Embed::cors](crate::Embed::cors).
+    pub fn cors<I>(mut self, origins: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        self.cors.origins = origins
+            .into_iter()
+            .map(|origin| origin.as_ref().trim_end_matches('/').to_owned())
+            .collect();
+        self
+    }
+
+    /// See [Embed::cors_credentials](crate::Embed::cors_credentials).
+    pub fn cors_credentials(mut self, allowed: bool) -> Self {
+        self.cors.credentials = allowed;
+        self
+    }
+
+    /// See [Embed::cors_private_network](crate::Embed::cors_private_network).
+    pub fn cors_private_network(mut self, allowed: bool) -> Self {
+        self.cors.private_network = allowed;
+        self
+    }
+
+    /// See [Embed::forwarded_policy](crate::Embed::forwarded_policy).
+    pub fn forwarded_policy(mut self, policy: ForwardedPolicy) -> Self {
+        self.forwarded_policy = policy;
+        self
+    }
+
+    /// See [Embed::rate_limiter](crate::Embed::rate_limiter).
+    pub fn rate_limiter<L>(self, limiter: L) -> Self
+    where
+        L: RateLimiter,
+    {
+        self.rate_limiter_with_key(limiter, peer_ip)
+    }
+
+    /// See [Embed::rate_limiter_with_key](crate::Embed::rate_limiter_with_key).
+    pub fn rate_limiter_with_key<L, K>(mut self, limiter: L, key: K) -> Self
+    where
+        L: RateLimiter,
+        K: Fn(&HttpRequest) -> Option<String> + 'static,
+    {
+        self.rate_limit = Some(Rc::new(RateLimit {
+            limiter: Box::new(limiter),
+            key: Box::new(key),
+        }));
+        self
+    }
+
+    /// See [Embed::record_decisions](crate::Embed::record_decisions).
+    pub fn record_decisions<H>(mut self, sink: H) -> Self
+    where
+        H: Fn(Decision) + 'static,
+    {
+        self.on_decision = Some(Rc::new(sink));
+        self
+    }
+
+    /// See [Embed::revalidation_hook](crate::Embed::revalidation_hook).
+    pub fn revalidation_hook<H>(mut self, hook: H) -> Self
+    where
+        H: Fn(&HttpRequest, &Validators<'_>) -> RevalidationDecision + 'static,
+    {
+        self.revalidation_hook = Some(Rc::new(hook));
+        self
+    }
+
+    /// See [Embed::on_serve](crate::Embed::on_serve).
+    pub fn on_serve<H>(mut self, hook: H) -> Self
+    where
+        H: Fn(ServeRecord) + 'static,
+    {
+        self.on_serve = Some(Rc::new(hook));
+        self
+    }
+
+    /// See [Embed::metadata_registry](crate::Embed::metadata_registry).
+    pub fn metadata_registry(mut self, enabled: bool) -> Self {
+        self.metadata_registry = enabled;
+        self
+    }
+
+    /// See [Embed::slow_serve_threshold](crate::Embed::slow_serve_threshold).
+    pub fn slow_serve_threshold(mut self, threshold: Duration) -> Self {
+        self.slow_serve_threshold = Some(threshold);
+        self
+    }
+
+    /// See [Embed::verify_hashes](crate::Embed::verify_hashes).
+    pub fn verify_hashes(mut self, enabled: bool) -> Self {
+        self.verify_hashes = enabled;
+        self
+    }
+
+    /// See [Embed::source_folder](crate::Embed::source_folder).
+    pub fn source_folder<P: Into<PathBuf>>(mut self, folder: P) -> Self {
+        self.source_folder = Some(folder.into());
+        self
+    }
+
+    /// See [Embed::metrics](crate::Embed::metrics).
+    pub fn metrics(mut self, metrics: ServeMetrics) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// See [Embed::with](crate::Embed::with).
+    pub fn with<P: EmbedPlugin>(mut self, plugin: P) -> Self {
+        self.plugins.push(plugin.name());
+        plugin.apply(self)
+    }
+
+    /// See [Embed::mount_registry](crate::Embed::mount_registry).
+    pub fn mount_registry(mut self, registry: &MountRegistry) -> Self {
+        self.mount_registry = Some(registry.clone());
+        self
+    }
+
+    /// See [Embed::strip_embed_prefix](crate::Embed::strip_embed_prefix).
+    pub fn strip_embed_prefix<P: AsRef<str>>(mut self, prefix: P) -> Self {
+        let prefix = prefix.as_ref().trim_matches('/');
+        self.strip_embed_prefix = (!prefix.is_empty()).then(|| prefix.to_owned());
+        self
+    }
+
+    /// See [Embed::registry](crate::Embed::registry).
+    pub fn registry(mut self, registry: EmbedRegistry) -> Self {
+        self.registry.sources.extend(registry.sources);
+        self.registry.policy = registry.policy;
+        self
+    }
+
+    /// See [Embed::namespace](crate::Embed::namespace).
+    pub fn namespace<P, N>(mut self, prefix: P, _source: &N) -> Self
+    where
+        P: AsRef<str>,
+        N: 'static + rust_embed::RustEmbed,
+    {
+        let source = Source::new::<N>(prefix.as_ref(), true);
+        if source.prefix.is_empty() {
+            let message = format!("the namespace of {} has an empty prefix", source.name);
+            self.errors.push(ConfigIssue::error("namespace", message));
+        } else if self
+            .registry
+            .namespaces()
+            .any(|prefix| prefix == source.prefix)
+        {
+            let message = format!("the namespace `{}` is added more than once", source.prefix);
+            self.errors.push(ConfigIssue::error("namespace", message));
+        } else {
+            self.registry.sources.push(source);
+        }
+        self
+    }
+
+    /// See [Embed::folder](crate::Embed::folder).
+    pub fn folder<P: AsRef<str>>(mut self, folder: P) -> Self {
+        let folder = folder.as_ref().trim_matches('/');
+        self.folder = (!folder.is_empty()).then(|| folder.to_owned());
+        self
+    }
+
+    /// See [Embed::exclude](crate::Embed::exclude).
+    pub fn exclude<I>(mut self, globs: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        self.excluded.extend(glob::globs(globs));
+        self
+    }
+
+    /// See [Embed::claim_only_known_paths](crate::Embed::claim_only_known_paths).
+    pub fn claim_only_known_paths(mut self, enabled: bool) -> Self {
+        self.claim_only_known_paths = enabled;
+        self
+    }
+
+    /// See [Embed::rate_limiter_failure](crate::Embed::rate_limiter_failure).
+    pub fn rate_limiter_failure(mut self, policy: FailurePolicy) -> Self {
+        self.rate_limit_failure = policy;
+        self
+    }
+
+    /// See [Embed::catch_hook_panics](crate::Embed::catch_hook_panics).
+    pub fn catch_hook_panics(mut self, catch: bool) -> Self {
+        self.hooks.catch_panics = catch;
+        self
+    }
+
+    /// See [Embed::on_hook_panic](crate::Embed::on_hook_panic).
+    pub fn on_hook_panic<H>(mut self, hook: H) -> Self
+    where
+        H: Fn(&HookPanic) + 'static,
+    {
+        self.hooks.on_panic = Some(Rc::new(hook));
+        self
+    }
+
+    /// See [Embed::hook_timeout](crate::Embed::hook_timeout).
+    pub fn hook_timeout(mut self, timeout: Duration) -> Self {
+        self.hooks.timeout = Some(timeout);
+        self
+    }
+
+    /// See [Embed::serve_timeout](crate::Embed::serve_timeout).
+    pub fn serve_timeout(mut self, timeout: Duration, action: TimeoutAction) -> Self {
+        self.serve_timeout = Some(ServeTimeout {
+            duration: timeout,
+            action,
+        });
+        self
+    }
+
+    /// See [Embed::hasher](crate::Embed::hasher).
+    pub fn hasher<H: Hasher>(mut self, hasher: H) -> Self {
+        self.hasher = Some(Rc::new(hasher));
+        self
+    }
+
+    /// See [Embed::version_endpoint](crate::Embed::version_endpoint).
+    pub fn version_endpoint<P: AsRef<str>>(mut self, path: P, info: VersionInfo) -> Self {
+        self.version_endpoint = Some(VersionEndpoint {
+            path: path.as_ref().trim_matches('/').to_owned(),
+            info,
+        });
+        self
+    }
+
+    /// See [Embed::responsive_images](crate::Embed::responsive_images).
+    pub fn responsive_images(mut self, enabled: bool) -> Self {
+        self.responsive_images = enabled;
+        self
+    }
+
+    /// See [Embed::preparation](crate::Embed::preparation).
+    pub fn preparation(mut self, preparation: Preparation) -> Self {
+        self.preparation = preparation;
+        self
+    }
+
+    /// See [Embed::on_prepare_progress](crate::Embed::on_prepare_progress).
+    pub fn on_prepare_progress<H>(mut self, hook: H) -> Self
+    where
+        H: Fn(PrepareProgress) + Send + Sync + 'static,
+    {
+        self.on_prepare_progress = Some(ProgressHook::new(hook));
+        self
+    }
+
+    /// See [Embed::hide_missing_paths](crate::Embed::hide_missing_paths).
+    pub fn hide_missing_paths(mut self, hide: bool) -> Self {
+        self.hide_missing_paths = hide;
+        self
+    }
+
+    /// See [Embed::variant_cache](crate::Embed::variant_cache).
+    pub fn variant_cache(mut self, max_per_path: usize) -> Self {
+        self.variant_cache = Some(max_per_path);
+        self
+    }
+
+    /// See [Embed::age_headers](crate::Embed::age_headers).
+    pub fn age_headers(mut self, enabled: bool) -> Self {
+        self.age_headers = enabled;
+        self
+    }
+
+    /// See [Embed::cache_status](crate::Embed::cache_status).
+    pub fn cache_status(mut self, enabled: bool) -> Self {
+        self.cache_status = enabled;
+        self
+    }
+
+    /// See [Embed::match_query](crate::Embed::match_query).
+    pub fn match_query<I>(mut self, rules: I) -> Self
+    where
+        I: IntoIterator<Item = QueryRule>,
+    {
+        self.query_rules.extend(rules);
+        self
+    }
+
+    /// See [Embed::shared_state](crate::Embed::shared_state).
+    pub fn shared_state(mut self, state: &SharedState) -> Self {
+        self.shared_state = Some(state.clone());
+        self
+    }
+
+    /// See [Embed::derived_cache](crate::Embed::derived_cache).
+    pub fn derived_cache(mut self, cache: &DerivedCache) -> Self {
+        self.derived_cache = Some(cache.clone());
+        self
+    }
+
+    /// See [Embed::maintenance](crate::Embed::maintenance).
+    pub fn maintenance<P: AsRef<str>>(
+        mut self,
+        handle: &EmbedHandle,
+        page: P,
+        retry_after: Duration,
+    ) -> Self {
+        self.maintenance = Some(Maintenance {
+            handle: handle.clone(),
+            page: page.as_ref().trim_start_matches('/').to_owned(),
+            retry_after,
+        });
+        self
+    }
+
+    /// See [Embed::maintenance_allow](crate::Embed::maintenance_allow).
+    pub fn maintenance_allow<I>(mut self, globs: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        self.maintenance_allowed.extend(glob::globs(globs));
+        self
+    }
+
+    /// See [Embed::require_signed_cookie](crate::Embed::require_signed_cookie).
+    pub fn require_signed_cookie<N, V>(mut self, name: N, verifier: V) -> Self
+    where
+        N: AsRef<str>,
+        V: CookieVerifier,
+    {
+        let name = name.as_ref();
+        if name.is_empty() || HeaderName::from_bytes(name.as_bytes()).is_err() {
+            let message = format!("{:?} is not a valid cookie name", name);
+            self.errors
+                .push(ConfigIssue::error("require_signed_cookie", message));
+            return self;
+        }
+        self.cookie_gate = Some(Rc::new(CookieGate {
+            name: name.to_owned(),
+            verifier: Box::new(verifier),
+        }));
+        self
+    }
+
+    /// See [Embed::login_redirect](crate::Embed::login_redirect).
+    pub fn login_redirect<U: AsRef<str>>(mut self, url: U) -> Self {
+        self.login_redirect = Some(url.as_ref().to_owned());
+        self
+    }
+
+    /// See [Embed::with_admin](crate::Embed::with_admin).
+    pub fn with_admin(mut self, admin: &EmbedAdmin) -> Self {
+        self.admin = admin.clone();
+        self
+    }
+
+    /// See [Embed::resolution_trace](crate::Embed::resolution_trace).
+    pub fn resolution_trace(mut self, enabled: bool) -> Self {
+        self.resolution_trace = enabled;
+        self
+    }
+
+    /// See [Embed::content_addressed](crate::Embed::content_addressed).
+    pub fn content_addressed(mut self, enabled: bool) -> Self {
+        self.content_addressed = enabled;
+        self
+    }
+
+    /// See [Embed::revalidation_endpoint](crate::Embed::revalidation_endpoint).
+    pub fn revalidation_endpoint<P: AsRef<str>>(mut self, path: P) -> Self {
+        self.revalidation_endpoint = Some(path.as_ref().trim_matches('/').to_owned());
+        self
+    }
+
+    /// See [Embed::listing_endpoint](crate::Embed::listing_endpoint).
+    pub fn listing_endpoint<P: AsRef<str>>(mut self, path: P) -> Self {
+        self.listing_endpoint = Some(path.as_ref().trim_matches('/').to_owned());
+        self
+    }
+
+    /// See [Embed::rules_endpoint](crate::Embed::rules_endpoint).
+    pub fn rules_endpoint<P: AsRef<str>>(mut self, path: P) -> Self {
+        self.rules_endpoint = Some(path.as_ref().trim_matches('/').to_owned());
+        self
+    }
+
+    /// See [Embed::packages](crate::Embed::packages).
+    pub fn packages<P: AsRef<str>>(mut self, dir: P) -> Self {
+        self.packages = Some(dir.as_ref().trim_matches('/').to_owned());
+        self
+    }
+
+    /// See [Embed::package_export](crate::Embed::package_export).
+    pub fn package_export<S: AsRef<str>, P: AsRef<str>>(mut self, specifier: S, path: P) -> Self {
+        self.package_exports.insert(
+            specifier.as_ref().trim_matches('/').to_owned(),
+            path.as_ref().trim_start_matches('/').to_owned(),
+        );
+        self
+    }
+
+    /// See [Embed::archive](crate::Embed::archive).
+    pub fn archive<P: AsRef<str>, D: AsRef<str>>(mut self, path: P, dir: D) -> Self {
+        self.archives.insert(
+            path.as_ref().trim_matches('/').to_owned(),
+            dir.as_ref().trim_matches('/').to_owned(),
+        );
+        self
+    }
+
+    /// See [Embed::canary](crate::Embed::canary).
+    pub fn canary<C>(mut self, percent: u8, _bundle: &C) -> Self
+    where
+        C: 'static + rust_embed::RustEmbed,
+    {
+        if percent > 100 {
+            let message = format!("{} is not a percentage", percent);
+            self.errors.push(ConfigIssue::error("canary", message));
+            return self;
+        }
+        self.canary = Some(Canary {
+            percent,
+            name: std::any::type_name::<C>(),
+            factory: crate::service::canary_service::<C>,
+        });
+        self
+    }
+
+    /// See [Embed::import_map](crate::Embed::import_map).
+    pub fn import_map<P, I>(mut self, path: P, globs: I) -> Self
+    where
+        P: AsRef<str>,
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        self.import_map = Some(ImportMapEndpoint {
+            path: path.as_ref().trim_matches('/').to_owned(),
+            globs: glob::globs(globs),
+        });
+        self
+    }
+
+    /// Returns the number of threads the startup work is spread across, all cores with
+    /// [Preparation::Eager].
+    pub(crate) fn preparation_threads(&self) -> usize {
+        match self.preparation {
+            Preparation::Lazy => 1,
+            Preparation::Eager => parallel::available_threads(),
+        }
+    }
+
+    /// Returns the mapping of request paths to the keys of embedded files.
+    pub(crate) fn keys(&self) -> KeyMap {
+        let keys = KeyMap::new(self.strip_embed_prefix.as_deref(), self.folder.as_deref())
+            .with_registry(&self.registry)
+            .with_allowlist(self.mime_allowlist.as_ref(), &self.mime_types);
+        #[cfg(feature = "sanitize-svg")]
+        let keys = keys.sanitize_svg(self.sanitize_svg);
+        keys
+    }
+
+    /// Returns the globs of the options, with the name of their option.
+    #[cfg(feature = "compact-globs")]
+    fn globs(&self) -> Vec<(&'static str, &Glob)> {
+        let rules = self.header_rules.iter().flat_map(|rule| &rule.globs);
+        let mut globs = rules.map(|glob| ("header_rules", glob)).collect::<Vec<_>>();
+        globs.extend(self.deprecated.iter().map(|glob| ("deprecated", glob)));
+        globs.extend(
+            self.availability
+                .iter()
+                .map(|rule| ("availability", &rule.glob)),
+        );
+        let precompress = self.precompress.iter().flat_map(|rule| &rule.globs);
+        globs.extend(precompress.map(|glob| ("precompress", glob)));
+        globs.extend(
+            self.throttles
+                .iter()
+                .map(|throttle| ("throttles", &throttle.glob)),
+        );
+        globs.extend(self.excluded.iter().map(|glob| ("exclude", glob)));
+        let allowed = self.maintenance_allowed.iter();
+        globs.extend(allowed.map(|glob| ("maintenance_allow", glob)));
+        if let Some(endpoint) = &self.import_map {
+            globs.extend(endpoint.globs.iter().map(|glob| ("import_map", glob)));
+        }
+        globs
+    }
+
+    /// Returns the invalid options, followed by the options which work but likely not as
+    /// intended with the files of `E`, see [Embed::issues](crate::Embed::issues).
+    pub(crate) fn issues<E>(&self) -> Vec<ConfigIssue>
+    where
+        E: 'static + rust_embed::RustEmbed,
+    {
+        let mut issues = self.errors.clone();
+        if self.cors.credentials_with_any_origin() {
+            let message = "credentials cannot be allowed for any origin, list the origins";
+            issues.push(ConfigIssue::error("cors", message));
+        }
+        let index = FileIndex::build::<E>(&self.keys());
+        let mut missing = |option, path: &str| {
+            if !index.contains(path) {
+                let message = format!("{:?} is not embedded", path);
+                issues.push(ConfigIssue::warning(option, message));
+            }
+        };
+        if let Some(path) = &self.index_file_path {
+            missing("index_file", path);
+        }
+        if let Some(path) = &self.spa_index {
+            missing("spa_index", path);
+        }
+        if let Some(localized) = &self.localized_index {
+            for (_, path) in &localized.candidates {
+                missing("localized_index", path);
+            }
+            missing("localized_index", &localized.default);
+        }
+        for target in self.redirects.values().collect::<BTreeSet<_>>() {
+            let file = target.split(['?', '#']).next().unwrap_or_default();
+            // redirects to directories are served by their index, or by other services
+            let directory = file.is_empty() || file.ends_with('/');
+            let clean = self.clean_urls && index.contains(&format!("{}.html", file));
+            if !directory && !clean {
+                missing("redirects", file);
+            }
+        }
+        for target in self.package_exports.values().collect::<BTreeSet<_>>() {
+            missing("package_exports", target);
+        }
+        for rule in self.availability.iter().filter(|rule| rule.is_empty()) {
+            let message = format!(
+                "the files matching {:?} are never available",
+                rule.glob.as_str()
+            );
+            issues.push(ConfigIssue::warning("availability", message));
+        }
+        if self.claim_only_known_paths && self.spa_index.is_some() {
+            let message = "client-side routes are not known paths, so the single-page \
+                           application index is not served for them";
+            issues.push(ConfigIssue::warning("claim_only_known_paths", message));
+        }
+        #[cfg(feature = "compact-globs")]
+        for (option, glob) in self.globs() {
+            if !glob.is_compact() {
+                let message = format!(
+                    "{:?} has no compact form, use a single `*`, `**`, `**/` or `**/*`",
+                    glob.as_str()
+                );
+                issues.push(ConfigIssue::error(option, message));
+            }
+        }
+        if self.login_redirect.is_some() && self.cookie_gate.is_none() {
+            let message = "no cookie is required, so no request is redirected";
+            issues.push(ConfigIssue::warning("login_redirect", message));
+        }
+        #[cfg(debug_assertions)]
+        if let Some(folder) = &self.source_folder {
+            if let Some(difference) = staleness::compare::<E>(folder) {
+                let message = format!(
+                    "{}; rebuild the application if changes to the assets are not showing",
+                    difference
+                );
+                issues.push(ConfigIssue::warning("source_folder", message));
+            }
+        }
+        if self.content_sniffing.is_some() {
+            for file in sniff::mislabeled::<E>(&index, &self.keys(), &self.mime_types) {
+                issues.push(ConfigIssue::warning("content_sniffing", file));
+            }
+        }
+        for (path, dir) in self.archives.iter().collect::<BTreeMap<_, _>>() {
+            if index.under(dir).is_empty() {
+                let message = format!("{:?} archives {:?}, which has no files", path, dir);
+                issues.push(ConfigIssue::warning("archives", message));
+            }
+        }
+
+        // the rules matching the same file, found once per pair of rules
+        let mut contradictions = Vec::new();
+        for path in index.paths() {
+            let rules = self
+                .header_rules
+                .iter()
+                .enumerate()
+                .filter(|(_, rule)| rule.matches(path))
+                .collect::<Vec<_>>();
+            for (i, (a, earlier)) in rules.iter().enumerate() {
+                for (b, later) in &rules[i + 1..] {
+                    let contradicting = later.headers.iter().find(|(name, value)| {
+                        earlier
+                            .headers
+                            .iter()
+                            .any(|(other, previous)| other == name && previous != value)
+                    });
+                    if let Some((name, _)) = contradicting {
+                        if !contradictions.iter().any(|found| found == &(*a, *b)) {
+                            contradictions.push((*a, *b));
+                            let globs = |rule: &HeaderRule| {
+                                rule.globs
+                                    .iter()
+                                    .map(|glob| glob.as_str().to_owned())
+                                    .collect::<Vec<_>>()
+                            };
+                            let message = format!(
+                                "the rules of {:?} and {:?} set different values of `{}` on \
+                                 {:?}, the later one is sent",
+                                globs(earlier),
+                                globs(later),
+                                name,
+                                path
+                            );
+                            issues.push(ConfigIssue::warning("header_rules", message));
+                        }
+                    }
+                }
+            }
+        }
+        let mut contradictions = Vec::new();
+        for path in index.paths() {
+            let mut matching = self
+                .throttles
+                .iter()
+                .filter(|throttle| throttle.glob.matches(path));
+            let first = match matching.next() {
+                Some(first) => first,
+                None => continue,
+            };
+            for other in matching.filter(|other| other.bytes_per_sec != first.bytes_per_sec) {
+                let pair = (first.glob.as_str(), other.glob.as_str());
+                if !contradictions.contains(&pair) {
+                    contradictions.push(pair);
+                    let message = format!(
+                        "{:?} and {:?} both match {:?} with different rates, the first one \
+                         applies",
+                        pair.0, pair.1, path
+                    );
+                    issues.push(ConfigIssue::warning("throttles", message));
+                }
+            }
+        }
+        issues
+    }
+
+    /// Describes the options, see [Embed::describe](crate::Embed::describe).
+    pub(crate) fn describe(&self) -> Vec<(String, Value)> {
+        let mut entries = Vec::new();
+        let mut entry = |name: &str, value: Value| entries.push((name.to_owned(), value));
+
+        entry(
+            "mount_kind",
+            match self.mount_kind {
+                MountKind::Prefix => "prefix",
+                MountKind::Exact => "exact",
+                MountKind::Tail => "tail",
+            }
+            .into(),
+        );
+        entry("index_file", self.index_file_path.as_deref().into());
+        entry("directory_index", self.directory_index.as_deref().into());
+        entry("spa_index", self.spa_index.as_deref().into());
+        entry("clean_urls", self.clean_urls.into());
+        entry(
+            "localized_index",
+            self.localized_index
+                .as_ref()
+                .map(|index| {
+                    Value::object([
+                        (
+                            "candidates",
+                            Value::object(
+                                index
+                                    .candidates
+                                    .iter()
+                                    .map(|(lang, path)| (lang.as_str(), path.as_str())),
+                            ),
+                        ),
+                        ("default", index.default.as_str().into()),
+                    ])
+                })
+                .into(),
+        );
+        entry(
+            "language_fallbacks",
+            Value::object(
+                self.language_fallbacks
+                    .chains
+                    .iter()
+                    .map(|(range, fallbacks)| {
+                        (
+                            range.as_str(),
+                            Value::list(fallbacks.iter().map(String::as_str)),
+                        )
+                    }),
+            ),
+        );
+        entry(
+            "language_override",
+            self.language_override
+                .as_ref()
+                .map(|choice| {
+                    Value::object([
+                        ("param", choice.param.as_str()),
+                        ("cookie", choice.cookie.as_str()),
+                    ])
+                })
+                .into(),
+        );
+        entry(
+            "content_language_from_path",
+            self.content_language_from_path.into(),
+        );
+        entry("content_location", self.content_location.into());
+        entry("strict_slash", self.strict_slash.into());
+        entry(
+            "path_tolerance",
+            match self.path_tolerance {
+                PathTolerance::Strict => "strict",
+                PathTolerance::Trim => "trim",
+                PathTolerance::Redirect => "redirect",
+            }
+            .into(),
+        );
+        entry("hide_missing_paths", self.hide_missing_paths.into());
+        entry("folder", self.folder.as_deref().into());
+        entry(
+            "strip_embed_prefix",
+            self.strip_embed_prefix.as_deref().into(),
+        );
+        entry("registry", Value::list(self.registry.prefixes()));
+        entry("namespaces", Value::list(self.registry.namespaces()));
+        entry("plugins", Value::list(self.plugins.iter().copied()));
+        entry(
+            "collision_policy",
+            match self.registry.policy {
+                CollisionPolicy::FirstWins => "first_wins",
+                CollisionPolicy::LastWins => "last_wins",
+                CollisionPolicy::Error => "error",
+            }
+            .into(),
+        );
+        entry(
+            "exclude",
+            Value::list(self.excluded.iter().map(Glob::as_str)),
+        );
+        entry("claim_only_known_paths", self.claim_only_known_paths.into());
+        entry(
+            "header_rules",
+            Value::list(self.header_rules.iter().map(|rule| {
+                Value::object([
+                    ("extensions", Value::list(rule.extensions.iter().copied())),
+                    ("globs", Value::list(rule.globs.iter().map(Glob::as_str))),
+                    (
+                        "headers",
+                        Value::object(rule.headers.iter().map(|(name, value)| {
+                            (name.as_str(), value.to_str().unwrap_or("<binary>"))
+                        })),
+                    ),
+                ])
+            })),
+        );
+        entry(
+            "byte_serving",
+            Value::list(self.byte_serving.iter().map(|&i| i as u64)),
+        );
+        entry(
+            "deprecated",
+            Value::list(self.deprecated.iter().map(Glob::as_str)),
+        );
+        entry(
+            "availability",
+            Value::list(self.availability.iter().map(Availability::describe)),
+        );
+        entry(
+            "not_found_cache_control",
+            self.not_found_cache_control
+                .as_ref()
+                .map(|value| value.to_str().unwrap_or("<binary>"))
+                .into(),
+        );
+        entry("mime_types", sorted_object(&self.mime_types));
+        entry(
+            "content_sniffing",
+            match self.content_sniffing {
+                Some(SniffAction::Report) => "report".into(),
+                Some(SniffAction::Correct) => "correct".into(),
+                None => Value::Null,
+            },
+        );
+        entry(
+            "hashed_filenames",
+            self.hashed_names.as_ref().map(HashedNames::pattern).into(),
+        );
+        entry(
+            "allowed_mime_types",
+            match &self.mime_allowlist {
+                Some(allowlist) => Value::object([
+                    ("types", Value::list(allowlist.types())),
+                    ("refused", (allowlist.refused.as_u16() as u64).into()),
+                ]),
+                None => Value::Null,
+            },
+        );
+        entry(
+            "accept_charset",
+            self.accept_charset
+                .map(|status| status.as_u16() as u64)
+                .into(),
+        );
+        #[cfg(feature = "sanitize-svg")]
+        entry("sanitize_svg", self.sanitize_svg.into());
+        #[cfg(feature = "minify")]
+        entry(
+            "minify",
+            self.minify
+                .map(|config| Value::object([("serve_originals", config.serve_originals)]))
+                .into(),
+        );
+        entry(
+            "use_precompressed",
+            Value::list(
+                self.precompressed_siblings
+                    .iter()
+                    .map(|encoding| encoding.as_str()),
+            ),
+        );
+        entry(
+            "precompress",
+            Value::list(self.precompress.iter().map(|rule| {
+                Value::object([
+                    ("encoding", Value::from(rule.encoding.as_str())),
+                    ("level", rule.level.into()),
+                    ("globs", Value::list(rule.globs.iter().map(Glob::as_str))),
+                ])
+            })),
+        );
+        entry("precompress_blocking", self.precompress_blocking.into());
+        entry(
+            "compress_endpoints",
+            Value::list(self.endpoint_compression.iter().map(|(encoding, level)| {
+                Value::object([
+                    ("encoding", Value::from(encoding.as_str())),
+                    ("level", (*level).into()),
+                ])
+            })),
+        );
+        entry(
+            "outer_compression",
+            match self.outer_compression {
+                OuterCompression::Absent => "absent",
+                OuterCompression::Bypass => "bypass",
+                OuterCompression::Allow => "allow",
+            }
+            .into(),
+        );
+        entry(
+            "accept_ranges",
+            match self.accept_ranges {
+                AcceptRanges::Unadvertised => "unadvertised",
+                AcceptRanges::Bytes => "bytes",
+                AcceptRanges::None => "none",
+            }
+            .into(),
+        );
+        entry(
+            "skip_compression",
+            Value::list(self.incompressible.iter().map(String::as_str)),
+        );
+        entry(
+            "derived_cache_dir",
+            self.disk_cache
+                .as_ref()
+                .map(|cache| cache.dir().display().to_string())
+                .into(),
+        );
+        entry("redirects", sorted_object(&self.redirects));
+        entry("suggestions", self.suggestions.into());
+        entry("auto_correct", self.auto_correct.into());
+        entry(
+            "canonical_host",
+            self.canonical_host
+                .as_ref()
+                .map(|canonical| format!("{}://{}", canonical.scheme, canonical.host))
+                .into(),
+        );
+        entry(
+            "cors",
+            Value::object([
+                (
+                    "origins",
+                    Value::list(
+                        self.cors
+                            .origins
+                            .iter()
+                            .map(|origin| Value::from(origin.as_str())),
+                    ),
+                ),
+                ("credentials", self.cors.credentials.into()),
+                ("private_network", self.cors.private_network.into()),
+            ]),
+        );
+        entry("http10_compat", self.http10_compat.into());
+        entry("yield_every", self.yield_every.map(u64::from).into());
+        entry(
+            "throttles",
+            Value::list(self.throttles.iter().map(|throttle| {
+                Value::object([
+                    ("glob", Value::from(throttle.glob.as_str())),
+                    ("bytes_per_sec", throttle.bytes_per_sec.into()),
+                ])
+            })),
+        );
+        entry("canonical_headers", self.canonical_headers.into());
+        entry(
+            "stream_chunk_size",
+            self.stream_chunk_size.unwrap_or(DEFAULT_CHUNK).into(),
+        );
+        entry(
+            "forwarded_policy",
+            match &self.forwarded_policy {
+                ForwardedPolicy::Ignore => "ignore".into(),
+                ForwardedPolicy::Trust => "trust".into(),
+                ForwardedPolicy::TrustedProxies(proxies) => Value::object([(
+                    "trusted_proxies",
+                    Value::list(proxies.iter().map(ToString::to_string)),
+                )]),
+            },
+        );
+        entry("rate_limiter", self.rate_limit.is_some().into());
+        entry(
+            "rate_limiter_failure",
+            match self.rate_limit_failure {
+                FailurePolicy::Open => "open".into(),
+                FailurePolicy::Closed(status) => {
+                    Value::object([("closed", status.as_u16() as u64)])
+                }
+            },
+        );
+        entry("on_serve", self.on_serve.is_some().into());
+        entry("revalidation_hook", self.revalidation_hook.is_some().into());
+        entry("record_decisions", self.on_decision.is_some().into());
+        entry("metadata_registry", self.metadata_registry.into());
+        entry(
+            "slow_serve_threshold",
+            self.slow_serve_threshold
+                .map(|threshold| threshold.as_millis() as u64)
+                .into(),
+        );
+        entry("verify_hashes", self.verify_hashes.into());
+        entry(
+            "source_folder",
+            self.source_folder
+                .as_ref()
+                .map(|folder| folder.display().to_string())
+                .into(),
+        );
+        entry("metrics", self.metrics.is_some().into());
+        entry("mount_registry", self.mount_registry.is_some().into());
+        entry("catch_hook_panics", self.hooks.catch_panics.into());
+        entry("on_hook_panic", self.hooks.on_panic.is_some().into());
+        entry(
+            "hook_timeout",
+            self.hooks
+                .timeout
+                .map(|timeout| timeout.as_millis() as u64)
+                .into(),
+        );
+        entry(
+            "serve_timeout",
+            self.serve_timeout
+                .map(|timeout| {
+                    Value::object([
+                        ("millis", Value::from(timeout.duration.as_millis() as u64)),
+                        (
+                            "action",
+                            match timeout.action {
+                                TimeoutAction::Identity => "identity",
+                                TimeoutAction::ServiceUnavailable => "service_unavailable",
+                            }
+                            .into(),
+                        ),
+                    ])
+                })
+                .into(),
+        );
+        entry(
+            "hasher",
+            match self.hasher {
+                Some(_) => "custom",
+                None => "sha256",
+            }
+            .into(),
+        );
+        entry(
+            "version_endpoint",
+            self.version_endpoint
+                .as_ref()
+                .map(|endpoint| {
+                    Value::object([
+                        ("path", Value::from(endpoint.path.as_str())),
+                        ("build", endpoint.info.build.as_deref().into()),
+                    ])
+                })
+                .into(),
+        );
+        entry("responsive_images", self.responsive_images.into());
+        entry(
+            "preparation",
+            match self.preparation {
+                Preparation::Lazy => "lazy",
+                Preparation::Eager => "eager",
+            }
+            .into(),
+        );
+        entry(
+            "on_prepare_progress",
+            self.on_prepare_progress.is_some().into(),
+        );
+        entry("variant_cache", self.variant_cache.into());
+        entry("age_headers", self.age_headers.into());
+        entry("cache_status", self.cache_status.into());
+        entry("shared_state", self.shared_state.is_some().into());
+        entry("derived_cache", self.derived_cache.is_some().into());
+        entry(
+            "match_query",
+            Value::list(self.query_rules.iter().map(QueryRule::describe)),
+        );
+        entry(
+            "maintenance",
+            self.maintenance
+                .as_ref()
+                .map(|maintenance| {
+                    Value::object([
+                        ("active", Value::from(maintenance.handle.is_maintenance())),
+                        ("page", maintenance.page.as_str().into()),
+                        ("retry_after", maintenance.retry_after.as_secs().into()),
+                    ])
+                })
+                .into(),
+        );
+        entry(
+            "maintenance_allow",
+            Value::list(self.maintenance_allowed.iter().map(Glob::as_str)),
+        );
+        entry(
+            "require_signed_cookie",
+            self.cookie_gate
+                .as_ref()
+                .map(|gate| gate.name.as_str())
+                .into(),
+        );
+        entry("login_redirect", self.login_redirect.as_deref().into());
+        entry("resolution_trace", self.resolution_trace.into());
+        entry("content_addressed", self.content_addressed.into());
+        entry(
+            "revalidation_endpoint",
+            self.revalidation_endpoint.as_deref().into(),
+        );
+        entry("listing_endpoint", self.listing_endpoint.as_deref().into());
+        // the endpoint is compiled out of release builds
+        let rules_endpoint = self.rules_endpoint.as_deref();
+        entry(
+            "rules_endpoint",
+            rules_endpoint.filter(|_| cfg!(debug_assertions)).into(),
+        );
+        entry("packages", self.packages.as_deref().into());
+        entry("package_exports", sorted_object(&self.package_exports));
+        entry(
+            "archives",
+            Value::object(
+                self.archives
+                    .iter()
+                    .map(|(path, dir)| (path.as_str(), dir.as_str())),
+            ),
+        );
+        entry(
+            "canary",
+            self.canary
+                .as_ref()
+                .map(|canary| {
+                    Value::object([
+                        ("percent", Value::from(canary.percent as u64)),
+                        ("bundle", Value::from(canary.name)),
+                    ])
+                })
+                .into(),
+        );
+        entry(
+            "import_map",
+            self.import_map
+                .as_ref()
+                .map(|endpoint| {
+                    Value::object([
+                        ("path", Value::from(endpoint.path.as_str())),
+                        (
+                            "globs",
+                            Value::list(endpoint.globs.iter().map(Glob::as_str)),
+                        ),
+                    ])
+                })
+                .into(),
+        );
+        entries
+    }
+}
+
+/// Describes `map` as an object sorted by key, so descriptions are stable.
+fn sorted_object(map: &HashMap<String, String>) -> Value {
+    let mut entries = map.iter().collect::<Vec<_>>();
+    entries.sort();
+    Value::object(
+        entries
+            .into_iter()
+            .map(|(key, value)| (key.as_str(), value.as_str())),
+    )
+}
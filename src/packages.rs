@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use crate::file_index::{FileIndex, KeyMap};
+
+/// The conditions of conditional exports used to serve modules to browsers, by priority.
+const CONDITIONS: &[&str] = &["browser", "import", "module", "default"];
+
+/// The entry points of embedded npm-style packages, keyed by bare specifier paths, e.g.
+/// `vendor/lodash` and `vendor/@preact/signals/utils`.
+///
+/// See [Embed::packages](crate::Embed::packages).
+#[derive(Debug, Default)]
+pub(crate) struct PackageExports {
+    entries: HashMap<String, String>,
+}
+
+impl PackageExports {
+    /// Reads the `package.json` files of the packages in the directory `dir`.
+    pub(crate) fn build<E>(index: &FileIndex, keys: &KeyMap, dir: &str) -> Self
+    where
+        E: 'static + rust_embed::RustEmbed,
+    {
+        let mut entries = HashMap::new();
+        let prefix = format!("{}/", dir);
+        for path in index.under(dir) {
+            let package = match path.strip_suffix("/package.json") {
+                Some(package) => package,
+                None => continue,
+            };
+            // packages are `<name>` or `@<scope>/<name>`
+            let name = &package[prefix.len()..];
+            let scoped = name.starts_with('@');
+            if name.matches('/').count() != usize::from(scoped) {
+                continue;
+            }
+            let manifest = keys
+                .get::<E>(path)
+                .and_then(|(_, file)| serde_json::from_slice::<Value>(&file.data).ok());
+            if let Some(manifest) = manifest {
+                for (subpath, target) in exports(&manifest) {
+                    let specifier = match subpath.trim_start_matches('.').trim_matches('/') {
+                        "" => package.to_owned(),
+                        subpath => format!("{}/{}", package, subpath),
+                    };
+                    let target = format!("{}/{}", package, target.trim_start_matches("./"));
+                    if index.contains(&target) {
+                        entries.insert(specifier, target);
+                    }
+                }
+            }
+        }
+        PackageExports { entries }
+    }
+
+    /// Returns the bare specifier paths and the paths of the files exported at them.
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.entries
+            .iter()
+            .map(|(specifier, path)| (specifier.as_str(), path.as_str()))
+    }
+
+    /// Returns the path of the file exported at `specifier`.
+    pub(crate) fn resolve(&self, specifier: &str) -> Option<&str> {
+        self.entries.get(specifier).map(String::as_str)
+    }
+}
+
+/// Returns the subpaths exported by the package with `manifest` and their targets.
+///
+/// Subpath patterns are not supported. Packages without `exports` export `module` or `main`,
+/// or `index.js`.
+fn exports(manifest: &Value) -> Vec<(&str, &str)> {
+    match manifest.get("exports") {
+        Some(Value::Object(map)) if map.keys().all(|key| key.starts_with('.')) => map
+            .iter()
+            .filter(|(subpath, _)| !subpath.contains('*'))
+            .filter_map(|(subpath, target)| Some((subpath.as_str(), condition(target)?)))
+            .collect(),
+        Some(exports) => condition(exports)
+            .map(|target| (".", target))
+            .into_iter()
+            .collect(),
+        None => {
+            let main = ["module", "main"]
+                .iter()
+                .find_map(|field| manifest.get(field)?.as_str())
+                .unwrap_or("index.js");
+            vec![(".", main)]
+        }
+    }
+}
+
+/// Resolves the conditional export `target` to the path used by browsers.
+fn condition(target: &Value) -> Option<&str> {
+    match target {
+        Value::String(target) => Some(target),
+        Value::Object(conditions) => CONDITIONS
+            .iter()
+            .find_map(|name| condition(conditions.get(*name)?)),
+        Value::Array(fallbacks) => fallbacks.iter().find_map(condition),
+        _ => None,
+    }
+}
@@ -0,0 +1,64 @@
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+
+/// Returns the number of threads to spread startup work across, one per core.
+pub(crate) fn available_threads() -> usize {
+    thread::available_parallelism().map_or(1, NonZeroUsize::get)
+}
+
+/// Maps `items` with `f` on up to `threads` threads, returning the results in the order of
+/// `items`.
+///
+/// Threads take the next item when they are done with one, so a few large items do not hold
+/// up the others. `progress` is called on the calling thread with the number of items mapped
+/// so far, after each item.
+pub(crate) fn map<T, R, F, P>(items: &[T], threads: usize, f: F, mut progress: P) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+    P: FnMut(usize),
+{
+    let threads = threads.min(items.len());
+    if threads <= 1 {
+        return items
+            .iter()
+            .enumerate()
+            .map(|(i, item)| {
+                let result = f(item);
+                progress(i + 1);
+                result
+            })
+            .collect();
+    }
+
+    let mut results = (0..items.len()).map(|_| None).collect::<Vec<_>>();
+    let next = AtomicUsize::new(0);
+    thread::scope(|scope| {
+        let (sender, receiver) = mpsc::channel();
+        for _ in 0..threads {
+            let (sender, next, f) = (sender.clone(), &next, &f);
+            scope.spawn(move || loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let item = match items.get(i) {
+                    Some(item) => item,
+                    None => break,
+                };
+                if sender.send((i, f(item))).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(sender);
+        for (done, (i, result)) in receiver.into_iter().enumerate() {
+            results[i] = Some(result);
+            progress(done + 1);
+        }
+    });
+    results
+        .into_iter()
+        .map(|result| result.expect("every item is mapped"))
+        .collect()
+}
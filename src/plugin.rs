@@ -0,0 +1,61 @@
+use crate::options::EmbedOptions;
+
+/// A reusable bundle of options, applied with [Embed::with](crate::Embed::with) or
+/// [EmbedOptions::with].
+///
+/// Plugins let other crates ship integrations, like authentication providers or the headers
+/// expected by a CDN, as a single value. They configure the instance through the builder
+/// methods of [EmbedOptions], so they can set anything users can, including hooks, header
+/// rules and exclusions, but none of the internals.
+///
+/// Closures taking and returning [EmbedOptions] are plugins too.
+///
+/// # Examples
+/// ```
+/// use actix_embed::{Embed, EmbedOptions, EmbedPlugin};
+/// use rust_embed::RustEmbed;
+///
+/// #[derive(RustEmbed)]
+/// #[folder = "testdata/"]
+/// struct Assets;
+///
+/// /// Lets the CDN cache files for `max_age` seconds, and revalidate them in the background.
+/// struct CdnPreset {
+///     max_age: u32,
+/// }
+///
+/// impl EmbedPlugin for CdnPreset {
+///     fn apply(self, options: EmbedOptions) -> EmbedOptions {
+///         let value = format!("public, s-maxage={}, stale-while-revalidate=60", self.max_age);
+///         options.header(["**"], "cdn-cache-control", value)
+///     }
+///
+///     fn name(&self) -> &'static str {
+///         "cdn"
+///     }
+/// }
+///
+/// let embed = Embed::new("/", &Assets)
+///     .with(CdnPreset { max_age: 86400 })
+///     .with(|options: EmbedOptions| options.exclude(["private/**"]));
+/// ```
+pub trait EmbedPlugin {
+    /// Returns `options` with the options of the plugin applied.
+    fn apply(self, options: EmbedOptions) -> EmbedOptions;
+
+    /// Returns the name of the plugin, listed by [Embed::describe](crate::Embed::describe).
+    ///
+    /// Defaults to the name of the type.
+    fn name(&self) -> &'static str {
+        std::any::type_name::<Self>()
+    }
+}
+
+impl<F> EmbedPlugin for F
+where
+    F: FnOnce(EmbedOptions) -> EmbedOptions,
+{
+    fn apply(self, options: EmbedOptions) -> EmbedOptions {
+        (self)(options)
+    }
+}
@@ -0,0 +1,245 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use actix_web::web::Bytes;
+
+use crate::disk_cache::{DiskCache, StoredArtifact};
+use crate::encoding::{self, Encoding};
+use crate::file_index::{KeyMap, SANITIZED};
+use crate::glob::Glob;
+use crate::metrics::CacheGauge;
+use crate::parallel;
+use crate::prepare::{PrepareStage, ProgressHook};
+
+/// Files matching `globs` are compressed with `encoding` when services are created.
+#[derive(Debug, Clone)]
+pub(crate) struct PrecompressRule {
+    pub(crate) encoding: Encoding,
+    pub(crate) level: u32,
+    pub(crate) globs: Vec<Glob>,
+}
+
+/// A compressed variant of an embedded file.
+#[derive(Debug, Clone)]
+pub(crate) enum Variant {
+    Memory(Bytes),
+    Disk(StoredArtifact),
+}
+
+impl Variant {
+    /// Returns the content of the variant, `None` if it can no longer be read.
+    pub(crate) async fn load(&self) -> Option<Bytes> {
+        match self {
+            Variant::Memory(data) => Some(data.clone()),
+            Variant::Disk(artifact) => artifact.read().await,
+        }
+    }
+
+    /// Returns the size of the variant in bytes, without reading it.
+    pub(crate) fn len(&self) -> u64 {
+        match self {
+            Variant::Memory(data) => data.len() as u64,
+            Variant::Disk(artifact) => artifact.len(),
+        }
+    }
+}
+
+/// A file to compress with the encoding and level of a rule.
+struct Job {
+    path: String,
+    encoding: Encoding,
+    level: u32,
+    /// The key of the variant in the disk cache.
+    key: String,
+    /// The index of the content of the file in the files collected to compress.
+    file: usize,
+}
+
+/// Compressed variants of embedded files, keyed by file path.
+#[derive(Debug, Default)]
+pub(crate) struct Precompressed {
+    variants: HashMap<String, Vec<(Encoding, Variant)>>,
+    /// The bytes of the variants compressed into memory.
+    pub(crate) gauge: CacheGauge,
+}
+
+impl Precompressed {
+    /// Collects the embedded variants of the files compressed with `siblings`, e.g.
+    /// `app.js.br` for `app.js`, in order, then compresses the files matching `rules` with the
+    /// other encodings.
+    ///
+    /// Variants which are not smaller than the original file are dropped, files with
+    /// extensions deemed incompressible are not compressed at all.
+    ///
+    /// If `disk_cache` is set, variants are stored there instead of memory, and variants stored
+    /// by previous runs are reused.
+    ///
+    /// The files of registered `sources` are keyed by the paths they are served at.
+    ///
+    /// Files are compressed on up to `threads` threads, reporting to `progress`.
+    pub(crate) fn build<E>(
+        keys: &KeyMap,
+        siblings: &[Encoding],
+        rules: &[PrecompressRule],
+        incompressible: &[String],
+        disk_cache: Option<&DiskCache>,
+        threads: usize,
+        progress: Option<&ProgressHook>,
+    ) -> Self
+    where
+        E: 'static + rust_embed::RustEmbed,
+    {
+        let mut variants = HashMap::<String, Vec<(Encoding, Variant)>>::new();
+        let gauge = CacheGauge::default();
+        if siblings.is_empty() && rules.is_empty() {
+            return Precompressed { variants, gauge };
+        }
+
+        // the files to compress, compressed on `threads` threads once all are collected
+        let mut files = Vec::new();
+        let mut jobs = Vec::new();
+        // files hidden by namespaces are never served
+        let embedded = E::iter()
+            .filter(|key| keys.path(key).is_none_or(|path| keys.visible(path, None)))
+            .map(|key| (key, None));
+        let sourced = keys.sources().iter().flat_map(|source| {
+            source
+                .paths()
+                .filter(move |path| keys.visible(path, Some(source)))
+                .map(move |path| (Cow::Owned(path), Some(source)))
+        });
+        for (path, source) in embedded.chain(sourced) {
+            let get = |path: &str| match source {
+                Some(source) => source.get(path),
+                None => E::get(path),
+            };
+            // the embedded variants of sanitized files are not sanitized
+            if !keys.is_sanitized(&path) {
+                for &encoding in siblings {
+                    let sibling = format!("{}.{}", path, encoding.file_extension());
+                    if let Some(file) = get(&sibling) {
+                        let data = match file.data {
+                            Cow::Borrowed(data) => Bytes::from_static(data),
+                            Cow::Owned(data) => {
+                                gauge.add(data.len() as u64);
+                                Bytes::from(data)
+                            }
+                        };
+                        let entry = variants.entry(path.to_string()).or_default();
+                        entry.push((encoding, Variant::Memory(data)));
+                    }
+                }
+            }
+            if encoding::is_incompressible(&path, incompressible) {
+                continue;
+            }
+            let mut file = None;
+            let mut queued = Vec::new();
+            for rule in rules {
+                if !rule.globs.iter().any(|glob| glob.matches(&path)) {
+                    continue;
+                }
+                let embedded = variants.get(path.as_ref()).map_or(&[][..], Vec::as_slice);
+                if queued.contains(&rule.encoding)
+                    || embedded
+                        .iter()
+                        .any(|(encoding, _)| *encoding == rule.encoding)
+                {
+                    continue;
+                }
+                let file = match *file.get_or_insert_with(|| {
+                    let file = keys.content(&path, get(&path)?);
+                    files.push(file);
+                    Some(files.len() - 1)
+                }) {
+                    Some(file) => file,
+                    None => break,
+                };
+
+                let mut hash = hex::encode(files[file].metadata.sha256_hash());
+                if keys.is_sanitized(&path) {
+                    hash.push_str(SANITIZED);
+                }
+                queued.push(rule.encoding);
+                jobs.push(Job {
+                    path: path.to_string(),
+                    encoding: rule.encoding,
+                    level: rule.level,
+                    key: format!("{}.{}.{}", hash, rule.encoding, rule.level),
+                    file,
+                });
+            }
+        }
+
+        let progress = ProgressHook::stage(progress, PrepareStage::Compression, jobs.len());
+        let compressed = parallel::map(
+            &jobs,
+            threads,
+            |job| {
+                if let Some(artifact) = disk_cache.and_then(|cache| cache.load(&job.key)) {
+                    return Some(Variant::Disk(artifact));
+                }
+                let data = &files[job.file].data;
+                let compressed = match compress(job.encoding, job.level, data) {
+                    Some(compressed) if compressed.len() < data.len() => compressed,
+                    _ => return None,
+                };
+                match disk_cache.map(|cache| cache.store(&job.key, &compressed)) {
+                    Some(Ok(artifact)) => Some(Variant::Disk(artifact)),
+                    // keep the variant in memory if it cannot be stored
+                    _ => {
+                        gauge.add(compressed.len() as u64);
+                        Some(Variant::Memory(Bytes::from(compressed)))
+                    }
+                }
+            },
+            progress,
+        );
+        for (job, variant) in jobs.into_iter().zip(compressed) {
+            if let Some(variant) = variant {
+                let entry = variants.entry(job.path).or_default();
+                entry.push((job.encoding, variant));
+            }
+        }
+        variants.retain(|_, entry| !entry.is_empty());
+
+        Precompressed { variants, gauge }
+    }
+
+    pub(crate) fn get(&self, path: &str) -> Option<&[(Encoding, Variant)]> {
+        self.variants.get(path).map(Vec::as_slice)
+    }
+}
+
+/// Compresses `data`, returns `None` if support for `encoding` is not enabled.
+///
+/// The output only depends on `encoding`, `level` and `data`, so every worker and replica
+/// produces byte-identical variants. In particular, no timestamps are written to gzip headers.
+#[allow(unused_variables)]
+pub(crate) fn compress(encoding: Encoding, level: u32, data: &[u8]) -> Option<Vec<u8>> {
+    match encoding {
+        #[cfg(feature = "compress-brotli")]
+        Encoding::Brotli => {
+            let params = brotli::enc::BrotliEncoderParams {
+                quality: level.min(11) as i32,
+                lgwin: 22,
+                ..Default::default()
+            };
+            let mut out = Vec::new();
+            brotli::BrotliCompress(&mut &data[..], &mut out, &params).ok()?;
+            Some(out)
+        }
+        #[cfg(feature = "compress-gzip")]
+        Encoding::Gzip => {
+            use std::io::Write;
+
+            let mut encoder = flate2::GzBuilder::new()
+                .mtime(0)
+                .write(Vec::new(), flate2::Compression::new(level.min(9)));
+            encoder.write_all(data).ok()?;
+            encoder.finish().ok()
+        }
+        #[allow(unreachable_patterns)]
+        _ => None,
+    }
+}
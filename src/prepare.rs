@@ -0,0 +1,130 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt::{Debug, Formatter};
+use std::rc::Rc;
+use std::sync::Arc;
+
+use crate::glob::Glob;
+
+/// When the values served with each file, like its entity tag and content type, are computed.
+///
+/// See [Embed::preparation](crate::Embed::preparation).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Preparation {
+    /// Compute the values of a file on its first request, and keep them for later requests.
+    #[default]
+    Lazy,
+    /// Compute the values of all files when services are created.
+    Eager,
+}
+
+/// The startup work reported by [Embed::on_prepare_progress](crate::Embed::on_prepare_progress).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrepareStage {
+    /// Compressing files with [Embed::precompress](crate::Embed::precompress).
+    Compression,
+    /// Hashing files with a [Hasher](crate::Hasher) whose hashes are not computed at
+    /// compile time, for [Preparation::Eager].
+    Hashing,
+}
+
+/// The progress of the startup work of a service, see
+/// [Embed::on_prepare_progress](crate::Embed::on_prepare_progress).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PrepareProgress {
+    /// The work in progress.
+    pub stage: PrepareStage,
+    /// The number of items done, compressed variants or hashed files.
+    pub done: usize,
+    /// The number of items of the stage.
+    pub total: usize,
+}
+
+/// The hook reporting the progress of the startup work, which may run on other threads.
+#[derive(Clone)]
+pub(crate) struct ProgressHook(Arc<dyn Fn(PrepareProgress) + Send + Sync>);
+
+impl ProgressHook {
+    pub(crate) fn new<H>(hook: H) -> Self
+    where
+        H: Fn(PrepareProgress) + Send + Sync + 'static,
+    {
+        ProgressHook(Arc::new(hook))
+    }
+
+    /// Returns a function reporting the number of items done of `total` in `stage`.
+    pub(crate) fn stage(
+        hook: Option<&ProgressHook>,
+        stage: PrepareStage,
+        total: usize,
+    ) -> impl FnMut(usize) + '_ {
+        move |done| {
+            if let Some(ProgressHook(hook)) = hook {
+                hook(PrepareProgress { stage, done, total });
+            }
+        }
+    }
+}
+
+impl Debug for ProgressHook {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ProgressHook")
+    }
+}
+
+/// The values served with a file, which only depend on the file and the configuration.
+#[derive(Debug)]
+pub(crate) struct PreparedFile {
+    /// The hex-encoded content hash.
+    pub(crate) hash: Rc<str>,
+    pub(crate) content_type: String,
+    /// The indexes of the header rules matching the file.
+    pub(crate) header_rules: Vec<usize>,
+    /// Whether the file is served as is, see
+    /// [Embed::byte_serving_preset](crate::Embed::byte_serving_preset).
+    pub(crate) byte_serving: bool,
+    /// Whether the name of the file has a content hash, if they are
+    /// [detected](crate::Embed::hashed_filenames) and no header rule sets `Cache-Control`.
+    pub(crate) hashed_name: Option<bool>,
+}
+
+/// Prepared files, keyed by file path, with the [epoch](crate::EmbedAdmin::epoch) they were
+/// prepared in.
+#[derive(Debug, Default)]
+pub(crate) struct PreparedFiles {
+    files: RefCell<HashMap<String, (usize, Rc<PreparedFile>)>>,
+}
+
+impl PreparedFiles {
+    /// Returns the prepared file at `path`, preparing it with `prepare` if it is not yet, or
+    /// was in an epoch before `epoch`.
+    pub(crate) fn get<P>(&self, path: &str, epoch: usize, prepare: P) -> Rc<PreparedFile>
+    where
+        P: FnOnce() -> PreparedFile,
+    {
+        if let Some((prepared_in, prepared)) = self.files.borrow().get(path) {
+            if *prepared_in >= epoch {
+                return prepared.clone();
+            }
+        }
+        let prepared = Rc::new(prepare());
+        self.files
+            .borrow_mut()
+            .insert(path.to_owned(), (epoch, prepared.clone()));
+        prepared
+    }
+
+    /// Drops the prepared file at `path`.
+    pub(crate) fn remove(&self, path: &str) {
+        self.files.borrow_mut().remove(path);
+    }
+
+    /// Drops the prepared files matching `glob`, all of them if it is `None`.
+    pub(crate) fn purge(&self, glob: Option<&Glob>) {
+        let mut files = self.files.borrow_mut();
+        match glob {
+            Some(glob) => files.retain(|path, _| !glob.matches(path)),
+            None => files.clear(),
+        }
+    }
+}
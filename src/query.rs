@@ -0,0 +1,98 @@
+use actix_web::HttpRequest;
+
+use crate::describe::Value;
+
+/// Selects a folder to look up files in by a query parameter of the request.
+///
+/// See [Embed::match_query](crate::Embed::match_query).
+///
+/// # Examples
+/// ```
+/// use actix_embed::QueryRule;
+///
+/// // `?lang=de` looks up files under `de/`, `?lang=en` under `en/`
+/// let by_language = QueryRule::subtree("lang");
+///
+/// // `?theme=dark` looks up files under `themes/dark/`
+/// let dark = QueryRule::value("theme", "dark", "themes/dark");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryRule {
+    param: String,
+    /// The value the parameter must have and the selected folder, `None` if the value is
+    /// the folder.
+    value: Option<(String, String)>,
+}
+
+impl QueryRule {
+    /// Select the folder named like the value of the query parameter `param`.
+    ///
+    /// Values which are empty, contain `/` or `\`, or start with `.` are ignored, so the
+    /// selected folder is always a direct subfolder.
+    pub fn subtree<P: AsRef<str>>(param: P) -> Self {
+        QueryRule {
+            param: param.as_ref().to_owned(),
+            value: None,
+        }
+    }
+
+    /// Select `folder` if the query parameter `param` has the value `value`.
+    pub fn value<P, V, F>(param: P, value: V, folder: F) -> Self
+    where
+        P: AsRef<str>,
+        V: AsRef<str>,
+        F: AsRef<str>,
+    {
+        QueryRule {
+            param: param.as_ref().to_owned(),
+            value: Some((
+                value.as_ref().to_owned(),
+                folder.as_ref().trim_matches('/').to_owned(),
+            )),
+        }
+    }
+
+    pub(crate) fn describe(&self) -> Value {
+        match &self.value {
+            Some((value, folder)) => Value::object([
+                ("param", self.param.as_str()),
+                ("value", value.as_str()),
+                ("folder", folder.as_str()),
+            ]),
+            None => Value::object([("param", self.param.as_str()), ("folder", "{value}")]),
+        }
+    }
+
+    /// Returns the folder selected for a query parameter `param` with value `value`.
+    fn select<'a>(&'a self, param: &str, value: &'a str) -> Option<&'a str> {
+        if param != self.param {
+            return None;
+        }
+        match &self.value {
+            Some((expected, folder)) => (value == expected).then_some(folder.as_str()),
+            None => {
+                let safe =
+                    !value.is_empty() && !value.starts_with('.') && !value.contains(['/', '\\']);
+                safe.then_some(value)
+            }
+        }
+    }
+}
+
+/// Returns the folder selected by the first of `rules` matching a query parameter of `req`.
+///
+/// Parameters are compared as sent, without percent-decoding.
+pub(crate) fn select_folder(rules: &[QueryRule], req: &HttpRequest) -> Option<String> {
+    let params = req
+        .query_string()
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| pair.split_once('=').unwrap_or((pair, "")))
+        .collect::<Vec<_>>();
+    rules.iter().find_map(|rule| {
+        params
+            .iter()
+            .find_map(|(param, value)| rule.select(param, value))
+            .map(str::to_owned)
+    })
+}
@@ -0,0 +1,67 @@
+use actix_web::http::header::Range;
+use actix_web::web::Bytes;
+use actix_web::{HttpMessage, HttpRequest};
+
+use crate::http_util;
+
+/// The maximum number of ranges of a request, requests for more are answered with the whole
+/// body, so a request cannot make the response much larger than the body.
+const MAX_RANGES: usize = 16;
+
+/// The part of a body requested by the `Range` header of a request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Requested {
+    /// The whole body, also if the header is absent, invalid or asks for too many ranges.
+    Full,
+    /// The bytes `start` to `end` inclusive.
+    Single(u64, u64),
+    /// Several ranges of bytes, sent as `multipart/byteranges`.
+    Multiple(Vec<(u64, u64)>),
+    /// None of the ranges overlaps the body.
+    Unsatisfiable,
+}
+
+/// Returns the part of a body of `len` bytes requested by `req`.
+pub(crate) fn requested(req: &HttpRequest, len: u64) -> Requested {
+    let specs = match req.get_header::<Range>() {
+        Some(Range::Bytes(specs)) if specs.len() <= MAX_RANGES => specs,
+        _ => return Requested::Full,
+    };
+    let mut ranges = specs
+        .iter()
+        .filter_map(|spec| spec.to_satisfiable_range(len))
+        .collect::<Vec<_>>();
+    match ranges.len() {
+        0 => Requested::Unsatisfiable,
+        1 => {
+            let (start, end) = ranges.remove(0);
+            Requested::Single(start, end)
+        }
+        _ => Requested::Multiple(ranges),
+    }
+}
+
+/// Returns the `multipart/byteranges` body with the `ranges` of `body`, whose parts are
+/// separated by `boundary` and have the type `content_type`.
+pub(crate) fn multipart(
+    body: &Bytes,
+    ranges: &[(u64, u64)],
+    boundary: &str,
+    content_type: &str,
+) -> Bytes {
+    let total = body.len() as u64;
+    let mut multipart = Vec::new();
+    for &(start, end) in ranges {
+        let head = format!(
+            "--{}\r\nContent-Type: {}\r\nContent-Range: {}\r\n\r\n",
+            boundary,
+            content_type,
+            http_util::content_range(start, end, total)
+        );
+        multipart.extend_from_slice(head.as_bytes());
+        multipart.extend_from_slice(&body[start as usize..=end as usize]);
+        multipart.extend_from_slice(b"\r\n");
+    }
+    multipart.extend_from_slice(format!("--{}--\r\n", boundary).as_bytes());
+    Bytes::from(multipart)
+}
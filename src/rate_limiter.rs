@@ -0,0 +1,58 @@
+use std::time::Duration;
+
+use actix_web::HttpRequest;
+
+/// The decision made by a [RateLimiter].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateLimitDecision {
+    /// The request is served.
+    Allow,
+    /// The request is rejected with `429 Too Many Requests` and a `Retry-After` header.
+    RetryAfter(Duration),
+    /// The limiter could not decide, e.g. because its backend is unreachable.
+    ///
+    /// The request is handled according to
+    /// [Embed::rate_limiter_failure](crate::Embed::rate_limiter_failure).
+    Unavailable,
+}
+
+/// Rate limiters are consulted before serving a request.
+///
+/// Each request is identified by a key, which is the client IP address by default.
+/// See [Embed::rate_limiter](crate::Embed::rate_limiter).
+pub trait RateLimiter: 'static {
+    #[allow(missing_docs)]
+    fn check(&self, key: &str) -> RateLimitDecision;
+}
+
+impl<T> RateLimiter for T
+where
+    T: Fn(&str) -> RateLimitDecision + 'static,
+{
+    fn check(&self, key: &str) -> RateLimitDecision {
+        (self)(key)
+    }
+}
+
+pub(crate) type RateLimitKeyFn = dyn Fn(&HttpRequest) -> Option<String>;
+
+pub(crate) struct RateLimit {
+    pub(crate) limiter: Box<dyn RateLimiter>,
+    pub(crate) key: Box<RateLimitKeyFn>,
+}
+
+impl RateLimit {
+    pub(crate) fn check(&self, req: &HttpRequest) -> RateLimitDecision {
+        match (self.key)(req) {
+            Some(key) => self.limiter.check(&key),
+            None => RateLimitDecision::Allow,
+        }
+    }
+}
+
+/// Returns the IP address of the peer.
+///
+/// Forwarded headers are not trusted here since they can be set by any client.
+pub(crate) fn peer_ip(req: &HttpRequest) -> Option<String> {
+    req.peer_addr().map(|addr| addr.ip().to_string())
+}
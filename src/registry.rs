@@ -0,0 +1,191 @@
+use std::borrow::Cow;
+use std::fmt::{Debug, Display, Formatter};
+
+use rust_embed::{EmbeddedFile, Filenames};
+
+/// The embedded files of several crates, each served under its own prefix by one
+/// [Embed](crate::Embed).
+///
+/// Feature crates register their assets, and the application serves them all with
+/// [Embed::registry](crate::Embed::registry).
+///
+/// # Examples
+/// ```
+/// use actix_embed::{Embed, EmbedRegistry};
+/// use actix_web::App;
+/// use rust_embed::RustEmbed;
+///
+/// #[derive(RustEmbed)]
+/// #[folder = "testdata/"]
+/// struct Assets;
+///
+/// #[derive(RustEmbed)]
+/// #[folder = "testdata/fonts/"]
+/// struct Fonts;
+///
+/// // e.g. `fn register(registry: EmbedRegistry) -> EmbedRegistry` in each feature crate
+/// let registry = EmbedRegistry::new().register("plugins/fonts", &Fonts);
+///
+/// let app = App::new().service(Embed::new("/static", &Assets).registry(registry));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct EmbedRegistry {
+    pub(crate) sources: Vec<Source>,
+    pub(crate) policy: CollisionPolicy,
+}
+
+/// Which file is served when several sources have a file at the same path, see
+/// [EmbedRegistry::collision_policy].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CollisionPolicy {
+    /// Serve the file of the source registered first, the files of `E` being first.
+    #[default]
+    FirstWins,
+    /// Serve the file of the source registered last.
+    LastWins,
+    /// Panic when the service is registered, e.g. in `App::service`.
+    Error,
+}
+
+/// Files of several sources served at the same path, see
+/// [Embed::collisions](crate::Embed::collisions).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssetCollision {
+    /// The path of the files, relative to the mount path.
+    pub path: String,
+    /// The name of the type of the embedded files whose file is served.
+    pub served: &'static str,
+    /// The names of the types of the embedded files whose files are shadowed, by precedence.
+    pub shadowed: Vec<&'static str>,
+}
+
+impl Display for AssetCollision {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "`{}` of {} shadows the same path of {}",
+            self.path,
+            self.served,
+            self.shadowed.join(", ")
+        )
+    }
+}
+
+impl EmbedRegistry {
+    /// Create new empty [EmbedRegistry].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Serve the files of `source` under `prefix`, relative to the mount path, e.g. the file
+    /// `icons.woff` of a source registered at `plugins/fonts` is served at
+    /// `plugins/fonts/icons.woff`.
+    ///
+    /// An empty prefix serves the files at the mount path itself.
+    pub fn register<P, E>(mut self, prefix: P, _source: &E) -> Self
+    where
+        P: AsRef<str>,
+        E: 'static + rust_embed::RustEmbed,
+    {
+        self.sources.push(Source::new::<E>(prefix.as_ref(), false));
+        self
+    }
+
+    /// Set which file is served when several sources, including the files of `E` of
+    /// [Embed](crate::Embed), have a file at the same path.
+    ///
+    /// By default the file of the source registered first is served, and the files of `E` take
+    /// precedence over all registered sources. Collisions are reported by
+    /// [Embed::collisions](crate::Embed::collisions) whatever the policy.
+    pub fn collision_policy(mut self, policy: CollisionPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Returns the prefixes of the registered sources, in the order of registration.
+    pub fn prefixes(&self) -> impl Iterator<Item = &str> {
+        self.sources
+            .iter()
+            .filter(|source| !source.namespace)
+            .map(|source| source.prefix.as_str())
+    }
+
+    /// Returns the prefixes of the [namespaces](crate::Embed::namespace), in the order they were
+    /// added.
+    pub(crate) fn namespaces(&self) -> impl Iterator<Item = &str> {
+        self.sources
+            .iter()
+            .filter(|source| source.namespace)
+            .map(|source| source.prefix.as_str())
+    }
+}
+
+/// Embedded files registered in an [EmbedRegistry].
+#[derive(Clone)]
+pub(crate) struct Source {
+    /// Relative to the mount path, without leading and trailing slashes.
+    pub(crate) prefix: String,
+    /// The name of the type of the embedded files.
+    pub(crate) name: &'static str,
+    /// Whether the source owns all paths under its prefix, see
+    /// [Embed::namespace](crate::Embed::namespace).
+    pub(crate) namespace: bool,
+    get: fn(&str) -> Option<EmbeddedFile>,
+    iter: fn() -> Filenames,
+}
+
+impl Debug for Source {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Source")
+            .field("prefix", &self.prefix)
+            .field("name", &self.name)
+            .field("namespace", &self.namespace)
+            .finish()
+    }
+}
+
+impl Source {
+    pub(crate) fn new<E>(prefix: &str, namespace: bool) -> Self
+    where
+        E: 'static + rust_embed::RustEmbed,
+    {
+        Source {
+            prefix: prefix.trim_matches('/').to_owned(),
+            name: std::any::type_name::<E>(),
+            namespace,
+            get: E::get,
+            iter: E::iter,
+        }
+    }
+
+    /// Returns `true` if the source is a namespace whose prefix contains `path`, relative to
+    /// the mount path.
+    pub(crate) fn owns(&self, path: &str) -> bool {
+        self.namespace
+            && path
+                .strip_prefix(self.prefix.as_str())
+                .is_some_and(|rest| rest.starts_with('/'))
+    }
+
+    /// Returns the path of the file with `key` relative to the mount path.
+    fn path(&self, key: &str) -> String {
+        match self.prefix.as_str() {
+            "" => key.to_owned(),
+            prefix => format!("{}/{}", prefix, key),
+        }
+    }
+
+    /// Returns the paths of the files, relative to the mount path.
+    pub(crate) fn paths(&self) -> impl Iterator<Item = String> + '_ {
+        (self.iter)().map(move |key: Cow<'static, str>| self.path(&key))
+    }
+
+    /// Returns the content of the file at `path`, relative to the mount path.
+    pub(crate) fn get(&self, path: &str) -> Option<EmbeddedFile> {
+        let key = match self.prefix.as_str() {
+            "" => path,
+            prefix => path.strip_prefix(prefix)?.strip_prefix('/')?,
+        };
+        (self.get)(key)
+    }
+}
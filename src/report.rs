@@ -0,0 +1,169 @@
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+
+use crate::encoding;
+
+/// The number of largest files listed by the [Display] implementation of [EmbedReport].
+const LISTED: usize = 10;
+
+/// Returns the sizes of the files embedded by `E`, to find what makes the binary large.
+///
+/// Nothing is read from disk, the report is computed from the embedded files, e.g. in a
+/// subcommand of the application printing it.
+///
+/// # Examples
+/// ```
+/// use actix_embed::embed_report;
+/// use rust_embed::RustEmbed;
+///
+/// #[derive(RustEmbed)]
+/// #[folder = "testdata/"]
+/// struct Assets;
+///
+/// let report = embed_report::<Assets>();
+/// for file in report.largest(3) {
+///     println!("{}: {} bytes", file.path, file.size);
+/// }
+/// println!("{}", report);
+/// ```
+pub fn embed_report<E>() -> EmbedReport
+where
+    E: rust_embed::RustEmbed,
+{
+    let mut files = E::iter()
+        .filter_map(|path| {
+            let file = E::get(&path)?;
+            let size = file.data.len() as u64;
+            let estimated_compressed = match encoding::is_incompressible(&path, &[]) {
+                true => size,
+                false => estimate_compressed(&file.data),
+            };
+            Some(FileSize {
+                path: path.into_owned(),
+                size,
+                estimated_compressed,
+            })
+        })
+        .collect::<Vec<_>>();
+    files.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.path.cmp(&b.path)));
+
+    let mut directories = BTreeMap::<&str, DirectorySize>::new();
+    for file in &files {
+        let ancestors = file.path.match_indices('/').map(|(i, _)| &file.path[..i]);
+        for dir in ancestors {
+            let total = directories.entry(dir).or_insert_with(|| DirectorySize {
+                path: dir.to_owned(),
+                files: 0,
+                size: 0,
+            });
+            total.files += 1;
+            total.size += file.size;
+        }
+    }
+
+    EmbedReport {
+        size: files.iter().map(|file| file.size).sum(),
+        estimated_compressed: files.iter().map(|file| file.estimated_compressed).sum(),
+        directories: directories.into_values().collect(),
+        files,
+    }
+}
+
+/// The sizes of embedded files, see [embed_report].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmbedReport {
+    /// The total size of the files in bytes.
+    pub size: u64,
+    /// The estimated total size of the files in bytes if they were compressed.
+    pub estimated_compressed: u64,
+    /// The totals of the directories, including their subdirectories, ordered by path.
+    pub directories: Vec<DirectorySize>,
+    /// The files, largest first.
+    pub files: Vec<FileSize>,
+}
+
+/// The size of an embedded file, see [embed_report].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileSize {
+    /// The path of the file, e.g. `assets/index.css`.
+    pub path: String,
+    /// The size of the file in bytes.
+    pub size: u64,
+    /// The estimated size of the file in bytes if it was compressed, its size if its type is
+    /// compressed already.
+    ///
+    /// The estimate is derived from the frequencies of the bytes of the file. Compressors
+    /// exploiting repetitions usually do better on text files, so it is a rough, pessimistic
+    /// figure rather than a prediction.
+    pub estimated_compressed: u64,
+}
+
+/// The total size of the embedded files under a directory, see [embed_report].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirectorySize {
+    /// The path of the directory, e.g. `assets`.
+    pub path: String,
+    /// The number of files under the directory.
+    pub files: usize,
+    /// The total size of the files under the directory in bytes.
+    pub size: u64,
+}
+
+impl EmbedReport {
+    /// Returns the `n` largest files, largest first.
+    pub fn largest(&self, n: usize) -> &[FileSize] {
+        &self.files[..n.min(self.files.len())]
+    }
+}
+
+impl Display for EmbedReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{} files, {} bytes, ~{} bytes compressed",
+            self.files.len(),
+            self.size,
+            self.estimated_compressed
+        )?;
+        if !self.directories.is_empty() {
+            writeln!(f, "directories:")?;
+            for dir in &self.directories {
+                writeln!(
+                    f,
+                    "  {}/: {} files, {} bytes",
+                    dir.path, dir.files, dir.size
+                )?;
+            }
+        }
+        writeln!(f, "largest files:")?;
+        for file in self.largest(LISTED) {
+            writeln!(
+                f,
+                "  {}: {} bytes, ~{} bytes compressed",
+                file.path, file.size, file.estimated_compressed
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Estimates the compressed size of `data` by the order-0 entropy of its bytes.
+fn estimate_compressed(data: &[u8]) -> u64 {
+    if data.is_empty() {
+        return 0;
+    }
+    let mut counts = [0u64; 256];
+    for &byte in data {
+        counts[byte as usize] += 1;
+    }
+    let len = data.len() as f64;
+    let bits = counts
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = count as f64 / len;
+            -p * p.log2() * len
+        })
+        .sum::<f64>();
+    ((bits / 8.0).ceil() as u64).min(data.len() as u64)
+}
@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+
+use actix_web::HttpRequest;
+
+use crate::file_index::FileIndex;
+
+/// The client hints used to select image variants, as sent in `Accept-CH` and `Vary`.
+pub(crate) const CLIENT_HINTS: &str = "Sec-CH-DPR, Sec-CH-Width";
+
+/// The descriptor of an image variant, e.g. `@2x` or `@640w` in `hero@2x.png`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Descriptor {
+    /// The pixel density the variant is meant for.
+    Density(f32),
+    /// The width of the variant in physical pixels.
+    Width(u32),
+}
+
+/// Variants of embedded images, keyed by the logical path of the image.
+///
+/// `img/hero@1x.png` and `img/hero@2x.png` are variants of the logical path `img/hero.png`.
+#[derive(Debug, Default)]
+pub(crate) struct ResponsiveImages {
+    variants: HashMap<String, Vec<(Descriptor, String)>>,
+}
+
+impl ResponsiveImages {
+    /// Indexes the image variants in `index`.
+    pub(crate) fn build(index: &FileIndex) -> Self {
+        let mut variants = HashMap::<String, Vec<(Descriptor, String)>>::new();
+        for path in index.paths() {
+            if let Some((logical, descriptor)) = parse(path) {
+                variants
+                    .entry(logical)
+                    .or_default()
+                    .push((descriptor, path.clone()));
+            }
+        }
+        ResponsiveImages { variants }
+    }
+
+    /// Returns the path of the variant of the logical image at `path` best matching the
+    /// client hints of the request, `None` if there are no variants.
+    ///
+    /// The smallest variant at least as wide as `Sec-CH-Width` is selected, or the smallest
+    /// variant with a density of at least `Sec-CH-DPR` if there are no width variants or no
+    /// width hint. The largest variant is selected if none is large enough.
+    pub(crate) fn select(&self, req: &HttpRequest, path: &str) -> Option<&str> {
+        let variants = self.variants.get(path)?;
+        let width = hint(req, "sec-ch-width", "width");
+        let dpr = hint(req, "sec-ch-dpr", "dpr").unwrap_or(1.0);
+
+        let widths = variants
+            .iter()
+            .filter_map(|(descriptor, path)| match descriptor {
+                Descriptor::Width(w) => Some((*w as f32, path)),
+                Descriptor::Density(_) => None,
+            });
+        let densities = variants
+            .iter()
+            .filter_map(|(descriptor, path)| match descriptor {
+                Descriptor::Density(x) => Some((*x, path)),
+                Descriptor::Width(_) => None,
+            });
+
+        let selected = match width {
+            Some(width) if widths.clone().next().is_some() => best(widths, width),
+            _ if densities.clone().next().is_some() => best(densities, dpr),
+            _ => best(widths, f32::INFINITY),
+        };
+        selected.map(String::as_str)
+    }
+}
+
+/// Returns the smallest candidate at least as large as `wanted`, or the largest one.
+fn best<'a, I>(candidates: I, wanted: f32) -> Option<&'a String>
+where
+    I: Iterator<Item = (f32, &'a String)>,
+{
+    let mut candidates = candidates.collect::<Vec<_>>();
+    candidates.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+    candidates
+        .iter()
+        .find(|(size, _)| *size >= wanted)
+        .or_else(|| candidates.last())
+        .map(|(_, path)| *path)
+}
+
+/// Parses variant paths like `img/hero@2x.png`, returns the logical path and descriptor.
+fn parse(path: &str) -> Option<(String, Descriptor)> {
+    let (dir, name) = match path.rsplit_once('/') {
+        Some((dir, name)) => (Some(dir), name),
+        None => (None, path),
+    };
+    let (stem, ext) = name.rsplit_once('.')?;
+    let (stem, descriptor) = stem.rsplit_once('@')?;
+    let descriptor = if let Some(x) = descriptor.strip_suffix('x') {
+        Descriptor::Density(x.parse().ok().filter(|x: &f32| *x > 0.0)?)
+    } else {
+        Descriptor::Width(descriptor.strip_suffix('w')?.parse().ok()?)
+    };
+    let logical = match dir {
+        Some(dir) => format!("{}/{}.{}", dir, stem, ext),
+        None => format!("{}.{}", stem, ext),
+    };
+    Some((logical, descriptor))
+}
+
+fn hint(req: &HttpRequest, name: &str, legacy: &str) -> Option<f32> {
+    let headers = req.headers();
+    let value = headers.get(name).or_else(|| headers.get(legacy))?;
+    value
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+        .filter(|v: &f32| v.is_finite() && *v > 0.0)
+}
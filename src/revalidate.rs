@@ -0,0 +1,90 @@
+use std::pin::Pin;
+use std::rc::Rc;
+use std::str::FromStr;
+
+use actix_web::dev::Payload;
+use actix_web::http::header::{self, EntityTag};
+use actix_web::HttpResponse;
+use futures_core::Stream;
+use serde::{Deserialize, Serialize};
+
+use crate::headers;
+
+/// The maximum size of revalidation request bodies, enough for tens of thousands of entries.
+const MAX_BODY: usize = 4 << 20;
+
+/// A file cached by the client, as sent to the revalidation endpoint.
+#[derive(Debug, Deserialize)]
+pub(crate) struct Cached {
+    path: String,
+    etag: String,
+}
+
+#[derive(Debug, Serialize)]
+struct Stale<'a> {
+    path: &'a str,
+    /// The current entity tag of the file, `None` if it is not served anymore.
+    etag: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct Report<'a> {
+    stale: Vec<Stale<'a>>,
+}
+
+/// Reads the cached files from the body of a revalidation request, returns the error response
+/// if the body is too large or malformed.
+pub(crate) async fn read(payload: &mut Payload) -> Result<Vec<Cached>, HttpResponse> {
+    let mut body = Vec::new();
+    while let Some(chunk) = std::future::poll_fn(|cx| Pin::new(&mut *payload).poll_next(cx)).await {
+        let chunk = chunk.map_err(|_| HttpResponse::BadRequest().finish())?;
+        if body.len() + chunk.len() > MAX_BODY {
+            return Err(HttpResponse::PayloadTooLarge().finish());
+        }
+        body.extend_from_slice(&chunk);
+    }
+    serde_json::from_slice(&body)
+        .map_err(|err| HttpResponse::BadRequest().body(format!("invalid request: {}", err)))
+}
+
+/// Returns the response listing the stale entries of `cached`.
+///
+/// `current` returns the content hash of the file at a path, `None` if there is no such file.
+pub(crate) fn response<C>(cached: &[Cached], current: C) -> HttpResponse
+where
+    C: Fn(&str) -> Option<Rc<str>>,
+{
+    let stale = cached
+        .iter()
+        .filter_map(|entry| {
+            let path = entry.path.trim_start_matches('/');
+            match current(path) {
+                Some(hash) if is_current(&entry.etag, &hash) => None,
+                hash => Some(Stale {
+                    path: &entry.path,
+                    etag: hash.map(|hash| EntityTag::new_strong(hash.to_string()).to_string()),
+                }),
+            }
+        })
+        .collect();
+    let body = serde_json::to_string(&Report { stale }).unwrap_or_default();
+    HttpResponse::Ok()
+        .content_type("application/json")
+        .insert_header((header::CACHE_CONTROL, headers::NO_CACHE))
+        .body(body)
+}
+
+/// Returns `true` if `etag` was sent with a variant of the file with content hash `hash`.
+///
+/// Entity tags of compressed and derived variants start with the hash of the file.
+fn is_current(etag: &str, hash: &str) -> bool {
+    let tag = match EntityTag::from_str(etag) {
+        Ok(etag) => etag.tag().to_owned(),
+        // tolerate unquoted tags
+        Err(_) => etag.to_owned(),
+    };
+    tag == hash
+        || tag
+            .strip_prefix(hash)
+            .is_some_and(|rest| rest.starts_with('-'))
+}
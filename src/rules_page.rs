@@ -0,0 +1,46 @@
+use actix_web::http::header::{self, Accept, Header};
+use actix_web::mime::TEXT_HTML;
+use actix_web::{HttpRequest, HttpResponse};
+
+use crate::describe::Description;
+use crate::fallback_handler::escape_html;
+use crate::headers;
+
+/// Returns the response rendering the effective configuration `description`, see
+/// [Embed::rules_endpoint](crate::Embed::rules_endpoint).
+///
+/// Browsers, which prefer `text/html`, get a table of the options, other clients the JSON
+/// of [Description].
+pub(crate) fn response(req: &HttpRequest, description: &Description) -> HttpResponse {
+    let html = Accept::parse(req)
+        .ok()
+        .and_then(|accept| accept.ranked().into_iter().next())
+        .is_some_and(|preferred| preferred == TEXT_HTML);
+    let mut builder = HttpResponse::Ok();
+    builder
+        .insert_header((header::CACHE_CONTROL, headers::NO_CACHE))
+        .insert_header((header::VARY, "Accept"));
+    if !html {
+        return builder
+            .content_type("application/json")
+            .body(description.to_string());
+    }
+    let rows = description
+        .entries()
+        .iter()
+        .map(|(name, value)| {
+            format!(
+                "<tr><th>{}</th><td><code>{}</code></td></tr>",
+                escape_html(name),
+                escape_html(&value.to_string())
+            )
+        })
+        .collect::<String>();
+    builder
+        .content_type("text/html; charset=utf-8")
+        .body(format!(
+            "<!DOCTYPE html><html><head><meta charset=\"utf-8\"><title>Embed rules</title></head>\
+         <body><h1>Embed rules</h1><table>{}</table></body></html>",
+            rows
+        ))
+}
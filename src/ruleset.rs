@@ -0,0 +1,224 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Display, Formatter};
+
+use crate::describe::Value;
+use crate::glob::Glob;
+use crate::headers;
+use crate::options::EmbedOptions;
+
+/// What a [Rule] does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuleKind {
+    /// Requests are redirected to the location in [Rule::value].
+    Redirect,
+    /// Requests are served with the file at the path in [Rule::value], only if there is no
+    /// file at the requested path.
+    Rewrite,
+    /// Responses carry the header [Rule::header] with [Rule::value].
+    Header,
+    /// Requests are served with a tar archive of the directory in [Rule::value].
+    Archive,
+}
+
+impl RuleKind {
+    /// Returns the name of the kind, e.g. `redirect`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RuleKind::Redirect => "redirect",
+            RuleKind::Rewrite => "rewrite",
+            RuleKind::Header => "header",
+            RuleKind::Archive => "archive",
+        }
+    }
+}
+
+/// A rule applied to requests, see [Ruleset].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rule {
+    /// What the rule does.
+    pub kind: RuleKind,
+    /// The request paths the rule applies to, including the mount path, e.g. `/static/` or
+    /// `/static/**/*.woff2`.
+    ///
+    /// `*` matches any characters except `/`, `**` any characters.
+    pub pattern: String,
+    /// The status code of redirects.
+    pub status: Option<u16>,
+    /// The name of the header of header rules.
+    pub header: Option<String>,
+    /// The location of redirects, the path of rewrites, the header value or the archived
+    /// directory.
+    ///
+    /// `{path}` stands for the request path, without trailing slash.
+    pub value: String,
+}
+
+/// The routing, redirect and header rules of an [Embed](crate::Embed), returned by
+/// [Embed::ruleset](crate::Embed::ruleset).
+///
+/// Rules are listed in the order `Embed` applies them, so they can be mirrored into the
+/// configuration of a CDN, keeping the edge consistent with the origin. The [Display]
+/// implementation formats the rules as JSON, [to_csv](Self::to_csv) as CSV.
+///
+/// Only rules depending on the request path alone are listed. Content negotiation, hooks,
+/// the fallback handler and [maintenance](crate::Embed::maintenance) are not.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ruleset {
+    /// The rules, in the order they are applied.
+    pub rules: Vec<Rule>,
+}
+
+impl Ruleset {
+    /// Collects the rules of the service mounted at `mount_path`, e.g. `/static` or `""`.
+    pub(crate) fn new(mount_path: &str, options: &EmbedOptions) -> Self {
+        let path = |relative: &str| format!("{}/{}", mount_path, relative);
+        let mut rules = Vec::new();
+        let mut rule = |kind, pattern, status, header: Option<&str>, value| {
+            rules.push(Rule {
+                kind,
+                pattern,
+                status,
+                header: header.map(str::to_owned),
+                value,
+            })
+        };
+
+        if let Some(canonical) = &options.canonical_host {
+            let value = format!("{}://{}{{path}}", canonical.scheme, canonical.host);
+            rule(RuleKind::Redirect, path("**"), Some(301), None, value);
+        }
+        for (from, dir) in sorted(&options.archives) {
+            rule(RuleKind::Archive, path(from), None, None, dir.clone());
+        }
+        if let Some(index) = &options.index_file_path {
+            rule(RuleKind::Rewrite, path(""), None, None, path(index));
+        }
+        if options.clean_urls {
+            let value = "{path}.html".to_owned();
+            rule(RuleKind::Rewrite, path("**"), None, None, value);
+        }
+        if let Some(name) = &options.directory_index {
+            let value = format!("{{path}}/{}", name);
+            rule(RuleKind::Rewrite, path("**"), None, None, value);
+        }
+        for (from, to) in sorted(&options.package_exports) {
+            rule(RuleKind::Redirect, path(from), Some(302), None, path(to));
+        }
+        for (from, to) in sorted(&options.redirects) {
+            rule(RuleKind::Redirect, path(from), Some(301), None, path(to));
+        }
+        if let Some(value) = options.accept_ranges.header_value() {
+            let value = value.to_owned();
+            rule(
+                RuleKind::Header,
+                path("**"),
+                None,
+                Some("accept-ranges"),
+                value,
+            );
+        }
+        for header_rule in &options.header_rules {
+            let patterns = match (header_rule.globs.is_empty(), header_rule.extensions) {
+                (false, []) => header_rule
+                    .globs
+                    .iter()
+                    .map(|glob| path(glob.as_str()))
+                    .collect(),
+                (false, extensions) => header_rule
+                    .globs
+                    .iter()
+                    .flat_map(|glob| {
+                        extensions
+                            .iter()
+                            .filter_map(|ext| with_extension(glob, ext))
+                    })
+                    .map(|glob| path(&glob))
+                    .collect(),
+                (true, []) => vec![path("**")],
+                (true, extensions) => extensions
+                    .iter()
+                    .map(|ext| path(&format!("**/*.{}", ext)))
+                    .collect::<Vec<_>>(),
+            };
+            for pattern in patterns {
+                for (name, value) in &header_rule.headers {
+                    let value = String::from_utf8_lossy(value.as_bytes()).into_owned();
+                    rule(
+                        RuleKind::Header,
+                        pattern.clone(),
+                        None,
+                        Some(name.as_str()),
+                        value,
+                    );
+                }
+            }
+        }
+        Ruleset { rules }
+    }
+
+    /// Formats the rules as CSV, with the columns `kind`, `pattern`, `status`, `header` and
+    /// `value`, and a header row.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("kind,pattern,status,header,value\r\n");
+        for rule in &self.rules {
+            let status = rule.status.map(|status| status.to_string());
+            let fields = [
+                rule.kind.as_str(),
+                &rule.pattern,
+                status.as_deref().unwrap_or_default(),
+                rule.header.as_deref().unwrap_or_default(),
+                &rule.value,
+            ];
+            for (i, field) in fields.iter().enumerate() {
+                if i > 0 {
+                    csv.push(',');
+                }
+                csv.push_str(&csv_field(field));
+            }
+            csv.push_str("\r\n");
+        }
+        csv
+    }
+}
+
+impl Display for Ruleset {
+    /// Formats the rules as a JSON list of objects.
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let rules = self.rules.iter().map(|rule| {
+            Value::object([
+                ("kind", Value::from(rule.kind.as_str())),
+                ("pattern", rule.pattern.as_str().into()),
+                ("status", rule.status.map(u32::from).into()),
+                ("header", rule.header.as_deref().into()),
+                ("value", rule.value.as_str().into()),
+            ])
+        });
+        write!(f, "{}", Value::list(rules))
+    }
+}
+
+/// Returns the entries of `map` ordered by key, so the rules are listed in a stable order.
+fn sorted(map: &HashMap<String, String>) -> BTreeMap<&String, &String> {
+    map.iter().collect()
+}
+
+/// Returns the pattern matching the paths matching `glob` with the extension `ext`, `None`
+/// if there are none.
+fn with_extension(glob: &Glob, ext: &str) -> Option<String> {
+    let glob = glob.as_str();
+    if glob.ends_with('*') {
+        return Some(format!("{}.{}", glob, ext));
+    }
+    headers::extension(glob)
+        .filter(|candidate| candidate.eq_ignore_ascii_case(ext))
+        .map(|_| glob.to_owned())
+}
+
+/// Quotes `field` if it contains separators, quotes or line breaks, as defined by RFC 4180.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
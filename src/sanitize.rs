@@ -0,0 +1,175 @@
+use std::collections::HashMap;
+use std::ops::Range;
+
+use xmlparser::{ElementEnd, Token, Tokenizer};
+
+use crate::file_index::{FileIndex, KeyMap};
+use crate::headers;
+
+/// Served instead of SVG images which cannot be parsed, so nothing unchecked is served.
+const EMPTY: &[u8] = b"<svg xmlns=\"http://www.w3.org/2000/svg\"/>";
+
+/// Elements removed with their content, since they run scripts or embed documents which can.
+const REMOVED_ELEMENTS: &[&str] = &["script", "foreignObject", "iframe", "embed", "object"];
+
+/// Attributes whose values are URLs, or may be set to URLs by animations.
+const URL_ATTRIBUTES: &[&str] = &[
+    "href",
+    "src",
+    "action",
+    "formaction",
+    "from",
+    "to",
+    "values",
+];
+
+/// Returns the sanitized content of the SVG images served by `E`, by key, see
+/// [Embed::sanitize_svg](crate::Embed::sanitize_svg).
+pub(crate) fn sanitize_all<E>(index: &FileIndex, keys: &KeyMap) -> HashMap<String, Vec<u8>>
+where
+    E: 'static + rust_embed::RustEmbed,
+{
+    index
+        .paths()
+        .iter()
+        .filter(|path| headers::extension(path).is_some_and(|ext| ext.eq_ignore_ascii_case("svg")))
+        .filter_map(|path| {
+            let (key, file) = keys.get::<E>(path)?;
+            Some((key, sanitize(&file.data)))
+        })
+        .collect()
+}
+
+/// Removes scripts, embedded documents, event handlers, `javascript:` URLs and document type
+/// declarations, which can define entities expanding to any of them, from an SVG image.
+///
+/// Everything else is kept byte for byte.
+pub(crate) fn sanitize(svg: &[u8]) -> Vec<u8> {
+    let removed = match std::str::from_utf8(svg).ok().and_then(removed_ranges) {
+        Some(removed) => removed,
+        None => return EMPTY.to_vec(),
+    };
+    let mut sanitized = Vec::with_capacity(svg.len());
+    let mut kept = 0;
+    for range in removed {
+        sanitized.extend_from_slice(&svg[kept..range.start]);
+        kept = range.end;
+    }
+    sanitized.extend_from_slice(&svg[kept..]);
+    sanitized
+}
+
+/// Returns the ranges of `svg` to remove in order, `None` if it is not well-formed.
+fn removed_ranges(svg: &str) -> Option<Vec<Range<usize>>> {
+    let mut removed = Vec::new();
+    // the depth of the current element in the removed element, `0` outside of it
+    let mut depth = 0;
+    let mut start = 0;
+    let mut previous_end = 0;
+    for token in Tokenizer::from(svg) {
+        let token = token.ok()?;
+        match token {
+            Token::ElementStart { local, span, .. } => {
+                if depth > 0 {
+                    depth += 1;
+                } else if REMOVED_ELEMENTS
+                    .iter()
+                    .any(|name| name.eq_ignore_ascii_case(&local))
+                {
+                    depth = 1;
+                    start = span.start();
+                }
+            }
+            Token::ElementEnd { end, span } if depth > 0 => {
+                if !matches!(end, ElementEnd::Open) {
+                    depth -= 1;
+                }
+                if depth == 0 {
+                    removed.push(start..span.end());
+                }
+            }
+            // with the whitespace before the attribute
+            Token::Attribute {
+                local, value, span, ..
+            } if depth == 0 && is_unsafe_attribute(&local, &value) => {
+                removed.push(previous_end..span.end());
+            }
+            Token::DtdStart { span, .. } if depth == 0 => start = span.start(),
+            Token::DtdEnd { span } if depth == 0 => removed.push(start..span.end()),
+            Token::EmptyDtd { span, .. } if depth == 0 => removed.push(span.range()),
+            _ => {}
+        }
+        previous_end = match token {
+            Token::Declaration { span, .. }
+            | Token::ProcessingInstruction { span, .. }
+            | Token::Comment { span, .. }
+            | Token::DtdStart { span, .. }
+            | Token::EmptyDtd { span, .. }
+            | Token::EntityDeclaration { span, .. }
+            | Token::DtdEnd { span }
+            | Token::ElementStart { span, .. }
+            | Token::Attribute { span, .. }
+            | Token::ElementEnd { span, .. }
+            | Token::Cdata { span, .. }
+            | Token::Text { text: span } => span.end(),
+        };
+    }
+    // unclosed removed elements
+    (depth == 0).then_some(removed)
+}
+
+/// Returns `true` for event handlers and URL attributes with scripts.
+fn is_unsafe_attribute(name: &str, value: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    if name.starts_with("on") {
+        return true;
+    }
+    if !URL_ATTRIBUTES.contains(&name.as_str()) {
+        return false;
+    }
+    // browsers ignore whitespace and control characters in schemes
+    let url = unescape(value)
+        .chars()
+        .filter(|c| !c.is_ascii_whitespace() && !c.is_control())
+        .take(11)
+        .collect::<String>()
+        .to_ascii_lowercase();
+    url.starts_with("javascript:") || url.starts_with("vbscript:")
+}
+
+/// Replaces the character and predefined entity references of an attribute value.
+fn unescape(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(i) = rest.find('&') {
+        unescaped.push_str(&rest[..i]);
+        rest = &rest[i..];
+        let reference = rest[1..].split_once(';').map(|(reference, _)| reference);
+        let c = reference.and_then(|reference| match reference {
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "amp" => Some('&'),
+            "apos" => Some('\''),
+            "quot" => Some('"'),
+            _ => {
+                let code = match reference.strip_prefix("#x") {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                    None => reference.strip_prefix('#')?.parse().ok()?,
+                };
+                char::from_u32(code)
+            }
+        });
+        match (c, reference) {
+            (Some(c), Some(reference)) => {
+                unescaped.push(c);
+                rest = &rest[reference.len() + 2..];
+            }
+            _ => {
+                unescaped.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    unescaped.push_str(rest);
+    unescaped
+}
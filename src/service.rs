@@ -1,18 +1,89 @@
-use std::fmt::{Debug, Formatter};
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::collections::HashSet;
+use std::fmt::{Debug, Display, Formatter};
 use std::marker::PhantomData;
+use std::ops::RangeBounds;
+use std::path::PathBuf;
 use std::rc::Rc;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
 
 use actix_web::body::BoxBody;
 use actix_web::dev::{
-    AppService, HttpServiceFactory, ResourceDef, Service, ServiceFactory, ServiceRequest,
-    ServiceResponse,
+    AppService, HttpServiceFactory, RequestHead, ResourceDef, Service, ServiceFactory,
+    ServiceRequest, ServiceResponse, Url,
 };
-use actix_web::http::{header, Method};
-use actix_web::HttpResponse;
+use actix_web::guard::{self, Guard};
+use actix_web::http::header::{EntityTag, HeaderName, HeaderValue};
+use actix_web::http::{header, Method, StatusCode};
+use actix_web::web::Bytes;
+use actix_web::{HttpMessage, HttpRequest, HttpResponse, Responder};
 use futures_core::future::LocalBoxFuture;
-use mime_guess::MimeGuess;
+use rust_embed::EmbeddedFile;
 
+use crate::admin::{EmbedAdmin, Generation};
+use crate::archive::TarStream;
+use crate::availability::{self, Clock, Window};
+use crate::cache_status::CacheStatus;
+use crate::canary::{CanaryService, Cohort, ErasedFallback};
+use crate::canonical::Scheme;
+use crate::charset;
+use crate::chunked::DEFAULT_CHUNK;
+use crate::conditional::{self, Precondition, RevalidationDecision, Validators};
+use crate::content_address::{self, ContentAddresses};
+use crate::context::ServeContext;
+use crate::cookie_gate::CookieVerifier;
+use crate::cors::Cors;
+use crate::decision::Decision;
+use crate::deprecation::Sunset;
+use crate::derived_cache::{Caches, DerivedCache};
+use crate::describe::Description;
+use crate::encoding;
+use crate::encoding::{Compression, Encoding, OuterCompression};
+use crate::endpoint_cache::EndpointCache;
+use crate::error::{ConfigIssue, Severity};
+use crate::events::{Deprecated, RecordedBody, ServeRecord, ServeStage, Served};
 use crate::fallback_handler::{DefaultFallbackHandler, FallbackHandler};
+use crate::file_index::{Entry, FileIndex, Visibility, SANITIZED};
+use crate::forwarded::ForwardedPolicy;
+use crate::glob::Glob;
+use crate::hasher::{ContentHashes, Hasher, Sha256};
+use crate::headers::{self, AcceptRanges, FramePolicy, HeaderRule, Priority};
+use crate::hooks::{FailurePolicy, HookError, HookFailure, HookPanic};
+use crate::http10;
+use crate::http_util;
+use crate::import_map::ImportMap;
+use crate::language;
+use crate::listing;
+use crate::maintenance::EmbedHandle;
+use crate::metrics::{CacheKind, ServeMetrics};
+#[cfg(feature = "minify")]
+use crate::minify::MinifyCache;
+use crate::mount_registry::{self, MountRegistry};
+use crate::options::EmbedOptions;
+use crate::plugin::EmbedPlugin;
+use crate::prepare::{
+    Preparation, PrepareProgress, PrepareStage, PreparedFile, PreparedFiles, ProgressHook,
+};
+use crate::query::{self, QueryRule};
+use crate::rate_limiter::{RateLimitDecision, RateLimiter};
+use crate::registry::{AssetCollision, CollisionPolicy, EmbedRegistry};
+use crate::responsive;
+use crate::revalidate;
+#[cfg(debug_assertions)]
+use crate::rules_page;
+use crate::ruleset::Ruleset;
+use crate::shared::{SharedState, WarmOptions, WarmState};
+use crate::sniff::SniffAction;
+use crate::stages::StageClock;
+use crate::suggest::Suggestions;
+use crate::throttle;
+use crate::timeout::{Deadline, TimedOut, TimeoutAction};
+use crate::tolerance::{self, PathTolerance};
+use crate::trace::Trace;
+use crate::variant_cache::{FileResponse, Generated, Profile, VariantCache};
+use crate::version::{BundleVersion, VersionInfo};
 
 /// Wrapper of rust_embed for actix.
 ///
@@ -33,18 +104,76 @@ use crate::fallback_handler::{DefaultFallbackHandler, FallbackHandler};
 /// let app = App::new()
 ///     .service(Embed::new("/static", &Assets));
 /// ```
+///
+/// With the `tracing` feature, each request is served in an `actix_embed::serve` span, a
+/// child of the span current when the request reaches the service, and user hooks such as
+/// the fallback handler, the rate limiter and [on_serve](Self::on_serve) are called in a
+/// `hook` span within it. Calls made from hooks are thereby correlated with the request.
 pub struct Embed<E, F>
 where
     E: 'static + rust_embed::RustEmbed,
     F: FallbackHandler,
 {
     mount_path: String,
-    index_file_path: Option<String>,
-    strict_slash: bool,
+    options: EmbedOptions,
     fallback_handler: F,
     _f: PhantomData<E>,
 }
 
+/// Determines how the mount path of [Embed] is matched against request paths.
+///
+/// Defaults to [MountKind::Prefix].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MountKind {
+    /// Match the mount path and every path under it, e.g. `/static` matches `/static`,
+    /// `/static/` and `/static/index.html`.
+    ///
+    /// A leading `/` is inserted when registered on the application root.
+    #[default]
+    Prefix,
+    /// Match the mount path only. Requests are served with the index file.
+    Exact,
+    /// Match paths under the mount path only, e.g. `/static` matches `/static/` and
+    /// `/static/index.html`, but not `/static`.
+    Tail,
+}
+
+/// The name of the dynamic segment capturing the file path for [MountKind::Tail].
+const TAIL_SEGMENT: &str = "embed_tail";
+
+/// The file served for an [index file](Embed::index_file) naming a directory, if there is no
+/// [directory index](Embed::directory_index).
+const DEFAULT_INDEX: &str = "index.html";
+
+impl MountKind {
+    pub(crate) fn resource_def(self, mount_path: &str, is_root: bool) -> ResourceDef {
+        let path = if is_root && !mount_path.is_empty() && !mount_path.starts_with('/') {
+            format!("/{}", mount_path)
+        } else {
+            mount_path.to_owned()
+        };
+        match self {
+            MountKind::Prefix => ResourceDef::prefix(path),
+            MountKind::Exact if is_root && path.is_empty() => ResourceDef::new("/"),
+            MountKind::Exact => ResourceDef::new(path),
+            MountKind::Tail => ResourceDef::new(format!(
+                "{}/{{{}}}*",
+                path.trim_end_matches('/'),
+                TAIL_SEGMENT
+            )),
+        }
+    }
+
+    /// Returns the part of the request path which should be looked up in the embedded files.
+    pub(crate) fn lookup_path(self, req: &ServiceRequest) -> &str {
+        match self {
+            MountKind::Prefix => req.match_info().unprocessed(),
+            MountKind::Exact => "",
+            MountKind::Tail => req.match_info().get(TAIL_SEGMENT).unwrap_or(""),
+        }
+    }
+}
+
 impl<E, F> Debug for Embed<E, F>
 where
     E: 'static + rust_embed::RustEmbed,
@@ -63,21 +192,166 @@ where
     ///
     /// # Arguments
     /// The first argument (`mount_path`) is the root URL at which the embed files are served.
-    /// For example, `/assets` will serve files at `example.com/assets/...`.
+    /// For example, `/assets` will serve files at `example.com/assets/...`. It is normalized to
+    /// start with `/`, trailing and repeated slashes are removed.
     ///
     /// The second argument (`assets`) is the instance implements [rust_embed::RustEmbed].
     /// For more information, see rust_embed documentation: https://docs.rs/rust-embed/
     ///
     /// # Notes
     /// If the mount path is set as the root path `/`, services registered after this one will
-    /// be inaccessible. Register more specific handlers and services before it.
-    #[allow(unused_variables)]
+    /// be inaccessible. Register more specific handlers and services before it, or let it
+    /// [claim only known paths](Self::claim_only_known_paths). Such conflicts can be detected
+    /// with a [MountRegistry].
     pub fn new<P: AsRef<str>>(mount_path: P, assets: &E) -> Self {
+        Self::with_options(mount_path, assets, EmbedOptions::default())
+    }
+
+    /// Create new [Embed] instance serving `/.well-known/*` with the files under `folder`,
+    /// e.g. `security.txt` and `change-password`, independent of other mount paths.
+    ///
+    /// Files are served with `Cache-Control: no-cache`, since clients must see changes to
+    /// them immediately. ACME challenges (`/.well-known/acme-challenge/*`) are excluded, so
+    /// they reach the ACME client's handler wherever it is registered. To serve them from
+    /// the embedded files as well, use [new](Self::new) with [folder](Self::folder).
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::Embed;
+    /// use actix_web::App;
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let app = App::new()
+    ///     .service(Embed::well_known(&Assets, "well-known/"))
+    ///     .service(Embed::new("/", &Assets).index_file("index.html"));
+    /// ```
+    pub fn well_known<P: AsRef<str>>(assets: &E, folder: P) -> Self {
+        let mut embed = Self::new("/.well-known", assets)
+            .folder(folder)
+            .exclude(["acme-challenge/**"]);
+        embed.options.header_rules.push(HeaderRule {
+            extensions: &[],
+            globs: Vec::new(),
+            headers: vec![(
+                header::CACHE_CONTROL,
+                HeaderValue::from_static(headers::NO_CACHE),
+            )],
+        });
+        embed
+    }
+
+    /// Create new [Embed] instance serving `/favicon.ico` with the file at `path`.
+    ///
+    /// The content type is derived from `path`, so icons in other formats can be served too.
+    pub fn favicon<P: AsRef<str>>(assets: &E, path: P) -> Self {
+        Self::new("/favicon.ico", assets)
+            .mount_kind(MountKind::Exact)
+            .index_file(path)
+    }
+
+    /// Create new [Embed] instance serving a single-page application at `mount_path`.
+    ///
+    /// `index.html` is served at the mount path, and with `200 OK` at every path without an
+    /// embedded file, so the client-side router handles it.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::Embed;
+    /// use actix_web::App;
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let app = App::new().service(Embed::spa("/app", &Assets));
+    /// ```
+    pub fn spa<P: AsRef<str>>(mount_path: P, assets: &E) -> Self {
+        Self::new(mount_path, assets)
+            .index_file("index.html")
+            .fallback_handler(
+                DefaultFallbackHandler::new()
+                    .status(StatusCode::OK)
+                    .file::<E, _>("index.html"),
+            )
+    }
+
+    /// Create new [Embed] instance serving a documentation site at `mount_path`, e.g. the
+    /// output of a static site generator.
+    ///
+    /// Directories are served with their `index.html`, and HTML files without their
+    /// extension, see [directory_index](Self::directory_index) and
+    /// [clean_urls](Self::clean_urls).
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::Embed;
+    /// use actix_web::App;
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let app = App::new().service(Embed::docs_site("/docs", &Assets));
+    /// ```
+    pub fn docs_site<P: AsRef<str>>(mount_path: P, assets: &E) -> Self {
+        Self::new(mount_path, assets)
+            .index_file("index.html")
+            .directory_index("index.html")
+            .clean_urls(true)
+    }
+
+    /// Create new [Embed] instance serving files for download at `mount_path`.
+    ///
+    /// Files are served with `Content-Disposition: attachment`, so browsers save them
+    /// instead of displaying them.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::Embed;
+    /// use actix_web::App;
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let app = App::new().service(Embed::downloads("/files", &Assets));
+    /// ```
+    pub fn downloads<P: AsRef<str>>(mount_path: P, assets: &E) -> Self {
+        let mut embed = Self::new(mount_path, assets);
+        embed.options.header_rules.push(HeaderRule {
+            extensions: &[],
+            globs: Vec::new(),
+            headers: vec![(
+                header::CONTENT_DISPOSITION,
+                HeaderValue::from_static("attachment"),
+            )],
+        });
+        embed
+    }
+
+    /// Create new [Embed] instance with the given settings.
+    ///
+    /// The arguments are the same as [new](Self::new), settings can be shared by multiple
+    /// instances with [EmbedOptions]. Further builder methods apply to this instance only.
+    #[allow(unused_variables)]
+    pub fn with_options<P: AsRef<str>>(mount_path: P, assets: &E, options: EmbedOptions) -> Self {
+        Self::mounted(mount_path.as_ref(), options)
+    }
+
+    /// Returns the instance serving the files of `E` at `mount_path` with `options`, for
+    /// builders which only know the type of the embedded files.
+    pub(crate) fn mounted(mount_path: &str, options: EmbedOptions) -> Self {
         Embed {
-            mount_path: mount_path.as_ref().trim_end_matches('/').to_owned(),
-            index_file_path: None,
-            strict_slash: false,
-            fallback_handler: DefaultFallbackHandler,
+            mount_path: mount_registry::normalize(mount_path),
+            options,
+            fallback_handler: DefaultFallbackHandler::default(),
             _f: Default::default(),
         }
     }
@@ -88,13 +362,164 @@ where
     E: 'static + rust_embed::RustEmbed,
     F: FallbackHandler,
 {
+    /// Try the languages `fallbacks` in order when no index file matches the language
+    /// `range`, e.g. `pt-BR -> pt -> en`.
+    ///
+    /// By default a range falls back to its primary language only, e.g. `pt-BR` to `pt`.
+    /// Ranges are matched case-insensitively, see [localized_index](Self::localized_index).
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::Embed;
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let embed = Embed::new("/", &Assets)
+    ///     .localized_index(["en/index.html", "de/index.html"], "en/index.html")
+    ///     .language_fallback("de-CH", ["de", "en"])
+    ///     .language_fallback("lb", ["de"]);
+    /// ```
+    pub fn language_fallback<R, I>(mut self, range: R, fallbacks: I) -> Self
+    where
+        R: AsRef<str>,
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        self.options = self.options.language_fallback(range, fallbacks);
+        self
+    }
+
+    /// Let users choose the language of the [localized index](Self::localized_index) with the
+    /// query parameter `param`, e.g. `/?lang=de`, remembered in the cookie `cookie`.
+    ///
+    /// By default the language is selected by the `Accept-Language` header only.
+    ///
+    /// An explicit choice beats `Accept-Language`: the language of the query parameter is
+    /// selected first, then the one remembered in the cookie. A chosen language with an index
+    /// file, directly or by its [fallback chain](Self::language_fallback), is remembered with
+    /// `Set-Cookie` for a year. Responses carry `Vary: Cookie`.
+    ///
+    /// An invalid cookie name is reported by [try_build](Self::try_build).
+    pub fn language_override<P: AsRef<str>, C: AsRef<str>>(mut self, param: P, cookie: C) -> Self {
+        self.options = self.options.language_override(param, cookie);
+        self
+    }
+
+    /// Set whether files in locale directories, e.g. `de/guide.html` or `pt-BR/index.html`,
+    /// are served with the `Content-Language` of the directory.
+    ///
+    /// Defaults to `false`.
+    ///
+    /// The first directory of the path is a locale directory if it is named after a two-letter
+    /// ISO 639-1 language code, optionally followed by a region or script subtag, e.g. `de`,
+    /// `pt-BR`, `es-419` or `zh-Hant`. Since the language does not depend on the request,
+    /// responses do not carry `Vary: Accept-Language`. The language selected by a
+    /// [localized index](Self::localized_index) takes precedence.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::Embed;
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let embed = Embed::new("/", &Assets).content_language_from_path(true);
+    /// ```
+    pub fn content_language_from_path(mut self, enabled: bool) -> Self {
+        self.options = self.options.content_language_from_path(enabled);
+        self
+    }
+
+    /// Set whether responses with a negotiated file carry its URL in `Content-Location`.
+    ///
+    /// Defaults to `false`.
+    ///
+    /// When the file served for a request is selected by the request headers rather than its
+    /// path, i.e. by a [localized index](Self::localized_index) or among
+    /// [responsive images](Self::responsive_images), the response carries the URL the
+    /// selected file is also served at, e.g. `Content-Location: /de/index.html`, so clients
+    /// and caches can request the variant directly. Compressed variants are not served at URLs
+    /// of their own, so the encoding does not change the `Content-Location`.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::Embed;
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let embed = Embed::new("/", &Assets)
+    ///     .localized_index(["en/index.html", "de/index.html"], "en/index.html")
+    ///     .content_location(true);
+    /// ```
+    pub fn content_location(mut self, enabled: bool) -> Self {
+        self.options = self.options.content_location(enabled);
+        self
+    }
+
     /// Set whether to ignore the trailing slash of the requested path.
     ///
     /// Defaults to `false`.
     ///
     /// If it's set to true, then file '/dir/file' cannot be accessed by request path '/dir/file/'.
     pub fn strict_slash(mut self, strict_slash: bool) -> Self {
-        self.strict_slash = strict_slash;
+        self.options = self.options.strict_slash(strict_slash);
+        self
+    }
+
+    /// Set how request paths ending in dots or spaces are treated, e.g. `/file.txt.` or
+    /// `/file.txt%20`, as found in links created on Windows.
+    ///
+    /// Defaults to [PathTolerance::Strict].
+    ///
+    /// With [PathTolerance::Trim] the trailing dots, spaces and encoded spaces are removed
+    /// before the path is looked up, so such files cannot be served at their own paths. With
+    /// [PathTolerance::Redirect], `GET` and `HEAD` requests are redirected to the trimmed path,
+    /// keeping the query.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::{Embed, PathTolerance};
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let embed = Embed::new("/", &Assets).path_tolerance(PathTolerance::Redirect);
+    /// ```
+    pub fn path_tolerance(mut self, tolerance: PathTolerance) -> Self {
+        self.options = self.options.path_tolerance(tolerance);
+        self
+    }
+
+    /// Set how the mount path is matched against request paths.
+    ///
+    /// Defaults to [MountKind::Prefix].
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::{Embed, MountKind};
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// // only `/home` is served, with the index file
+    /// let embed = Embed::new("/home", &Assets)
+    ///     .mount_kind(MountKind::Exact)
+    ///     .index_file("index.html");
+    /// ```
+    pub fn mount_kind(mut self, mount_kind: MountKind) -> Self {
+        self.options = self.options.mount_kind(mount_kind);
         self
     }
 
@@ -102,21 +527,2687 @@ where
     ///
     /// By default there is no index file.
     ///
-    /// The index file is treated as the default file returned when a request
-    /// visit the root directory.
-    pub fn index_file<P: AsRef<str>>(mut self, path: P) -> Self {
-        self.index_file_path = Some(
-            path.as_ref()
-                .trim_end_matches('/')
-                .trim_start_matches('/')
-                .to_string(),
-        );
+    /// The index file is treated as the default file returned when a request
+    /// visit the root directory.
+    ///
+    /// The index file may name a directory, e.g. `docs/`, whose
+    /// [directory index](Self::directory_index) is then served, `index.html` if none is set.
+    ///
+    /// Requests are resolved in this order, the first match being served:
+    /// 1. the file at the requested path, the index file for the mount path;
+    /// 2. the HTML file of a [clean URL](Self::clean_urls);
+    /// 3. the directory index of the requested path, or of the directory named by the index
+    ///    file;
+    /// 4. [package exports](Self::package_export) and [redirects](Self::redirect);
+    /// 5. the [single-page application index](Self::spa_index), for paths without extension;
+    /// 6. the [fallback handler](Self::fallback_handler), which serves `index.html` for
+    ///    [single-page applications](Self::spa).
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::Embed;
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// // `en/index.html` is served at `/`
+    /// let embed = Embed::new("/", &Assets).index_file("en/");
+    /// ```
+    pub fn index_file<P: AsRef<str>>(mut self, path: P) -> Self {
+        self.options = self.options.index_file(path);
+        self
+    }
+
+    /// Serve the file named `file_name` in a directory for requests for the directory, e.g.
+    /// `guide/index.html` at `/guide/` and `/guide`.
+    ///
+    /// By default only the mount path has an [index file](Self::index_file).
+    ///
+    /// The index of a directory is only served if there is no file at the requested path.
+    pub fn directory_index<N: AsRef<str>>(mut self, file_name: N) -> Self {
+        self.options = self.options.directory_index(file_name);
+        self
+    }
+
+    /// Serve the file at `path` for `GET` and `HEAD` requests without a file whose last path
+    /// segment has no extension, e.g. `index.html` at `/settings/profile`, so the client-side
+    /// router of a single-page application handles them.
+    ///
+    /// By default such requests are passed to the fallback handler.
+    ///
+    /// Requests for paths with an extension, e.g. a missing `/app.js`, are still passed to the
+    /// fallback handler, so they are not answered with HTML. To serve the file for all misses,
+    /// use [spa](Self::spa). The file is served like any other file, with the headers set for
+    /// it by [header](Self::header).
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::Embed;
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let embed = Embed::new("/", &Assets)
+    ///     .index_file("index.html")
+    ///     .spa_index("index.html")
+    ///     .directory_index("index.html")
+    ///     .header(["assets/**"], "cache-control", "public, max-age=31536000, immutable")
+    ///     .header(["index.html"], "cache-control", "no-cache");
+    /// ```
+    pub fn spa_index<P: AsRef<str>>(mut self, path: P) -> Self {
+        self.options = self.options.spa_index(path);
+        self
+    }
+
+    /// Set whether HTML files are served at their paths without the `.html` extension, e.g.
+    /// `guide/install.html` at `/guide/install`.
+    ///
+    /// By default files are only served at their paths.
+    ///
+    /// The HTML file is only served if there is no file at the requested path. Files are
+    /// still served at their paths with the extension as well.
+    pub fn clean_urls(mut self, enabled: bool) -> Self {
+        self.options = self.options.clean_urls(enabled);
+        self
+    }
+
+    /// Set index files of different languages, selected by the `Accept-Language` header.
+    ///
+    /// The language of each index file is the first segment of its path, e.g. `de` for
+    /// `de/index.html`. A language range of the request also matches index files of its
+    /// primary language, e.g. `de-AT` matches `de/index.html`. The `default` index file is
+    /// served if no index file matches.
+    ///
+    /// Responses carry the `Content-Language` of the selected index file and
+    /// `Vary: Accept-Language`. This takes precedence over [index_file](Self::index_file).
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::Embed;
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let embed = Embed::new("/", &Assets)
+    ///     .localized_index(["en/index.html", "de/index.html"], "en/index.html");
+    /// ```
+    pub fn localized_index<I, P>(mut self, paths: I, default: P) -> Self
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+        P: AsRef<str>,
+    {
+        self.options = self.options.localized_index(paths, default);
+        self
+    }
+
+    /// Only serve the embedded files whose media types are in `types`, and respond with
+    /// `refused` to requests for the others, e.g. so an executable or archive shipped in the
+    /// bundle by mistake is never served.
+    ///
+    /// By default files of all media types are served. Types are `type/subtype` or `type/*`,
+    /// compared case-insensitively with the media type resolved from the extension of the file,
+    /// as sent in `Content-Type` without parameters. Refused files are treated as missing
+    /// everywhere, e.g. in listings, archives and [content addresses](Self::content_addresses).
+    /// With `404 Not Found` requests for them reach the [fallback handler](Self::fallback_handler)
+    /// like requests for missing files, other statuses are sent as is. Invalid types and
+    /// statuses other than client errors are reported by [try_build](Self::try_build).
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::Embed;
+    /// use actix_web::http::StatusCode;
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let embed = Embed::new("/", &Assets).allowed_mime_types(
+    ///     ["text/html", "text/css", "text/javascript", "image/*", "font/*"],
+    ///     StatusCode::FORBIDDEN,
+    /// );
+    /// ```
+    pub fn allowed_mime_types<I>(mut self, types: I, refused: StatusCode) -> Self
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        self.options = self.options.allowed_mime_types(types, refused);
+        self
+    }
+
+    /// Serve text files with `charset=utf-8`, and respond with `not_acceptable`, usually
+    /// `406 Not Acceptable`, to requests whose `Accept-Charset` header excludes UTF-8.
+    ///
+    /// By default content types have no charset and `Accept-Charset` is ignored.
+    ///
+    /// Text files are those served as `text/*`, JavaScript, JSON, XML and SVG, which must be
+    /// UTF-8 encoded. They are never transcoded. Responses with text files carry
+    /// `Vary: Accept-Charset`.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::Embed;
+    /// use actix_web::http::StatusCode;
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let embed = Embed::new("/", &Assets).accept_charset(StatusCode::NOT_ACCEPTABLE);
+    /// ```
+    pub fn accept_charset(mut self, not_acceptable: StatusCode) -> Self {
+        self.options = self.options.accept_charset(not_acceptable);
+        self
+    }
+
+    /// Insert the header `name: value` into responses for the files whose paths match any of
+    /// `globs`, or for all files if `globs` is empty.
+    ///
+    /// Globs are matched against the keys of the embedded files as by
+    /// [precompress](Self::precompress). The name and the value are validated here: an invalid
+    /// one is reported by [try_build](Self::try_build), and the rule is dropped.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::Embed;
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let embed = Embed::new("/", &Assets)
+    ///     .header(["**/*.wasm"], "cross-origin-resource-policy", "same-origin")
+    ///     .header(["assets/**"], "x-robots-tag", "noindex")
+    ///     .try_build()
+    ///     .expect("valid headers");
+    /// ```
+    pub fn header<I, K, V>(mut self, globs: I, name: K, value: V) -> Self
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+        K: TryInto<HeaderName>,
+        K::Error: Display,
+        V: TryInto<HeaderValue>,
+        V::Error: Display,
+    {
+        self.options = self.options.header(globs, name, value);
+        self
+    }
+
+    /// Serve files matching `globs` with a `Deprecation` header, and a `Sunset` header with the
+    /// removal date of `sunset` if it is scheduled.
+    ///
+    /// Use it to confirm an embedded file is unused before deleting it from the bundle:
+    /// [on_serve](Self::on_serve) receives records with
+    /// [deprecated](crate::ServeRecord::deprecated) set, and with the `tracing` feature a
+    /// warning event names each deprecated file served. Globs are matched against the keys of
+    /// the embedded files as by [precompress](Self::precompress), and the headers are added
+    /// like those of [header](Self::header).
+    ///
+    /// # Examples
+    /// ```
+    /// use std::time::{Duration, SystemTime};
+    /// use actix_embed::{Embed, ServeRecord, Sunset};
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let removal = SystemTime::UNIX_EPOCH + Duration::from_secs(1_900_000_000);
+    /// let embed = Embed::new("/", &Assets)
+    ///     .deprecated(["images/old/**"], Sunset::date(removal))
+    ///     .on_serve(|record: ServeRecord| {
+    ///         if record.deprecated {
+    ///             eprintln!("deprecated file served: {:?}", record.path);
+    ///         }
+    ///     });
+    /// ```
+    pub fn deprecated<I>(mut self, globs: I, sunset: Sunset) -> Self
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        self.options = self.options.deprecated(globs, sunset);
+        self
+    }
+
+    /// Serve files matching `glob` only within `window`, e.g. marketing assets under embargo.
+    ///
+    /// Before the window starts, requests for the files are answered by the
+    /// [fallback handler](Self::fallback_handler) as if the files were missing. Once it ended,
+    /// they are answered with `410 Gone`. Windows may be open-ended, e.g. `from..` or
+    /// `..until`. A file matching multiple globs is only served within all of their windows.
+    /// The glob is matched against the paths of the embedded files as by
+    /// [deprecated](Self::deprecated), and windows are evaluated against the
+    /// [clock](Self::clock) of the service. A window containing no point in time is reported
+    /// by [try_build](Self::try_build).
+    ///
+    /// Caches downstream may keep serving responses after the window ends, so limit their
+    /// freshness with `Cache-Control` headers of the files, see [header](Self::header).
+    ///
+    /// # Examples
+    /// ```
+    /// use std::time::{Duration, SystemTime};
+    /// use actix_embed::Embed;
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let launch = SystemTime::UNIX_EPOCH + Duration::from_secs(1_900_000_000);
+    /// let end = launch + Duration::from_secs(7 * 24 * 60 * 60);
+    /// let embed = Embed::new("/", &Assets)
+    ///     .available("promo/**", launch..end)
+    ///     .header(["promo/**"], "cache-control", "public, max-age=300");
+    /// ```
+    pub fn available<G, R>(mut self, glob: G, window: R) -> Self
+    where
+        G: AsRef<str>,
+        R: RangeBounds<SystemTime>,
+    {
+        self.options = self.options.available(glob, window);
+        self
+    }
+
+    /// Set the clock telling the time [availability windows](Self::available) are evaluated
+    /// at, e.g. a fixed time in tests.
+    ///
+    /// By default the system clock is used.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::time::{Duration, SystemTime};
+    /// use actix_embed::Embed;
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let launch = SystemTime::UNIX_EPOCH + Duration::from_secs(1_900_000_000);
+    /// let embed = Embed::new("/", &Assets)
+    ///     .available("promo/**", launch..)
+    ///     .clock(move || launch + Duration::from_secs(60));
+    /// ```
+    pub fn clock<C: Clock>(mut self, clock: C) -> Self {
+        self.options = self.options.clock(clock);
+        self
+    }
+
+    /// Set the `Cache-Control` header of the responses of the
+    /// [fallback handler](Self::fallback_handler), e.g. `public, max-age=60`, so CDNs cache
+    /// missing assets briefly instead of forwarding every request for them.
+    ///
+    /// By default fallback responses have no `Cache-Control` header. The header is not
+    /// overwritten if the fallback handler sets it, nor set on server errors, e.g. of panicking
+    /// handlers. An invalid value is reported by
+    /// [try_build](Self::try_build).
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::Embed;
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let embed = Embed::new("/static", &Assets).not_found_cache_control("public, max-age=60");
+    /// ```
+    pub fn not_found_cache_control<V>(mut self, value: V) -> Self
+    where
+        V: TryInto<HeaderValue>,
+        V::Error: Display,
+    {
+        self.options = self.options.not_found_cache_control(value);
+        self
+    }
+
+    /// Serve files matching `globs` with a `CDN-Cache-Control` header, e.g.
+    /// `max-age=86400`, which CDNs follow instead of `Cache-Control`.
+    ///
+    /// Use it when the edge should keep files longer or shorter than browsers: `Cache-Control`
+    /// stays the policy of browsers and of caches which do not know `CDN-Cache-Control`.
+    /// Globs are matched against the keys of the embedded files as by
+    /// [precompress](Self::precompress), and the header is added like those of
+    /// [header](Self::header). Responses gated by
+    /// [require_signed_cookie](Self::require_signed_cookie) are sent without it. An invalid
+    /// value is reported by [try_build](Self::try_build).
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::Embed;
+    /// use actix_web::http::header;
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let embed = Embed::new("/", &Assets)
+    ///     .header(["**"], header::CACHE_CONTROL, "public, max-age=60")
+    ///     .cdn_cache_control(["**"], "max-age=86400");
+    /// ```
+    pub fn cdn_cache_control<I, V>(mut self, globs: I, value: V) -> Self
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+        V: TryInto<HeaderValue>,
+        V::Error: Display,
+    {
+        self.options = self.options.cdn_cache_control(globs, value);
+        self
+    }
+
+    /// Set which pages may embed files matching `glob` in frames, e.g. to let partner sites
+    /// frame embedded widgets while no site may frame admin pages.
+    ///
+    /// By default no policy is sent and any page may frame the files. The glob is matched
+    /// against the keys of the embedded files as by [precompress](Self::precompress), and the
+    /// headers of the policy are added like those of [header](Self::header), so a later
+    /// policy or header rule setting `Content-Security-Policy` on the same files replaces it;
+    /// [try_build](Self::try_build) warns about such rules. Add `frame-ancestors` to that
+    /// header instead when the files need other directives. Invalid origins are reported by
+    /// [try_build](Self::try_build).
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::{Embed, FramePolicy};
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let partners = vec!["https://partner.example".to_owned(), "'self'".to_owned()];
+    /// let embed = Embed::new("/", &Assets)
+    ///     .frame_policy("widgets/**", FramePolicy::AllowFrom(partners))
+    ///     .frame_policy("admin/**", FramePolicy::Deny);
+    /// ```
+    pub fn frame_policy<G: AsRef<str>>(mut self, glob: G, policy: FramePolicy) -> Self {
+        self.options = self.options.frame_policy(glob, policy);
+        self
+    }
+
+    /// Set the priority of the responses for files matching `glob`, so clients and proxies
+    /// supporting the `Priority` header of RFC 9218 over HTTP/2 and HTTP/3 send critical files
+    /// first, e.g. the stylesheets and scripts blocking the first render.
+    ///
+    /// By default no priority is sent, and clients pick one by the type of request. The glob
+    /// is matched against the keys of the embedded files as by [precompress](Self::precompress),
+    /// and the header is added like those of [header](Self::header). `actix-http` does not
+    /// schedule responses by priority itself, the header is a hint for the client and for
+    /// proxies terminating HTTP/2 or HTTP/3 in front of the application. Urgencies out of
+    /// range are reported by [try_build](Self::try_build).
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::{Embed, Priority};
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let embed = Embed::new("/", &Assets)
+    ///     .priority("assets/*.css", Priority::High)
+    ///     .priority("images/**", Priority::Urgency { urgency: 5, incremental: true });
+    /// ```
+    pub fn priority<G: AsRef<str>>(mut self, glob: G, priority: Priority) -> Self {
+        self.options = self.options.priority(glob, priority);
+        self
+    }
+
+    /// Configure serving of font files (`woff`, `woff2`, `ttf` and `otf`).
+    ///
+    /// Font files are served with:
+    /// - `Access-Control-Allow-Origin: *`, since browsers refuse cross-origin fonts without it;
+    /// - `Cache-Control: public, max-age=31536000, immutable`;
+    /// - the content types defined by RFC 8081, e.g. `font/woff2`.
+    pub fn fonts_preset(mut self) -> Self {
+        self.options = self.options.fonts_preset();
+        self
+    }
+
+    /// Configure serving of data files read in ranges by clients, like SQLite databases
+    /// queried over HTTP by `sql.js-httpvfs` and WebAssembly modules (`sqlite`, `sqlite3`, `db`
+    /// and `wasm`), under `globs`. All data files match if `globs` is empty.
+    ///
+    /// Data files are served with:
+    /// - range requests honored and `Accept-Ranges: bytes`, whatever
+    ///   [accept_ranges](Self::accept_ranges) and [outer_compression](Self::outer_compression)
+    ///   say for other files;
+    /// - their embedded bytes, never a compressed or derived variant, and
+    ///   `Content-Encoding: identity`, so compressing middleware leaves them alone and ranges
+    ///   refer to the bytes of the file;
+    /// - `Cache-Control: public, max-age=31536000, immutable`;
+    /// - `Cross-Origin-Resource-Policy: same-origin`, so pages isolated with
+    ///   `Cross-Origin-Embedder-Policy: require-corp` may load them;
+    /// - `application/vnd.sqlite3` or `application/wasm`.
+    ///
+    /// Each of them missing breaks those clients in a different way, e.g. a compressing
+    /// proxy makes ranges refer to bytes the client never sees. The headers are added as a
+    /// header rule, which later rules override, e.g. to allow other origins.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::Embed;
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let embed = Embed::new("/", &Assets).byte_serving_preset(["data/**"]);
+    /// ```
+    pub fn byte_serving_preset<I>(mut self, globs: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        self.options = self.options.byte_serving_preset(globs);
+        self
+    }
+
+    /// Compare the content of the served files with the media type of their extension when
+    /// the service starts, and report the files which do not match, like a PNG image named
+    /// `.jpg` or an HTML page named `.txt`, which usually come from a broken build of the
+    /// assets.
+    ///
+    /// By default files are served with the media type of their extension, unchecked.
+    ///
+    /// Mismatches are listed by [issues](Self::issues). With [SniffAction::Correct],
+    /// mislabeled files are served with the media type of their content instead. Images, PDF
+    /// documents, WebAssembly modules and HTML pages are recognized by their first bytes;
+    /// files of other or unknown types are not checked. Every file is read once at startup,
+    /// so large embeds start slower.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::{Embed, SniffAction};
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let embed = Embed::new("/", &Assets).content_sniffing(SniffAction::Report);
+    /// for issue in embed.issues() {
+    ///     eprintln!("{}", issue);
+    /// }
+    /// ```
+    pub fn content_sniffing(mut self, action: SniffAction) -> Self {
+        self.options = self.options.content_sniffing(action);
+        self
+    }
+
+    /// Set whether the caching of files is derived from their names: files whose name has a
+    /// content hash, like `app.3f2a9c1d.js` written by bundlers, are served with
+    /// `Cache-Control: public, max-age=31536000, immutable`, and other files with
+    /// `Cache-Control: no-cache`, so clients revalidate them.
+    ///
+    /// Defaults to `false`, files are served without `Cache-Control` unless a
+    /// [header](Self::header) sets it. Files with a header rule setting `Cache-Control` keep
+    /// it. Names match if they contain a `.` followed by at least 8 lowercase hexadecimal
+    /// digits and another `.`, see [hashed_filename_pattern](Self::hashed_filename_pattern).
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::Embed;
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let embed = Embed::new("/", &Assets)
+    ///     .hashed_filenames(true)
+    ///     .header(["sw.js"], "cache-control", "no-store");
+    /// ```
+    pub fn hashed_filenames(mut self, enabled: bool) -> Self {
+        self.options = self.options.hashed_filenames(enabled);
+        self
+    }
+
+    /// Derive the caching of files from their names like [hashed_filenames](Self::hashed_filenames),
+    /// detecting content hashes with the regular expression `pattern` instead, e.g.
+    /// `-[0-9A-Za-z]{8}\.` for `index-BmHaDxBx.js` written by Vite.
+    ///
+    /// The pattern is matched against the last segment of the path of the file. An invalid
+    /// pattern is a configuration error.
+    ///
+    /// Requires the `hashed-filename-pattern` feature.
+    #[cfg(feature = "hashed-filename-pattern")]
+    pub fn hashed_filename_pattern<P: AsRef<str>>(mut self, pattern: P) -> Self {
+        self.options = self.options.hashed_filename_pattern(pattern);
+        self
+    }
+
+    /// Harden the serving of files matching `globs` whose content is not trusted, like
+    /// exports of user-generated HTML snippets or SVG images, against stored cross-site
+    /// scripting. All files match if `globs` is empty.
+    ///
+    /// The files are served with:
+    /// - `Content-Security-Policy: sandbox`, so documents opened anyway run in a unique origin
+    ///   without scripts;
+    /// - `X-Content-Type-Options: nosniff`, so browsers do not guess a type able to run
+    ///   scripts from the content;
+    /// - `Content-Disposition: attachment` for HTML, XHTML and SVG files, so browsers
+    ///   download them instead of rendering them.
+    ///
+    /// The headers are added as header rules, which override headers of earlier rules.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::Embed;
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let embed = Embed::new("/", &Assets).untrusted_preset(["exports/**", "avatars/*.svg"]);
+    /// ```
+    pub fn untrusted_preset<I>(mut self, globs: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        self.options = self.options.untrusted_preset(globs);
+        self
+    }
+
+    /// Set whether to minify HTML, CSS and JavaScript files.
+    ///
+    /// Defaults to `false`.
+    ///
+    /// Files are minified on first access and kept in memory. Minified files are served with
+    /// entity tags derived from the original file and the minified output, so conditional
+    /// requests keep working and the tags change whenever the output does. Files that cannot
+    /// be parsed are served as they are.
+    #[cfg(feature = "minify")]
+    pub fn minify(mut self, minify: bool) -> Self {
+        self.options = self.options.minify(minify);
+        self
+    }
+
+    /// Set whether original files stay accessible when minification is enabled.
+    ///
+    /// Defaults to `false`.
+    ///
+    /// If it's set to true, requests with the `original` query parameter, e.g.
+    /// `/app.js?original`, are served with the file as embedded. Meant for debugging.
+    #[cfg(feature = "minify")]
+    pub fn serve_originals(mut self, serve_originals: bool) -> Self {
+        self.options = self.options.serve_originals(serve_originals);
+        self
+    }
+
+    /// Set whether to sanitize SVG images, for embedded icon packs or other images which are
+    /// not fully trusted.
+    ///
+    /// Defaults to `false`.
+    ///
+    /// Files with the `svg` extension are sanitized when the service is created, and served,
+    /// compressed and archived with their sanitized content. Sanitizing removes:
+    /// - `script`, `foreignObject`, `iframe`, `embed` and `object` elements with their content;
+    /// - event handler attributes, e.g. `onload`;
+    /// - `javascript:` and `vbscript:` URLs;
+    /// - document type declarations, whose entities could expand to any of the above.
+    ///
+    /// Everything else is kept as it is. Images which cannot be parsed are served as an empty
+    /// image. Sanitized images are served with entity tags differing from the original files.
+    ///
+    /// Requires the `sanitize-svg` feature.
+    #[cfg(feature = "sanitize-svg")]
+    pub fn sanitize_svg(mut self, enabled: bool) -> Self {
+        self.options = self.options.sanitize_svg(enabled);
+        self
+    }
+
+    /// Compress files matching any of `globs` with `encoding` when the service is created.
+    ///
+    /// Both the original and the compressed variants are kept in memory, and the variant
+    /// is selected according to the `Accept-Encoding` header of each request.
+    /// Compressed variants are served with entity tags suffixed with the encoding, and
+    /// responses for files with compressed variants carry `Vary: Accept-Encoding`.
+    ///
+    /// `level` ranges from 0 to 11 for [Encoding::Brotli] and from 0 to 9 for
+    /// [Encoding::Gzip]. Variants which are not smaller than the original file are dropped.
+    ///
+    /// Levels are set per rule, so patterns get their own tradeoff, e.g. the maximum level
+    /// for large vendor bundles and a fast one for everything else. A file is compressed with
+    /// each encoding at most once, by the first rule of the encoding matching it, so rules
+    /// with specific globs go before catch-all ones.
+    ///
+    /// Requires the `compress-brotli` or `compress-gzip` feature for the respective encoding,
+    /// encodings whose feature is not enabled are ignored.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::{Embed, Encoding};
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let embed = Embed::new("/static", &Assets)
+    ///     .precompress(Encoding::Brotli, 11, ["vendor/**"])
+    ///     .precompress(Encoding::Brotli, 5, ["**/*.html", "**/*.css", "**/*.js"]);
+    /// ```
+    #[cfg(any(feature = "compress-brotli", feature = "compress-gzip"))]
+    pub fn precompress<I>(mut self, encoding: Encoding, level: u32, globs: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        self.options = self.options.precompress(encoding, level, globs);
+        self
+    }
+
+    /// Compress the bodies of the [version](Self::version_endpoint),
+    /// [import map](Self::import_map) and [listing](Self::listing_endpoint) endpoints with
+    /// `encoding`.
+    ///
+    /// By default the bodies are only sent uncompressed.
+    ///
+    /// The bodies of the endpoints are generated once per path and query and cached with
+    /// their compressed variants, which are selected according to the `Accept-Encoding`
+    /// header of each request like those of [precompressed](Self::precompress) files. `level`
+    /// is interpreted as by [precompress](Self::precompress), and each encoding is used at
+    /// most once, with the level of its first call.
+    ///
+    /// Requires the `compress-brotli` or `compress-gzip` feature for the respective encoding,
+    /// encodings whose feature is not enabled are ignored.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::{Embed, Encoding};
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let embed = Embed::new("/static", &Assets)
+    ///     .listing_endpoint("_files")
+    ///     .compress_endpoints(Encoding::Brotli, 5)
+    ///     .compress_endpoints(Encoding::Gzip, 6);
+    /// ```
+    #[cfg(any(feature = "compress-brotli", feature = "compress-gzip"))]
+    pub fn compress_endpoints(mut self, encoding: Encoding, level: u32) -> Self {
+        self.options = self.options.compress_endpoints(encoding, level);
+        self
+    }
+
+    /// Serve the embedded variants of files compressed with `encodings`, e.g. `app.js.br` and
+    /// `app.js.gz` next to `app.js`, to clients accepting them.
+    ///
+    /// By default embedded variants are only served at their own paths.
+    ///
+    /// The variant is selected according to the `Accept-Encoding` header of each request, and
+    /// served with the media type of the original file, its encoding in `Content-Encoding`
+    /// and an entity tag suffixed with the encoding, like the variants compressed by
+    /// [precompress](Self::precompress). Responses for files with variants carry
+    /// `Vary: Accept-Encoding`. If the client accepts several encodings equally, Brotli is
+    /// preferred. Files with an embedded variant of an encoding are not
+    /// compressed with it again. The variants remain available at their own paths.
+    ///
+    /// Embedded variants are served as they are, they are neither checked against the
+    /// original file nor compressed by this crate, so no compression feature is required.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::{Embed, Encoding};
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let embed = Embed::new("/", &Assets).use_precompressed(&[Encoding::Brotli, Encoding::Gzip]);
+    /// ```
+    pub fn use_precompressed(mut self, encodings: &[Encoding]) -> Self {
+        self.options = self.options.use_precompressed(encodings);
+        self
+    }
+
+    /// Set whether precompression runs on the blocking thread pool.
+    ///
+    /// Defaults to `false`.
+    ///
+    /// Compressing many files may take a while, running it on the blocking thread pool keeps
+    /// the worker responsive meanwhile.
+    #[cfg(any(feature = "compress-brotli", feature = "compress-gzip"))]
+    pub fn precompress_blocking(mut self, blocking: bool) -> Self {
+        self.options = self.options.precompress_blocking(blocking);
+        self
+    }
+
+    /// Never compress files with any of `extensions`, in addition to the built-in list of
+    /// compressed file types.
+    ///
+    /// Images (`png`, `jpg`, `jpeg`, `gif`, `webp`, `avif`), audio and video (`mp4`, `webm`,
+    /// `mov`, `mp3`, `ogg`), fonts (`woff`, `woff2`), archives (`zip`, `gz`, `br`, `zst`,
+    /// `7z`, `rar`) and `pdf` files are compressed already, and are skipped by
+    /// [precompress](Self::precompress) even if they match its globs, since compressing them
+    /// again wastes CPU for no gain. Extensions are matched case-insensitively.
+    ///
+    /// Why a file was served with or without compression is reported by
+    /// [ServeRecord::compression](crate::ServeRecord::compression).
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::{Embed, Encoding};
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let embed = Embed::new("/static", &Assets)
+    ///     .precompress(Encoding::Gzip, 9, ["**/*"])
+    ///     .skip_compression(["ktx2", "basis"]);
+    /// ```
+    #[cfg(any(feature = "compress-brotli", feature = "compress-gzip"))]
+    pub fn skip_compression<I>(mut self, extensions: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        self.options = self.options.skip_compression(extensions);
+        self
+    }
+
+    /// Adapt responses to compressing middleware wrapping the service, such as
+    /// `actix_web::middleware::Compress`.
+    ///
+    /// Defaults to [OuterCompression::Absent].
+    ///
+    /// Compressing middleware compresses responses of files without compressed variants,
+    /// and keeps their strong entity tags, although the bytes sent differ. Use
+    /// [OuterCompression::Bypass] to let `Embed` decide alone which responses are compressed,
+    /// e.g. with [precompress](Self::precompress), or [OuterCompression::Allow] to let the
+    /// middleware compress the others, weakening their entity tags and answering their range
+    /// requests with the whole file.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::{Embed, OuterCompression};
+    /// use actix_web::{middleware, App};
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let app = App::new()
+    ///     .wrap(middleware::Compress::default())
+    ///     .service(Embed::new("/", &Assets).outer_compression(OuterCompression::Allow));
+    /// ```
+    pub fn outer_compression(mut self, outer: OuterCompression) -> Self {
+        self.options = self.options.outer_compression(outer);
+        self
+    }
+
+    /// Set whether range requests for files are honored, and whether responses for files and
+    /// archives advertise it.
+    ///
+    /// Defaults to [AcceptRanges::Unadvertised].
+    ///
+    /// Honored range requests of `GET` requests are answered with `206 Partial Content` and a
+    /// `Content-Range`, or a `multipart/byteranges` body for several ranges, and with
+    /// `416 Range Not Satisfiable` if no range overlaps the file. A range request with an
+    /// `If-Range` not matching the entity tag or modification time of the file is answered
+    /// with the whole file. Archives are streamed, range requests for them are always answered
+    /// with the whole archive and advertised as `Accept-Ranges: none`.
+    ///
+    /// Ranges refer to the bytes of the representation selected by `Accept-Encoding`, e.g. the
+    /// brotli variant of a file, so `Content-Range` counts the compressed bytes. Each encoding
+    /// has its own entity tag, so an `If-Range` of another encoding yields the whole
+    /// representation instead of mixing bytes of two encodings. `416` responses carry the
+    /// `Vary` headers of the file. With [OuterCompression::Allow], uncompressed responses may
+    /// still be compressed by middleware, so their range requests are answered with the whole
+    /// file and they are advertised as `Accept-Ranges: none`.
+    ///
+    /// Use [AcceptRanges::None] to send `Accept-Ranges: none` and answer range requests with
+    /// the whole file, so download managers download files in one piece.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::{AcceptRanges, Embed};
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let embed = Embed::new("/downloads", &Assets).accept_ranges(AcceptRanges::None);
+    /// ```
+    pub fn accept_ranges(mut self, accept_ranges: AcceptRanges) -> Self {
+        self.options = self.options.accept_ranges(accept_ranges);
+        self
+    }
+
+    /// Set the directory in which derived artifacts are stored.
+    ///
+    /// By default derived artifacts are kept in memory.
+    ///
+    /// If it's set, compressed variants (see [precompress](Self::precompress)) are written to
+    /// the directory instead and read from it when served. Artifacts are keyed by the hash of
+    /// the original file, so artifacts stored by previous runs are reused after restarts. Each
+    /// artifact is stored with its sha256 hash, artifacts failing the integrity check are
+    /// recreated.
+    ///
+    /// Artifacts that cannot be written are kept in memory.
+    pub fn derived_cache_dir<P: Into<PathBuf>>(mut self, dir: P) -> Self {
+        self.options = self.options.derived_cache_dir(dir);
+        self
+    }
+
+    /// Redirect requests for the file at `from`, which no longer exists, to the file at `to`.
+    ///
+    /// Both paths are relative to the mount path. Redirects use `301 Moved Permanently` and
+    /// only apply if no file exists at `from`.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::Embed;
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let embed = Embed::new("/docs", &Assets)
+    ///     .redirect("guide/install.html", "getting-started.html")
+    ///     .redirect("style.css", "assets/index.css");
+    /// ```
+    pub fn redirect<P: AsRef<str>, Q: AsRef<str>>(mut self, from: P, to: Q) -> Self {
+        self.options = self.options.redirect(from, to);
+        self
+    }
+
+    /// Redirect requests matching no file with `301 Moved Permanently` to the only file
+    /// whose path differs from the requested one in case, or in a missing, extra or different
+    /// extension, e.g. `Docs/Guide` to `docs/guide.html` or `notes.txt` to `notes.md`.
+    ///
+    /// By default such requests reach the fallback handler. This recovers from the most
+    /// common broken inbound links, e.g. to embedded documentation. Nothing is redirected if
+    /// several files are as close, and [redirects](Self::redirect) and package exports take
+    /// precedence. The query string is kept. [Suggestions](Self::suggestions) are still made
+    /// for the requests left to the fallback handler.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::Embed;
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// // `/docs/Index` is redirected to `/docs/index.html`
+    /// let embed = Embed::new("/docs", &Assets).auto_correct(true);
+    /// ```
+    pub fn auto_correct(mut self, enabled: bool) -> Self {
+        self.options = self.options.auto_correct(enabled);
+        self
+    }
+
+    /// Set the number of similar files suggested when no file matches a request.
+    ///
+    /// By default no files are suggested.
+    ///
+    /// Suggestions are passed to the fallback handler as [Suggestions] in the request
+    /// extensions. The default fallback handler renders them as a "did you mean" page.
+    ///
+    /// Files differing in case or directory are suggested first, followed by files whose
+    /// paths are within a small edit distance.
+    pub fn suggestions(mut self, limit: usize) -> Self {
+        self.options = self.options.suggestions(limit);
+        self
+    }
+
+    /// Redirect requests not using the canonical `host` and `scheme`.
+    ///
+    /// By default requests are served regardless of their host and scheme.
+    ///
+    /// Requests whose `Host` or scheme differs are redirected with `301 Moved Permanently` to
+    /// the same path and query on the canonical host. This is evaluated before anything else.
+    ///
+    /// Behind reverse proxies, set a [ForwardedPolicy] trusting the proxy with
+    /// [forwarded_policy](Self::forwarded_policy).
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::{Embed, Scheme};
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let embed = Embed::new("/", &Assets).canonical_host("www.example.com", Scheme::Https);
+    /// ```
+    pub fn canonical_host<H: AsRef<str>>(mut self, host: H, scheme: Scheme) -> Self {
+        self.options = self.options.canonical_host(host, scheme);
+        self
+    }
+
+    /// Allow pages from `origins` to read the responses, e.g. `https://dashboard.example.com`,
+    /// `*` allowing any origin.
+    ///
+    /// By default no cross-origin request is allowed.
+    ///
+    /// Responses to requests from allowed origins carry `Access-Control-Allow-Origin`, and
+    /// preflight requests for paths served by the service are answered with
+    /// `204 No Content`, allowing `GET` and `HEAD` with the requested headers. All responses
+    /// carry `Vary: Origin`, so caches keep the responses to different origins apart.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::Embed;
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let embed = Embed::new("/", &Assets).cors(["https://intranet.example.com"]);
+    /// ```
+    pub fn cors<I>(mut self, origins: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        self.options = self.options.cors(origins);
+        self
+    }
+
+    /// Set whether [cross-origin](Self::cors) requests may carry credentials, i.e. cookies
+    /// and HTTP authentication.
+    ///
+    /// Defaults to `false`.
+    ///
+    /// When enabled, responses to origins listed in [cors](Self::cors) carry
+    /// `Access-Control-Allow-Credentials: true` and the origin of the request in
+    /// `Access-Control-Allow-Origin`. Origins only allowed by `*` never get credentials, so
+    /// enabling them with `*` is a configuration error, see [try_build](Self::try_build).
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::Embed;
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let embed = Embed::new("/", &Assets)
+    ///     .cors(["https://intranet.example.com"])
+    ///     .cors_credentials(true);
+    /// ```
+    pub fn cors_credentials(mut self, allowed: bool) -> Self {
+        self.options = self.options.cors_credentials(allowed);
+        self
+    }
+
+    /// Set whether pages from [allowed origins](Self::cors) on less private networks, e.g.
+    /// public sites, may request the files when they are served on a private network.
+    ///
+    /// Defaults to `false`.
+    ///
+    /// When enabled, preflight requests with `Access-Control-Request-Private-Network: true`,
+    /// sent by Chrome as part of Private Network Access, are answered with
+    /// `Access-Control-Allow-Private-Network: true`.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::Embed;
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let embed = Embed::new("/", &Assets)
+    ///     .cors(["https://intranet.example.com"])
+    ///     .cors_private_network(true);
+    /// ```
+    pub fn cors_private_network(mut self, allowed: bool) -> Self {
+        self.options = self.options.cors_private_network(allowed);
+        self
+    }
+
+    /// Set whether responses to HTTP/1.0 requests are adapted for HTTP/1.0 clients, e.g.
+    /// embedded devices fetching a web interface.
+    ///
+    /// By default HTTP/1.0 requests are served like HTTP/1.1 requests.
+    ///
+    /// With the compatibility mode, responses to HTTP/1.0 requests:
+    /// - carry `Expires` next to `Cache-Control`, and `Pragma: no-cache` if they must be
+    ///   revalidated, since HTTP/1.0 caches ignore `Cache-Control`;
+    /// - have a `Content-Length`, archives being built in memory instead of being streamed;
+    /// - are not redirected to the [canonical host](Self::canonical_host) if the request has
+    ///   no `Host` header, which HTTP/1.0 clients need not send.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::Embed;
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let embed = Embed::new("/", &Assets).http10_compat(true);
+    /// ```
+    pub fn http10_compat(mut self, enabled: bool) -> Self {
+        self.options = self.options.http10_compat(enabled);
+        self
+    }
+
+    /// Set the number of responses after which the service yields to the other tasks of the
+    /// worker, `0` to never yield.
+    ///
+    /// By default the service never yields.
+    ///
+    /// Embedded files are served without waiting for I/O, so a client pipelining hundreds of
+    /// requests over HTTP/1.1, or multiplexing them over HTTP/2, is served without the worker
+    /// switching to other connections in between. Yielding every few responses lets the other
+    /// connections of the worker progress during such bursts, at the cost of a task switch.
+    /// The count is kept per worker, across connections.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::Embed;
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let embed = Embed::new("/", &Assets).yield_every(16);
+    /// ```
+    pub fn yield_every(mut self, responses: u32) -> Self {
+        self.options = self.options.yield_every(responses);
+        self
+    }
+
+    /// Limit the rate at which files matching `glob` are sent to `bytes_per_sec` bytes per
+    /// second, so large downloads do not saturate the uplink of the server.
+    ///
+    /// By default files are sent as fast as clients read them.
+    ///
+    /// The limit applies to each response: the body is split into chunks of a tenth of the
+    /// rate, up to 64 KiB, each sent once the previous ones have been sent at the rate. The
+    /// `Content-Length` is still sent. Files matching multiple globs are limited by the first
+    /// throttle added, and a rate of `0` is a configuration error.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::Embed;
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// // 2 MiB/s
+    /// let embed = Embed::new("/", &Assets).throttle("downloads/**", 2 << 20);
+    /// ```
+    pub fn throttle<G: AsRef<str>>(mut self, glob: G, bytes_per_sec: u64) -> Self {
+        self.options = self.options.throttle(glob, bytes_per_sec);
+        self
+    }
+
+    /// Set whether list headers are sent in a canonical form, so responses of the same
+    /// request carry identical header values whichever options added to them.
+    ///
+    /// By default the elements of list headers are sent in the order the options of the
+    /// service added them, which is the same across runs and workers, but may repeat elements,
+    /// e.g. `Vary: Cookie` added by both [language_override](Self::language_override) and
+    /// [require_signed_cookie](Self::require_signed_cookie). With this option the values of
+    /// `Vary`, `Cache-Control`, `Accept-CH` and the `Access-Control-Allow-Headers`,
+    /// `-Allow-Methods` and `-Expose-Headers` headers are merged into a single value, with the
+    /// elements sorted case-insensitively and without duplicates, e.g.
+    /// `Vary: Accept-Encoding, Cookie, Origin`. Values with quoted strings are left as is.
+    ///
+    /// The order of the header names themselves is decided by `actix-http`, which does not
+    /// keep it stable, so compare responses by their header values rather than their bytes.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::Embed;
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let embed = Embed::new("/", &Assets).canonical_headers(true);
+    /// ```
+    pub fn canonical_headers(mut self, enabled: bool) -> Self {
+        self.options = self.options.canonical_headers(enabled);
+        self
+    }
+
+    /// Set the size in bytes of the chunks bodies of embedded files are streamed in.
+    ///
+    /// Defaults to 64 KiB, four TLS records of 16 KiB, so chunks fill whole records. Bodies
+    /// up to the chunk size are sent in one piece, and `0` sends every body in one piece,
+    /// which buffers large files whole before they are written. The `Content-Length` is still
+    /// sent, and [throttled](Self::throttle) bodies are split as by the throttle.
+    ///
+    /// Larger chunks need fewer writes, e.g. for embedded video, smaller ones hold less
+    /// memory per response while it is written.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::Embed;
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let embed = Embed::new("/", &Assets).stream_chunk_size(256 << 10);
+    /// ```
+    pub fn stream_chunk_size(mut self, bytes: usize) -> Self {
+        self.options = self.options.stream_chunk_size(bytes);
+        self
+    }
+
+    /// Set whether `Forwarded` and `X-Forwarded-*` headers are trusted when generating
+    /// absolute URLs.
+    ///
+    /// Defaults to [ForwardedPolicy::Ignore].
+    pub fn forwarded_policy(mut self, policy: ForwardedPolicy) -> Self {
+        self.options = self.options.forwarded_policy(policy);
+        self
+    }
+
+    /// Set the rate limiter consulted before serving each request.
+    ///
+    /// Requests are keyed by the IP address of the peer. Use
+    /// [rate_limiter_with_key](Self::rate_limiter_with_key) to derive keys differently.
+    ///
+    /// Rejected requests get `429 Too Many Requests` responses with a `Retry-After` header.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::time::Duration;
+    /// use actix_embed::{Embed, RateLimitDecision};
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let embed = Embed::new("/static", &Assets).rate_limiter(|ip: &str| {
+    ///     if ip == "10.0.0.1" {
+    ///         RateLimitDecision::RetryAfter(Duration::from_secs(60))
+    ///     } else {
+    ///         RateLimitDecision::Allow
+    ///     }
+    /// });
+    /// ```
+    pub fn rate_limiter<L>(mut self, limiter: L) -> Self
+    where
+        L: RateLimiter,
+    {
+        self.options = self.options.rate_limiter(limiter);
+        self
+    }
+
+    /// Set the rate limiter consulted before serving each request, with a custom key.
+    ///
+    /// Requests for which `key` returns `None` are not limited.
+    pub fn rate_limiter_with_key<L, K>(mut self, limiter: L, key: K) -> Self
+    where
+        L: RateLimiter,
+        K: Fn(&HttpRequest) -> Option<String> + 'static,
+    {
+        self.options = self.options.rate_limiter_with_key(limiter, key);
+        self
+    }
+
+    /// Record the decision made for each request into `sink`, to replay them against later
+    /// versions of the configuration or of this crate with
+    /// [compliance::replay_decisions](crate::compliance::replay_decisions).
+    ///
+    /// Each [Decision] holds the method, the URI and the [recorded
+    /// headers](crate::decision::RECORDED_HEADERS) of the request, and the status, served file,
+    /// content encoding and redirect location of the response. Cookies are not recorded, so
+    /// decisions depending on them, e.g. of [require_signed_cookie](Self::require_signed_cookie),
+    /// replay as made without cookies.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::cell::RefCell;
+    /// use std::rc::Rc;
+    /// use actix_embed::decision::Decision;
+    /// use actix_embed::Embed;
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let decisions = Rc::new(RefCell::new(Vec::new()));
+    /// let sink = decisions.clone();
+    /// let embed = Embed::new("/static", &Assets).record_decisions(move |decision: Decision| {
+    ///     sink.borrow_mut().push(serde_json::to_string(&decision).unwrap());
+    /// });
+    /// ```
+    pub fn record_decisions<H>(mut self, sink: H) -> Self
+    where
+        H: Fn(Decision) + 'static,
+    {
+        self.options = self.options.record_decisions(sink);
+        self
+    }
+
+    /// Set the hook called after each response is sent.
+    ///
+    /// The hook receives a [ServeRecord] describing the response, including the path of the
+    /// embedded file that was served and the number of bytes sent. It is called once the
+    /// body is complete, or dropped, e.g. because the client went away.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::{Embed, ServeRecord};
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let embed = Embed::new("/static", &Assets).on_serve(|record: ServeRecord| {
+    ///     println!("{:?} {} {} bytes", record.path, record.status, record.bytes);
+    /// });
+    /// ```
+    pub fn on_serve<H>(mut self, hook: H) -> Self
+    where
+        H: Fn(ServeRecord) + 'static,
+    {
+        self.options = self.options.on_serve(hook);
+        self
+    }
+
+    /// Set the hook deciding how conditional requests for embedded files are answered.
+    ///
+    /// By default the preconditions of requests are evaluated against the entity tag and the
+    /// modification time of the served representation, answering with `304 Not Modified` or
+    /// `412 Precondition Failed` as defined by RFC 9110. The hook receives the request and the
+    /// [Validators] before they are evaluated, and overrides the decision for some requests,
+    /// e.g. to send full responses to clients whose caches mishandle `304` responses, while
+    /// keeping `ETag` and `Last-Modified` for all others. Returning
+    /// [RevalidationDecision::Evaluate] keeps the default, as does a panicking hook.
+    ///
+    /// The hook is called for requests with and without preconditions, and not for the
+    /// [version](Self::version_endpoint) and other endpoints.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::{Embed, RevalidationDecision, Validators};
+    /// use actix_web::http::header;
+    /// use actix_web::HttpRequest;
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let embed = Embed::new("/static", &Assets).revalidation_hook(
+    ///     |req: &HttpRequest, _: &Validators| {
+    ///         let agent = req.headers().get(header::USER_AGENT);
+    ///         match agent.and_then(|agent| agent.to_str().ok()) {
+    ///             Some(agent) if agent.starts_with("LegacyTV/") => RevalidationDecision::Full,
+    ///             _ => RevalidationDecision::Evaluate,
+    ///         }
+    ///     },
+    /// );
+    /// ```
+    pub fn revalidation_hook<H>(mut self, hook: H) -> Self
+    where
+        H: Fn(&HttpRequest, &Validators<'_>) -> RevalidationDecision + 'static,
+    {
+        self.options = self.options.revalidation_hook(hook);
+        self
+    }
+
+    /// Set whether the [MetadataRegistry](crate::MetadataRegistry) of the served files is
+    /// passed to hooks.
+    ///
+    /// By default it is not built.
+    ///
+    /// The registry holds the size, hash, media type and modification time of every file,
+    /// taken from the metadata embedded with the files. It is inserted into the request
+    /// extensions, where the [fallback handler](Self::fallback_handler) and the key function
+    /// of the [rate limiter](Self::rate_limiter_with_key) find it, and the metadata of the
+    /// served file is passed to [on_serve](Self::on_serve) in [ServeRecord::metadata].
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::{Embed, ServeRecord};
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let embed = Embed::new("/", &Assets)
+    ///     .metadata_registry(true)
+    ///     .on_serve(|record: ServeRecord| {
+    ///         if let Some(file) = record.metadata {
+    ///             println!("served {} bytes of {}", file.size, file.mime);
+    ///         }
+    ///     });
+    /// ```
+    pub fn metadata_registry(mut self, enabled: bool) -> Self {
+        self.options = self.options.metadata_registry(enabled);
+        self
+    }
+
+    /// Report serves taking longer than `threshold` to the [on_serve](Self::on_serve) hook,
+    /// with the time spent in each stage in [ServeRecord::slow].
+    ///
+    /// By default stages are not timed.
+    ///
+    /// This helps finding pathological files, e.g. huge files minified on first access, or
+    /// slow hooks. The time spent sending the body is not included.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::time::Duration;
+    ///
+    /// use actix_embed::{Embed, ServeRecord};
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let embed = Embed::new("/static", &Assets)
+    ///     .slow_serve_threshold(Duration::from_millis(50))
+    ///     .on_serve(|record: ServeRecord| {
+    ///         if let Some(slow) = &record.slow {
+    ///             eprintln!("slow serve of {:?}, mostly {:?}", record.path, slow.dominant());
+    ///         }
+    ///     });
+    /// ```
+    pub fn slow_serve_threshold(mut self, threshold: Duration) -> Self {
+        self.options = self.options.slow_serve_threshold(threshold);
+        self
+    }
+
+    /// Set whether to check, before serving a file, that its content still has the hash
+    /// recorded when the file was first served.
+    ///
+    /// By default hashes are not checked. The check is skipped by release builds.
+    ///
+    /// A mismatch is recorded as the `stale` outcome of `verify_hashes` in the
+    /// [ServeContext](crate::ServeContext) passed to [on_serve](Self::on_serve), and logged as
+    /// a warning with the `tracing` feature.
+    ///
+    /// Debug builds of `rust_embed` read the files from disk, so edited assets are served with
+    /// the entity tags of their previous content, and clients keep revalidating stale copies
+    /// with `304 Not Modified`. With the check, the file is prepared again with its new hash.
+    /// Responses kept by the [variant cache](Self::variant_cache) are served without the check.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::Embed;
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let embed = Embed::new("/static", &Assets).verify_hashes(cfg!(debug_assertions));
+    /// ```
+    pub fn verify_hashes(mut self, enabled: bool) -> Self {
+        self.options = self.options.verify_hashes(enabled);
+        self
+    }
+
+    /// Set the folder the embedded files are built from, to warn when they look stale compared
+    /// to it.
+    ///
+    /// Debug builds compare the number and the total size of the embedded files with those of
+    /// the files in `folder`, and report a difference in the number of files or of more than
+    /// 10% in size as a warning of [issues](Self::issues), pointing to rebuild the
+    /// application. Only the sizes are read, so the check is cheap, but small edits go
+    /// unnoticed. The check is skipped by release builds.
+    ///
+    /// Debug builds of `rust_embed` read the files from disk, so they only differ if the files
+    /// are embedded anyway, e.g. with its `debug-embed` feature, and changes to the assets
+    /// are not showing until the next build. Files in `folder` excluded from embedding, e.g.
+    /// with `#[exclude]`, count as differences. Relative folders are resolved against the
+    /// working directory.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::Embed;
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let embed = Embed::new("/static", &Assets)
+    ///     .source_folder(concat!(env!("CARGO_MANIFEST_DIR"), "/testdata"));
+    /// ```
+    pub fn source_folder<P: Into<PathBuf>>(mut self, folder: P) -> Self {
+        self.options = self.options.source_folder(folder);
+        self
+    }
+
+    /// Set the counters updated after each response is generated.
+    ///
+    /// Responses are counted by [ServeOutcome](crate::ServeOutcome), so full, partial, not
+    /// modified and fallback responses can be told apart. Pass clones of the same
+    /// [ServeMetrics] to count the responses of several services together.
+    ///
+    /// The services also report the bytes held by their caches of compressed, minified and
+    /// negotiated responses, see [ServeMetrics::cache_sizes], to tune the limits of the caches.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::{Embed, ServeMetrics};
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let metrics = ServeMetrics::new();
+    /// let embed = Embed::new("/static", &Assets).metrics(metrics.clone());
+    ///
+    /// let snapshot = metrics.snapshot();
+    /// println!("{} ok, {} bytes", snapshot.ok, snapshot.bytes_served);
+    /// ```
+    pub fn metrics(mut self, metrics: ServeMetrics) -> Self {
+        self.options = self.options.metrics(metrics);
+        self
+    }
+
+    /// Remove `prefix` from the keys of embedded files which start with it.
+    ///
+    /// By default keys are served as they are.
+    ///
+    /// Some setups embed a top-level folder with the files, e.g. `public/index.html`. Stripping
+    /// `public/` serves that file at `index.html`, so request paths don't reveal the folder.
+    /// Unlike [folder](Self::folder), which only serves the files under a folder, keys without
+    /// the prefix are still served as they are, except that files with the prefix take
+    /// precedence. Keys with the prefix are only served without it. [folder](Self::folder) and
+    /// the file listings of other options apply to the rewritten keys.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::Embed;
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// // `/static/index.css` is served with `assets/index.css`
+    /// let embed = Embed::new("/static", &Assets).strip_embed_prefix("assets/");
+    /// ```
+    pub fn strip_embed_prefix<P: AsRef<str>>(mut self, prefix: P) -> Self {
+        self.options = self.options.strip_embed_prefix(prefix);
+        self
+    }
+
+    /// Also serve the embedded files of other crates, registered in `registry` under their
+    /// prefixes.
+    ///
+    /// By default only the files of `E` are served.
+    ///
+    /// Which file is served when several sources have a file at the same path depends on the
+    /// [collision policy](EmbedRegistry::collision_policy) of the last `registry`, see
+    /// [collisions](Self::collisions). [strip_embed_prefix](Self::strip_embed_prefix) and
+    /// [folder](Self::folder) only apply to the files of `E`, other options apply to all served
+    /// files. Registries of several calls are combined.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::{Embed, EmbedRegistry};
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/fonts/"]
+    /// struct Fonts;
+    ///
+    /// // `/static/plugins/fonts/icons.woff` is served with `icons.woff` of `Fonts`
+    /// let embed = Embed::new("/static", &Assets)
+    ///     .registry(EmbedRegistry::new().register("plugins/fonts", &Fonts));
+    /// ```
+    pub fn registry(mut self, registry: EmbedRegistry) -> Self {
+        self.options = self.options.registry(registry);
+        self
+    }
+
+    /// Serve the embedded files of `N` under `prefix`, relative to the mount path, sharing all
+    /// options and caches with the files of `E`, e.g. to serve several folders embedded as
+    /// separate types under one mount.
+    ///
+    /// Unlike the sources of a [registry](Self::registry), a namespace owns its prefix: paths
+    /// under it are only looked up in `N`, and files of `E` or of registered sources under the
+    /// same prefix are never served nor reported as [collisions](Self::collisions). A nested
+    /// namespace owns its prefix within the outer one. Empty and repeated prefixes are
+    /// reported by [try_build](Self::try_build).
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::Embed;
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/fonts/"]
+    /// struct Icons;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/assets/"]
+    /// struct Docs;
+    ///
+    /// // `/static/icons/icons.woff` is served with `icons.woff` of `Icons`
+    /// let embed = Embed::new("/static", &Assets)
+    ///     .namespace("icons", &Icons)
+    ///     .namespace("docs", &Docs);
+    /// ```
+    pub fn namespace<P, N>(mut self, prefix: P, source: &N) -> Self
+    where
+        P: AsRef<str>,
+        N: 'static + rust_embed::RustEmbed,
+    {
+        self.options = self.options.namespace(prefix, source);
+        self
+    }
+
+    /// Returns the paths at which the files of several sources of the [registry](Self::registry)
+    /// collide, sorted, e.g. to log them at startup.
+    ///
+    /// With [CollisionPolicy::Error], registering the service panics if there are any.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::{Embed, EmbedRegistry};
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/fonts/"]
+    /// struct Fonts;
+    ///
+    /// let embed = Embed::new("/static", &Assets)
+    ///     .registry(EmbedRegistry::new().register("fonts", &Fonts));
+    /// for collision in embed.collisions() {
+    ///     eprintln!("warning: {}", collision);
+    /// }
+    /// ```
+    pub fn collisions(&self) -> Vec<AssetCollision> {
+        self.options.keys().collisions::<E>()
+    }
+
+    /// Check the options, returning all [issues](Self::issues) if any of them is an error.
+    ///
+    /// Options taking values which may be invalid, e.g. [header](Self::header), are validated
+    /// when they are set, and invalid ones are ignored. An [Embed] with invalid options panics
+    /// when it is registered, e.g. in `App::service`, so the error is never found at request
+    /// time. Warnings alone do not fail the build.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::Embed;
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let result = Embed::new("/", &Assets)
+    ///     .header(["**"], "x-build", "line\nbreak")
+    ///     .try_build();
+    /// for issue in result.unwrap_err() {
+    ///     eprintln!("{}: {}", issue.severity, issue);
+    /// }
+    /// ```
+    pub fn try_build(self) -> Result<Self, Vec<ConfigIssue>> {
+        let issues = self.issues();
+        match issues.iter().any(|issue| issue.severity == Severity::Error) {
+            true => Err(issues),
+            false => Ok(self),
+        }
+    }
+
+    /// Returns the configuration problems, errors first, e.g. for startup diagnostics.
+    ///
+    /// Errors are invalid options, see [try_build](Self::try_build). Warnings are options which
+    /// work but likely not as intended:
+    /// - index files, archived directories and the targets of [redirects](Self::redirect)
+    ///   and [package exports](Self::package_export) which are not embedded;
+    /// - [header](Self::header) rules setting different values of the same header on a file;
+    /// - [throttles](Self::throttle) with different rates matching the same file;
+    /// - embedded files which look stale compared to their [source folder](Self::source_folder).
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::{Embed, Severity};
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let embed = Embed::new("/", &Assets).index_file("missing.html");
+    /// let issues = embed.issues();
+    /// assert_eq!(issues[0].severity, Severity::Warning);
+    /// ```
+    pub fn issues(&self) -> Vec<ConfigIssue> {
+        self.options.issues::<E>()
+    }
+
+    /// Serve the files under `folder` of the embedded files instead of all of them.
+    ///
+    /// By default all embedded files are served.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::Embed;
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// // `/css/index.css` is served with `assets/index.css`
+    /// let embed = Embed::new("/css", &Assets).folder("assets");
+    /// ```
+    pub fn folder<P: AsRef<str>>(mut self, folder: P) -> Self {
+        self.options = self.options.folder(folder);
+        self
+    }
+
+    /// Exclude request paths matching any of `globs` from this service.
+    ///
+    /// Paths are relative to the mount path, percent-decoded. Requests for excluded paths are
+    /// not handled by this service, so they reach services registered after it. Files at
+    /// excluded paths are never served, also when requested at other paths, e.g. as
+    /// [clean URLs](Self::clean_urls).
+    pub fn exclude<I>(mut self, globs: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        self.options = self.options.exclude(globs);
+        self
+    }
+
+    /// Set whether this service only handles requests whose first path segment, relative to
+    /// the mount path, names an embedded top-level file or directory, or an endpoint, archive
+    /// or redirect of this service.
+    ///
+    /// Defaults to `false`, all requests under the mount path are handled.
+    ///
+    /// Other requests reach services registered after this one, so a service mounted at `/`
+    /// no longer has to be registered last. Requests for the mount path itself are always
+    /// handled. With [clean_urls](Self::clean_urls), top-level HTML files are also known
+    /// without their extension, and with [content_addressed](Self::content_addressed) the
+    /// content-addressed URLs. Unknown paths are not handled at all, so they get no
+    /// [suggestions](Self::suggestions) or fallback response from this service, and no
+    /// [single-page application index](Self::spa_index) for client-side routes, which is
+    /// reported by [issues](Self::issues).
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::Embed;
+    /// use actix_web::{web, App, HttpResponse};
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let app = App::new()
+    ///     .service(Embed::new("/", &Assets).claim_only_known_paths(true))
+    ///     // still reachable
+    ///     .route("/api/health", web::get().to(HttpResponse::Ok));
+    /// ```
+    pub fn claim_only_known_paths(mut self, enabled: bool) -> Self {
+        self.options = self.options.claim_only_known_paths(enabled);
+        self
+    }
+
+    /// Set how requests are handled when the rate limiter fails to decide, by panicking or
+    /// returning [RateLimitDecision::Unavailable].
+    ///
+    /// Defaults to rejecting the request with `500 Internal Server Error`.
+    ///
+    /// With [FailurePolicy::Open] the request is served as if the limiter allowed it, with
+    /// [FailurePolicy::Closed] it is rejected with the given status code. Either way the
+    /// failure and the applied policy are reported by
+    /// [ServeRecord::hook_failure](crate::ServeRecord::hook_failure).
+    ///
+    /// Panics are only handled if they are caught, see
+    /// [catch_hook_panics](Self::catch_hook_panics).
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::{Embed, FailurePolicy, RateLimitDecision};
+    /// use actix_web::http::StatusCode;
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let embed = Embed::new("/static", &Assets)
+    ///     .rate_limiter(|_: &str| RateLimitDecision::Unavailable)
+    ///     .rate_limiter_failure(FailurePolicy::Closed(StatusCode::SERVICE_UNAVAILABLE))
+    ///     .on_serve(|record| {
+    ///         if let Some(failure) = record.hook_failure {
+    ///             eprintln!("{} failed: {:?}", failure.hook, failure.policy);
+    ///         }
+    ///     });
+    /// ```
+    pub fn rate_limiter_failure(mut self, policy: FailurePolicy) -> Self {
+        self.options = self.options.rate_limiter_failure(policy);
+        self
+    }
+
+    /// Set whether panics in user hooks are caught.
+    ///
+    /// Defaults to `true`.
+    ///
+    /// User hooks are the fallback handler, the rate limiter and the [on_serve](Self::on_serve)
+    /// hook, including the futures of [async hooks](Self::hook_timeout). If a panic is
+    /// caught, the request is answered with `500 Internal Server Error`, or the response is
+    /// sent unchanged if the panic occurred in `on_serve`. Panics in the rate limiter are
+    /// handled according to [rate_limiter_failure](Self::rate_limiter_failure).
+    /// Panics are still printed by the panic hook of the process.
+    pub fn catch_hook_panics(mut self, catch: bool) -> Self {
+        self.options = self.options.catch_hook_panics(catch);
+        self
+    }
+
+    /// Set the hook called with each panic caught in user hooks, e.g. for logging.
+    ///
+    /// See [catch_hook_panics](Self::catch_hook_panics).
+    pub fn on_hook_panic<H>(mut self, hook: H) -> Self
+    where
+        H: Fn(&HookPanic) + 'static,
+    {
+        self.options = self.options.on_hook_panic(hook);
+        self
+    }
+
+    /// Set the time after which async hooks are abandoned, e.g. a fallback handler forwarding
+    /// misses to another server with [execute_async](FallbackHandler::execute_async).
+    ///
+    /// By default async hooks are awaited until they complete.
+    ///
+    /// Requests whose fallback handler runs out of time are answered with
+    /// `504 Gateway Timeout`. Synchronous hooks cannot be interrupted, so they are not timed
+    /// out.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::time::Duration;
+    ///
+    /// use actix_embed::Embed;
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let embed = Embed::new("/", &Assets).hook_timeout(Duration::from_secs(5));
+    /// ```
+    pub fn hook_timeout(mut self, timeout: Duration) -> Self {
+        self.options = self.options.hook_timeout(timeout);
+        self
+    }
+
+    /// Set the time after which derived work for a request is abandoned.
+    ///
+    /// By default there is no timeout.
+    ///
+    /// Derived work includes reading compressed variants from the
+    /// [derived cache directory](Self::derived_cache_dir) and minifying files on first access.
+    /// Once the timeout passes, the request is answered according to `action`, either with the
+    /// original file or `503 Service Unavailable`. This keeps slow derived work from tying up
+    /// workers under load spikes.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::time::Duration;
+    /// use actix_embed::{Embed, TimeoutAction};
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let embed = Embed::new("/static", &Assets)
+    ///     .derived_cache_dir("/var/cache/app")
+    ///     .serve_timeout(Duration::from_millis(200), TimeoutAction::Identity);
+    /// ```
+    pub fn serve_timeout(mut self, timeout: Duration, action: TimeoutAction) -> Self {
+        self.options = self.options.serve_timeout(timeout, action);
+        self
+    }
+
+    /// Set the hasher computing the content hashes used in entity tags.
+    ///
+    /// Defaults to [Sha256], whose hashes are computed at compile time. Other hashers compute
+    /// the hash of each file on first access, e.g. [Blake3](crate::Blake3) with the
+    /// `hash-blake3` feature or [Xxh3](crate::Xxh3) with the `hash-xxhash` feature.
+    pub fn hasher<H: Hasher>(mut self, hasher: H) -> Self {
+        self.options = self.options.hasher(hasher);
+        self
+    }
+
+    /// Serve the version of the embedded files at `path` as JSON, so deployment tooling and
+    /// applications can detect when the served files changed.
+    ///
+    /// By default there is no version endpoint.
+    ///
+    /// `path` is relative to the mount path and takes precedence over embedded files. The
+    /// response looks like `{"build":"4f2c9e1","content_hash":"…","files":12}`, where
+    /// `content_hash` is derived from the paths and content hashes of all served files, and
+    /// `build` is taken from `info`. Responses carry the content hash as entity tag, so they
+    /// can be polled with conditional requests.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::{Embed, VersionInfo};
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let embed = Embed::new("/", &Assets)
+    ///     .version_endpoint("/__version", VersionInfo::new().build("4f2c9e1"));
+    /// ```
+    pub fn version_endpoint<P: AsRef<str>>(mut self, path: P, info: VersionInfo) -> Self {
+        self.options = self.options.version_endpoint(path, info);
+        self
+    }
+
+    /// Set whether image variants are selected by client hints.
+    ///
+    /// Defaults to `false`.
+    ///
+    /// Embedded files named like `hero@2x.png` or `hero@640w.png` are variants of the logical
+    /// image `hero.png`, meant for a pixel density or a width in physical pixels. Requests for
+    /// a logical image are served with the variant best matching the `Sec-CH-Width` and
+    /// `Sec-CH-DPR` client hints: the smallest variant large enough, or the largest one.
+    /// Without hints, a density of 1 is assumed.
+    ///
+    /// Responses for logical images carry `Vary: Sec-CH-DPR, Sec-CH-Width`. HTML files are
+    /// served with `Accept-CH: Sec-CH-DPR, Sec-CH-Width`, which asks browsers to send the hints
+    /// with subsequent requests.
+    pub fn responsive_images(mut self, enabled: bool) -> Self {
+        self.options = self.options.responsive_images(enabled);
+        self
+    }
+
+    /// Set when the values served with each file are computed.
+    ///
+    /// Defaults to [Preparation::Lazy].
+    ///
+    /// Serving a file requires its content hash for the entity tag, its content type and the
+    /// header rules matching it. These are computed on the first request for the file and kept
+    /// for later requests, so services start quickly even with tens of thousands of files. With
+    /// [Preparation::Eager], they are computed for all files when services are created instead,
+    /// which delays startup but keeps first requests as fast as later ones. This mostly matters
+    /// with a [hasher](Self::hasher) which reads the whole file.
+    ///
+    /// With [Preparation::Eager], the files are hashed and [compressed](Self::precompress) on
+    /// all cores of the host, so large embeds start in a fraction of the time. Hashers which
+    /// cannot be shared between threads, see [Hasher::as_sync], hash on a single thread.
+    /// Report the progress with [on_prepare_progress](Self::on_prepare_progress).
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::{Embed, Preparation};
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let embed = Embed::new("/", &Assets).preparation(Preparation::Eager);
+    /// ```
+    pub fn preparation(mut self, preparation: Preparation) -> Self {
+        self.options = self.options.preparation(preparation);
+        self
+    }
+
+    /// Set the hook reporting the progress of the startup work of services.
+    ///
+    /// The hook is called after each file is compressed by [precompress](Self::precompress)
+    /// and, with [Preparation::Eager], after each file is hashed, with the number of items
+    /// done and the total of the [stage](PrepareStage), e.g. to log the progress of slow
+    /// starts. It is called on the thread creating the service, or on the blocking thread pool
+    /// with [precompress_blocking](Self::precompress_blocking), and panics are not caught.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::{Embed, Preparation, PrepareProgress};
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let embed = Embed::new("/", &Assets)
+    ///     .preparation(Preparation::Eager)
+    ///     .on_prepare_progress(|progress: PrepareProgress| {
+    ///         if progress.done % 1000 == 0 || progress.done == progress.total {
+    ///             println!("{:?}: {}/{}", progress.stage, progress.done, progress.total);
+    ///         }
+    ///     });
+    /// ```
+    pub fn on_prepare_progress<H>(mut self, hook: H) -> Self
+    where
+        H: Fn(PrepareProgress) + Send + Sync + 'static,
+    {
+        self.options = self.options.on_prepare_progress(hook);
+        self
+    }
+
+    /// Set whether requests with a method other than `GET` are only rejected for existing
+    /// paths.
+    ///
+    /// Defaults to `false`, requests with other methods are answered with
+    /// `405 Method Not Allowed` before the path is looked up.
+    ///
+    /// If it's set to true, the path is looked up first. Requests for files, redirects and the
+    /// [version endpoint](Self::version_endpoint) are still answered with `405`, while requests
+    /// for paths that don't exist are handled by the fallback handler whatever their method,
+    /// so the status doesn't reveal which paths exist.
+    pub fn hide_missing_paths(mut self, hide: bool) -> Self {
+        self.options = self.options.hide_missing_paths(hide);
+        self
+    }
+
+    /// Returns a description of the effective configuration.
+    ///
+    /// The description lists the mount path, the fallback handler type and every option, and
+    /// formats as JSON, so it can be logged at startup or served on an admin endpoint.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::{Embed, Value};
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let embed = Embed::new("/static", &Assets).index_file("index.html");
+    /// let description = embed.describe();
+    /// assert_eq!(description.get("index_file"), Some(&Value::from("index.html")));
+    /// println!("serving embedded files: {}", description);
+    /// ```
+    pub fn describe(&self) -> Description {
+        let mut entries = vec![
+            (
+                "mount_path".to_owned(),
+                mount_registry::display(&self.mount_path).into(),
+            ),
+            (
+                "fallback_handler".to_owned(),
+                std::any::type_name::<F>().into(),
+            ),
+        ];
+        entries.extend(self.options.describe());
+        Description::new(entries)
+    }
+
+    /// Returns the routing, redirect and header rules applied to requests, see [Ruleset].
+    ///
+    /// The rules format as JSON or CSV, to be mirrored into the configuration of a CDN.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::{Embed, RuleKind};
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let embed = Embed::new("/static", &Assets)
+    ///     .redirect("style.css", "assets/index.css")
+    ///     .header(["**/*.css"], "cache-control", "max-age=3600");
+    /// let ruleset = embed.ruleset();
+    /// assert_eq!(ruleset.rules[0].kind, RuleKind::Redirect);
+    /// assert_eq!(ruleset.rules[0].value, "/static/assets/index.css");
+    /// println!("{}", ruleset.to_csv());
+    /// ```
+    pub fn ruleset(&self) -> Ruleset {
+        Ruleset::new(&self.mount_path, &self.options)
+    }
+
+    /// Cache negotiated responses, keeping at most `max_per_path` per request path.
+    ///
+    /// By default responses are negotiated for each request.
+    ///
+    /// Serving a file involves selecting the index file, image variant and encoding for the
+    /// request, and building the headers. With the cache, the finished headers and body are
+    /// kept per request path and combination of the request headers they depend on, like
+    /// `Accept-Encoding` and `Accept-Language`, so this is done once per combination.
+    /// Responses degraded by the [serve timeout](Self::serve_timeout) are not cached.
+    ///
+    /// Clients send many distinct combinations of these headers, so the number of cached
+    /// responses per path is bounded by `max_per_path`. The
+    /// [metrics](Self::metrics) count cached and uncached responses, to detect paths reaching
+    /// the bound.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::{Embed, ServeMetrics};
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let metrics = ServeMetrics::new();
+    /// let embed = Embed::new("/", &Assets)
+    ///     .variant_cache(16)
+    ///     .metrics(metrics.clone());
+    /// ```
+    pub fn variant_cache(mut self, max_per_path: usize) -> Self {
+        self.options = self.options.variant_cache(max_per_path);
+        self
+    }
+
+    /// Set whether responses for embedded files carry the `Date` they were generated at, and
+    /// their `Age`.
+    ///
+    /// By default the server sets `Date` to the time each response is sent, and no `Age` is
+    /// sent.
+    ///
+    /// Responses kept by the [variant cache](Self::variant_cache) are generated once and sent
+    /// many times. With this option, they are sent with the `Date` they were generated at
+    /// and the number of seconds since in `Age`, measured with a monotonic clock, so caches
+    /// downstream compute their freshness from when they were generated, like for responses
+    /// of any other cache. Responses generated for the request are sent with `Age: 0`.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::Embed;
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let embed = Embed::new("/", &Assets).variant_cache(16).age_headers(true);
+    /// ```
+    pub fn age_headers(mut self, enabled: bool) -> Self {
+        self.options = self.options.age_headers(enabled);
+        self
+    }
+
+    /// Set whether responses for embedded files carry a `Cache-Status` header (RFC 9211)
+    /// telling how the caches of the service took part in them.
+    ///
+    /// By default no `Cache-Status` header is sent.
+    ///
+    /// The header lists the cache closest to the embedded files first:
+    /// - `actix-embed-derived` is the cache of the files prepared for serving, with their
+    ///   content type, hash and headers, see [derived_cache](Self::derived_cache). It has a
+    ///   `hit` if the file was prepared by an earlier request, otherwise `fwd=miss; stored`;
+    /// - `actix-embed` is the [variant cache](Self::variant_cache), if enabled. It has a `hit`
+    ///   for responses sent from it, which list no other cache, otherwise `fwd=uri-miss`, with
+    ///   `stored` if the generated response was kept.
+    ///
+    /// Caches downstream append their own members, e.g.
+    /// `actix-embed; hit, ExampleCDN; fwd=uri-miss; stored`.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::Embed;
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let embed = Embed::new("/", &Assets).variant_cache(16).cache_status(true);
+    /// ```
+    pub fn cache_status(mut self, enabled: bool) -> Self {
+        self.options = self.options.cache_status(enabled);
+        self
+    }
+
+    /// Look up files in folders selected by query parameters, for URLs which can't be changed
+    /// to include the folder.
+    ///
+    /// By default query strings are ignored when looking up files.
+    ///
+    /// The first rule matching a query parameter of the request selects a folder, and the
+    /// requested file is looked up in that folder first, e.g. `/index.html?lang=de` is served
+    /// with `de/index.html` with [QueryRule::subtree]. Files missing in the selected folder
+    /// are looked up as without rules.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::{Embed, QueryRule};
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let embed = Embed::new("/", &Assets).match_query([QueryRule::subtree("lang")]);
+    /// ```
+    pub fn match_query<I>(mut self, rules: I) -> Self
+    where
+        I: IntoIterator<Item = QueryRule>,
+    {
+        self.options = self.options.match_query(rules);
+        self
+    }
+
+    /// Share the indexes and compressed variants built when services are created with the
+    /// services of other workers, see [SharedState].
+    ///
+    /// By default each service builds them for itself.
+    pub fn shared_state(mut self, state: &SharedState) -> Self {
+        self.options = self.options.shared_state(state);
+        self
+    }
+
+    /// Share the content hashes, prepared headers and minified variants derived while files
+    /// are served with the other services of the worker, see [DerivedCache].
+    ///
+    /// By default each service derives them for itself.
+    ///
+    /// Pass the same cache to each mount of the same embedded files, e.g. `/static` and
+    /// `/cdn`, so they derive the values once.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::{DerivedCache, Embed};
+    /// use actix_web::App;
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let cache = DerivedCache::new();
+    /// let app = App::new()
+    ///     .service(Embed::new("/static", &Assets).derived_cache(&cache))
+    ///     .service(Embed::new("/cdn", &Assets).derived_cache(&cache));
+    /// ```
+    pub fn derived_cache(mut self, cache: &DerivedCache) -> Self {
+        self.options = self.options.derived_cache(cache);
+        self
+    }
+
+    /// Answer requests with the embedded `page` and `503 Service Unavailable` while
+    /// maintenance mode is switched on with `handle`, see [EmbedHandle].
+    ///
+    /// By default requests are always served.
+    ///
+    /// Responses carry `Retry-After` with `retry_after` in seconds, and are not cached by
+    /// clients. Endpoints, e.g. the [version endpoint](Self::version_endpoint), are unavailable
+    /// too. Paths matching globs passed to [maintenance_allow](Self::maintenance_allow) are
+    /// served as usual.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::time::Duration;
+    ///
+    /// use actix_embed::{Embed, EmbedHandle};
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let handle = EmbedHandle::new();
+    /// let embed = Embed::new("/", &Assets)
+    ///     .maintenance(&handle, "maintenance.html", Duration::from_secs(300))
+    ///     .maintenance_allow(["assets/**"]);
+    ///
+    /// // later, e.g. in an admin endpoint
+    /// handle.set_maintenance(true);
+    /// ```
+    pub fn maintenance<P: AsRef<str>>(
+        mut self,
+        handle: &EmbedHandle,
+        page: P,
+        retry_after: Duration,
+    ) -> Self {
+        self.options = self.options.maintenance(handle, page, retry_after);
+        self
+    }
+
+    /// Keep serving paths matching `globs` in maintenance mode, e.g. the assets of the
+    /// maintenance page and health checks.
+    ///
+    /// See [maintenance](Self::maintenance).
+    pub fn maintenance_allow<I>(mut self, globs: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        self.options = self.options.maintenance_allow(globs);
+        self
+    }
+
+    /// Serve the mount only to requests with the cookie `name` accepted by `verifier`, e.g.
+    /// the session cookie of an internal dashboard.
+    ///
+    /// Other requests get `401 Unauthorized` responses, or are redirected to the
+    /// [login URL](Self::login_redirect). The version, import map and listing endpoints are
+    /// gated too, [CORS](Self::cors) preflights are not since browsers send them without
+    /// cookies. Served responses carry `Vary: Cookie` and are marked `private`, so shared
+    /// caches do not serve them to other clients. A verifier that panics rejects the request.
+    ///
+    /// Closures receive the value of the cookie. With the `secure-cookies` feature,
+    /// [SignedCookie](crate::SignedCookie) and [PrivateCookie](crate::PrivateCookie) accept
+    /// cookies set with the signed and private jars of `actix-web` and a `Key`.
+    ///
+    /// An invalid cookie name is reported by [try_build](Self::try_build).
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::Embed;
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let embed = Embed::new("/dashboard", &Assets)
+    ///     .require_signed_cookie("session", |value: &str| value == "valid-session")
+    ///     .login_redirect("/login?return_to={return_to}");
+    /// ```
+    pub fn require_signed_cookie<N, V>(mut self, name: N, verifier: V) -> Self
+    where
+        N: AsRef<str>,
+        V: CookieVerifier,
+    {
+        self.options = self.options.require_signed_cookie(name, verifier);
+        self
+    }
+
+    /// Redirect requests rejected by [require_signed_cookie](Self::require_signed_cookie) to
+    /// `url` with `303 See Other`.
+    ///
+    /// `{return_to}` in `url` is replaced with the percent-encoded path and query of the
+    /// rejected request, so the login page can send users back.
+    pub fn login_redirect<U: AsRef<str>>(mut self, url: U) -> Self {
+        self.options = self.options.login_redirect(url);
+        self
+    }
+
+    /// Describe how each request was resolved in the `X-Embed-Resolution` response header,
+    /// e.g. `lookup ""; index file "index.html"; found "index.html"; encoding identity`.
+    ///
+    /// Disabled by default. The trace is meant for debugging why a path is not found or served
+    /// with an unexpected variant, and reveals the layout of the embedded files, so it is
+    /// only sent by debug builds, the option has no effect in release builds.
+    pub fn resolution_trace(mut self, enabled: bool) -> Self {
+        self.options = self.options.resolution_trace(enabled);
+        self
+    }
+
+    /// Returns the handle toggling features of the services at runtime, see [EmbedAdmin].
+    ///
+    /// Each `Embed` has its own handle unless one is set with [with_admin](Self::with_admin).
+    pub fn admin(&self) -> EmbedAdmin {
+        self.options.admin.clone()
+    }
+
+    /// Toggle features of the services at runtime with `admin`, e.g. to control the services
+    /// of all workers with one handle.
+    ///
+    /// By default each `Embed` has its own handle, see [admin](Self::admin).
+    pub fn with_admin(mut self, admin: &EmbedAdmin) -> Self {
+        self.options = self.options.with_admin(admin);
+        self
+    }
+
+    /// Serve every file additionally at a content-addressed URL,
+    /// `_by-hash/<sha256>/<path>` under the mount path, with
+    /// `Cache-Control: public, max-age=31536000, immutable`.
+    ///
+    /// Defaults to `false`.
+    ///
+    /// The URL of a file changes whenever its content does, so clients can cache responses
+    /// forever without renaming build outputs. Requests with a hash not matching the current
+    /// content of the file are handled like requests for missing files. Emit the URLs with
+    /// [content_addresses](Self::content_addresses).
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::Embed;
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let embed = Embed::new("/static", &Assets).content_addressed(true);
+    /// let addresses = embed.content_addresses();
+    /// let url = addresses.get("assets/index.css").unwrap();
+    /// assert!(url.starts_with("/static/_by-hash/"));
+    /// ```
+    pub fn content_addressed(mut self, enabled: bool) -> Self {
+        self.options = self.options.content_addressed(enabled);
+        self
+    }
+
+    /// Returns the content-addressed URLs of the served files, see
+    /// [content_addressed](Self::content_addressed).
+    ///
+    /// The URLs only depend on the embedded files and the mount path, so they can be computed
+    /// once at startup, e.g. to be passed to templates.
+    pub fn content_addresses(&self) -> ContentAddresses {
+        ContentAddresses::build::<E>(&self.mount_path, &self.options.keys())
+    }
+
+    /// Serve an endpoint at `path` under the mount path, telling which of many cached files
+    /// are stale in one request, e.g. for CDNs and edge workers revalidating their caches.
+    ///
+    /// Disabled by default.
+    ///
+    /// The endpoint accepts `POST` requests with a JSON list of cached files and their entity
+    /// tags, with paths relative to the mount path:
+    /// ```json
+    /// [{"path": "index.html", "etag": "\"5f1c...\""}]
+    /// ```
+    /// and responds with the stale files and their current entity tags, `null` for files
+    /// which are not served anymore, including [excluded](Self::exclude) files:
+    /// ```json
+    /// {"stale": [{"path": "index.html", "etag": "\"9a0b...\""}]}
+    /// ```
+    /// Entity tags of compressed and minified variants are current as long as the file is.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::Embed;
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let embed = Embed::new("/static", &Assets).revalidation_endpoint("__revalidate");
+    /// ```
+    pub fn revalidation_endpoint<P: AsRef<str>>(mut self, path: P) -> Self {
+        self.options = self.options.revalidation_endpoint(path);
+        self
+    }
+
+    /// Serve an endpoint at `path` under the mount path, listing the entries of directories
+    /// as JSON for UIs and scripts browsing the served files.
+    ///
+    /// Disabled by default.
+    ///
+    /// The endpoint accepts `GET` requests with the query parameters:
+    /// - `dir`: the listed directory, relative to the mount path, the root by default;
+    /// - `sort`: `name`, `size` or `mtime`, `name` by default;
+    /// - `order`: `asc` or `desc`, `asc` by default;
+    /// - `offset` and `limit`: the page, the first 100 entries by default, at most 1000.
+    ///
+    /// and responds with a page of the entries:
+    /// ```json
+    /// {"dir": "assets", "total": 2, "offset": 0, "next_offset": null, "entries": [
+    ///   {"name": "fonts", "kind": "dir", "size": null, "mtime": null},
+    ///   {"name": "index.css", "kind": "file", "size": 347, "mtime": 1700000000}
+    /// ]}
+    /// ```
+    /// The order is total, entries with equal values are ordered by name, and directories and
+    /// files without embedded modification times go last when sorting by `size` or `mtime`,
+    /// so pages of a directory never overlap nor miss entries. Unknown directories are
+    /// answered with `404 Not Found`, invalid queries with `400 Bad Request`. Excluded paths
+    /// and files outside their [availability windows](Self::available) are not listed.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::Embed;
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let embed = Embed::new("/static", &Assets).listing_endpoint("__listing");
+    /// ```
+    pub fn listing_endpoint<P: AsRef<str>>(mut self, path: P) -> Self {
+        self.options = self.options.listing_endpoint(path);
+        self
+    }
+
+    /// Serve the effective configuration at `path`, relative to the mount path, in debug
+    /// builds.
+    ///
+    /// The endpoint renders the [description](Self::describe) of the service: the mount path,
+    /// globs, header and cache rules, redirects and every other option, as an HTML table for
+    /// browsers and as JSON for other clients, to inspect what a service was configured with
+    /// during development. Responses are not cached.
+    ///
+    /// The endpoint is compiled out of release builds, where requests for `path` are looked
+    /// up like other paths, so it cannot expose the configuration in production.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::Embed;
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let embed = Embed::new("/static", &Assets).rules_endpoint("__embed/rules");
+    /// ```
+    pub fn rules_endpoint<P: AsRef<str>>(mut self, path: P) -> Self {
+        self.options = self.options.rules_endpoint(path);
+        self
+    }
+
+    /// Resolve bare specifiers of the npm-style packages in the directory `dir` to the
+    /// files they export, for vendored JavaScript modules imported directly by browsers.
+    ///
+    /// By default packages are served as any other files.
+    ///
+    /// The `package.json` files of the packages in `dir`, like `vendor/lodash` or
+    /// `vendor/@preact/signals`, are read when services are created. Requests for a package,
+    /// e.g. `/vendor/lodash`, or one of its subpath exports, e.g.
+    /// `/vendor/@preact/signals/utils`, are redirected with `302 Found` to the exported file,
+    /// so relative imports in the module resolve against its actual location.
+    ///
+    /// The `browser`, `import`, `module` and `default` conditions of `exports` are supported
+    /// in this order, subpath patterns are not. Packages without `exports` export their
+    /// `module` or `main` file, or `index.js`. Further specifiers can be mapped with
+    /// [package_export](Self::package_export).
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::Embed;
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let embed = Embed::new("/", &Assets).packages("vendor");
+    /// ```
+    pub fn packages<P: AsRef<str>>(mut self, dir: P) -> Self {
+        self.options = self.options.packages(dir);
+        self
+    }
+
+    /// Resolve the bare specifier `specifier` to the file at `path`, e.g. for packages
+    /// without `package.json`, or to override their exports.
+    ///
+    /// Both are relative to the mount path, see [packages](Self::packages).
+    pub fn package_export<S: AsRef<str>, P: AsRef<str>>(mut self, specifier: S, path: P) -> Self {
+        self.options = self.options.package_export(specifier, path);
+        self
+    }
+
+    /// Serve the files under the directory `dir` as a tar archive at `path`, relative to the
+    /// mount path, e.g. to download a documentation tree.
+    ///
+    /// By default no archives are served.
+    ///
+    /// Archives are streamed: the files are sent one after the other in chunks of 64 KiB, as
+    /// fast as the client reads them, so large trees are not buffered in memory. Archives of
+    /// the same files are byte-identical, with fixed modification times and permissions.
+    /// Paths and sizes too large for ustar headers are stored in pax extended headers.
+    /// [Excluded](Self::exclude) files and files outside their
+    /// [availability windows](Self::available) are left out.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::Embed;
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// // `/static/fonts.tar` contains `icons.woff` and `icons.woff2`
+    /// let embed = Embed::new("/static", &Assets).archive("fonts.tar", "fonts");
+    /// ```
+    pub fn archive<P: AsRef<str>, D: AsRef<str>>(mut self, path: P, dir: D) -> Self {
+        self.options = self.options.archive(path, dir);
+        self
+    }
+
+    /// Serve `percent` percent of the sessions from the embedded files of `bundle` instead of
+    /// `E`, e.g. to roll out a new frontend build gradually from a binary embedding both.
+    ///
+    /// By default all requests are served from `E`.
+    ///
+    /// Sessions are assigned to the canary or the stable bundle on their first request, and
+    /// stay there with a cookie kept for 30 days. Responses carry `Vary: Cookie`. The canary
+    /// bundle is served with the same options and fallback handler, set before or after this
+    /// one. A percentage above 100 is reported by [try_build](Self::try_build).
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::Embed;
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/en/"]
+    /// struct NextAssets;
+    ///
+    /// let embed = Embed::new("/", &Assets).canary(5, &NextAssets);
+    /// ```
+    pub fn canary<C>(mut self, percent: u8, bundle: &C) -> Self
+    where
+        C: 'static + rust_embed::RustEmbed,
+    {
+        self.options = self.options.canary(percent, bundle);
+        self
+    }
+
+    /// Serve an import map at `path`, so browsers resolve ES modules without a bundler.
+    ///
+    /// By default there is no import map endpoint.
+    ///
+    /// The import map maps the URLs of the files matching `globs` to the URLs they are
+    /// served at, and the bare specifiers of [packages](Self::packages), e.g. `lodash`, to
+    /// their exported files. With [content_addressed](Self::content_addressed) the files are
+    /// mapped to their content-addressed URLs, which change with their content, so modules
+    /// importing each other by their plain URLs are cached forever but never outdated.
+    ///
+    /// Entries resolving to [excluded](Self::exclude) files or files outside their
+    /// [availability windows](Self::available) are left out. The import map is served with
+    /// the `application/importmap+json` content type, and can be referenced with
+    /// `<script type="importmap" src="...">` or inlined by the server.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::Embed;
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let embed = Embed::new("/", &Assets)
+    ///     .packages("vendor")
+    ///     .content_addressed(true)
+    ///     .import_map("importmap.json", ["**/*.js", "**/*.mjs"]);
+    /// ```
+    pub fn import_map<P, I>(mut self, path: P, globs: I) -> Self
+    where
+        P: AsRef<str>,
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        self.options = self.options.import_map(path, globs);
+        self
+    }
+
+    /// Apply the options bundled by `plugin`, e.g. a preset shipped by another crate.
+    ///
+    /// Plugins are applied in order, so later options override theirs as usual. Their names
+    /// are listed by [describe](Self::describe). See [EmbedPlugin].
+    pub fn with<P: EmbedPlugin>(mut self, plugin: P) -> Self {
+        self.options = self.options.with(plugin);
+        self
+    }
+
+    /// Record the mount path in `registry` when registered, to detect conflicting mounts.
+    ///
+    /// See [MountRegistry].
+    pub fn mount_registry(mut self, registry: &MountRegistry) -> Self {
+        self.options = self.options.mount_registry(registry);
         self
     }
 
     /// Sets fallback handler which is used when no matched file could be found.
     ///
-    /// The default fallback handler returns 404 responses.
+    /// The default fallback handler returns 404 responses, see [DefaultFallbackHandler].
+    /// Closures can be passed more conveniently with [fallback](Self::fallback).
     ///
     /// # Examples
     /// ```
@@ -145,12 +3236,125 @@ where
     {
         Embed {
             mount_path: self.mount_path,
-            index_file_path: self.index_file_path,
-            strict_slash: self.strict_slash,
+            options: self.options,
             fallback_handler: handler,
             _f: Default::default(),
         }
     }
+
+    /// Sets a closure as fallback handler, which is used when no matched file could be found.
+    ///
+    /// The closure can return anything implementing [Responder], and needs no type annotation
+    /// for its parameter.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_embed::Embed;
+    /// use actix_web::http::StatusCode;
+    /// use rust_embed::RustEmbed;
+    ///
+    /// #[derive(RustEmbed)]
+    /// #[folder = "testdata/"]
+    /// struct Assets;
+    ///
+    /// let embed = Embed::new("/static", &Assets)
+    ///     .fallback(|req| (format!("{} not found", req.path()), StatusCode::NOT_FOUND));
+    /// ```
+    pub fn fallback<H, R>(self, handler: H) -> Embed<E, H>
+    where
+        H: Fn(&HttpRequest) -> R + Clone + 'static,
+        R: Responder,
+    {
+        self.fallback_handler(handler)
+    }
+}
+
+impl<E, F> Embed<E, F>
+where
+    E: 'static + rust_embed::RustEmbed,
+    F: FallbackHandler,
+{
+    /// Returns the guard rejecting requests for excluded paths, so they reach other services.
+    fn exclusion_guard(&self) -> Option<Box<dyn Guard>> {
+        if self.options.excluded.is_empty() {
+            return None;
+        }
+        let excluded = self.options.excluded.clone();
+        let mount = format!("{}/", self.mount_path);
+        Some(Box::new(guard::fn_guard(move |ctx| {
+            match path_under(ctx.head(), &mount) {
+                Some(path) => !excluded.iter().any(|glob| glob.matches(&path)),
+                None => true,
+            }
+        })))
+    }
+
+    /// Returns the guard rejecting requests whose first path segment is not known, see
+    /// [claim_only_known_paths](Self::claim_only_known_paths).
+    fn known_paths_guard(&self) -> Option<Box<dyn Guard>> {
+        if !self.options.claim_only_known_paths {
+            return None;
+        }
+        let options = &self.options;
+        let first_segment = |path: &str| {
+            let path = path.trim_start_matches('/');
+            path.split('/').next().unwrap_or(path).to_owned()
+        };
+        let mut known = HashSet::new();
+        for entry in FileIndex::build::<E>(&options.keys()).entries("") {
+            let (Entry::File(name) | Entry::Dir(name)) = entry;
+            if let (Entry::File(_), true) = (&entry, options.clean_urls) {
+                if let Some(stem) = name.strip_suffix(".html") {
+                    known.insert(stem.to_owned());
+                }
+            }
+            known.insert(name.to_owned());
+        }
+        let endpoints = [
+            options
+                .version_endpoint
+                .as_ref()
+                .map(|endpoint| &endpoint.path),
+            options.import_map.as_ref().map(|endpoint| &endpoint.path),
+            options.listing_endpoint.as_ref(),
+            options
+                .rules_endpoint
+                .as_ref()
+                .filter(|_| cfg!(debug_assertions)),
+            options.revalidation_endpoint.as_ref(),
+        ];
+        let paths = endpoints
+            .into_iter()
+            .flatten()
+            .chain(options.archives.keys())
+            .chain(options.redirects.keys())
+            .chain(options.package_exports.keys());
+        known.extend(paths.map(|path| first_segment(path)));
+        if options.content_addressed {
+            known.insert(first_segment(content_address::BY_HASH));
+        }
+
+        let mount = format!("{}/", self.mount_path);
+        Some(Box::new(guard::fn_guard(move |ctx| {
+            let Some(path) = path_under(ctx.head(), &mount) else {
+                return true;
+            };
+            let segment = first_segment(&path);
+            segment.is_empty() || known.contains(&segment)
+        })))
+    }
+}
+
+/// Returns the path of the request relative to `mount`, ending with `/`, decoded as the path
+/// looked up in the embedded files, `None` if the request is not under it.
+///
+/// Guards only see the request head, so the mount path is searched in the whole path, which
+/// may start with the paths of enclosing scopes.
+fn path_under(head: &RequestHead, mount: &str) -> Option<String> {
+    let url = Url::new(head.uri.clone());
+    let full = url.path();
+    let start = full.find(mount)?;
+    Some(full[start + mount.len()..].to_owned())
 }
 
 impl<E, F> HttpServiceFactory for Embed<E, F>
@@ -159,12 +3363,32 @@ where
     F: FallbackHandler,
 {
     fn register(self, config: &mut AppService) {
-        let resource_def = if config.is_root() {
-            ResourceDef::root_prefix(&self.mount_path)
-        } else {
-            ResourceDef::prefix(&self.mount_path)
-        };
-        config.register_service(resource_def, None, self, None)
+        if let Some(registry) = &self.options.mount_registry {
+            registry.record(config, &self.mount_path, self.options.mount_kind);
+        }
+        if let Some(err) = self.options.errors.first() {
+            panic!("invalid configuration: {}", err);
+        }
+        if self.options.registry.policy == CollisionPolicy::Error {
+            let collisions = self.collisions();
+            if !collisions.is_empty() {
+                let collisions = collisions.iter().map(ToString::to_string);
+                panic!(
+                    "colliding embedded files: {}",
+                    collisions.collect::<Vec<_>>().join("; ")
+                );
+            }
+        }
+        let resource_def = self
+            .options
+            .mount_kind
+            .resource_def(&self.mount_path, config.is_root());
+        let guards = [self.exclusion_guard(), self.known_paths_guard()]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>();
+        let guards = (!guards.is_empty()).then_some(guards);
+        config.register_service(resource_def, guards, self, None)
     }
 }
 
@@ -181,16 +3405,92 @@ where
     type Future = LocalBoxFuture<'static, Result<Self::Service, Self::InitError>>;
 
     fn new_service(&self, _: ()) -> Self::Future {
-        let strict_slash = self.strict_slash;
+        let options = self.options.clone();
+        #[cfg(debug_assertions)]
+        let rules = options.rules_endpoint.as_ref().map(|_| self.describe());
+        let init = || Caches {
+            #[cfg(feature = "minify")]
+            minify: options.minify.map(MinifyCache::new),
+            hashes: ContentHashes::new(options.hasher.clone().unwrap_or_else(|| Rc::new(Sha256))),
+            prepared: PreparedFiles::default(),
+        };
+        let caches = match &options.derived_cache {
+            Some(cache) => cache.get_or_init::<E, _>(init),
+            None => Rc::new(init()),
+        };
+        let warm_options = WarmOptions::new(&options);
+        let variant_cache = options.variant_cache.map(VariantCache::new);
         let fallback_handler = self.fallback_handler.clone();
-        let index_file_path = self.index_file_path.clone();
+        let canary = options.canary.as_ref().map(|canary| {
+            let mut canary_options = options.clone();
+            canary_options.canary = None;
+            // the shared state holds the files of `E`
+            canary_options.shared_state = None;
+            let fallback = ErasedFallback::new(fallback_handler.clone());
+            let service = (canary.factory)(canary_options, fallback);
+            (service, canary.percent)
+        });
 
         Box::pin(async move {
-            Ok(EmbedService::new(EmbedServiceInner {
-                strict_slash,
-                index_file_path,
+            let canary = match canary {
+                Some((service, percent)) => Some((service.await?, percent)),
+                None => None,
+            };
+            let shared = options.shared_state.clone();
+            let build = move || match shared {
+                Some(shared) => {
+                    shared.get_or_init(|| Arc::new(WarmState::build::<E>(warm_options)))
+                }
+                None => Arc::new(WarmState::build::<E>(warm_options)),
+            };
+            let warm = match options.precompress_blocking {
+                true => actix_web::rt::task::spawn_blocking(build)
+                    .await
+                    .map_err(|_| ())?,
+                false => build(),
+            };
+            let version = options.version_endpoint.as_ref().map(|endpoint| {
+                BundleVersion::build::<E>(
+                    &endpoint.info,
+                    warm.index::<E>(),
+                    &warm.keys,
+                    &caches.hashes,
+                )
+            });
+            let import_map = options.import_map.as_ref().map(|endpoint| {
+                let packages = options.packages.as_deref().zip(warm.packages.as_ref());
+                ImportMap::build::<E>(
+                    endpoint,
+                    warm.index::<E>(),
+                    &warm.keys,
+                    packages,
+                    options.content_addressed,
+                )
+            });
+
+            let endpoints = EndpointCache::new(options.endpoint_compression.clone());
+            let inner = EmbedServiceInner {
+                options,
+                caches,
+                warm,
+                variant_cache,
+                generation: Generation::default(),
+                version,
+                import_map,
+                endpoints,
+                canary,
+                served: Cell::new(0),
+                #[cfg(debug_assertions)]
+                rules,
                 fallback_handler,
-            }))
+            };
+            if let Some(metrics) = &inner.options.metrics {
+                inner.register_gauges(metrics);
+            }
+            if inner.options.preparation == Preparation::Eager {
+                inner.prepare_all::<E>();
+            }
+            Ok(EmbedService::new(inner))
         })
     }
 }
@@ -232,8 +3532,24 @@ pub(crate) struct EmbedServiceInner<F>
 where
     F: FallbackHandler,
 {
-    strict_slash: bool,
-    index_file_path: Option<String>,
+    options: EmbedOptions,
+    /// The hashes, prepared files and minified variants, see [Embed::derived_cache].
+    caches: Rc<Caches>,
+    warm: Arc<WarmState>,
+    variant_cache: Option<VariantCache>,
+    /// The purges applied to the derived caches, see [EmbedAdmin::purge].
+    generation: Generation,
+    version: Option<BundleVersion>,
+    import_map: Option<ImportMap>,
+    /// The generated bodies of the endpoints, see [Embed::compress_endpoints].
+    endpoints: EndpointCache,
+    /// The service of the canary bundle and the percentage of sessions it serves.
+    canary: Option<(CanaryService, u8)>,
+    /// The number of requests served by the worker, see [Embed::yield_every].
+    served: Cell<u32>,
+    /// The description served by [Embed::rules_endpoint].
+    #[cfg(debug_assertions)]
+    rules: Option<Description>,
     fallback_handler: F,
 }
 
@@ -250,49 +3566,1265 @@ where
 
     fn call(&self, req: ServiceRequest) -> Self::Future {
         let this = self.inner.clone();
+        // the span is a child of the span current when the request is dispatched, e.g. the
+        // root span of the request, and stays current in hooks awaited with the response
+        #[cfg(feature = "tracing")]
+        let span = tracing::info_span!(
+            "actix_embed::serve",
+            method = %req.method(),
+            path = %req.path(),
+        );
+        #[cfg(feature = "tracing")]
+        let record_span = span.clone();
 
-        Box::pin(async move {
-            if Method::GET.ne(req.method()) {
-                return Ok(req.into_response(HttpResponse::MethodNotAllowed()));
+        let fut = async move {
+            if this.should_yield() {
+                actix_web::rt::task::yield_now().await;
+            }
+            let start = Instant::now();
+            let cohort = this
+                .canary
+                .as_ref()
+                .map(|(_, percent)| Cohort::of(req.request(), *percent, this.mount_base(&req)));
+            if let (Some(cohort), Some((service, _))) = (&cohort, &this.canary) {
+                if cohort.canary {
+                    let mut resp = service(req).await?;
+                    cohort.apply(&mut resp);
+                    return Ok(resp);
+                }
+            }
+            if let Some(registry) = &this.warm.metadata {
+                req.extensions_mut().insert(registry.clone());
+            }
+            let admin = &this.options.admin;
+            for glob in this.generation.purges(admin) {
+                this.purge(glob.as_ref());
+            }
+            let trace = Trace::new(admin.resolution_trace(this.options.resolution_trace));
+            let threshold = this.options.slow_serve_threshold;
+            let clock = StageClock::new(threshold.is_some());
+            let path = this.options.mount_kind.lookup_path(&req);
+            let mut context = ServeContext::new(
+                req.method().clone(),
+                path.trim_start_matches('/'),
+                admin.epoch(),
+            );
+            let (mut resp, served) = this.serve::<E>(req, &trace, &clock, &mut context).await;
+            trace.apply(&mut resp);
+            resp.request().extensions_mut().insert(context.clone());
+            if this.options.cors.is_enabled() {
+                let req = resp.request().clone();
+                this.options.cors.apply(&req, resp.headers_mut());
+            }
+            if let Some(gate) = &this.options.cookie_gate {
+                gate.apply(resp.headers_mut());
+            }
+            if let Some(cohort) = &cohort {
+                cohort.apply(&mut resp);
             }
-            let mut path = req.path();
-            path = path.trim_start_matches('/');
-            if !this.strict_slash {
-                path = path.trim_end_matches('/');
+            if this.options.http10_compat && http10::is_http10(resp.request()) {
+                http10::add_expires(resp.headers_mut(), SystemTime::now());
             }
-            if path.is_empty() {
-                path = this.index_file_path.as_deref().unwrap_or("")
+            if let Served::File(key) = &served {
+                let path = this.warm.keys.path(key).unwrap_or(key);
+                if this
+                    .options
+                    .deprecated
+                    .iter()
+                    .any(|glob| glob.matches(path))
+                {
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!(path, "deprecated file served");
+                    resp.request().extensions_mut().insert(Deprecated);
+                }
+            }
+            if let (Some(registry), Served::File(key)) = (&this.warm.metadata, &served) {
+                let path = this.warm.keys.path(key).unwrap_or(key);
+                if let Some(metadata) = registry.get(path) {
+                    resp.request().extensions_mut().insert(metadata.clone());
+                }
+            }
+            if this.options.canonical_headers {
+                headers::canonicalize(resp.headers_mut());
+            }
+            clock.finish(ServeStage::Write);
+            if let Some(sink) = &this.options.on_decision {
+                let decision = Decision::new(&resp, &served);
+                let _ = this
+                    .options
+                    .hooks
+                    .call("record_decisions", || sink(decision));
+            }
+            if this.options.on_serve.is_some() || this.options.metrics.is_some() {
+                let elapsed = start.elapsed();
+                let slow = clock.slow(elapsed, threshold);
+                let record = ServeRecord::new(&resp, served, context, elapsed, slow);
+                let inner = this.clone();
+                // the hook is called in the span of the request, also once it was served
+                #[cfg(feature = "tracing")]
+                let span = record_span.clone();
+                let emit = Box::new(move |record| {
+                    #[cfg(feature = "tracing")]
+                    let _entered = span.enter();
+                    inner.emit(record)
+                });
+                resp = resp.map_body(|_, body| BoxBody::new(RecordedBody::new(body, record, emit)));
+            }
+            Ok(resp)
+        };
+        #[cfg(feature = "tracing")]
+        let fut = tracing::Instrument::instrument(fut, span);
+        Box::pin(fut)
+    }
+}
+
+impl<F> EmbedServiceInner<F>
+where
+    F: FallbackHandler,
+{
+    /// Passes the record of a response whose body was sent to the metrics and the
+    /// [on_serve](Embed::on_serve) hook.
+    fn emit(&self, record: ServeRecord) {
+        if let Some(metrics) = &self.options.metrics {
+            metrics.record(&record);
+        }
+        if let Some(on_serve) = &self.options.on_serve {
+            // the response is sent anyway, the panic is reported by the policy
+            let _ = self.options.hooks.call("on_serve", || on_serve(record));
+        }
+    }
+
+    /// Counts a request, returns `true` if the worker should yield before serving it.
+    fn should_yield(&self) -> bool {
+        let every = match self.options.yield_every {
+            Some(every) => every,
+            None => return false,
+        };
+        let served = self.served.get().wrapping_add(1);
+        self.served.set(served);
+        served.is_multiple_of(every)
+    }
+
+    /// Drops the derived artifacts of files matching `glob`, all of them if it is `None`.
+    fn purge(&self, glob: Option<&Glob>) {
+        self.caches.prepared.purge(glob);
+        self.endpoints.clear();
+        if let Some(cache) = &self.variant_cache {
+            cache.purge(glob);
+        }
+        #[cfg(feature = "minify")]
+        if let Some(minify) = &self.caches.minify {
+            minify.purge(glob);
+        }
+    }
+
+    /// Returns the body and entity tag of the variant derived from the file at `path`, if any.
+    #[cfg(feature = "minify")]
+    fn derived_variant(
+        &self,
+        req: &HttpRequest,
+        path: &str,
+        data: &[u8],
+        hash: &str,
+        deadline: &Deadline,
+    ) -> Result<Option<(Bytes, EntityTag)>, TimedOut> {
+        let minify = match &self.caches.minify {
+            Some(minify) => minify,
+            None => return Ok(None),
+        };
+        let minified = minify.get(req, path, data, hash, deadline)?;
+        Ok(minified.map(|minified| (minified.data.clone(), minified.etag.clone())))
+    }
+
+    #[cfg(not(feature = "minify"))]
+    fn derived_variant(
+        &self,
+        _: &HttpRequest,
+        _: &str,
+        _: &[u8],
+        _: &str,
+        _: &Deadline,
+    ) -> Result<Option<(Bytes, EntityTag)>, TimedOut> {
+        Ok(None)
+    }
+
+    /// Returns the media type served for the file at `path`, the type of its content if it
+    /// is [corrected](Embed::content_sniffing).
+    fn content_type(&self, path: &str) -> String {
+        let corrected = self
+            .warm
+            .sniffed
+            .as_ref()
+            .filter(|_| self.options.content_sniffing == Some(SniffAction::Correct))
+            .and_then(|sniffed| sniffed.get(path));
+        match corrected {
+            Some(mime) => mime.to_string(),
+            None => headers::content_type(path, &self.options.mime_types),
+        }
+    }
+
+    /// Returns the file served for `path` by [clean URLs](Embed::clean_urls) or the
+    /// [directory index](Embed::directory_index), if there is no file at `path`.
+    ///
+    /// `index` is `true` if `path` is the [index file](Embed::index_file).
+    fn alias<E>(&self, path: &str, index: bool, trace: &Trace) -> Option<(String, EmbeddedFile)>
+    where
+        E: 'static + rust_embed::RustEmbed,
+    {
+        let alias = self
+            .alias_candidates(path, index)
+            .find_map(|candidate| self.warm.keys.get::<E>(&candidate))?;
+        trace.step(|| format!("alias {:?}", alias.0));
+        Some(alias)
+    }
+
+    /// Returns the paths of the files which may be served for `path` by [alias](Self::alias),
+    /// in order of precedence.
+    fn alias_candidates(&self, path: &str, index: bool) -> impl Iterator<Item = String> {
+        let dir = path.trim_end_matches('/');
+        let clean = self
+            .options
+            .clean_urls
+            .then(|| format!("{}.html", dir))
+            .filter(|_| !dir.is_empty());
+        // an index file naming a directory is resolved even without directory indexes
+        let name = match (&self.options.directory_index, index) {
+            (Some(name), _) => Some(name.as_str()),
+            (None, true) => Some(DEFAULT_INDEX),
+            (None, false) => None,
+        };
+        let index = name
+            .filter(|_| !dir.is_empty())
+            .map(|name| format!("{}/{}", dir, name));
+        clean.into_iter().chain(index)
+    }
+
+    /// Returns the [single-page application index](Embed::spa_index) served for `path`
+    /// without a file, unless `path` has an extension or is exported or redirected.
+    fn spa_index<E>(
+        &self,
+        path: &str,
+        method_allowed: bool,
+        trace: &Trace,
+    ) -> Option<(String, EmbeddedFile)>
+    where
+        E: 'static + rust_embed::RustEmbed,
+    {
+        let spa_index = self.options.spa_index.as_deref()?;
+        let name = path.rsplit('/').next().unwrap_or(path);
+        if !method_allowed
+            || name.contains('.')
+            || self.options.package_exports.contains_key(path)
+            || self.options.redirects.contains_key(path)
+            || self
+                .warm
+                .packages
+                .as_ref()
+                .is_some_and(|packages| packages.resolve(path).is_some())
+        {
+            return None;
+        }
+        let found = self.warm.keys.get::<E>(spa_index)?;
+        trace.step(|| format!("single-page application index {:?}", found.0));
+        Some(found)
+    }
+
+    /// Returns `true` if the body served for `path` may be derived from the file, so its
+    /// entity tag is only known once the file is read.
+    #[cfg(feature = "minify")]
+    fn may_derive(&self, path: &str) -> bool {
+        self.caches
+            .minify
+            .as_ref()
+            .is_some_and(|minify| minify.may_minify(path))
+    }
+
+    #[cfg(not(feature = "minify"))]
+    fn may_derive(&self, _: &str) -> bool {
+        false
+    }
+
+    /// Computes the values served with the file at `path`.
+    fn prepare(&self, path: &str, file: &EmbeddedFile) -> PreparedFile {
+        let mut hash = self.caches.hashes.get(path, file);
+        if self.warm.keys.is_sanitized(path) {
+            hash = format!("{}{}", hash, SANITIZED).into();
+        }
+        let header_rules = self
+            .options
+            .header_rules
+            .iter()
+            .enumerate()
+            .filter(|(_, rule)| rule.matches(path))
+            .map(|(i, _)| i)
+            .collect::<Vec<_>>();
+        PreparedFile {
+            hash,
+            content_type: match self.options.accept_charset {
+                Some(_) => charset::with_utf8(self.content_type(path)),
+                None => self.content_type(path),
+            },
+            byte_serving: header_rules
+                .iter()
+                .any(|i| self.options.byte_serving.contains(i)),
+            hashed_name: self
+                .options
+                .hashed_names
+                .as_ref()
+                .filter(|_| {
+                    !header_rules.iter().any(|&i| {
+                        let headers = &self.options.header_rules[i].headers;
+                        headers
+                            .iter()
+                            .any(|(name, _)| name == header::CACHE_CONTROL)
+                    })
+                })
+                .map(|hashed_names| hashed_names.is_hashed(path)),
+            header_rules,
+        }
+    }
+
+    /// Adds the gauges of the caches to [ServeMetrics::cache_sizes].
+    fn register_gauges(&self, metrics: &ServeMetrics) {
+        let precompressed = &self.warm.precompressed;
+        metrics.register(CacheKind::CompressedVariants, &precompressed.gauge);
+        #[cfg(feature = "minify")]
+        if let Some(minify) = &self.caches.minify {
+            metrics.register(CacheKind::Minified, &minify.gauge);
+        }
+        if let Some(cache) = &self.variant_cache {
+            metrics.register(CacheKind::VariantCache, &cache.gauge);
+        }
+        metrics.register(CacheKind::Endpoints, &self.endpoints.gauge);
+    }
+
+    /// Prepares all served files, see [Preparation::Eager].
+    fn prepare_all<E>(&self)
+    where
+        E: 'static + rust_embed::RustEmbed,
+    {
+        let index = self.warm.index::<E>();
+        let files = index
+            .paths()
+            .iter()
+            .filter_map(|path| self.warm.keys.get::<E>(path))
+            .collect::<Vec<_>>();
+        let progress = self.options.on_prepare_progress.as_ref();
+        let progress = ProgressHook::stage(progress, PrepareStage::Hashing, files.len());
+        let threads = self.options.preparation_threads();
+        self.caches.hashes.prefill(&files, threads, progress);
+        let epoch = self.options.admin.epoch();
+        for (key, file) in &files {
+            self.caches
+                .prepared
+                .get(key, epoch, || self.prepare(key, file));
+        }
+    }
+
+    /// Returns `true` if the file with `key` is [excluded](Embed::exclude).
+    fn excluded(&self, key: &str) -> bool {
+        let path = self.warm.keys.path(key).unwrap_or(key);
+        self.options.excluded.iter().any(|glob| glob.matches(path))
+    }
+
+    /// Returns the current time, as told by the [clock](Embed::clock) if set.
+    fn now(&self) -> SystemTime {
+        match &self.options.clock {
+            Some(clock) => clock.now(),
+            None => SystemTime::now(),
+        }
+    }
+
+    /// Returns whether the file with `key` is within its availability window, see
+    /// [Embed::available].
+    fn window(&self, key: &str) -> Window {
+        let rules = &self.options.availability;
+        if rules.is_empty() {
+            return Window::Open;
+        }
+        availability::window(rules, self.warm.keys.path(key).unwrap_or(key), self.now())
+    }
+
+    /// Returns the files shown by the endpoints enumerating them.
+    fn visibility(&self) -> Visibility<'_> {
+        Visibility::new(
+            &self.options.excluded,
+            &self.options.availability,
+            self.now(),
+        )
+    }
+
+    /// Evaluates the preconditions of `req` for the representation of the file at `path`,
+    /// as decided by the [revalidation hook](Embed::revalidation_hook).
+    fn precondition(
+        &self,
+        req: &HttpRequest,
+        context: &ServeContext,
+        path: &str,
+        etag: &EntityTag,
+        last_modified: Option<SystemTime>,
+    ) -> Precondition {
+        let decision = self.options.revalidation_hook.as_ref().map(|hook| {
+            let validators = Validators {
+                path,
+                etag,
+                last_modified,
+                context,
+            };
+            // a panicking hook keeps the default
+            self.options
+                .hooks
+                .call("revalidation_hook", || hook(req, &validators))
+                .unwrap_or(RevalidationDecision::Evaluate)
+        });
+        match decision.unwrap_or(RevalidationDecision::Evaluate) {
+            RevalidationDecision::Evaluate => conditional::evaluate(req, etag, last_modified),
+            RevalidationDecision::Full => Precondition::Passed,
+            RevalidationDecision::NotModified => Precondition::NotModified,
+        }
+    }
+
+    /// Answers a request for a resolved path with a method other than `GET` and `HEAD`:
+    /// [CORS](Embed::cors) preflights are answered, other requests are rejected with `405`.
+    fn not_readable(&self, req: ServiceRequest, trace: &Trace) -> (ServiceResponse, Served) {
+        if self.options.cors.is_enabled() && Cors::is_preflight(req.request()) {
+            trace.step(|| "cors preflight".to_owned());
+            let resp = self.options.cors.preflight(req.request());
+            return (req.into_response(resp), Served::Rejected);
+        }
+        trace.step(|| format!("method {} not allowed", req.method()));
+        method_not_allowed(req, READ_METHODS)
+    }
+
+    /// Returns the request path at which the mount path is matched, ending with `/`.
+    fn mount_base(&self, req: &ServiceRequest) -> String {
+        let full = req.path();
+        let base = full
+            .strip_suffix(self.options.mount_kind.lookup_path(req))
+            .unwrap_or(full);
+        format!("{}/", base.trim_end_matches('/'))
+    }
+
+    /// Serves the request, returning the response and how it was handled.
+    async fn serve<E>(
+        &self,
+        mut req: ServiceRequest,
+        trace: &Trace,
+        clock: &StageClock,
+        context: &mut ServeContext,
+    ) -> (ServiceResponse, Served)
+    where
+        E: 'static + rust_embed::RustEmbed,
+    {
+        let deadline = Deadline::new(self.options.serve_timeout);
+        let http10 = self.options.http10_compat && http10::is_http10(req.request());
+        let hostless = http10 && http10::missing_host(req.request());
+
+        if let (Some(canonical_host), false) = (&self.options.canonical_host, hostless) {
+            if let Some(location) =
+                canonical_host.redirect_url(req.request(), &self.options.forwarded_policy)
+            {
+                trace.step(|| format!("canonical host redirect to {}", location));
+                context.decide("canonical_host", "redirect");
+                let resp = HttpResponse::MovedPermanently()
+                    .insert_header((header::LOCATION, http_util::header_value(&location)))
+                    .finish();
+                return (req.into_response(resp), Served::Rejected);
             }
+        }
 
-            match E::get(path) {
-                Some(f) => {
-                    let hash = hex::encode(f.metadata.sha256_hash());
+        if let Some(rate_limit) = &self.options.rate_limit {
+            let decision = self
+                .options
+                .hooks
+                .call("rate_limiter", || rate_limit.check(req.request()));
+            context.decide(
+                "rate_limiter",
+                match decision {
+                    Ok(RateLimitDecision::Allow) => "allow",
+                    Ok(RateLimitDecision::RetryAfter(_)) => "retry_after",
+                    Ok(RateLimitDecision::Unavailable) => "unavailable",
+                    Err(_) => "failed",
+                },
+            );
+            let message = match decision {
+                Ok(RateLimitDecision::Allow) => None,
+                Ok(RateLimitDecision::RetryAfter(after)) => {
+                    trace.step(|| "rate limited".to_owned());
+                    let resp = HttpResponse::TooManyRequests()
+                        .insert_header((header::RETRY_AFTER, after.as_secs().max(1)))
+                        .finish();
+                    return (req.into_response(resp), Served::Rejected);
+                }
+                Ok(RateLimitDecision::Unavailable) => Some(None),
+                Err(panic) => Some(panic.message),
+            };
+            if let Some(message) = message {
+                let policy = self.options.rate_limit_failure;
+                req.extensions_mut().insert(HookFailure {
+                    hook: "rate_limiter",
+                    message,
+                    policy,
+                });
+                trace.step(|| format!("rate limiter failed, {:?}", policy));
+                if let FailurePolicy::Closed(status) = policy {
+                    return (
+                        req.into_response(HttpResponse::new(status)),
+                        Served::Rejected,
+                    );
+                }
+            }
+        }
 
-                    if req
-                        .headers()
-                        .get(header::IF_NONE_MATCH)
-                        .map(|v| v.to_str().unwrap_or("0").eq(&hash))
+        if let Some(gate) = &self.options.cookie_gate {
+            let preflight = self.options.cors.is_enabled() && Cors::is_preflight(req.request());
+            let allowed = preflight
+                || gate.value(req.request()).is_some_and(|value| {
+                    // a panicking verifier rejects the request
+                    self.options
+                        .hooks
+                        .call("cookie_verifier", || {
+                            gate.verifier.verify(&gate.name, value)
+                        })
                         .unwrap_or(false)
-                    {
-                        return Ok(req.into_response(HttpResponse::NotModified()));
+                });
+            context.decide("cookie_gate", if allowed { "allowed" } else { "rejected" });
+            if !allowed {
+                trace.step(|| format!("cookie {:?} missing or rejected", gate.name));
+                let login_url = self.options.login_redirect.as_deref();
+                let resp = gate.reject(req.request(), login_url);
+                return (req.into_response(resp), Served::Rejected);
+            }
+        }
+
+        // endpoints are unavailable too, unless allowed
+        if let Some(maintenance) = &self.options.maintenance {
+            let requested = self.options.mount_kind.lookup_path(&req).trim_matches('/');
+            if maintenance.handle.is_maintenance()
+                && !self
+                    .options
+                    .maintenance_allowed
+                    .iter()
+                    .any(|glob| glob.matches(requested))
+            {
+                let mut builder = HttpResponse::ServiceUnavailable();
+                builder
+                    .insert_header((header::RETRY_AFTER, maintenance.retry_after.as_secs()))
+                    .insert_header((header::CACHE_CONTROL, headers::NO_CACHE));
+                trace.step(|| "maintenance".to_owned());
+                context.decide("maintenance", "unavailable");
+                let resp = match self.warm.keys.get::<E>(&maintenance.page) {
+                    Some((page, f)) => builder
+                        .content_type(self.content_type(&page))
+                        .body(file_body(f.data)),
+                    None => builder.finish(),
+                };
+                return (req.into_response(resp), Served::Rejected);
+            }
+        }
+
+        if let Some(endpoint) = &self.options.revalidation_endpoint {
+            let requested = self.options.mount_kind.lookup_path(&req).trim_matches('/');
+            if requested == endpoint {
+                trace.step(|| "revalidation endpoint".to_owned());
+                if req.method() != Method::POST {
+                    return method_not_allowed(req, "POST");
+                }
+                let cached = match revalidate::read(&mut req.take_payload()).await {
+                    Ok(cached) => cached,
+                    Err(resp) => return (req.into_response(resp), Served::Rejected),
+                };
+                // excluded files, files outside their availability window and files of
+                // media types which are not allowed are not served
+                let visibility = self.visibility();
+                let resp = revalidate::response(&cached, |path| {
+                    let (key, f) = self.warm.keys.get::<E>(path)?;
+                    if !visibility.shows(self.warm.keys.path(&key).unwrap_or(&key)) {
+                        return None;
+                    }
+                    Some(
+                        self.caches
+                            .prepared
+                            .get(&key, context.epoch(), || self.prepare(&key, &f))
+                            .hash
+                            .clone(),
+                    )
+                });
+                return (req.into_response(resp), Served::Rejected);
+            }
+        }
+
+        // preflights are answered once the path resolves, like the requests they precede
+        let preflight = self.options.cors.is_enabled() && Cors::is_preflight(req.request());
+        let method_allowed = matches!(*req.method(), Method::GET | Method::HEAD);
+        if !method_allowed && !preflight && !self.options.hide_missing_paths {
+            trace.step(|| format!("method {} not allowed", req.method()));
+            return method_not_allowed(req, READ_METHODS);
+        }
+        let mut path = self.options.mount_kind.lookup_path(&req);
+        path = path.trim_start_matches('/');
+        if !self.options.strict_slash {
+            path = path.trim_end_matches('/');
+        }
+        let trimmed = tolerance::trim(path);
+        if trimmed.len() < path.len() {
+            match self.options.path_tolerance {
+                PathTolerance::Strict => {}
+                PathTolerance::Redirect if method_allowed => {
+                    trace.step(|| format!("redirect to trimmed {:?}", trimmed));
+                    context.decide("path_tolerance", "redirect");
+                    let mut location = format!("{}{}", self.mount_base(&req), trimmed);
+                    if !req.query_string().is_empty() {
+                        location = format!("{}?{}", location, req.query_string());
+                    }
+                    let resp = HttpResponse::MovedPermanently()
+                        .insert_header((header::LOCATION, http_util::header_value(&location)))
+                        .finish();
+                    return (req.into_response(resp), Served::Rejected);
+                }
+                PathTolerance::Trim | PathTolerance::Redirect => {
+                    context.decide("path_tolerance", "trim");
+                    path = trimmed;
+                }
+            }
+        }
+        context.set_path(path);
+        trace.step(|| format!("lookup {:?}", path));
+        if let (Some(endpoint), Some(version)) = (&self.options.version_endpoint, &self.version) {
+            if path == endpoint.path {
+                trace.step(|| "version endpoint".to_owned());
+                if !method_allowed {
+                    return self.not_readable(req, trace);
+                }
+                let resp = self
+                    .endpoints
+                    .respond(req.request(), "", || version.response(req.request()));
+                return (req.into_response(resp), Served::Rejected);
+            }
+        }
+        if let (Some(endpoint), Some(import_map)) = (&self.options.import_map, &self.import_map) {
+            if path == endpoint.path {
+                trace.step(|| "import map".to_owned());
+                if !method_allowed {
+                    return self.not_readable(req, trace);
+                }
+                let visibility = self.visibility();
+                let resp = self
+                    .endpoints
+                    .respond(req.request(), &visibility.windows(), || {
+                        import_map.response(req.request(), &self.mount_base(&req), &visibility)
+                    });
+                return (req.into_response(resp), Served::Rejected);
+            }
+        }
+        if let Some(endpoint) = &self.options.listing_endpoint {
+            if path == endpoint {
+                trace.step(|| "listing endpoint".to_owned());
+                if !method_allowed {
+                    return self.not_readable(req, trace);
+                }
+                let visibility = self.visibility();
+                let resp = self
+                    .endpoints
+                    .respond(req.request(), &visibility.windows(), || {
+                        listing::response::<E>(
+                            req.request(),
+                            self.warm.index::<E>(),
+                            &self.warm.keys,
+                            &visibility,
+                        )
+                    });
+                return (req.into_response(resp), Served::Rejected);
+            }
+        }
+        #[cfg(debug_assertions)]
+        if let (Some(endpoint), Some(rules)) = (&self.options.rules_endpoint, &self.rules) {
+            if path == endpoint {
+                trace.step(|| "rules endpoint".to_owned());
+                if !method_allowed {
+                    return self.not_readable(req, trace);
+                }
+                let resp = rules_page::response(req.request(), rules);
+                return (req.into_response(resp), Served::Rejected);
+            }
+        }
+        if let Some(dir) = self.options.archives.get(path) {
+            trace.step(|| format!("archive of {:?}", dir));
+            if !method_allowed {
+                return self.not_readable(req, trace);
+            }
+            let name = path.rsplit('/').next().unwrap_or(path);
+            let mut builder = HttpResponse::Ok();
+            builder
+                .content_type("application/x-tar")
+                .insert_header((header::CONTENT_DISPOSITION, http_util::attachment(name)))
+                .insert_header((header::CACHE_CONTROL, headers::NO_CACHE));
+            // archives are streamed, so ranges of them are never honored
+            if let Some(value) = self.options.accept_ranges.header_value() {
+                let value = match self.options.accept_ranges.honors_ranges() {
+                    true => "none",
+                    false => value,
+                };
+                builder.insert_header((header::ACCEPT_RANGES, value));
+            }
+            let archive = TarStream::<E>::new(self.warm.clone(), dir, &self.visibility());
+            let resp = match http10 {
+                true => builder.body(archive.into_bytes()),
+                false => builder.streaming(archive),
+            };
+            return (req.into_response(resp), Served::Rejected);
+        }
+        let cached = match &self.variant_cache {
+            Some(cache) if method_allowed => {
+                let cookie = self.options.language_override.as_ref();
+                let profile = Profile::of(&req, cookie.map(|choice| choice.cookie.as_str()));
+                let cached = cache.get(path, &profile, context.epoch());
+                // responses of files outside their availability window are not reused
+                if let Some(resp) = cached.filter(|resp| self.window(&resp.path) == Window::Open) {
+                    trace.step(|| format!("cached response of {:?}", resp.path));
+                    context.decide("variant_cache", "hit");
+                    context.resolve(&resp.path);
+                    context.select_encoding(resp.encoding, resp.compression);
+                    let precondition = self.precondition(
+                        req.request(),
+                        context,
+                        &resp.path,
+                        &resp.etag,
+                        resp.last_modified,
+                    );
+                    let (mut resp, served) = resp.respond(req, precondition);
+                    if self.options.cache_status {
+                        CacheStatus::VariantHit.apply(resp.headers_mut());
+                    }
+                    return (resp, served);
+                }
+                Some((cache, path.to_owned(), profile))
+            }
+            _ => None,
+        };
+        let mut immutable = false;
+        if self.options.content_addressed {
+            if let Some((hash, file_path)) = content_address::split(path) {
+                let current = self.warm.keys.get::<E>(file_path);
+                if current.is_some_and(|(_, f)| content_address::hash(&f) == hash) {
+                    trace.step(|| format!("content address of {:?}", file_path));
+                    context.decide("content_addressed", "immutable");
+                    path = file_path;
+                    immutable = true;
+                } else {
+                    trace.step(|| "outdated content address".to_owned());
+                    context.decide("content_addressed", "outdated");
+                }
+            }
+        }
+        let mut language = None;
+        let mut remember = None;
+        // whether the file is selected by the request headers rather than the path
+        let mut header_selected = false;
+        let index = path.is_empty();
+        if index {
+            path = match &self.options.localized_index {
+                Some(index) => {
+                    header_selected = true;
+                    let selection = index.select(
+                        req.request(),
+                        &self.options.language_fallbacks,
+                        self.options.language_override.as_ref(),
+                    );
+                    let selected = selection.path;
+                    remember = selection.remember;
+                    language = Some(selected.split('/').next().unwrap_or(selected));
+                    context.select_language(language.unwrap_or_default());
+                    selected
+                }
+                None => self.options.index_file_path.as_deref().unwrap_or(""),
+            };
+            trace.step(|| format!("index file {:?}", path));
+            context.set_path(path);
+        }
+        let mut client_hints = false;
+        // content-addressed requests are served with exactly the addressed file
+        if let (Some(images), false) = (&self.warm.responsive_images, immutable) {
+            if let Some(selected) = images.select(req.request(), path) {
+                trace.step(|| format!("image variant {:?}", selected));
+                context.decide("responsive_images", "selected");
+                path = selected;
+                context.set_path(path);
+                client_hints = true;
+                header_selected = true;
+            }
+        }
+
+        let folder =
+            query::select_folder(&self.options.query_rules, req.request()).filter(|_| !immutable);
+        if !method_allowed && !preflight {
+            // whether a file exists decides between `405` and the fallback handler, so the
+            // files are looked up in the index without being read
+            let files = self.warm.index::<E>();
+            let exists = folder
+                .as_ref()
+                .is_some_and(|folder| files.contains(&format!("{}/{}", folder, path)))
+                || files.contains(path)
+                || self
+                    .alias_candidates(path, index)
+                    .any(|candidate| files.contains(&candidate));
+            if exists {
+                trace.step(|| format!("method {} not allowed", req.method()));
+                return method_not_allowed(req, READ_METHODS);
+            }
+        }
+        let selected = folder.and_then(|folder| {
+            let candidate = format!("{}/{}", folder, path);
+            trace.step(|| format!("query selects {:?}", candidate));
+            self.warm.keys.get::<E>(&candidate)
+        });
+        let mut found = selected
+            .or_else(|| self.warm.keys.get::<E>(path))
+            .or_else(|| self.alias::<E>(path, index, trace))
+            .or_else(|| self.spa_index::<E>(path, method_allowed, trace));
+        // excluded files are missing, also at paths the guard does not match, e.g. aliases
+        if let Some((path, _)) = found.as_ref().filter(|(path, _)| self.excluded(path)) {
+            trace.step(|| format!("{:?} excluded", path));
+            found = None;
+        }
+        match found.as_ref().map(|(path, _)| (path, self.window(path))) {
+            Some((path, Window::Before)) => {
+                trace.step(|| format!("{:?} not available yet", path));
+                context.decide("availability", "not_yet");
+                found = None;
+            }
+            Some((path, Window::After)) => {
+                trace.step(|| format!("{:?} no longer available", path));
+                context.decide("availability", "gone");
+                return (req.into_response(HttpResponse::Gone()), Served::Rejected);
+            }
+            _ => {}
+        }
+        // files of refused media types are missing, unless refused with another status
+        if let Some(status) = found
+            .is_none()
+            .then(|| self.warm.keys.refused::<E>(path))
+            .flatten()
+            .filter(|status| *status != StatusCode::NOT_FOUND)
+        {
+            trace.step(|| format!("media type of {:?} refused", path));
+            context.decide("allowed_mime_types", "refused");
+            return (
+                req.into_response(HttpResponse::new(status)),
+                Served::Rejected,
+            );
+        }
+        match found {
+            Some(_) if !method_allowed => self.not_readable(req, trace),
+            Some((path, f)) => {
+                trace.step(|| format!("found {:?}", path));
+                context.resolve(&path);
+                let mut derived_hit = true;
+                let mut prepared = self.caches.prepared.get(&path, context.epoch(), || {
+                    derived_hit = false;
+                    self.prepare(&path, &f)
+                });
+                // sanitized files differ from their embedded content by design
+                if cfg!(debug_assertions)
+                    && self.options.verify_hashes
+                    && !self.warm.keys.is_sanitized(&path)
+                {
+                    if let Some(_mismatch) = self.caches.hashes.verify(&path, &prepared.hash, &f) {
+                        #[cfg(feature = "tracing")]
+                        tracing::warn!(
+                            "{}; the assets changed since they were first served, check that \
+                             the build is not stale",
+                            _mismatch
+                        );
+                        context.decide("verify_hashes", "stale");
+                        trace.step(|| format!("stale hash of {:?}", path));
+                        self.caches.prepared.remove(&path);
+                        prepared = self
+                            .caches
+                            .prepared
+                            .get(&path, context.epoch(), || self.prepare(&path, &f));
+                    }
+                }
+                clock.finish(ServeStage::Lookup);
+                match prepared.hashed_name {
+                    Some(true) => context.decide("hashed_filenames", "immutable"),
+                    Some(false) => context.decide("hashed_filenames", "revalidate"),
+                    None => {}
+                }
+                let text = self.options.accept_charset.is_some()
+                    && charset::is_text(&prepared.content_type);
+                if let (Some(status), true) = (self.options.accept_charset, text) {
+                    if !charset::accepts_utf8(req.request()) {
+                        trace.step(|| "UTF-8 not acceptable".to_owned());
+                        context.decide("accept_charset", "not_acceptable");
+                        let resp = HttpResponse::build(status)
+                            .append_header((header::VARY, "Accept-Charset"))
+                            .finish();
+                        return (req.into_response(resp), Served::File(path));
                     }
+                }
+                let hash = &prepared.hash;
+                // files served as bytes are sent as embedded, so ranges refer to their bytes
+                let variants = match prepared.byte_serving {
+                    true => Default::default(),
+                    false => self.warm.precompressed.get(&path).unwrap_or_default(),
+                };
+                let outer_compression = match prepared.byte_serving {
+                    true => OuterCompression::Bypass,
+                    false => self.options.outer_compression,
+                };
+                let available = variants.iter().map(|(e, _)| *e).collect::<Vec<_>>();
+                let encoding = encoding::negotiate(req.request(), &available);
+                context.negotiate(encoding);
+                clock.finish(ServeStage::Negotiation);
+                let last_modified = f
+                    .metadata
+                    .last_modified()
+                    .map(|secs| SystemTime::UNIX_EPOCH + Duration::from_secs(secs));
+
+                // an unchanged file is answered with `304 Not Modified` without reading its
+                // body, unless the entity tag depends on a derived variant
+                let derives = !prepared.byte_serving && self.may_derive(&path);
+                let early = (encoding.is_some() || !derives).then(|| {
+                    let outer = match encoding {
+                        Some(_) => OuterCompression::Absent,
+                        None => outer_compression,
+                    };
+                    let etag =
+                        http_util::outer_entity_tag(http_util::entity_tag(hash, encoding), outer);
+                    let precondition =
+                        self.precondition(req.request(), context, &path, &etag, last_modified);
+                    (etag, precondition)
+                });
+                let header_only = matches!(early, Some((_, Precondition::NotModified)));
+                if header_only {
+                    trace.step(|| "not modified, body not read".to_owned());
+                }
+
+                let unavailable = deadline.action == TimeoutAction::ServiceUnavailable;
+                // responses degraded by the serve timeout are not cached
+                let mut timed_out = false;
+                let negotiated = variants.iter().find(|(e, _)| Some(*e) == encoding);
+                let compressed = match negotiated {
+                    Some((encoding, _)) if header_only => Some((*encoding, Bytes::new())),
+                    Some((encoding, variant)) => match deadline.run(variant.load()).await {
+                        Some(data) => data.map(|data| (*encoding, data)),
+                        None if unavailable => {
+                            let resp = HttpResponse::ServiceUnavailable().finish();
+                            return (req.into_response(resp), Served::File(path));
+                        }
+                        None => {
+                            timed_out = true;
+                            None
+                        }
+                    },
+                    None => None,
+                };
+                let encoding = compressed.as_ref().map(|(encoding, _)| *encoding);
+                let compression = match encoding {
+                    Some(encoding) => Compression::Compressed(encoding),
+                    None if encoding::is_incompressible(&path, &self.options.incompressible) => {
+                        Compression::Incompressible
+                    }
+                    None if variants.is_empty() || timed_out => Compression::Unavailable,
+                    None => Compression::NotAccepted,
+                };
+                context.select_encoding(encoding, compression);
+                trace.step(|| match encoding {
+                    Some(encoding) => format!("encoding {}", encoding),
+                    None if timed_out => "encoding identity, timed out".to_owned(),
+                    None => "encoding identity".to_owned(),
+                });
 
-                    let mime = MimeGuess::from_path(path).first_or_octet_stream();
-                    let data = f.data.into_owned();
+                let derived = match compressed {
+                    Some(_) => Ok(None),
+                    None if header_only || prepared.byte_serving => Ok(None),
+                    None => self.derived_variant(req.request(), &path, &f.data, hash, &deadline),
+                };
+                let derived = match derived {
+                    Ok(derived) => derived,
+                    Err(TimedOut) if unavailable => {
+                        let resp = HttpResponse::ServiceUnavailable().finish();
+                        return (req.into_response(resp), Served::File(path));
+                    }
+                    Err(TimedOut) => {
+                        timed_out = true;
+                        None
+                    }
+                };
+                clock.finish(ServeStage::Compression);
+                if timed_out {
+                    context.decide("serve_timeout", "timed_out");
+                }
+                if derived.is_some() {
+                    trace.step(|| "derived variant".to_owned());
+                }
+                let outer = match encoding {
+                    Some(_) => OuterCompression::Absent,
+                    None => outer_compression,
+                };
+                let file_size = f.data.len() as u64;
+                let (data, etag) = match (compressed, derived) {
+                    (Some((encoding, data)), _) => {
+                        (data, http_util::entity_tag(hash, Some(encoding)))
+                    }
+                    (None, Some(variant)) => variant,
+                    (None, None) if header_only => {
+                        (Bytes::new(), http_util::entity_tag(hash, None))
+                    }
+                    (None, None) => (file_body(f.data), http_util::entity_tag(hash, None)),
+                };
+                let etag = http_util::outer_entity_tag(etag, outer);
+                let size = match (header_only, negotiated) {
+                    (false, _) => data.len() as u64,
+                    (true, Some((_, variant))) => variant.len(),
+                    (true, None) => file_size,
+                };
+                let mut builder = HttpResponse::Ok();
+                builder.insert_header(header::ETag(etag.clone()));
+                if let Some(last_modified) = last_modified {
+                    builder.insert_header(header::LastModified(last_modified.into()));
+                }
+                if !variants.is_empty() || outer == OuterCompression::Allow {
+                    builder.append_header((header::VARY, "Accept-Encoding"));
+                }
+                if text {
+                    builder.append_header((header::VARY, "Accept-Charset"));
+                }
+                if let Some(language) = language {
+                    builder
+                        .append_header((header::VARY, "Accept-Language"))
+                        .insert_header((header::CONTENT_LANGUAGE, language));
+                    if let Some(choice) = &self.options.language_override {
+                        builder.append_header((header::VARY, "Cookie"));
+                        if let Some(chosen) = &remember {
+                            let cookie = format!(
+                                "{}={}; Path={}; Max-Age=31536000; SameSite=Lax",
+                                choice.cookie,
+                                chosen,
+                                self.mount_base(&req)
+                            );
+                            let cookie = http_util::header_value(&cookie);
+                            builder.insert_header((header::SET_COOKIE, cookie));
+                        }
+                    }
+                } else if self.options.content_language_from_path {
+                    if let Some(language) = language::from_path(&path) {
+                        builder.insert_header((header::CONTENT_LANGUAGE, language));
+                    }
+                }
+                if client_hints {
+                    builder.append_header((header::VARY, responsive::CLIENT_HINTS));
+                }
+                if header_selected && self.options.content_location {
+                    let location = format!("{}{}", self.mount_base(&req), path);
+                    let location = http_util::header_value(&location);
+                    builder.insert_header((header::CONTENT_LOCATION, location));
+                }
+                if self.warm.responsive_images.is_some()
+                    && headers::extension(&path).is_some_and(|ext| ext.starts_with("htm"))
+                {
+                    builder.insert_header(("accept-ch", responsive::CLIENT_HINTS));
+                }
+                // ranges of bytes compressing middleware may still encode would not refer to
+                // the bytes sent, so they are only honored for the representation served here
+                let ranges = prepared.byte_serving
+                    || self.options.accept_ranges.honors_ranges()
+                        && outer != OuterCompression::Allow;
+                if let Some(value) = self.options.accept_ranges.header_value() {
+                    let value = if ranges { value } else { "none" };
+                    builder.insert_header((header::ACCEPT_RANGES, value));
+                }
+                for &i in &prepared.header_rules {
+                    self.options.header_rules[i].apply(&mut builder);
+                }
+                if let Some(hashed) = prepared.hashed_name {
+                    let value = if hashed {
+                        headers::IMMUTABLE
+                    } else {
+                        headers::NO_CACHE
+                    };
+                    builder.insert_header((header::CACHE_CONTROL, value));
+                }
+                if immutable {
+                    builder.insert_header((header::CACHE_CONTROL, headers::IMMUTABLE));
+                }
 
-                    Ok(req.into_response(
-                        HttpResponse::Ok()
-                            .content_type(mime.as_ref())
-                            .insert_header((header::ETAG, hash))
-                            .body(data),
-                    ))
+                let resp = Rc::new(FileResponse {
+                    throttle: throttle::rate(&self.options.throttles, &path),
+                    chunk_size: self.options.stream_chunk_size.unwrap_or(DEFAULT_CHUNK),
+                    path,
+                    etag,
+                    headers: builder.finish().headers().clone(),
+                    content_type: prepared.content_type.clone(),
+                    encoding,
+                    compression,
+                    identity: outer == OuterCompression::Bypass,
+                    size,
+                    last_modified,
+                    ranges,
+                    body: data,
+                    generated: self.options.age_headers.then(Generated::now),
+                });
+                // responses without their body are not cached
+                let stored = cached.as_ref().map(|_| !(timed_out || header_only));
+                if let (Some((cache, lookup, profile)), Some(true)) = (cached, stored) {
+                    cache.insert(
+                        &lookup,
+                        profile,
+                        context.epoch(),
+                        resp.clone(),
+                        self.options.metrics.as_ref(),
+                    );
                 }
-                None => {
-                    let (req, _) = req.into_parts();
-                    let resp = this.fallback_handler.execute(&req);
-                    Ok(ServiceResponse::new(req, resp))
+                // the hook is not asked again for the same representation
+                let precondition = match early {
+                    Some((etag, precondition)) if etag == resp.etag => precondition,
+                    _ => self.precondition(
+                        req.request(),
+                        context,
+                        &resp.path,
+                        &resp.etag,
+                        last_modified,
+                    ),
+                };
+                let (mut resp, served) = resp.respond(req, precondition);
+                if self.options.cache_status {
+                    let status = CacheStatus::Generated {
+                        derived_hit,
+                        stored,
+                    };
+                    status.apply(resp.headers_mut());
                 }
+                (resp, served)
             }
-        })
+            None => {
+                trace.step(|| "no file found".to_owned());
+                clock.finish(ServeStage::Lookup);
+                let export = self.options.package_exports.get(path).map(String::as_str);
+                let export = export.or_else(|| self.warm.packages.as_ref()?.resolve(path));
+                if let Some(target) = export {
+                    trace.step(|| format!("package export {:?}", target));
+                    if !method_allowed {
+                        return self.not_readable(req, trace);
+                    }
+                    let location = format!("{}{}", self.mount_base(&req), target);
+                    let resp = HttpResponse::Found()
+                        .insert_header((header::LOCATION, http_util::header_value(&location)))
+                        .insert_header((header::CACHE_CONTROL, headers::NO_CACHE))
+                        .finish();
+                    return (req.into_response(resp), Served::Rejected);
+                }
+                if let Some(target) = self.options.redirects.get(path) {
+                    trace.step(|| format!("redirect to {:?}", target));
+                    if !method_allowed {
+                        return self.not_readable(req, trace);
+                    }
+                    let location = format!("{}{}", self.mount_base(&req), target);
+                    let resp = HttpResponse::MovedPermanently()
+                        .insert_header((header::LOCATION, http_util::header_value(&location)))
+                        .finish();
+                    return (req.into_response(resp), Served::Rejected);
+                }
+                let similar_paths = self.warm.similar_paths.as_ref();
+                let corrected = similar_paths
+                    .filter(|_| self.options.auto_correct && method_allowed)
+                    .and_then(|index| index.correct(path));
+                if let Some(target) = corrected {
+                    trace.step(|| format!("auto-correct to {:?}", target));
+                    context.decide("auto_correct", "redirect");
+                    let mut location = format!("{}{}", self.mount_base(&req), target);
+                    if !req.query_string().is_empty() {
+                        location = format!("{}?{}", location, req.query_string());
+                    }
+                    let resp = HttpResponse::MovedPermanently()
+                        .insert_header((header::LOCATION, http_util::header_value(&location)))
+                        .finish();
+                    return (req.into_response(resp), Served::Rejected);
+                }
+                if let (Some(index), Some(limit), false) = (
+                    similar_paths,
+                    self.options.suggestions,
+                    self.options.admin.suggestions_disabled(),
+                ) {
+                    let base = self.mount_base(&req);
+                    let urls = index
+                        .suggest(path, limit)
+                        .into_iter()
+                        .map(|key| format!("{}{}", base, key))
+                        .collect();
+                    trace.step(|| format!("suggested {:?}", urls));
+                    req.extensions_mut().insert(Suggestions { urls });
+                }
+
+                trace.step(|| "fallback handler".to_owned());
+                req.extensions_mut().insert(context.clone());
+                let (req, _) = req.into_parts();
+                let resp = self
+                    .options
+                    .hooks
+                    .call_async("fallback_handler", || {
+                        self.fallback_handler.execute_async(req.clone())
+                    })
+                    .await;
+                let mut resp = match resp {
+                    Ok(resp) => resp,
+                    Err(HookError::Panicked(_)) => HttpResponse::InternalServerError().finish(),
+                    Err(HookError::TimedOut) => {
+                        trace.step(|| "fallback handler timed out".to_owned());
+                        HttpResponse::GatewayTimeout().finish()
+                    }
+                };
+                if let Some(value) = &self.options.not_found_cache_control {
+                    if !resp.status().is_server_error() {
+                        let headers = resp.headers_mut();
+                        headers::insert_default(headers, header::CACHE_CONTROL, value);
+                    }
+                }
+                (ServiceResponse::new(req, resp), Served::Fallback)
+            }
+        }
+    }
+}
+
+/// Returns the body of an embedded file, without copying the data of files embedded in the
+/// binary.
+fn file_body(data: Cow<'static, [u8]>) -> Bytes {
+    match data {
+        Cow::Borrowed(data) => Bytes::from_static(data),
+        Cow::Owned(data) => Bytes::from(data),
     }
 }
+
+/// The methods files and most endpoints are served for.
+const READ_METHODS: &str = "GET, HEAD";
+
+/// Returns the `405 Method Not Allowed` response, listing the `allowed` methods in `Allow`.
+fn method_not_allowed(req: ServiceRequest, allowed: &'static str) -> (ServiceResponse, Served) {
+    let resp = HttpResponse::MethodNotAllowed()
+        .insert_header((header::ALLOW, allowed))
+        .finish();
+    (req.into_response(resp), Served::Rejected)
+}
+
+/// Creates the service serving the canary bundle `C`, see [Embed::canary].
+pub(crate) fn canary_service<C>(
+    options: EmbedOptions,
+    fallback: ErasedFallback,
+) -> LocalBoxFuture<'static, Result<CanaryService, ()>>
+where
+    C: 'static + rust_embed::RustEmbed,
+{
+    let embed = Embed::<C, ErasedFallback> {
+        mount_path: String::new(),
+        options,
+        fallback_handler: fallback,
+        _f: PhantomData,
+    };
+    let service = embed.new_service(());
+    Box::pin(async move {
+        let service = service.await?;
+        let service: CanaryService = Rc::new(move |req| service.call(req));
+        Ok(service)
+    })
+}
@@ -0,0 +1,207 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Debug, Formatter};
+use std::sync::{Arc, OnceLock};
+
+use crate::disk_cache::DiskCache;
+use crate::encoding::Encoding;
+use crate::file_index::{FileIndex, KeyMap};
+use crate::metadata::MetadataRegistry;
+use crate::options::EmbedOptions;
+use crate::packages::PackageExports;
+use crate::precompress::{PrecompressRule, Precompressed};
+use crate::prepare::ProgressHook;
+use crate::responsive::ResponsiveImages;
+#[cfg(feature = "sanitize-svg")]
+use crate::sanitize;
+use crate::sniff;
+use crate::suggest::SuggestionIndex;
+
+/// State prepared when services are created, shared by the services of all workers.
+///
+/// Each worker creates its own service, which builds its own indexes and compressed variants
+/// by default. Create one `SharedState` outside the application factory and pass it to
+/// [Embed::shared_state](crate::Embed::shared_state) so the workers of a server build them
+/// once. The state is kept for the lifetime of the `SharedState`, e.g. across server restarts.
+///
+/// `SharedState` is a handle, clones share the same state. Share it only between services
+/// serving the same embedded files with the same options, like the instances created by each
+/// worker, since the state is built with the options of the first service created.
+///
+/// # Examples
+/// ```no_run
+/// use actix_embed::{Embed, SharedState};
+/// use actix_web::{App, HttpServer};
+/// use rust_embed::RustEmbed;
+///
+/// #[derive(RustEmbed)]
+/// #[folder = "testdata/"]
+/// struct Assets;
+///
+/// # async fn run() -> std::io::Result<()> {
+/// let state = SharedState::new();
+/// HttpServer::new(move || {
+///     App::new().service(Embed::new("/", &Assets).shared_state(&state))
+/// })
+/// .bind(("127.0.0.1", 8080))?
+/// .run()
+/// .await
+/// # }
+/// ```
+#[derive(Clone, Default)]
+pub struct SharedState {
+    state: Arc<OnceLock<Arc<WarmState>>>,
+}
+
+impl Debug for SharedState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SharedState")
+            .field("built", &self.is_built())
+            .finish()
+    }
+}
+
+impl SharedState {
+    /// Create new [SharedState], which is built by the first service created.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns `true` if the state has been built.
+    pub fn is_built(&self) -> bool {
+        self.state.get().is_some()
+    }
+
+    /// Returns the state, building it with `build` if no other service did yet.
+    ///
+    /// Services created concurrently wait for the first one to build the state.
+    pub(crate) fn get_or_init<B>(&self, build: B) -> Arc<WarmState>
+    where
+        B: FnOnce() -> Arc<WarmState>,
+    {
+        self.state.get_or_init(build).clone()
+    }
+}
+
+/// The options [WarmState] is built with, which can be sent to other threads.
+#[derive(Debug, Clone)]
+pub(crate) struct WarmOptions {
+    keys: KeyMap,
+    precompressed_siblings: Vec<Encoding>,
+    precompress: Vec<PrecompressRule>,
+    incompressible: Vec<String>,
+    disk_cache: Option<DiskCache>,
+    /// Whether the [SuggestionIndex] is built, for suggestions or auto-correction.
+    similar_paths: bool,
+    responsive_images: bool,
+    packages: Option<String>,
+    /// The media types of [MetadataRegistry], `None` if it is not built.
+    metadata: Option<HashMap<String, String>>,
+    /// The media types compared with the content of the files, `None` if they are not
+    /// [sniffed](crate::Embed::content_sniffing).
+    sniffing: Option<HashMap<String, String>>,
+    /// The number of threads files are compressed on, see
+    /// [Preparation::Eager](crate::Preparation::Eager).
+    threads: usize,
+    progress: Option<ProgressHook>,
+}
+
+impl WarmOptions {
+    pub(crate) fn new(options: &EmbedOptions) -> Self {
+        WarmOptions {
+            keys: options.keys(),
+            precompressed_siblings: options.precompressed_siblings.clone(),
+            precompress: options.precompress.clone(),
+            incompressible: options.incompressible.clone(),
+            disk_cache: options.disk_cache.clone(),
+            similar_paths: options.suggestions.is_some() || options.auto_correct,
+            responsive_images: options.responsive_images,
+            packages: options.packages.clone(),
+            metadata: options
+                .metadata_registry
+                .then(|| options.mime_types.clone()),
+            sniffing: options.content_sniffing.map(|_| options.mime_types.clone()),
+            threads: options.preparation_threads(),
+            progress: options.on_prepare_progress.clone(),
+        }
+    }
+}
+
+/// State which only depends on the embedded files and the options.
+#[derive(Debug)]
+pub(crate) struct WarmState {
+    pub(crate) keys: KeyMap,
+    /// The index of the served files, only built if a feature needs it, so large embeds start
+    /// faster without it.
+    index: OnceLock<FileIndex>,
+    pub(crate) precompressed: Precompressed,
+    pub(crate) similar_paths: Option<SuggestionIndex>,
+    pub(crate) responsive_images: Option<ResponsiveImages>,
+    pub(crate) packages: Option<PackageExports>,
+    pub(crate) metadata: Option<MetadataRegistry>,
+    /// The media types of the content of mislabeled files, by key, if files are
+    /// [sniffed](crate::Embed::content_sniffing).
+    pub(crate) sniffed: Option<BTreeMap<String, &'static str>>,
+}
+
+impl WarmState {
+    pub(crate) fn build<E>(options: WarmOptions) -> Self
+    where
+        E: 'static + rust_embed::RustEmbed,
+    {
+        #[allow(unused_mut)]
+        let mut options = options;
+        let index = OnceLock::new();
+        #[cfg(feature = "sanitize-svg")]
+        if options.keys.sanitizes_svg() {
+            let files = index.get_or_init(|| FileIndex::build::<E>(&options.keys));
+            let sanitized = sanitize::sanitize_all::<E>(files, &options.keys);
+            options.keys.with_sanitized(sanitized);
+        }
+        let file_index = || index.get_or_init(|| FileIndex::build::<E>(&options.keys));
+        let similar_paths = options
+            .similar_paths
+            .then(|| SuggestionIndex::build(file_index()));
+        let responsive_images = options
+            .responsive_images
+            .then(|| ResponsiveImages::build(file_index()));
+        let packages = options
+            .packages
+            .as_deref()
+            .map(|dir| PackageExports::build::<E>(file_index(), &options.keys, dir));
+        let metadata = options.metadata.as_ref().map(|mime_types| {
+            MetadataRegistry::build::<E>(file_index(), &options.keys, mime_types)
+        });
+        let sniffed = options.sniffing.as_ref().map(|mime_types| {
+            let mislabeled = sniff::mislabeled::<E>(file_index(), &options.keys, mime_types);
+            sniff::corrections(&mislabeled)
+        });
+        let precompressed = Precompressed::build::<E>(
+            &options.keys,
+            &options.precompressed_siblings,
+            &options.precompress,
+            &options.incompressible,
+            options.disk_cache.as_ref(),
+            options.threads,
+            options.progress.as_ref(),
+        );
+
+        WarmState {
+            keys: options.keys,
+            index,
+            precompressed,
+            similar_paths,
+            responsive_images,
+            packages,
+            metadata,
+            sniffed,
+        }
+    }
+
+    /// Returns the index of the served files.
+    pub(crate) fn index<E>(&self) -> &FileIndex
+    where
+        E: 'static + rust_embed::RustEmbed,
+    {
+        self.index.get_or_init(|| FileIndex::build::<E>(&self.keys))
+    }
+}
@@ -0,0 +1,112 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Display, Formatter};
+
+use crate::file_index::{FileIndex, KeyMap};
+use crate::headers;
+
+/// What [Embed](crate::Embed) does with files whose content does not match the media type of
+/// their extension.
+///
+/// See [Embed::content_sniffing](crate::Embed::content_sniffing).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SniffAction {
+    /// Report the files, and serve them with the media type of their extension.
+    Report,
+    /// Report the files, and serve them with the media type of their content.
+    Correct,
+}
+
+/// The media types recognized by their leading bytes.
+const SIGNATURES: &[(&[u8], &str)] = &[
+    (b"\x89PNG\r\n\x1a\n", "image/png"),
+    (b"\xff\xd8\xff", "image/jpeg"),
+    (b"GIF87a", "image/gif"),
+    (b"GIF89a", "image/gif"),
+    (b"%PDF-", "application/pdf"),
+    (b"\0asm", "application/wasm"),
+];
+
+/// Returns the media type of `data` by its magic bytes, `None` if it is not recognized.
+pub(crate) fn sniff(data: &[u8]) -> Option<&'static str> {
+    if let Some((_, mime)) = SIGNATURES.iter().find(|(magic, _)| data.starts_with(magic)) {
+        return Some(mime);
+    }
+    if data.len() >= 12 && &data[..4] == b"RIFF" && &data[8..12] == b"WEBP" {
+        return Some("image/webp");
+    }
+    let text = data.strip_prefix(b"\xef\xbb\xbf").unwrap_or(data);
+    let start = text.iter().position(|b| !b.is_ascii_whitespace())?;
+    let text = &text[start..];
+    let html = [b"<!doctype html".as_slice(), b"<html"]
+        .iter()
+        .any(|tag| text.len() >= tag.len() && text[..tag.len()].eq_ignore_ascii_case(tag));
+    html.then_some("text/html")
+}
+
+/// A file whose content does not match the media type of its extension.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Mislabeled {
+    /// The key of the embedded file.
+    pub(crate) key: String,
+    /// The media type of the extension.
+    pub(crate) declared: String,
+    /// The media type of the content.
+    pub(crate) sniffed: &'static str,
+}
+
+impl Display for Mislabeled {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:?} is served as {} but its content is {}",
+            self.key, self.declared, self.sniffed
+        )
+    }
+}
+
+/// Returns the files in `index` whose content does not match the media type of their
+/// extension, resolved with `mime_types`.
+///
+/// Files of unknown type are not reported, and HTML only in files of other than HTML or XML
+/// types, since templates and XHTML documents start alike.
+pub(crate) fn mislabeled<E>(
+    index: &FileIndex,
+    keys: &KeyMap,
+    mime_types: &HashMap<String, String>,
+) -> Vec<Mislabeled>
+where
+    E: 'static + rust_embed::RustEmbed,
+{
+    let mut mislabeled = Vec::new();
+    for path in index.paths() {
+        let Some((key, file)) = keys.get::<E>(path) else {
+            continue;
+        };
+        let Some(sniffed) = sniff(&file.data) else {
+            continue;
+        };
+        let content_type = headers::content_type(path, mime_types);
+        let declared = content_type.split(';').next().unwrap_or_default().trim();
+        let declared = declared.to_ascii_lowercase();
+        let unknown = declared == "application/octet-stream";
+        let markup =
+            sniffed == "text/html" && (declared.contains("html") || declared.contains("xml"));
+        if declared != sniffed && !unknown && !markup {
+            mislabeled.push(Mislabeled {
+                key,
+                declared,
+                sniffed,
+            });
+        }
+    }
+    mislabeled
+}
+
+/// The media types of the content of mislabeled files, by key, served with
+/// [SniffAction::Correct].
+pub(crate) fn corrections(mislabeled: &[Mislabeled]) -> BTreeMap<String, &'static str> {
+    mislabeled
+        .iter()
+        .map(|file| (file.key.clone(), file.sniffed))
+        .collect()
+}
@@ -0,0 +1,213 @@
+use std::fmt::{Debug, Formatter};
+use std::marker::PhantomData;
+
+use actix_web::dev::{fn_service, AppService, HttpServiceFactory, ResourceDef, ServiceRequest};
+use actix_web::guard::{self, Guard};
+use actix_web::http::StatusCode;
+
+use crate::fallback_handler::{DefaultFallbackHandler, FallbackHandler};
+use crate::mount_registry;
+use crate::options::EmbedOptions;
+use crate::service::Embed;
+
+/// Create new [Spa] builder serving a single-page application from the files of `E`.
+///
+/// The builder has the API of `actix_web_lab::web::spa()`, so an application served from the
+/// filesystem can switch to the embedded files by replacing the call, with paths naming
+/// embedded files instead of files on disk:
+///
+/// - the [index file](Spa::index_file) is served at every path without a static resource,
+/// - [static resources](Spa::static_resources_location) are served at
+///   [their mount](Spa::static_resources_mount),
+/// - requests under [excluded prefixes](Spa::exclude_prefixes), e.g. `/api`, are not handled,
+///   so they reach the services of the API instead of getting the index file.
+///
+/// The static resources are served by an [Embed] instance with the
+/// [single-page application index](Embed::spa_index), so the index file gets the same
+/// headers as with [Embed::spa].
+///
+/// # Examples
+/// ```
+/// use actix_web::{web, App, HttpResponse};
+/// use rust_embed::RustEmbed;
+///
+/// #[derive(RustEmbed)]
+/// #[folder = "testdata/"]
+/// struct Assets;
+///
+/// let app = App::new()
+///     .service(
+///         actix_embed::spa(&Assets)
+///             .index_file("./index.html")
+///             .static_resources_mount("/static")
+///             .static_resources_location("./assets")
+///             .exclude_prefixes(["/api"])
+///             .finish(),
+///     )
+///     .route("/api/users", web::get().to(HttpResponse::Ok));
+/// ```
+pub fn spa<E>(assets: &E) -> Spa<E>
+where
+    E: 'static + rust_embed::RustEmbed,
+{
+    let _ = assets;
+    Spa {
+        index_file: "index.html".to_owned(),
+        static_resources_mount: String::new(),
+        static_resources_location: String::new(),
+        excluded_prefixes: Vec::new(),
+        _e: PhantomData,
+    }
+}
+
+/// Builder of a single-page application service, see [spa].
+pub struct Spa<E> {
+    index_file: String,
+    static_resources_mount: String,
+    static_resources_location: String,
+    excluded_prefixes: Vec<String>,
+    _e: PhantomData<E>,
+}
+
+impl<E> Debug for Spa<E> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Spa")
+            .field("index_file", &self.index_file)
+            .field("static_resources_mount", &self.static_resources_mount)
+            .field("static_resources_location", &self.static_resources_location)
+            .field("excluded_prefixes", &self.excluded_prefixes)
+            .finish()
+    }
+}
+
+impl<E> Spa<E>
+where
+    E: 'static + rust_embed::RustEmbed,
+{
+    /// Set the path of the embedded index file, e.g. `dist/index.html`.
+    ///
+    /// Defaults to `index.html`.
+    pub fn index_file<P: AsRef<str>>(mut self, path: P) -> Self {
+        self.index_file = embedded_path(path.as_ref());
+        self
+    }
+
+    /// Set the path at which static resources are served, e.g. `/static`.
+    ///
+    /// Defaults to `/`.
+    pub fn static_resources_mount<P: AsRef<str>>(mut self, mount_path: P) -> Self {
+        self.static_resources_mount = mount_registry::normalize(mount_path.as_ref());
+        self
+    }
+
+    /// Set the folder of the embedded files served as static resources, e.g. `dist`.
+    ///
+    /// Defaults to all embedded files, see [Embed::folder].
+    pub fn static_resources_location<P: AsRef<str>>(mut self, folder: P) -> Self {
+        self.static_resources_location = embedded_path(folder.as_ref());
+        self
+    }
+
+    /// Exclude request paths under any of `prefixes`, e.g. `/api`, from the application.
+    ///
+    /// Requests for excluded paths are not handled, so they reach services registered after
+    /// it, or get `404 Not Found` instead of the index file.
+    pub fn exclude_prefixes<I>(mut self, prefixes: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        let prefixes = prefixes
+            .into_iter()
+            .map(|prefix| mount_registry::normalize(prefix.as_ref()));
+        self.excluded_prefixes.extend(prefixes);
+        self
+    }
+
+    /// Returns the service to register, like `actix_web_lab::web::Spa::finish`.
+    pub fn finish(self) -> impl HttpServiceFactory {
+        self
+    }
+
+    /// Returns the handler serving the index file with `200 OK`.
+    fn index_handler(&self) -> DefaultFallbackHandler {
+        DefaultFallbackHandler::new()
+            .status(StatusCode::OK)
+            .file::<E, _>(&self.index_file)
+    }
+
+    /// Returns the instance serving the static resources, and the index file at their mount
+    /// path and without a static resource.
+    fn static_resources(&self) -> Embed<E, DefaultFallbackHandler> {
+        let mount = &self.static_resources_mount;
+        let mut options = EmbedOptions::default().folder(&self.static_resources_location);
+        // the index file is served by the pipeline if it is a static resource, and by the
+        // fallback handler otherwise
+        let folder = format!("{}/", self.static_resources_location);
+        let index = match self.static_resources_location.as_str() {
+            "" => Some(self.index_file.as_str()),
+            _ => self.index_file.strip_prefix(&folder),
+        };
+        if let Some(index) = index {
+            options = options.index_file(index).spa_index(index);
+        }
+        let mut excluded = Vec::new();
+        for prefix in &self.excluded_prefixes {
+            if !under(prefix, mount) {
+                continue;
+            }
+            match prefix[mount.len()..].trim_start_matches('/') {
+                "" => excluded.push("**".to_owned()),
+                relative => excluded.extend([relative.to_owned(), format!("{}/**", relative)]),
+            }
+        }
+        Embed::mounted(mount, options.exclude(excluded)).fallback_handler(self.index_handler())
+    }
+
+    /// Returns the guard rejecting requests under excluded prefixes.
+    fn exclusion_guard(&self) -> Option<Box<dyn Guard>> {
+        if self.excluded_prefixes.is_empty() {
+            return None;
+        }
+        let excluded = self.excluded_prefixes.clone();
+        Some(Box::new(guard::fn_guard(move |ctx| {
+            let path = ctx.head().uri.path();
+            !excluded.iter().any(|prefix| under(path, prefix))
+        })))
+    }
+}
+
+impl<E> HttpServiceFactory for Spa<E>
+where
+    E: 'static + rust_embed::RustEmbed,
+{
+    fn register(self, config: &mut AppService) {
+        self.static_resources().register(config);
+        if self.static_resources_mount.is_empty() {
+            return;
+        }
+        // every other path gets the index file, for the client-side router
+        let handler = self.index_handler();
+        let index = fn_service(move |req: ServiceRequest| {
+            let resp = handler.execute(req.request());
+            async move { Ok(req.into_response(resp)) }
+        });
+        let guards = self.exclusion_guard().map(|guard| vec![guard]);
+        config.register_service(ResourceDef::prefix(""), guards, index, None)
+    }
+}
+
+/// Returns `path` as the key of an embedded file or folder, without the `./` and `/` of
+/// filesystem paths.
+fn embedded_path(path: &str) -> String {
+    let path = path.trim_start_matches("./").trim_matches('/');
+    path.to_owned()
+}
+
+/// Returns `true` if `path` is `prefix` or under it.
+fn under(path: &str, prefix: &str) -> bool {
+    match path.strip_prefix(prefix) {
+        Some(rest) => prefix.is_empty() || rest.is_empty() || rest.starts_with('/'),
+        None => false,
+    }
+}
@@ -0,0 +1,49 @@
+use std::cell::RefCell;
+use std::time::{Duration, Instant};
+
+use crate::events::{ServeStage, SlowServe};
+
+/// Measures the time spent in each stage of serving a request, see
+/// [Embed::slow_serve_threshold](crate::Embed::slow_serve_threshold).
+#[derive(Debug)]
+pub(crate) struct StageClock {
+    /// `None` if no threshold is set.
+    marks: Option<RefCell<Marks>>,
+}
+
+#[derive(Debug)]
+struct Marks {
+    last: Instant,
+    stages: Vec<(ServeStage, Duration)>,
+}
+
+impl StageClock {
+    pub(crate) fn new(enabled: bool) -> Self {
+        StageClock {
+            marks: enabled.then(|| {
+                RefCell::new(Marks {
+                    last: Instant::now(),
+                    stages: Vec::new(),
+                })
+            }),
+        }
+    }
+
+    /// Ends `stage`, which took the time since the previous stage ended.
+    pub(crate) fn finish(&self, stage: ServeStage) {
+        if let Some(marks) = &self.marks {
+            let mut marks = marks.borrow_mut();
+            let now = Instant::now();
+            let elapsed = now - marks.last;
+            marks.last = now;
+            marks.stages.push((stage, elapsed));
+        }
+    }
+
+    /// Returns the report of the serve if it took longer than `threshold`.
+    pub(crate) fn slow(self, elapsed: Duration, threshold: Option<Duration>) -> Option<SlowServe> {
+        let threshold = threshold?;
+        let stages = self.marks?.into_inner().stages;
+        (elapsed > threshold).then_some(SlowServe { threshold, stages })
+    }
+}
@@ -0,0 +1,67 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// How much the total sizes of the served and the on-disk files may differ, in percent,
+/// before they are reported, so edits in progress are not.
+const SIZE_TOLERANCE: u64 = 10;
+
+/// The number and total size of a set of files.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Stats {
+    files: usize,
+    bytes: u64,
+}
+
+impl Stats {
+    fn embedded<E: rust_embed::RustEmbed>() -> Self {
+        E::iter()
+            .filter_map(|path| E::get(&path))
+            .fold(Stats::default(), |stats, file| Stats {
+                files: stats.files + 1,
+                bytes: stats.bytes + file.data.len() as u64,
+            })
+    }
+
+    fn on_disk(folder: &Path) -> io::Result<Self> {
+        let mut stats = Stats::default();
+        let mut dirs = vec![folder.to_owned()];
+        while let Some(dir) = dirs.pop() {
+            for entry in fs::read_dir(dir)? {
+                let entry = entry?;
+                let metadata = fs::metadata(entry.path())?;
+                if metadata.is_dir() {
+                    dirs.push(entry.path());
+                } else {
+                    stats.files += 1;
+                    stats.bytes += metadata.len();
+                }
+            }
+        }
+        Ok(stats)
+    }
+}
+
+/// Returns why the files served by `E` look stale compared to `folder`, see
+/// [Embed::source_folder](crate::Embed::source_folder).
+///
+/// The files are compared by their number and total size only, without reading them.
+pub(crate) fn compare<E: rust_embed::RustEmbed>(folder: &Path) -> Option<String> {
+    let on_disk = match Stats::on_disk(folder) {
+        Ok(stats) => stats,
+        Err(err) => return Some(format!("cannot read {}: {}", folder.display(), err)),
+    };
+    let served = Stats::embedded::<E>();
+    let tolerance = served.bytes.max(on_disk.bytes) * SIZE_TOLERANCE / 100;
+    if served.files == on_disk.files && served.bytes.abs_diff(on_disk.bytes) <= tolerance {
+        return None;
+    }
+    Some(format!(
+        "the embedded files ({} files, {} bytes) differ from {} ({} files, {} bytes)",
+        served.files,
+        served.bytes,
+        folder.display(),
+        on_disk.files,
+        on_disk.bytes
+    ))
+}
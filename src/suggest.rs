@@ -0,0 +1,121 @@
+use crate::file_index::FileIndex;
+
+/// Suggested alternatives for a request path that matched no file.
+///
+/// When suggestions are enabled with [Embed::suggestions](crate::Embed::suggestions), it is
+/// inserted into the request extensions before the fallback handler is called.
+///
+/// # Examples
+/// ```
+/// use actix_embed::Suggestions;
+/// use actix_web::{HttpMessage, HttpRequest, HttpResponse};
+///
+/// fn fallback(req: &HttpRequest) -> HttpResponse {
+///     match req.extensions().get::<Suggestions>().and_then(|s| s.urls.first()) {
+///         Some(url) => HttpResponse::NotFound().body(format!("did you mean {}?", url)),
+///         None => HttpResponse::NotFound().finish(),
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Suggestions {
+    /// Request paths of the suggested files, most similar first.
+    pub urls: Vec<String>,
+}
+
+/// Paths of all embedded files, for finding the most similar ones.
+#[derive(Debug, Default)]
+pub(crate) struct SuggestionIndex {
+    /// `(lowercase path, path)`
+    keys: Vec<(String, String)>,
+}
+
+impl SuggestionIndex {
+    /// Indexes the files in `index`.
+    pub(crate) fn build(index: &FileIndex) -> Self {
+        let mut keys = index
+            .paths()
+            .iter()
+            .map(|key| (key.to_lowercase(), key.clone()))
+            .collect::<Vec<_>>();
+        keys.sort();
+        SuggestionIndex { keys }
+    }
+
+    /// Returns at most `limit` paths similar to `path`, most similar first.
+    ///
+    /// Paths differing only in case or directory are considered most similar, followed by
+    /// paths within a small edit distance.
+    pub(crate) fn suggest(&self, path: &str, limit: usize) -> Vec<&str> {
+        let path = path.to_lowercase();
+        let name = file_name(&path);
+        let max_distance = (path.chars().count() / 4).max(2);
+
+        let mut candidates = self
+            .keys
+            .iter()
+            .filter_map(|(lower, key)| {
+                let distance = if *lower == path {
+                    0
+                } else if file_name(lower) == name {
+                    1
+                } else {
+                    levenshtein(&path, lower) + 1
+                };
+                (distance <= max_distance).then_some((distance, key.as_str()))
+            })
+            .collect::<Vec<_>>();
+        candidates.sort();
+        candidates.truncate(limit);
+        candidates.into_iter().map(|(_, key)| key).collect()
+    }
+}
+
+impl SuggestionIndex {
+    /// Returns the only path differing from `path` in case, or in a missing, extra or
+    /// different extension of the file name, `None` if there is none or several, see
+    /// [Embed::auto_correct](crate::Embed::auto_correct).
+    pub(crate) fn correct(&self, path: &str) -> Option<&str> {
+        let lower = path.to_lowercase();
+        let stem = match lower.rsplit_once('.') {
+            Some((stem, ext)) if !ext.contains('/') && !file_name(stem).is_empty() => stem,
+            _ => lower.as_str(),
+        };
+        // paths starting with the stem are adjacent in sorted order
+        let start = self.keys.partition_point(|(key, _)| key.as_str() < stem);
+        let mut candidates = self.keys[start..]
+            .iter()
+            .take_while(|(key, _)| key.starts_with(stem))
+            .filter(|(key, original)| {
+                let extension = key[stem.len()..].strip_prefix('.');
+                original != path
+                    && (*key == lower
+                        || key == stem
+                        || extension
+                            .is_some_and(|ext| !ext.is_empty() && !ext.contains(['.', '/'])))
+            })
+            .map(|(_, original)| original.as_str());
+        let candidate = candidates.next()?;
+        candidates.next().is_none().then_some(candidate)
+    }
+}
+
+fn file_name(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+/// Computes the edit distance between `a` and `b`.
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut row = (0..=b.len()).collect::<Vec<_>>();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(row[j + 1] + 1);
+        }
+    }
+    row[b.len()]
+}
@@ -0,0 +1,105 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use actix_web::rt::time::{sleep, Sleep};
+use actix_web::web::Bytes;
+use futures_core::Stream;
+
+use crate::glob::Glob;
+
+/// The maximum size of the chunks of paced bodies.
+const MAX_CHUNK: u64 = 64 << 10;
+
+/// The number of chunks per second paced bodies are split into, unless chunks would exceed
+/// [MAX_CHUNK].
+const CHUNKS_PER_SEC: u64 = 10;
+
+/// Limits the rate at which files matching a glob are sent, see
+/// [Embed::throttle](crate::Embed::throttle).
+#[derive(Debug, Clone)]
+pub(crate) struct Throttle {
+    pub(crate) glob: Glob,
+    pub(crate) bytes_per_sec: u64,
+}
+
+/// Returns the rate of the first throttle matching `path`, `None` if it is not throttled.
+pub(crate) fn rate(throttles: &[Throttle], path: &str) -> Option<u64> {
+    throttles
+        .iter()
+        .find(|throttle| throttle.glob.matches(path))
+        .map(|throttle| throttle.bytes_per_sec)
+}
+
+/// A body sent at a fixed rate.
+///
+/// The body is split into chunks, each chunk is yielded once the previous ones have been
+/// sent at the rate, so a slow client still reads at its own pace but a fast one does not
+/// exceed the rate.
+pub(crate) struct Paced {
+    remaining: Bytes,
+    bytes_per_sec: u64,
+    chunk: usize,
+    /// The number of bytes yielded.
+    sent: u64,
+    /// When the first chunk was yielded.
+    started: Option<Instant>,
+    delay: Option<Pin<Box<Sleep>>>,
+}
+
+impl Paced {
+    pub(crate) fn new(body: Bytes, bytes_per_sec: u64) -> Self {
+        let chunk = (bytes_per_sec / CHUNKS_PER_SEC).clamp(1, MAX_CHUNK);
+        Paced {
+            remaining: body,
+            bytes_per_sec,
+            chunk: chunk as usize,
+            sent: 0,
+            started: None,
+            delay: None,
+        }
+    }
+
+    /// Returns how long to wait before yielding the next chunk, `None` if it is due.
+    fn wait(&self) -> Option<Duration> {
+        let started = self.started?;
+        let due = Duration::from_secs_f64(self.sent as f64 / self.bytes_per_sec as f64);
+        due.checked_sub(started.elapsed())
+            .filter(|wait| !wait.is_zero())
+    }
+}
+
+impl Stream for Paced {
+    type Item = Result<Bytes, actix_web::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if this.remaining.is_empty() {
+            return Poll::Ready(None);
+        }
+        if let Some(delay) = &mut this.delay {
+            if delay.as_mut().poll(cx).is_pending() {
+                return Poll::Pending;
+            }
+            this.delay = None;
+        } else if let Some(wait) = this.wait() {
+            let mut delay = Box::pin(sleep(wait));
+            if delay.as_mut().poll(cx).is_pending() {
+                this.delay = Some(delay);
+                return Poll::Pending;
+            }
+        }
+
+        let len = this.chunk.min(this.remaining.len());
+        let chunk = this.remaining.split_to(len);
+        this.started.get_or_insert_with(Instant::now);
+        this.sent += len as u64;
+        Poll::Ready(Some(Ok(chunk)))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let chunks = self.remaining.len().div_ceil(self.chunk);
+        (chunks, Some(chunks))
+    }
+}
@@ -0,0 +1,56 @@
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+/// What [Embed](crate::Embed) does when deriving a response takes longer than the serve
+/// timeout.
+///
+/// See [Embed::serve_timeout](crate::Embed::serve_timeout).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TimeoutAction {
+    /// Serve the original file with identity encoding.
+    #[default]
+    Identity,
+    /// Respond with `503 Service Unavailable`.
+    ServiceUnavailable,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ServeTimeout {
+    pub(crate) duration: Duration,
+    pub(crate) action: TimeoutAction,
+}
+
+/// Derived work was abandoned because the deadline passed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct TimedOut;
+
+/// The point in time after which derived work is abandoned for a request.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Deadline {
+    at: Option<Instant>,
+    pub(crate) action: TimeoutAction,
+}
+
+impl Deadline {
+    pub(crate) fn new(timeout: Option<ServeTimeout>) -> Self {
+        Deadline {
+            at: timeout.map(|timeout| Instant::now() + timeout.duration),
+            action: timeout.map(|timeout| timeout.action).unwrap_or_default(),
+        }
+    }
+
+    pub(crate) fn passed(&self) -> bool {
+        self.at.is_some_and(|at| Instant::now() >= at)
+    }
+
+    /// Runs `work` until the deadline, returns `None` if it passes first.
+    pub(crate) async fn run<F: Future>(&self, work: F) -> Option<F::Output> {
+        match self.at {
+            Some(at) => {
+                let remaining = at.saturating_duration_since(Instant::now());
+                actix_web::rt::time::timeout(remaining, work).await.ok()
+            }
+            None => Some(work.await),
+        }
+    }
+}
@@ -0,0 +1,34 @@
+/// How request paths ending in dots or spaces are treated, e.g. `/file.txt.` or
+/// `/file.txt%20` in links created on Windows, which drops them from file names.
+///
+/// See [Embed::path_tolerance](crate::Embed::path_tolerance).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PathTolerance {
+    /// Paths are looked up as requested.
+    #[default]
+    Strict,
+    /// Trailing dots and spaces are removed before the path is looked up.
+    Trim,
+    /// Requests for paths with trailing dots or spaces are redirected with
+    /// `301 Moved Permanently` to the path without them.
+    Redirect,
+}
+
+/// Returns `path` without trailing dots, spaces and percent-encoded spaces in the name of
+/// its last segment. Segments of only dots and spaces, like `..`, are left as they are.
+pub(crate) fn trim(path: &str) -> &str {
+    let start = path.rfind('/').map_or(0, |i| i + 1);
+    let mut trimmed = path;
+    loop {
+        let shorter = trimmed.trim_end_matches(['.', ' ']);
+        let shorter = shorter.strip_suffix("%20").unwrap_or(shorter);
+        if shorter.len() == trimmed.len() {
+            break;
+        }
+        trimmed = shorter;
+    }
+    match trimmed.len() > start {
+        true => trimmed,
+        false => path,
+    }
+}
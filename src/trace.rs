@@ -0,0 +1,48 @@
+use std::cell::RefCell;
+
+use actix_web::dev::ServiceResponse;
+use actix_web::http::header::HeaderName;
+
+use crate::http_util;
+
+/// The response header describing how the request was resolved.
+const RESOLUTION_HEADER: &str = "x-embed-resolution";
+
+/// The steps taken to resolve a request, see
+/// [Embed::resolution_trace](crate::Embed::resolution_trace).
+#[derive(Debug, Default)]
+pub(crate) struct Trace {
+    /// `None` if tracing is disabled.
+    steps: Option<RefCell<Vec<String>>>,
+}
+
+impl Trace {
+    pub(crate) fn new(enabled: bool) -> Self {
+        Trace {
+            // the trace reveals the layout of the embedded files, never send it from release builds
+            steps: (enabled && cfg!(debug_assertions)).then(RefCell::default),
+        }
+    }
+
+    /// Records a step, `step` is only called if tracing is enabled.
+    pub(crate) fn step<S>(&self, step: S)
+    where
+        S: FnOnce() -> String,
+    {
+        if let Some(steps) = &self.steps {
+            steps.borrow_mut().push(step());
+        }
+    }
+
+    /// Adds the recorded steps to `resp`.
+    pub(crate) fn apply(self, resp: &mut ServiceResponse) {
+        let steps = match self.steps {
+            Some(steps) => steps.into_inner(),
+            None => return,
+        };
+        // steps quote request paths, which may contain any character
+        let value = http_util::header_value(&steps.join("; "));
+        resp.headers_mut()
+            .insert(HeaderName::from_static(RESOLUTION_HEADER), value);
+    }
+}
@@ -0,0 +1,340 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::time::{Instant, SystemTime};
+
+use actix_web::body::SizedStream;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{self, EntityTag, HeaderMap, HeaderValue};
+use actix_web::http::Method;
+use actix_web::web::Bytes;
+use actix_web::{HttpMessage, HttpResponse};
+
+use crate::chunked::Chunked;
+use crate::conditional::{self, Precondition};
+use crate::encoding::{Compression, Encoding};
+use crate::events::{Served, ServedAsset};
+use crate::glob::Glob;
+use crate::http_util;
+use crate::language;
+use crate::metrics::{CacheGauge, ServeMetrics};
+use crate::range::{self, Requested};
+use crate::throttle::Paced;
+
+/// The request headers responses can depend on.
+const PROFILE_HEADERS: &[&str] = &[
+    "accept-charset",
+    "accept-encoding",
+    "accept-language",
+    "sec-ch-dpr",
+    "dpr",
+    "sec-ch-width",
+    "width",
+];
+
+/// The values of the request headers, the query string and the cookie responses can depend
+/// on.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct Profile {
+    headers: Vec<Option<HeaderValue>>,
+    query: String,
+    cookie: Option<String>,
+}
+
+impl Profile {
+    /// Returns the profile of `req`, `cookie` is the name of the cookie responses depend on.
+    pub(crate) fn of(req: &ServiceRequest, cookie: Option<&str>) -> Self {
+        Profile {
+            headers: PROFILE_HEADERS
+                .iter()
+                .map(|name| req.headers().get(*name).cloned())
+                .collect(),
+            query: req.query_string().to_owned(),
+            cookie: cookie
+                .and_then(|name| language::cookie(req.request(), name))
+                .map(str::to_owned),
+        }
+    }
+}
+
+/// A negotiated response for an embedded file, ready to be sent.
+#[derive(Debug, Clone)]
+pub(crate) struct FileResponse {
+    /// The key of the served file.
+    pub(crate) path: String,
+    pub(crate) etag: EntityTag,
+    /// The headers sent with `200` and `304` responses.
+    pub(crate) headers: HeaderMap,
+    pub(crate) content_type: String,
+    pub(crate) encoding: Option<Encoding>,
+    pub(crate) compression: Compression,
+    /// Whether `200` responses are marked with `Content-Encoding: identity`, see
+    /// [OuterCompression::Bypass](crate::OuterCompression::Bypass).
+    pub(crate) identity: bool,
+    /// Empty if only headers are sent, see [size](Self::size).
+    pub(crate) body: Bytes,
+    /// The size of the body in bytes, also known if the body was not read.
+    pub(crate) size: u64,
+    /// The modification time of the file, if embedded, see `Last-Modified`.
+    pub(crate) last_modified: Option<SystemTime>,
+    /// Whether range requests are honored, see [Embed::accept_ranges](crate::Embed::accept_ranges).
+    pub(crate) ranges: bool,
+    /// The rate in bytes per second the body is sent at, see
+    /// [Embed::throttle](crate::Embed::throttle).
+    pub(crate) throttle: Option<u64>,
+    /// The size of the chunks the body is streamed in, `0` to send it in one piece, see
+    /// [Embed::stream_chunk_size](crate::Embed::stream_chunk_size).
+    pub(crate) chunk_size: usize,
+    /// When the response was generated, if it is sent with `Date` and `Age`, see
+    /// [Embed::age_headers](crate::Embed::age_headers).
+    pub(crate) generated: Option<Generated>,
+}
+
+/// The time a response was generated at.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Generated {
+    /// For `Age`, unaffected by changes of the system clock.
+    instant: Instant,
+    date: SystemTime,
+}
+
+impl Generated {
+    pub(crate) fn now() -> Self {
+        Generated {
+            instant: Instant::now(),
+            date: SystemTime::now(),
+        }
+    }
+
+    /// Inserts `Date` and `Age` into `headers`.
+    fn insert(&self, headers: &mut HeaderMap) {
+        let date = http_util::http_date(self.date);
+        let age = self.instant.elapsed().as_secs();
+        if let Ok(date) = HeaderValue::from_str(&date) {
+            headers.insert(header::DATE, date);
+        }
+        headers.insert(header::AGE, HeaderValue::from(age));
+    }
+}
+
+impl FileResponse {
+    /// Responds to `req` as decided by `precondition`, the result of evaluating its
+    /// preconditions against the entity tag and the modification time, and its `Range`
+    /// header.
+    pub(crate) fn respond(
+        &self,
+        req: ServiceRequest,
+        precondition: Precondition,
+    ) -> (ServiceResponse, Served) {
+        let served = Served::File(self.path.clone());
+        let not_modified = match precondition {
+            Precondition::Passed => false,
+            Precondition::NotModified => true,
+            Precondition::Failed => {
+                let resp = HttpResponse::PreconditionFailed().finish();
+                return (req.into_response(resp), served);
+            }
+        };
+
+        if not_modified {
+            let mut builder = HttpResponse::NotModified();
+            for (name, value) in &self.headers {
+                builder.append_header((name.clone(), value.clone()));
+            }
+            return self.finish(req, builder.finish(), served);
+        }
+
+        // ranges are only defined for GET
+        let requested = match self.ranges && req.method() == Method::GET {
+            true if conditional::range_applies(req.request(), &self.etag, self.last_modified) => {
+                range::requested(req.request(), self.body.len() as u64)
+            }
+            _ => Requested::Full,
+        };
+        if requested == Requested::Unsatisfiable {
+            let total = self.body.len() as u64;
+            let mut builder = HttpResponse::RangeNotSatisfiable();
+            builder.insert_header((header::CONTENT_RANGE, http_util::unsatisfied_range(total)));
+            // the length depends on the negotiated representation
+            for value in self.headers.get_all(header::VARY) {
+                builder.append_header((header::VARY, value.clone()));
+            }
+            let resp = builder.finish();
+            return self.finish(req, resp, served);
+        }
+
+        let mut builder = match requested {
+            Requested::Full => HttpResponse::Ok(),
+            _ => HttpResponse::PartialContent(),
+        };
+        builder.content_type(self.content_type.as_str());
+        match self.encoding {
+            Some(encoding) => {
+                builder.insert_header((header::CONTENT_ENCODING, encoding.as_str()));
+            }
+            None if self.identity => {
+                builder.insert_header((header::CONTENT_ENCODING, "identity"));
+            }
+            None => {}
+        }
+        for (name, value) in &self.headers {
+            // header rules may override the content type and encoding
+            if name == header::CONTENT_TYPE || name == header::CONTENT_ENCODING {
+                builder.insert_header((name.clone(), value.clone()));
+            } else {
+                builder.append_header((name.clone(), value.clone()));
+            }
+        }
+        let body = match requested {
+            Requested::Single(start, end) => {
+                let total = self.body.len() as u64;
+                let content_range = http_util::content_range(start, end, total);
+                builder.insert_header((header::CONTENT_RANGE, content_range));
+                self.body.slice(start as usize..=end as usize)
+            }
+            Requested::Multiple(ranges) => {
+                let content_type = match self.headers.get(header::CONTENT_TYPE) {
+                    Some(value) => value.to_str().unwrap_or_default(),
+                    None => self.content_type.as_str(),
+                };
+                let boundary = http_util::multipart_boundary(&self.etag);
+                let body = range::multipart(&self.body, &ranges, &boundary, content_type);
+                let multipart = format!("multipart/byteranges; boundary={}", boundary);
+                builder.insert_header((header::CONTENT_TYPE, multipart));
+                body
+            }
+            Requested::Full | Requested::Unsatisfiable => self.body.clone(),
+        };
+        let resp = match self.throttle {
+            Some(rate) => {
+                let len = body.len() as u64;
+                builder.body(SizedStream::new(len, Paced::new(body, rate)))
+            }
+            None if self.chunk_size > 0 && body.len() > self.chunk_size => {
+                let len = body.len() as u64;
+                builder.body(SizedStream::new(len, Chunked::new(body, self.chunk_size)))
+            }
+            None => builder.body(body),
+        };
+        self.finish(req, resp, served)
+    }
+
+    /// Wraps `resp`, recording the served asset in the extensions of the request and response.
+    fn finish(
+        &self,
+        req: ServiceRequest,
+        mut resp: HttpResponse,
+        served: Served,
+    ) -> (ServiceResponse, Served) {
+        if let Some(generated) = &self.generated {
+            generated.insert(resp.headers_mut());
+        }
+        let mime = match resp.headers().get(header::CONTENT_TYPE) {
+            Some(value) => value.to_str().unwrap_or_default().to_owned(),
+            None => self.content_type.clone(),
+        };
+        let asset = ServedAsset {
+            path: self.path.clone(),
+            size: self.size,
+            etag: self.etag.clone(),
+            mime,
+        };
+        resp.extensions_mut().insert(asset.clone());
+        req.extensions_mut().insert(asset);
+        req.extensions_mut().insert(self.compression);
+        (req.into_response(resp), served)
+    }
+}
+
+/// The cached responses of a path, by profile, with the [epoch](crate::EmbedAdmin::epoch)
+/// they were generated in.
+type Profiles = Vec<(Profile, usize, Rc<FileResponse>)>;
+
+/// Negotiated responses, keyed by request path and [Profile].
+///
+/// See [Embed::variant_cache](crate::Embed::variant_cache).
+#[derive(Debug)]
+pub(crate) struct VariantCache {
+    /// The maximum number of profiles cached per path.
+    limit: usize,
+    entries: RefCell<HashMap<String, Profiles>>,
+    /// The bytes of the bodies of the cached responses.
+    pub(crate) gauge: CacheGauge,
+}
+
+impl VariantCache {
+    pub(crate) fn new(limit: usize) -> Self {
+        VariantCache {
+            limit,
+            entries: RefCell::new(HashMap::new()),
+            gauge: CacheGauge::default(),
+        }
+    }
+
+    /// Returns the response cached for `path` and `profile`, unless it was generated in an
+    /// epoch before `epoch`.
+    pub(crate) fn get(
+        &self,
+        path: &str,
+        profile: &Profile,
+        epoch: usize,
+    ) -> Option<Rc<FileResponse>> {
+        let entries = self.entries.borrow();
+        entries
+            .get(path)?
+            .iter()
+            .find(|(cached, generated_in, _)| cached == profile && *generated_in >= epoch)
+            .map(|(_, _, resp)| resp.clone())
+    }
+
+    /// Drops the cached responses with files matching `glob`, all of them if it is `None`.
+    pub(crate) fn purge(&self, glob: Option<&Glob>) {
+        let mut entries = self.entries.borrow_mut();
+        entries.retain(|_, profiles| {
+            profiles.retain(|(_, _, resp)| {
+                let purged = glob.is_none_or(|glob| glob.matches(&resp.path));
+                if purged {
+                    self.gauge.sub(resp.body.len() as u64);
+                }
+                !purged
+            });
+            !profiles.is_empty()
+        });
+    }
+
+    /// Caches `resp`, generated in `epoch`, for requests for `path` with `profile`, unless
+    /// the path already has as many profiles as allowed. A response of the profile generated
+    /// in an earlier epoch is replaced.
+    pub(crate) fn insert(
+        &self,
+        path: &str,
+        profile: Profile,
+        epoch: usize,
+        resp: Rc<FileResponse>,
+        metrics: Option<&ServeMetrics>,
+    ) {
+        let mut entries = self.entries.borrow_mut();
+        let profiles = entries.entry(path.to_owned()).or_default();
+        if let Some(i) = profiles
+            .iter()
+            .position(|(cached, _, _)| *cached == profile)
+        {
+            if profiles[i].1 >= epoch {
+                return;
+            }
+            let (_, _, stale) = profiles.remove(i);
+            self.gauge.sub(stale.body.len() as u64);
+        }
+        if profiles.len() >= self.limit {
+            if let Some(metrics) = metrics {
+                metrics.record_variant_rejected();
+            }
+            return;
+        }
+        self.gauge.add(resp.body.len() as u64);
+        profiles.push((profile, epoch, resp));
+        if let Some(metrics) = metrics {
+            metrics.record_variant_cached();
+        }
+    }
+}
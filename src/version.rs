@@ -0,0 +1,112 @@
+use actix_web::http::header::{self, EntityTag};
+use actix_web::{HttpRequest, HttpResponse};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::conditional::{self, Precondition};
+use crate::file_index::{FileIndex, KeyMap};
+use crate::hasher::ContentHashes;
+use crate::headers;
+
+/// Build information served by the version endpoint.
+///
+/// See [Embed::version_endpoint](crate::Embed::version_endpoint).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VersionInfo {
+    pub(crate) build: Option<String>,
+}
+
+impl VersionInfo {
+    /// Create new [VersionInfo] without build information.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the identifier of the build, e.g. the commit sha.
+    pub fn build<S: AsRef<str>>(mut self, build: S) -> Self {
+        self.build = Some(build.as_ref().to_owned());
+        self
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct VersionEndpoint {
+    /// Relative to the mount path, without leading and trailing slashes.
+    pub(crate) path: String,
+    pub(crate) info: VersionInfo,
+}
+
+/// The version of the served files, derived from the hashes of all files.
+#[derive(Debug)]
+pub(crate) struct BundleVersion {
+    content_hash: String,
+    body: String,
+}
+
+/// The body of responses of the version endpoint.
+#[derive(Debug, Serialize)]
+struct Body<'a> {
+    build: Option<&'a str>,
+    content_hash: &'a str,
+    files: usize,
+}
+
+impl BundleVersion {
+    /// Computes the version of the files in `index`.
+    pub(crate) fn build<E>(
+        info: &VersionInfo,
+        index: &FileIndex,
+        keys: &KeyMap,
+        hashes: &ContentHashes,
+    ) -> Self
+    where
+        E: 'static + rust_embed::RustEmbed,
+    {
+        let mut hasher = Sha256::new();
+        let mut files = 0;
+        // the index is sorted, the content hash does not depend on the order of embedding
+        for path in index.paths() {
+            if let Some((key, file)) = keys.get::<E>(path) {
+                hasher.update(key.as_bytes());
+                hasher.update([0]);
+                hasher.update(hashes.get(&key, &file).as_bytes());
+                hasher.update([b'\n']);
+                files += 1;
+            }
+        }
+        let content_hash = hex::encode(hasher.finalize());
+
+        let body = serde_json::to_string(&Body {
+            build: info.build.as_deref(),
+            content_hash: &content_hash,
+            files,
+        })
+        .unwrap_or_default();
+
+        BundleVersion { content_hash, body }
+    }
+
+    pub(crate) fn etag(&self) -> EntityTag {
+        EntityTag::new_strong(self.content_hash.clone())
+    }
+
+    /// Returns the response of the version endpoint, honoring conditional requests.
+    pub(crate) fn response(&self, req: &HttpRequest) -> HttpResponse {
+        let etag = self.etag();
+        let mut builder = match conditional::evaluate(req, &etag, None) {
+            Precondition::Passed => HttpResponse::Ok(),
+            Precondition::NotModified => {
+                return HttpResponse::NotModified()
+                    .insert_header((header::CACHE_CONTROL, headers::NO_CACHE))
+                    .insert_header(header::ETag(etag))
+                    .finish()
+            }
+            Precondition::Failed => return HttpResponse::PreconditionFailed().finish(),
+        };
+        builder
+            .content_type("application/json")
+            .insert_header((header::CACHE_CONTROL, headers::NO_CACHE))
+            .insert_header(header::ETag(etag))
+            .body(self.body.clone())
+    }
+}
@@ -0,0 +1 @@
+export const version = "3f2a9c1d";
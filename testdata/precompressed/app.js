@@ -0,0 +1,8 @@
+// Precompressed next to `app.js.br` and `app.js.gz`.
+export function greet(name) {
+  return `Hello, ${name}! Welcome to the embedded application.`;
+}
+
+export function farewell(name) {
+  return `Goodbye, ${name}! Thank you for using the embedded application.`;
+}